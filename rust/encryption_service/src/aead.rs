@@ -0,0 +1,157 @@
+//! Authenticated encryption (requires the `aead` feature).
+//!
+//! ChaCha20-Poly1305 with a 32-byte key and a 12-byte nonce. The ciphertext
+//! carries a 16-byte authentication tag, so any modification is detected on
+//! decryption. Callers are responsible for never reusing a nonce with the
+//! same key.
+
+use chacha20poly1305::aead::Aead;
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::CryptoError;
+
+/// AEAD key size in bytes.
+pub const AEAD_KEY_SIZE: usize = 32;
+
+/// AEAD nonce size in bytes.
+pub const AEAD_NONCE_SIZE: usize = 12;
+
+/// Size of the authentication tag appended to each ciphertext.
+pub const AEAD_TAG_SIZE: usize = 16;
+
+/// Encrypt and authenticate `plaintext`.
+///
+/// # Arguments
+/// * `key` - [`AEAD_KEY_SIZE`]-byte key
+/// * `nonce` - [`AEAD_NONCE_SIZE`]-byte nonce, unique per message for this key
+/// * `plaintext` - The data to encrypt
+///
+/// # Returns
+/// * `Ok(Vec<u8>)` - Ciphertext followed by the [`AEAD_TAG_SIZE`]-byte tag
+/// * `Err(CryptoError::InvalidLength)` - If the key or nonce has the wrong length
+pub fn encrypt(key: &[u8], nonce: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let (cipher, nonce) = cipher_and_nonce(key, nonce)?;
+    // Encryption only fails for messages over 256 GiB.
+    Ok(cipher
+        .encrypt(nonce, plaintext)
+        .expect("plaintext within ChaCha20-Poly1305 length limit"))
+}
+
+/// Authenticate and decrypt `ciphertext`.
+///
+/// # Arguments
+/// * `key` - The key used to encrypt
+/// * `nonce` - The nonce used to encrypt
+/// * `ciphertext` - Output of [`encrypt`]
+///
+/// # Returns
+/// * `Ok(Vec<u8>)` - The plaintext
+/// * `Err(CryptoError::InvalidLength)` - If the key or nonce has the wrong length
+/// * `Err(CryptoError::DecryptionFailed)` - If the ciphertext was modified or the key is wrong
+pub fn decrypt(key: &[u8], nonce: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let (cipher, nonce) = cipher_and_nonce(key, nonce)?;
+    cipher
+        .decrypt(nonce, ciphertext)
+        .map_err(|_| CryptoError::DecryptionFailed)
+}
+
+fn cipher_and_nonce<'n>(
+    key: &[u8],
+    nonce: &'n [u8],
+) -> Result<(ChaCha20Poly1305, &'n Nonce), CryptoError> {
+    if key.len() != AEAD_KEY_SIZE {
+        return Err(CryptoError::InvalidLength {
+            expected: AEAD_KEY_SIZE,
+            actual: key.len(),
+        });
+    }
+    if nonce.len() != AEAD_NONCE_SIZE {
+        return Err(CryptoError::InvalidLength {
+            expected: AEAD_NONCE_SIZE,
+            actual: nonce.len(),
+        });
+    }
+    Ok((
+        ChaCha20Poly1305::new(Key::from_slice(key)),
+        Nonce::from_slice(nonce),
+    ))
+}
+
+/// Encrypt a payload (Python binding).
+#[pyfunction]
+#[pyo3(name = "encrypt")]
+pub(crate) fn py_encrypt<'py>(
+    py: Python<'py>,
+    key: Vec<u8>,
+    nonce: Vec<u8>,
+    plaintext: Vec<u8>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let ciphertext = encrypt(&key, &nonce, &plaintext)?;
+    Ok(PyBytes::new_bound(py, &ciphertext))
+}
+
+/// Decrypt a payload; raises `ValueError` if authentication fails (Python binding).
+#[pyfunction]
+#[pyo3(name = "decrypt")]
+pub(crate) fn py_decrypt<'py>(
+    py: Python<'py>,
+    key: Vec<u8>,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let plaintext = decrypt(&key, &nonce, &ciphertext)?;
+    Ok(PyBytes::new_bound(py, &plaintext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen;
+
+    const NONCE: [u8; AEAD_NONCE_SIZE] = [7; AEAD_NONCE_SIZE];
+
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() {
+        let key = keygen(42);
+        let ciphertext = encrypt(&key, &NONCE, b"BUY AAPL 10").unwrap();
+        assert_eq!(ciphertext.len(), b"BUY AAPL 10".len() + AEAD_TAG_SIZE);
+        assert_ne!(&ciphertext[..11], b"BUY AAPL 10");
+        assert_eq!(decrypt(&key, &NONCE, &ciphertext).unwrap(), b"BUY AAPL 10");
+    }
+
+    #[test]
+    fn test_decrypt_rejects_tampering_and_wrong_key() {
+        let key = keygen(42);
+        let mut ciphertext = encrypt(&key, &NONCE, b"payload").unwrap();
+        assert_eq!(
+            decrypt(&keygen(43), &NONCE, &ciphertext),
+            Err(CryptoError::DecryptionFailed)
+        );
+        ciphertext[0] ^= 0xff;
+        assert_eq!(
+            decrypt(&key, &NONCE, &ciphertext),
+            Err(CryptoError::DecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn test_rejects_wrong_key_and_nonce_lengths() {
+        let key = keygen(42);
+        assert_eq!(
+            encrypt(&key[..16], &NONCE, b"payload"),
+            Err(CryptoError::InvalidLength {
+                expected: AEAD_KEY_SIZE,
+                actual: 16,
+            })
+        );
+        assert_eq!(
+            decrypt(&key, &NONCE[..8], b"payload"),
+            Err(CryptoError::InvalidLength {
+                expected: AEAD_NONCE_SIZE,
+                actual: 8,
+            })
+        );
+    }
+}
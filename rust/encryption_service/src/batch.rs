@@ -0,0 +1,76 @@
+//! Verifying many signatures at once.
+//!
+//! [`verify_batch`] checks `(payload, sig)` pairs one after another; with
+//! the `parallel` feature, [`verify_parallel`] spreads the same work across
+//! rayon's global thread pool. Both return one result per item, in input
+//! order, and count every item in telemetry exactly as [`verify`] does.
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::verify;
+
+/// Verify every `(payload, sig)` pair under `key`, serially.
+///
+/// # Arguments
+/// * `key` - The verification key shared by all items
+/// * `items` - `(payload, sig)` pairs
+///
+/// # Returns
+/// `verify(key, payload, sig)` for each item, in input order
+pub fn verify_batch(key: &[u8], items: &[(Vec<u8>, Vec<u8>)]) -> Vec<bool> {
+    items
+        .iter()
+        .map(|(payload, sig)| verify(key, payload, sig))
+        .collect()
+}
+
+/// Verify every `(payload, sig)` pair under `key` on rayon's thread pool.
+///
+/// Same results as [`verify_batch`], in the same order. Worth it from a
+/// few thousand items up; below that the fan-out costs more than it saves.
+/// Run `cargo test --release -p encryption_service --features parallel
+/// --test parallel_verify -- --ignored --nocapture` to compare the two on
+/// 1M items.
+#[cfg(feature = "parallel")]
+pub fn verify_parallel(key: &[u8], items: &[(Vec<u8>, Vec<u8>)]) -> Vec<bool> {
+    items
+        .par_iter()
+        .map(|(payload, sig)| verify(key, payload, sig))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{keygen, sign};
+
+    fn items(key: &[u8], count: usize) -> Vec<(Vec<u8>, Vec<u8>)> {
+        (0..count)
+            .map(|i| {
+                let payload = format!("order-{i}").into_bytes();
+                let mut sig = sign(key, &payload);
+                if i % 3 == 0 {
+                    sig[0] ^= 1;
+                }
+                (payload, sig)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_verify_batch_keeps_input_order() {
+        let key = keygen(42);
+        let results = verify_batch(&key, &items(&key, 7));
+        assert_eq!(results, vec![false, true, true, false, true, true, false]);
+        assert!(verify_batch(&key, &[]).is_empty());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_verify_parallel_matches_verify_batch() {
+        let key = keygen(42);
+        let items = items(&key, 10_000);
+        assert_eq!(verify_parallel(&key, &items), verify_batch(&key, &items));
+    }
+}
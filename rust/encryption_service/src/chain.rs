@@ -0,0 +1,111 @@
+//! Forward-only key chains.
+//!
+//! A [`KeyChain`] ratchets an internal state with HMAC-SHA256 and derives
+//! each output key from the new state:
+//!
+//! ```text
+//! state_0 = keygen(seed)
+//! state_n = HMAC(state_{n-1}, "ratchet")
+//! key_n   = HMAC(state_n, "key")
+//! ```
+//!
+//! The previous state is overwritten on every step, so someone who obtains
+//! the current state or key cannot recover earlier keys.
+
+use std::fmt;
+
+use hmac::Mac;
+use zeroize::Zeroize;
+
+use crate::{keygen, HmacSha256, KEY_SIZE};
+
+/// Label HMAC'd under the old state to produce the next one.
+const RATCHET_LABEL: &[u8] = b"ratchet";
+
+/// Label HMAC'd under the current state to produce an output key.
+const KEY_LABEL: &[u8] = b"key";
+
+/// Deterministic sequence of 32-byte keys from a ratcheting state.
+///
+/// Two chains created from the same seed produce the same keys in the same
+/// order.
+pub struct KeyChain {
+    state: [u8; KEY_SIZE],
+}
+
+impl KeyChain {
+    /// Start a chain whose initial state is [`keygen(seed)`](keygen).
+    pub fn new(seed: u64) -> Self {
+        let mut initial = keygen(seed);
+        let mut state = [0u8; KEY_SIZE];
+        state.copy_from_slice(&initial);
+        initial.zeroize();
+        Self { state }
+    }
+
+    /// Ratchet the state forward and return the next 32-byte key.
+    // Not an `Iterator`: the chain never ends, so `Option` would only add noise.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Vec<u8> {
+        let mut next = hmac(&self.state, RATCHET_LABEL);
+        self.state.zeroize();
+        self.state.copy_from_slice(&next);
+        next.zeroize();
+        hmac(&self.state, KEY_LABEL)
+    }
+}
+
+fn hmac(key: &[u8], label: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC can take key of any size");
+    mac.update(label);
+    mac.finalize().into_bytes().to_vec()
+}
+
+impl Drop for KeyChain {
+    fn drop(&mut self) {
+        self.state.zeroize();
+    }
+}
+
+impl fmt::Debug for KeyChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyChain")
+            .field("state", &"<redacted>")
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let mut first = KeyChain::new(42);
+        let mut second = KeyChain::new(42);
+        for _ in 0..5 {
+            let key = first.next();
+            assert_eq!(key.len(), KEY_SIZE);
+            assert_eq!(key, second.next());
+        }
+    }
+
+    #[test]
+    fn test_keys_differ_across_steps_and_seeds() {
+        let mut chain = KeyChain::new(42);
+        let keys: Vec<Vec<u8>> = (0..4).map(|_| chain.next()).collect();
+        for (i, key) in keys.iter().enumerate() {
+            assert!(keys[i + 1..].iter().all(|other| other != key));
+            assert_ne!(key, &keygen(42));
+        }
+        assert_ne!(KeyChain::new(43).next(), keys[0]);
+    }
+
+    #[test]
+    fn test_debug_redacts_state() {
+        assert_eq!(
+            format!("{:?}", KeyChain::new(42)),
+            "KeyChain { state: \"<redacted>\" }"
+        );
+    }
+}
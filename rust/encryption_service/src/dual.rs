@@ -0,0 +1,428 @@
+//! Dual signatures for algorithm migration.
+//!
+//! While moving from HMAC-SHA256 to a new algorithm, messages carry two
+//! signatures: the legacy HMAC-SHA256 one and one produced with the new
+//! algorithm. A [`DualSignature`] holds both, each tagged with its algorithm,
+//! and is exchanged as CBOR. Verifiers choose how strict to be with a
+//! [`DualPolicy`].
+//!
+//! `HmacSha512` stands in for the post-quantum algorithm until one is wired
+//! in; the format and policies do not depend on which algorithm it is.
+
+use std::fmt;
+use std::str::FromStr;
+
+use hmac::{Hmac, Mac};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use serde::{Deserialize, Serialize};
+use sha2::Sha512;
+
+use crate::{constant_time_eq, sign, CryptoError};
+
+/// Signature algorithm tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SigAlgorithm {
+    /// HMAC-SHA256, the legacy algorithm (see [`sign`]).
+    HmacSha256,
+    /// HMAC-SHA512.
+    HmacSha512,
+}
+
+impl SigAlgorithm {
+    /// Sign `payload` with `key` using this algorithm.
+    pub fn sign(self, key: &[u8], payload: &[u8]) -> Vec<u8> {
+        match self {
+            SigAlgorithm::HmacSha256 => sign(key, payload),
+            SigAlgorithm::HmacSha512 => {
+                let mut mac = <Hmac<Sha512> as Mac>::new_from_slice(key)
+                    .expect("HMAC can take key of any size");
+                mac.update(payload);
+                mac.finalize().into_bytes().to_vec()
+            }
+        }
+    }
+
+    /// Verify `sig` over `payload` in constant time.
+    pub fn verify(self, key: &[u8], payload: &[u8], sig: &[u8]) -> bool {
+        constant_time_eq(&self.sign(key, payload), sig)
+    }
+}
+
+impl fmt::Display for SigAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SigAlgorithm::HmacSha256 => write!(f, "hmac-sha256"),
+            SigAlgorithm::HmacSha512 => write!(f, "hmac-sha512"),
+        }
+    }
+}
+
+impl FromStr for SigAlgorithm {
+    type Err = CryptoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hmac-sha256" => Ok(SigAlgorithm::HmacSha256),
+            "hmac-sha512" => Ok(SigAlgorithm::HmacSha512),
+            other => Err(CryptoError::Unsupported(other.to_string())),
+        }
+    }
+}
+
+/// How [`verify_dual`] combines the two signatures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DualPolicy {
+    /// Both signatures must be present and valid.
+    RequireBoth,
+    /// At least one signature must be present and valid.
+    RequireEither,
+    /// Use the new signature if present; otherwise fall back to the legacy one.
+    PreferNewFallbackOld,
+}
+
+impl FromStr for DualPolicy {
+    type Err = CryptoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "require_both" => Ok(DualPolicy::RequireBoth),
+            "require_either" => Ok(DualPolicy::RequireEither),
+            "prefer_new_fallback_old" => Ok(DualPolicy::PreferNewFallbackOld),
+            other => Err(CryptoError::Unsupported(other.to_string())),
+        }
+    }
+}
+
+/// A legacy HMAC-SHA256 signature and a new-algorithm signature over the
+/// same payload. Either signature may be missing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DualSignature {
+    /// Algorithm of `legacy_sig`; always [`SigAlgorithm::HmacSha256`] when valid.
+    pub legacy_alg: SigAlgorithm,
+    /// The legacy signature, if present.
+    #[serde(with = "serde_bytes")]
+    pub legacy_sig: Option<Vec<u8>>,
+    /// Algorithm of `alt_sig`.
+    pub alt_alg: SigAlgorithm,
+    /// The new-algorithm signature, if present.
+    #[serde(with = "serde_bytes")]
+    pub alt_sig: Option<Vec<u8>>,
+}
+
+impl DualSignature {
+    /// Encode as CBOR.
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        ciborium::into_writer(self, &mut out).expect("writing CBOR to a Vec cannot fail");
+        out
+    }
+
+    /// Decode from CBOR.
+    ///
+    /// # Returns
+    /// * `Ok(DualSignature)` - The decoded signature pair
+    /// * `Err(CryptoError::InvalidEncoding)` - If `bytes` is not a valid encoding
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, CryptoError> {
+        ciborium::from_reader(bytes).map_err(|err| CryptoError::InvalidEncoding(err.to_string()))
+    }
+}
+
+/// Sign a payload with both the legacy HMAC-SHA256 key and a new-algorithm key.
+///
+/// # Arguments
+/// * `hmac_key` - The legacy HMAC-SHA256 key
+/// * `alt_key` - The key for the new algorithm
+/// * `alg` - The new algorithm
+/// * `payload` - The data to sign
+///
+/// # Returns
+/// A [`DualSignature`] carrying both signatures
+pub fn sign_dual(
+    hmac_key: &[u8],
+    alt_key: &[u8],
+    alg: SigAlgorithm,
+    payload: &[u8],
+) -> DualSignature {
+    DualSignature {
+        legacy_alg: SigAlgorithm::HmacSha256,
+        legacy_sig: Some(sign(hmac_key, payload)),
+        alt_alg: alg,
+        alt_sig: Some(alg.sign(alt_key, payload)),
+    }
+}
+
+/// Verify a [`DualSignature`] under `policy`.
+///
+/// The algorithm tags are checked before any signature: the legacy tag must
+/// be HMAC-SHA256 and the new tag must equal `alg`.
+///
+/// # Arguments
+/// * `policy` - How to combine the two results
+/// * `hmac_key` - The legacy HMAC-SHA256 key
+/// * `alt_key` - The key for the new algorithm
+/// * `alg` - The expected new algorithm
+/// * `payload` - The data that was signed
+/// * `dual_sig` - The signature pair
+///
+/// # Returns
+/// * `Ok(bool)` - Whether the signatures satisfy `policy`
+/// * `Err(CryptoError::AlgorithmMismatch)` - If a tag does not match
+pub fn verify_dual(
+    policy: DualPolicy,
+    hmac_key: &[u8],
+    alt_key: &[u8],
+    alg: SigAlgorithm,
+    payload: &[u8],
+    dual_sig: &DualSignature,
+) -> Result<bool, CryptoError> {
+    check_alg(SigAlgorithm::HmacSha256, dual_sig.legacy_alg)?;
+    check_alg(alg, dual_sig.alt_alg)?;
+
+    let legacy_ok = || {
+        dual_sig
+            .legacy_sig
+            .as_deref()
+            .is_some_and(|sig| SigAlgorithm::HmacSha256.verify(hmac_key, payload, sig))
+    };
+    let alt_ok = || {
+        dual_sig
+            .alt_sig
+            .as_deref()
+            .is_some_and(|sig| alg.verify(alt_key, payload, sig))
+    };
+
+    Ok(match policy {
+        DualPolicy::RequireBoth => legacy_ok() && alt_ok(),
+        DualPolicy::RequireEither => legacy_ok() || alt_ok(),
+        DualPolicy::PreferNewFallbackOld => match dual_sig.alt_sig {
+            Some(_) => alt_ok(),
+            None => legacy_ok(),
+        },
+    })
+}
+
+fn check_alg(expected: SigAlgorithm, actual: SigAlgorithm) -> Result<(), CryptoError> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(CryptoError::AlgorithmMismatch { expected, actual })
+    }
+}
+
+/// Sign a payload with both keys, returning the CBOR envelope (Python binding).
+///
+/// `alg` is `"hmac-sha256"` or `"hmac-sha512"`.
+#[pyfunction]
+#[pyo3(name = "sign_dual")]
+pub(crate) fn py_sign_dual<'py>(
+    py: Python<'py>,
+    hmac_key: Vec<u8>,
+    alt_key: Vec<u8>,
+    alg: &str,
+    payload: Vec<u8>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let alg = alg.parse()?;
+    let dual = sign_dual(&hmac_key, &alt_key, alg, &payload);
+    Ok(PyBytes::new_bound(py, &dual.to_cbor()))
+}
+
+/// Verify a CBOR dual-signature envelope (Python binding).
+///
+/// `policy` is `"require_both"`, `"require_either"` or
+/// `"prefer_new_fallback_old"`. Raises `ValueError` on a malformed envelope,
+/// unknown names or mismatched algorithm tags.
+#[pyfunction]
+#[pyo3(name = "verify_dual")]
+pub(crate) fn py_verify_dual(
+    policy: &str,
+    hmac_key: Vec<u8>,
+    alt_key: Vec<u8>,
+    alg: &str,
+    payload: Vec<u8>,
+    envelope: Vec<u8>,
+) -> PyResult<bool> {
+    let dual = DualSignature::from_cbor(&envelope)?;
+    Ok(verify_dual(
+        policy.parse()?,
+        &hmac_key,
+        &alt_key,
+        alg.parse()?,
+        &payload,
+        &dual,
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen;
+
+    const PAYLOAD: &[u8] = b"migrate me";
+    const ALG: SigAlgorithm = SigAlgorithm::HmacSha512;
+
+    struct Keys {
+        hmac: Vec<u8>,
+        alt: Vec<u8>,
+    }
+
+    fn keys() -> Keys {
+        Keys {
+            hmac: keygen(1),
+            alt: keygen(2),
+        }
+    }
+
+    fn verify_with(policy: DualPolicy, dual: &DualSignature) -> bool {
+        let keys = keys();
+        verify_dual(policy, &keys.hmac, &keys.alt, ALG, PAYLOAD, dual).unwrap()
+    }
+
+    /// A dual signature with a good legacy signature and a bad new one.
+    fn good_legacy_bad_alt() -> DualSignature {
+        let keys = keys();
+        let mut dual = sign_dual(&keys.hmac, &keys.alt, ALG, PAYLOAD);
+        dual.alt_sig.as_mut().unwrap()[0] ^= 0xff;
+        dual
+    }
+
+    /// A dual signature with a bad legacy signature and a good new one.
+    fn bad_legacy_good_alt() -> DualSignature {
+        let keys = keys();
+        let mut dual = sign_dual(&keys.hmac, &keys.alt, ALG, PAYLOAD);
+        dual.legacy_sig.as_mut().unwrap()[0] ^= 0xff;
+        dual
+    }
+
+    #[test]
+    fn test_sign_dual_verifies_under_every_policy() {
+        let keys = keys();
+        let dual = sign_dual(&keys.hmac, &keys.alt, ALG, PAYLOAD);
+        assert_eq!(dual.alt_sig.as_ref().unwrap().len(), 64);
+        for policy in [
+            DualPolicy::RequireBoth,
+            DualPolicy::RequireEither,
+            DualPolicy::PreferNewFallbackOld,
+        ] {
+            assert!(verify_with(policy, &dual), "{policy:?}");
+        }
+    }
+
+    #[test]
+    fn test_require_both_rejects_one_bad_signature() {
+        assert!(!verify_with(
+            DualPolicy::RequireBoth,
+            &good_legacy_bad_alt()
+        ));
+        assert!(!verify_with(
+            DualPolicy::RequireBoth,
+            &bad_legacy_good_alt()
+        ));
+    }
+
+    #[test]
+    fn test_require_either_accepts_one_good_signature() {
+        assert!(verify_with(
+            DualPolicy::RequireEither,
+            &good_legacy_bad_alt()
+        ));
+        assert!(verify_with(
+            DualPolicy::RequireEither,
+            &bad_legacy_good_alt()
+        ));
+    }
+
+    #[test]
+    fn test_prefer_new_uses_new_signature_when_present() {
+        assert!(!verify_with(
+            DualPolicy::PreferNewFallbackOld,
+            &good_legacy_bad_alt()
+        ));
+        assert!(verify_with(
+            DualPolicy::PreferNewFallbackOld,
+            &bad_legacy_good_alt()
+        ));
+    }
+
+    #[test]
+    fn test_missing_signatures_follow_policy() {
+        let keys = keys();
+        let mut legacy_only = sign_dual(&keys.hmac, &keys.alt, ALG, PAYLOAD);
+        legacy_only.alt_sig = None;
+        assert!(!verify_with(DualPolicy::RequireBoth, &legacy_only));
+        assert!(verify_with(DualPolicy::RequireEither, &legacy_only));
+        assert!(verify_with(DualPolicy::PreferNewFallbackOld, &legacy_only));
+
+        let mut neither = legacy_only;
+        neither.legacy_sig = None;
+        assert!(!verify_with(DualPolicy::RequireEither, &neither));
+        assert!(!verify_with(DualPolicy::PreferNewFallbackOld, &neither));
+    }
+
+    #[test]
+    fn test_mismatched_algorithm_tag_errors() {
+        let keys = keys();
+        let mut dual = sign_dual(&keys.hmac, &keys.alt, ALG, PAYLOAD);
+        dual.alt_alg = SigAlgorithm::HmacSha256;
+        assert_eq!(
+            verify_dual(
+                DualPolicy::RequireEither,
+                &keys.hmac,
+                &keys.alt,
+                ALG,
+                PAYLOAD,
+                &dual
+            ),
+            Err(CryptoError::AlgorithmMismatch {
+                expected: SigAlgorithm::HmacSha512,
+                actual: SigAlgorithm::HmacSha256,
+            })
+        );
+
+        let mut dual = sign_dual(&keys.hmac, &keys.alt, ALG, PAYLOAD);
+        dual.legacy_alg = SigAlgorithm::HmacSha512;
+        assert!(matches!(
+            verify_dual(
+                DualPolicy::RequireEither,
+                &keys.hmac,
+                &keys.alt,
+                ALG,
+                PAYLOAD,
+                &dual
+            ),
+            Err(CryptoError::AlgorithmMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_cbor_roundtrip() {
+        let keys = keys();
+        let mut dual = sign_dual(&keys.hmac, &keys.alt, ALG, PAYLOAD);
+        assert_eq!(DualSignature::from_cbor(&dual.to_cbor()).unwrap(), dual);
+
+        dual.legacy_sig = None;
+        assert_eq!(DualSignature::from_cbor(&dual.to_cbor()).unwrap(), dual);
+    }
+
+    #[test]
+    fn test_from_cbor_rejects_garbage() {
+        assert!(matches!(
+            DualSignature::from_cbor(b"not cbor"),
+            Err(CryptoError::InvalidEncoding(_))
+        ));
+    }
+
+    #[test]
+    fn test_names_parse() {
+        assert_eq!("hmac-sha512".parse(), Ok(SigAlgorithm::HmacSha512));
+        assert_eq!(
+            "require_both".parse::<DualPolicy>(),
+            Ok(DualPolicy::RequireBoth)
+        );
+        assert_eq!(
+            "rsa".parse::<SigAlgorithm>(),
+            Err(CryptoError::Unsupported("rsa".to_string()))
+        );
+    }
+}
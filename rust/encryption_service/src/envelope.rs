@@ -0,0 +1,208 @@
+//! Versioned signature envelopes.
+//!
+//! Bare signatures carry no indication of how they were produced, which makes
+//! algorithm migration impossible. An envelope prefixes the signature with a
+//! magic marker and a version byte:
+//!
+//! ```text
+//! +------+------+---------+----------------------+
+//! | 'T'  | 'W'  | version | signature (32 bytes) |
+//! +------+------+---------+----------------------+
+//! ```
+//!
+//! Legacy bare signatures are exactly [`SIG_SIZE`] bytes long. Because an
+//! envelope is always longer than that, a 32-byte blob is unambiguously
+//! treated as legacy.
+
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::{sign, verify, CryptoError, SIG_SIZE};
+
+/// Magic marker identifying a signature envelope.
+pub const ENVELOPE_MAGIC: [u8; 2] = *b"TW";
+
+/// Envelope version for HMAC-SHA256 signatures.
+pub const ENVELOPE_V1: u8 = 1;
+
+/// Length of the envelope header (magic + version).
+const HEADER_SIZE: usize = ENVELOPE_MAGIC.len() + 1;
+
+/// Sign a payload and wrap the signature in a versioned envelope.
+///
+/// # Arguments
+/// * `key` - The signing key
+/// * `payload` - The data to sign
+///
+/// # Returns
+/// A `HEADER_SIZE + SIG_SIZE` byte envelope
+pub fn sign_envelope(key: &[u8], payload: &[u8]) -> Vec<u8> {
+    wrap_v1(&sign(key, payload))
+}
+
+/// Verify either a bare legacy signature or a versioned envelope.
+///
+/// A blob of exactly [`SIG_SIZE`] bytes is always treated as a legacy
+/// signature. Anything else must be a well-formed envelope of a known
+/// version; unknown versions and malformed envelopes fail verification.
+///
+/// # Arguments
+/// * `key` - The verification key
+/// * `payload` - The data that was signed
+/// * `sig` - A legacy signature or an envelope
+///
+/// # Returns
+/// `true` if the signature is valid, `false` otherwise
+pub fn verify_compat(key: &[u8], payload: &[u8], sig: &[u8]) -> bool {
+    if sig.len() == SIG_SIZE {
+        return verify(key, payload, sig);
+    }
+    match sig.split_first_chunk::<HEADER_SIZE>() {
+        Some((&[m0, m1, ENVELOPE_V1], inner))
+            if [m0, m1] == ENVELOPE_MAGIC && inner.len() == SIG_SIZE =>
+        {
+            verify(key, payload, inner)
+        }
+        _ => false,
+    }
+}
+
+/// Re-emit a legacy signature in envelope form.
+///
+/// The legacy signature is verified first so that invalid data is never
+/// laundered into the new format.
+///
+/// # Arguments
+/// * `key` - The verification key
+/// * `payload` - The data that was signed
+/// * `legacy_sig` - A bare [`SIG_SIZE`]-byte signature
+///
+/// # Returns
+/// * `Ok(Vec<u8>)` - The equivalent v1 envelope
+/// * `Err(CryptoError)` - If the signature has the wrong length or fails verification
+pub fn migrate_signature(
+    key: &[u8],
+    payload: &[u8],
+    legacy_sig: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    if legacy_sig.len() != SIG_SIZE {
+        return Err(CryptoError::InvalidLength {
+            expected: SIG_SIZE,
+            actual: legacy_sig.len(),
+        });
+    }
+    if !verify(key, payload, legacy_sig) {
+        return Err(CryptoError::VerificationFailed);
+    }
+    Ok(wrap_v1(legacy_sig))
+}
+
+fn wrap_v1(sig: &[u8]) -> Vec<u8> {
+    let mut envelope = Vec::with_capacity(HEADER_SIZE + sig.len());
+    envelope.extend_from_slice(&ENVELOPE_MAGIC);
+    envelope.push(ENVELOPE_V1);
+    envelope.extend_from_slice(sig);
+    envelope
+}
+
+/// Sign a payload into a versioned envelope (Python binding).
+#[pyfunction]
+#[pyo3(name = "sign_envelope")]
+pub(crate) fn py_sign_envelope<'py>(
+    py: Python<'py>,
+    key: Vec<u8>,
+    payload: Vec<u8>,
+) -> Bound<'py, PyBytes> {
+    PyBytes::new_bound(py, &sign_envelope(&key, &payload))
+}
+
+/// Verify a legacy signature or envelope (Python binding).
+#[pyfunction]
+#[pyo3(name = "verify_compat")]
+pub(crate) fn py_verify_compat(key: Vec<u8>, payload: Vec<u8>, sig: Vec<u8>) -> bool {
+    verify_compat(&key, &payload, &sig)
+}
+
+/// Migrate a legacy signature to envelope form (Python binding).
+///
+/// Raises `ValueError` if the legacy signature does not verify.
+#[pyfunction]
+#[pyo3(name = "migrate_signature")]
+pub(crate) fn py_migrate_signature<'py>(
+    py: Python<'py>,
+    key: Vec<u8>,
+    payload: Vec<u8>,
+    legacy_sig: Vec<u8>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let envelope = migrate_signature(&key, &payload, &legacy_sig)?;
+    Ok(PyBytes::new_bound(py, &envelope))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen;
+
+    const PAYLOAD: &[u8] = b"hello deterministic world";
+
+    #[test]
+    fn test_verify_compat_accepts_legacy_signature() {
+        let key = keygen(42);
+        let legacy = sign(&key, PAYLOAD);
+        assert_eq!(legacy.len(), SIG_SIZE);
+        assert!(verify_compat(&key, PAYLOAD, &legacy));
+    }
+
+    #[test]
+    fn test_verify_compat_accepts_envelope() {
+        let key = keygen(42);
+        let envelope = sign_envelope(&key, PAYLOAD);
+        assert_eq!(envelope.len(), HEADER_SIZE + SIG_SIZE);
+        assert_eq!(&envelope[..2], &ENVELOPE_MAGIC);
+        assert_eq!(envelope[2], ENVELOPE_V1);
+        assert!(verify_compat(&key, PAYLOAD, &envelope));
+        assert!(!verify_compat(&keygen(43), PAYLOAD, &envelope));
+    }
+
+    #[test]
+    fn test_verify_compat_rejects_unknown_version() {
+        let key = keygen(42);
+        let mut envelope = sign_envelope(&key, PAYLOAD);
+        envelope[2] = 0xff;
+        assert!(!verify_compat(&key, PAYLOAD, &envelope));
+        assert!(!verify_compat(&key, PAYLOAD, &envelope[..HEADER_SIZE]));
+    }
+
+    #[test]
+    fn test_migrate_signature_roundtrip() {
+        let key = keygen(42);
+        let legacy = sign(&key, PAYLOAD);
+        let envelope = migrate_signature(&key, PAYLOAD, &legacy).unwrap();
+        assert_eq!(envelope, sign_envelope(&key, PAYLOAD));
+        assert!(verify_compat(&key, PAYLOAD, &envelope));
+    }
+
+    #[test]
+    fn test_migrate_tampered_signature_fails() {
+        let key = keygen(42);
+        let mut legacy = sign(&key, PAYLOAD);
+        legacy[0] ^= 0xff;
+        assert_eq!(
+            migrate_signature(&key, PAYLOAD, &legacy),
+            Err(CryptoError::VerificationFailed)
+        );
+    }
+
+    #[test]
+    fn test_migrate_rejects_non_legacy_length() {
+        let key = keygen(42);
+        let envelope = sign_envelope(&key, PAYLOAD);
+        assert_eq!(
+            migrate_signature(&key, PAYLOAD, &envelope),
+            Err(CryptoError::InvalidLength {
+                expected: SIG_SIZE,
+                actual: HEADER_SIZE + SIG_SIZE,
+            })
+        );
+    }
+}
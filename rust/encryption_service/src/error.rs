@@ -0,0 +1,108 @@
+//! Error type shared by the fallible encryption APIs.
+
+use std::fmt;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::PyErr;
+
+use crate::SigAlgorithm;
+
+/// Errors returned by the encryption service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CryptoError {
+    /// A signature or envelope had an unexpected length.
+    InvalidLength {
+        /// Expected length in bytes
+        expected: usize,
+        /// Actual length in bytes
+        actual: usize,
+    },
+    /// A signature did not verify against the given key and payload.
+    VerificationFailed,
+    /// A usage-limited key has already signed its maximum number of messages.
+    KeyUsageExceeded {
+        /// The key's usage limit
+        limit: u64,
+    },
+    /// Every key in a key ring has been exhausted.
+    KeyRingExhausted,
+    /// A signature was tagged with a different algorithm than expected.
+    AlgorithmMismatch {
+        /// Algorithm the verifier expected
+        expected: SigAlgorithm,
+        /// Algorithm the signature was tagged with
+        actual: SigAlgorithm,
+    },
+    /// Encoded signature data could not be decoded.
+    InvalidEncoding(String),
+    /// A ciphertext failed authentication (tampered, or wrong key or nonce).
+    DecryptionFailed,
+    /// A public key was all zeros or a low-order point.
+    InvalidPublicKey,
+    /// A key label is already registered.
+    DuplicateLabel(String),
+    /// No key is registered under a label (or key ring version).
+    UnknownKey(String),
+    /// An algorithm or policy name is not recognised.
+    Unsupported(String),
+    /// A key source such as an environment variable is not set.
+    MissingKey(String),
+    /// A requested MAC truncation length is outside the allowed range.
+    InvalidTruncation {
+        /// Requested length in bytes
+        len: usize,
+        /// Minimum allowed length
+        min: usize,
+        /// Maximum allowed length
+        max: usize,
+    },
+    /// A key-stretching iteration count is below the allowed minimum.
+    TooFewIterations {
+        /// Requested iteration count
+        iterations: u32,
+        /// Minimum allowed count
+        min: u32,
+    },
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CryptoError::InvalidLength { expected, actual } => {
+                write!(f, "invalid length: expected {expected} bytes, got {actual}")
+            }
+            CryptoError::VerificationFailed => write!(f, "signature verification failed"),
+            CryptoError::KeyUsageExceeded { limit } => {
+                write!(f, "key usage limit of {limit} signatures exceeded")
+            }
+            CryptoError::KeyRingExhausted => write!(f, "no key with remaining uses in key ring"),
+            CryptoError::AlgorithmMismatch { expected, actual } => {
+                write!(f, "algorithm mismatch: expected {expected}, got {actual}")
+            }
+            CryptoError::InvalidEncoding(msg) => write!(f, "invalid encoding: {msg}"),
+            CryptoError::DecryptionFailed => write!(f, "decryption failed"),
+            CryptoError::InvalidPublicKey => write!(f, "invalid public key"),
+            CryptoError::DuplicateLabel(label) => write!(f, "key label {label:?} already exists"),
+            CryptoError::UnknownKey(label) => write!(f, "no key registered under {label:?}"),
+            CryptoError::Unsupported(name) => write!(f, "unsupported algorithm or policy: {name}"),
+            CryptoError::MissingKey(source) => write!(f, "no key found in {source}"),
+            CryptoError::InvalidTruncation { len, min, max } => {
+                write!(f, "MAC truncation length {len} outside {min}..={max} bytes")
+            }
+            CryptoError::TooFewIterations { iterations, min } => {
+                write!(
+                    f,
+                    "{iterations} PBKDF2 iterations is below the minimum of {min}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for CryptoError {}
+
+impl From<CryptoError> for PyErr {
+    fn from(err: CryptoError) -> PyErr {
+        PyValueError::new_err(err.to_string())
+    }
+}
@@ -0,0 +1,72 @@
+//! Entry points for fuzzing.
+//!
+//! A fuzzer hands over one arbitrary byte string; [`fuzz_verify`] carves it
+//! into the inputs of [`verify`] so a `cargo fuzz` target only has to call
+//! it. The split is deterministic, so any crash reproduces from its input.
+
+use crate::verify;
+
+/// Split `data` into a key, payload and signature and [`verify`] them.
+///
+/// Layout: `data[0]` is the key length and `data[1]` the signature length,
+/// each capped to the bytes left; then come the key, the signature and,
+/// taking the rest, the payload. Missing bytes read as empty segments, so
+/// every input (the empty one included) maps to some call.
+///
+/// # Returns
+/// Whatever [`verify`] returns; this never panics.
+pub fn fuzz_verify(data: &[u8]) -> bool {
+    let (key_len, sig_len, rest) = match data {
+        [] => (0, 0, data),
+        [key_len] => (*key_len, 0, &data[1..]),
+        [key_len, sig_len, rest @ ..] => (*key_len, *sig_len, rest),
+    };
+    let (key, rest) = rest.split_at(usize::from(key_len).min(rest.len()));
+    let (sig, payload) = rest.split_at(usize::from(sig_len).min(rest.len()));
+    verify(key, payload, sig)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{keygen, sign};
+
+    #[test]
+    fn test_pathological_inputs_do_not_panic() {
+        let huge = vec![0xAB; 1 << 20];
+        let inputs: [&[u8]; 7] = [
+            &[],
+            &[0],
+            &[0, 0],
+            &[255, 255],
+            &[0, 0, 1, 2, 3],
+            &[4, 255, 1, 2],
+            &huge,
+        ];
+        for input in inputs {
+            assert!(!fuzz_verify(input));
+        }
+
+        // Empty keys and signatures of every length up to a full MAC.
+        for sig_len in 0..=40 {
+            assert!(!verify(&[], b"payload", &vec![0; sig_len]));
+        }
+        assert!(!verify(&keygen(1), &huge, &[]));
+    }
+
+    #[test]
+    fn test_split_reaches_valid_signatures() {
+        let key = keygen(42);
+        let sig = sign(&key, b"payload");
+        let mut data = vec![key.len() as u8, sig.len() as u8];
+        data.extend_from_slice(&key);
+        data.extend_from_slice(&sig);
+        data.extend_from_slice(b"payload");
+        assert!(fuzz_verify(&data));
+
+        let empty_key_sig = sign(&[], &[]);
+        let mut data = vec![0, empty_key_sig.len() as u8];
+        data.extend_from_slice(&empty_key_sig);
+        assert!(fuzz_verify(&data));
+    }
+}
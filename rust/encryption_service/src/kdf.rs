@@ -0,0 +1,321 @@
+//! Key derivation helpers.
+//!
+//! All derived keys in this crate go through [`hkdf_sha256`] so that every
+//! use has its own `info` label and keys for different purposes can never
+//! collide. The exception is [`keygen_from_passphrase`], which stretches a
+//! low-entropy passphrase with PBKDF2 instead.
+
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use sha2::Sha256;
+
+use crate::{CryptoError, KEY_SIZE};
+
+/// Maximum output length of [`hkdf_sha256`] and [`prf`] (255 * 32 bytes).
+pub const MAX_KDF_OUTPUT: usize = 255 * 32;
+
+/// Fewest PBKDF2 iterations [`keygen_from_passphrase`] accepts.
+pub const MIN_PBKDF2_ITERATIONS: u32 = 10_000;
+
+/// Derive `len` bytes from `ikm` with HKDF-SHA256 (RFC 5869).
+///
+/// # Arguments
+/// * `ikm` - Input key material
+/// * `salt` - Optional salt; an empty slice means no salt
+/// * `info` - Context label binding the output to its purpose
+/// * `len` - Output length in bytes (at most [`MAX_KDF_OUTPUT`])
+///
+/// # Returns
+/// The derived key material
+///
+/// # Panics
+/// If `len` exceeds the HKDF-SHA256 output limit of 8160 bytes.
+pub fn hkdf_sha256(ikm: &[u8], salt: &[u8], info: &[u8], len: usize) -> Vec<u8> {
+    let salt = (!salt.is_empty()).then_some(salt);
+    let mut okm = vec![0u8; len];
+    Hkdf::<Sha256>::new(salt, ikm)
+        .expand(info, &mut okm)
+        .expect("HKDF output length is within the RFC 5869 limit");
+    okm
+}
+
+/// Keyed pseudo-random function with arbitrary-length output.
+///
+/// Computes `HMAC-SHA256(key, input)` and stretches it to `out_len` bytes
+/// with HKDF-Expand, so the output is deterministic for a given key and
+/// input and unpredictable without the key. Suitable for per-order jitter
+/// and sampling decisions; not a substitute for a CSPRNG when secrets are
+/// needed from random inputs.
+///
+/// # Arguments
+/// * `key` - PRF key
+/// * `input` - Input to evaluate the PRF on
+/// * `out_len` - Output length in bytes
+///
+/// # Returns
+/// `out_len` pseudo-random bytes
+///
+/// # Panics
+/// If `out_len` exceeds [`MAX_KDF_OUTPUT`].
+pub fn prf(key: &[u8], input: &[u8], out_len: usize) -> Vec<u8> {
+    // HKDF-Extract with `key` as the salt is exactly HMAC-SHA256(key, input).
+    let mut out = vec![0u8; out_len];
+    Hkdf::<Sha256>::new(Some(key), input)
+        .expand(b"tinywindow prf", &mut out)
+        .expect("PRF output length is within the RFC 5869 limit");
+    out
+}
+
+/// Evaluate the keyed PRF (Python binding).
+///
+/// Raises `ValueError` if `out_len` exceeds the HKDF output limit.
+#[pyfunction]
+#[pyo3(name = "prf")]
+pub(crate) fn py_prf<'py>(
+    py: Python<'py>,
+    key: Vec<u8>,
+    input: Vec<u8>,
+    out_len: usize,
+) -> PyResult<Bound<'py, PyBytes>> {
+    if out_len > MAX_KDF_OUTPUT {
+        return Err(PyValueError::new_err(format!(
+            "out_len {out_len} exceeds the maximum of {MAX_KDF_OUTPUT} bytes"
+        )));
+    }
+    Ok(PyBytes::new_bound(py, &prf(&key, &input, out_len)))
+}
+
+/// Derive a key from an operator passphrase with PBKDF2-HMAC-SHA256
+/// (RFC 8018).
+///
+/// Each iteration is one more HMAC an attacker must compute per guess, so
+/// use as many as the key's startup budget allows; OWASP recommends
+/// 600,000 for PBKDF2-HMAC-SHA256. The salt should be random, at least 16
+/// bytes, and stored alongside whatever the key protects.
+///
+/// # Arguments
+/// * `passphrase` - The operator's passphrase
+/// * `salt` - Per-deployment salt
+/// * `iterations` - Iteration count, at least [`MIN_PBKDF2_ITERATIONS`]
+///
+/// # Returns
+/// * `Ok(Vec<u8>)` - A 32-byte key
+/// * `Err(CryptoError::TooFewIterations)` - If `iterations` is below the minimum
+pub fn keygen_from_passphrase(
+    passphrase: &str,
+    salt: &[u8],
+    iterations: u32,
+) -> Result<Vec<u8>, CryptoError> {
+    if iterations < MIN_PBKDF2_ITERATIONS {
+        return Err(CryptoError::TooFewIterations {
+            iterations,
+            min: MIN_PBKDF2_ITERATIONS,
+        });
+    }
+    Ok(pbkdf2_sha256(passphrase.as_bytes(), salt, iterations))
+}
+
+/// The first (and, for a 32-byte key, only) PBKDF2-HMAC-SHA256 block.
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32) -> Vec<u8> {
+    let prf = Hmac::<Sha256>::new_from_slice(password).expect("HMAC can take key of any size");
+    let mut mac = prf.clone();
+    mac.update(salt);
+    mac.update(&1u32.to_be_bytes());
+    let mut u = mac.finalize().into_bytes();
+    let mut block = u;
+    for _ in 1..iterations {
+        let mut mac = prf.clone();
+        mac.update(&u);
+        u = mac.finalize().into_bytes();
+        for (out, byte) in block.iter_mut().zip(&u) {
+            *out ^= byte;
+        }
+    }
+    block.to_vec()
+}
+
+/// Derive a key from a passphrase (Python binding).
+///
+/// Releases the GIL while stretching. Raises `ValueError` if `iterations`
+/// is below the minimum.
+#[pyfunction]
+#[pyo3(name = "keygen_from_passphrase")]
+pub(crate) fn py_keygen_from_passphrase<'py>(
+    py: Python<'py>,
+    passphrase: &str,
+    salt: Vec<u8>,
+    iterations: u32,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let key = py.allow_threads(|| keygen_from_passphrase(passphrase, &salt, iterations))?;
+    Ok(PyBytes::new_bound(py, &key))
+}
+
+/// Derive the key for one rotation epoch from a root secret.
+///
+/// The HKDF `info` is the epoch as 8 big-endian bytes followed by
+/// `purpose`, so every `(epoch, purpose)` pair yields an independent key and
+/// re-deriving with the same inputs always gives the same key. Because the
+/// epoch has a fixed width, no two pairs produce the same `info`.
+///
+/// # Arguments
+/// * `root` - Root secret shared by all epochs
+/// * `epoch` - Rotation epoch number
+/// * `purpose` - What the key is for, e.g. `"order-signing"`
+///
+/// # Returns
+/// A 32-byte key
+pub fn derive_epoch_key(root: &[u8], epoch: u64, purpose: &str) -> Vec<u8> {
+    let mut info = Vec::with_capacity(8 + purpose.len());
+    info.extend_from_slice(&epoch.to_be_bytes());
+    info.extend_from_slice(purpose.as_bytes());
+    hkdf_sha256(root, b"", &info, KEY_SIZE)
+}
+
+/// Derive an epoch key from a root secret (Python binding).
+#[pyfunction]
+#[pyo3(name = "derive_epoch_key")]
+pub(crate) fn py_derive_epoch_key<'py>(
+    py: Python<'py>,
+    root: Vec<u8>,
+    epoch: u64,
+    purpose: &str,
+) -> Bound<'py, PyBytes> {
+    PyBytes::new_bound(py, &derive_epoch_key(&root, epoch, purpose))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unhex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_hkdf_rfc5869_case_1() {
+        let okm = hkdf_sha256(
+            &[0x0b; 22],
+            &unhex("000102030405060708090a0b0c"),
+            &unhex("f0f1f2f3f4f5f6f7f8f9"),
+            42,
+        );
+        assert_eq!(
+            okm,
+            unhex(
+                "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf\
+                 34007208d5b887185865"
+            )
+        );
+    }
+
+    #[test]
+    fn test_pbkdf2_known_answers() {
+        assert_eq!(
+            pbkdf2_sha256(b"passwd", b"salt", 1),
+            unhex("55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc")
+        );
+        assert_eq!(
+            pbkdf2_sha256(b"password", b"salt", 4096),
+            unhex("c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a")
+        );
+    }
+
+    #[test]
+    fn test_keygen_from_passphrase() {
+        let key = keygen_from_passphrase("correct horse", b"tinywindow", 10_000).unwrap();
+        assert_eq!(
+            key,
+            unhex("1c61c5401fefe7bbfe144d8b88d1c832f36f8eed2c44a207cc0a9bba574fe092")
+        );
+        assert_eq!(
+            key,
+            keygen_from_passphrase("correct horse", b"tinywindow", 10_000).unwrap()
+        );
+        assert_ne!(
+            key,
+            keygen_from_passphrase("correct horse", b"other salt", 10_000).unwrap()
+        );
+        assert_ne!(
+            key,
+            keygen_from_passphrase("correct horse", b"tinywindow", 10_001).unwrap()
+        );
+        assert_eq!(
+            keygen_from_passphrase("correct horse", b"tinywindow", 9_999),
+            Err(CryptoError::TooFewIterations {
+                iterations: 9_999,
+                min: MIN_PBKDF2_ITERATIONS,
+            })
+        );
+    }
+
+    #[test]
+    fn test_hkdf_info_separates_outputs() {
+        let a = hkdf_sha256(b"secret", b"", b"purpose-a", 32);
+        let b = hkdf_sha256(b"secret", b"", b"purpose-b", 32);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_derive_epoch_key_is_stable() {
+        let key = derive_epoch_key(b"root secret", 1, "order-signing");
+        assert_eq!(key.len(), KEY_SIZE);
+        assert_eq!(key, derive_epoch_key(b"root secret", 1, "order-signing"));
+    }
+
+    #[test]
+    fn test_derive_epoch_key_separates_epochs_and_purposes() {
+        let epoch_1 = derive_epoch_key(b"root secret", 1, "order-signing");
+        let epoch_2 = derive_epoch_key(b"root secret", 2, "order-signing");
+        assert_ne!(epoch_1, epoch_2);
+        assert_ne!(
+            epoch_1,
+            derive_epoch_key(b"root secret", 1, "audit-log"),
+            "purposes must be independent"
+        );
+        assert_ne!(epoch_1, derive_epoch_key(b"other root", 1, "order-signing"));
+    }
+
+    #[test]
+    fn test_prf_output_length_and_determinism() {
+        for out_len in [0, 1, 32, 100, MAX_KDF_OUTPUT] {
+            let out = prf(b"key", b"order-42", out_len);
+            assert_eq!(out.len(), out_len);
+            assert_eq!(out, prf(b"key", b"order-42", out_len));
+        }
+        assert_ne!(
+            prf(b"key", b"order-42", 32),
+            prf(b"other key", b"order-42", 32)
+        );
+    }
+
+    #[test]
+    fn test_prf_one_bit_input_change_changes_output() {
+        let input = b"order-42".to_vec();
+        let mut flipped = input.clone();
+        flipped[0] ^= 0x01;
+
+        let a = prf(b"key", &input, 64);
+        let b = prf(b"key", &flipped, 64);
+        let differing_bits: u32 = a.iter().zip(&b).map(|(x, y)| (x ^ y).count_ones()).sum();
+        // 512 output bits; an unrelated output differs in about half of them.
+        assert!(
+            (192..=320).contains(&differing_bits),
+            "{differing_bits} of 512 bits differ"
+        );
+    }
+
+    #[test]
+    fn test_prf_prk_is_hmac_of_input() {
+        use crate::sign;
+        let expected = Hkdf::<Sha256>::from_prk(&sign(b"key", b"order-42")).unwrap();
+        let mut out = [0u8; 40];
+        expected.expand(b"tinywindow prf", &mut out).unwrap();
+        assert_eq!(prf(b"key", b"order-42", 40), out);
+    }
+}
@@ -0,0 +1,220 @@
+//! Usage-limited keys and versioned key rings.
+//!
+//! Security policy caps how many messages a single MAC key may sign.
+//! [`CountedKey`] enforces that cap atomically, and [`KeyRing`] holds a
+//! sequence of versioned keys, rolling forward to the next version when the
+//! current one is exhausted. During rotation several versions stay live, so
+//! a ring can also sign under a chosen version and find the version a
+//! signature was made with.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{CryptoError, SecretKey};
+
+/// A [`SecretKey`] that may sign at most `limit` messages.
+#[derive(Debug)]
+pub struct CountedKey {
+    key: SecretKey,
+    used: AtomicU64,
+    limit: u64,
+}
+
+impl CountedKey {
+    /// Wrap `key` with a usage limit.
+    pub fn new(key: SecretKey, limit: u64) -> Self {
+        Self {
+            key,
+            used: AtomicU64::new(0),
+            limit,
+        }
+    }
+
+    /// Sign a payload, consuming one use of the key.
+    ///
+    /// Safe to call concurrently: exactly `limit` calls succeed in total.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<u8>)` - The signature
+    /// * `Err(CryptoError::KeyUsageExceeded)` - If the key is exhausted
+    pub fn sign_counted(&self, payload: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                (used < self.limit).then_some(used + 1)
+            })
+            .map_err(|_| CryptoError::KeyUsageExceeded { limit: self.limit })?;
+        Ok(self.key.sign(payload))
+    }
+
+    /// Number of signatures this key may still produce.
+    pub fn remaining(&self) -> u64 {
+        self.limit.saturating_sub(self.used.load(Ordering::Acquire))
+    }
+
+    /// Whether the key has no uses left.
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// The underlying key (e.g. for verification, which is not counted).
+    pub fn key(&self) -> &SecretKey {
+        &self.key
+    }
+}
+
+/// Versioned set of keys used during rotation.
+///
+/// The current signing key is the lowest version that still has uses left.
+#[derive(Debug, Default)]
+pub struct KeyRing {
+    keys: BTreeMap<u32, CountedKey>,
+}
+
+impl KeyRing {
+    /// Create an empty key ring.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a usage-limited key under `version`, replacing any existing one.
+    pub fn add_counted_key(&mut self, version: u32, key: CountedKey) {
+        self.keys.insert(version, key);
+    }
+
+    /// Add a key without a usage limit under `version`, replacing any
+    /// existing one.
+    pub fn add_key(&mut self, version: u32, key: SecretKey) {
+        self.add_counted_key(version, CountedKey::new(key, u64::MAX));
+    }
+
+    /// Version of the key [`sign_current`](Self::sign_current) would use next.
+    pub fn current_version(&self) -> Option<u32> {
+        self.keys
+            .iter()
+            .find(|(_, key)| !key.is_exhausted())
+            .map(|(version, _)| *version)
+    }
+
+    /// Sign with the current key, rolling to the next version when the
+    /// current one is exhausted.
+    ///
+    /// # Returns
+    /// * `Ok((version, signature))` - The key version used and the signature
+    /// * `Err(CryptoError::KeyRingExhausted)` - If every key is used up
+    pub fn sign_current(&self, payload: &[u8]) -> Result<(u32, Vec<u8>), CryptoError> {
+        for (version, key) in &self.keys {
+            match key.sign_counted(payload) {
+                Ok(sig) => return Ok((*version, sig)),
+                Err(CryptoError::KeyUsageExceeded { .. }) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        Err(CryptoError::KeyRingExhausted)
+    }
+
+    /// Sign with the key registered under `version`, consuming one of its uses.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<u8>)` - The signature
+    /// * `Err(CryptoError::UnknownKey)` - If no key has that version
+    /// * `Err(CryptoError::KeyUsageExceeded)` - If that key is exhausted
+    pub fn sign_with_version(&self, version: u32, payload: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.keys
+            .get(&version)
+            .ok_or_else(|| CryptoError::UnknownKey(version.to_string()))?
+            .sign_counted(payload)
+    }
+
+    /// Find the key version `sig` was made with.
+    ///
+    /// Tries every key, exhausted ones included, from the lowest version up.
+    ///
+    /// # Returns
+    /// * `Some(version)` - The first version under which `sig` verifies
+    /// * `None` - If no key in the ring verifies it
+    pub fn verify_any(&self, payload: &[u8], sig: &[u8]) -> Option<u32> {
+        self.keys
+            .iter()
+            .find(|(_, key)| key.key().verify(payload, sig))
+            .map(|(version, _)| *version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_counted_key_enforces_limit() {
+        let key = CountedKey::new(SecretKey::from_seed(42), 2);
+        assert_eq!(key.remaining(), 2);
+        assert!(key.sign_counted(b"one").is_ok());
+        assert!(key.sign_counted(b"two").is_ok());
+        assert_eq!(
+            key.sign_counted(b"three"),
+            Err(CryptoError::KeyUsageExceeded { limit: 2 })
+        );
+        assert_eq!(key.remaining(), 0);
+        assert!(key.is_exhausted());
+    }
+
+    #[test]
+    fn test_counted_key_exact_limit_under_concurrency() {
+        const LIMIT: u64 = 100;
+        let key = Arc::new(CountedKey::new(SecretKey::from_seed(42), LIMIT));
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let key = Arc::clone(&key);
+                thread::spawn(move || {
+                    (0..50)
+                        .filter(|_| key.sign_counted(b"payload").is_ok())
+                        .count() as u64
+                })
+            })
+            .collect();
+        let successes: u64 = handles.into_iter().map(|h| h.join().unwrap()).sum();
+
+        assert_eq!(successes, LIMIT);
+        assert_eq!(key.remaining(), 0);
+    }
+
+    #[test]
+    fn test_key_ring_rolls_to_next_key() {
+        let mut ring = KeyRing::new();
+        ring.add_counted_key(1, CountedKey::new(SecretKey::from_seed(1), 1));
+        ring.add_counted_key(2, CountedKey::new(SecretKey::from_seed(2), 1));
+
+        let (v1, sig1) = ring.sign_current(b"payload").unwrap();
+        assert_eq!(v1, 1);
+        assert!(SecretKey::from_seed(1).verify(b"payload", &sig1));
+        assert_eq!(ring.current_version(), Some(2));
+
+        let (v2, sig2) = ring.sign_current(b"payload").unwrap();
+        assert_eq!(v2, 2);
+        assert!(SecretKey::from_seed(2).verify(b"payload", &sig2));
+
+        assert_eq!(
+            ring.sign_current(b"payload"),
+            Err(CryptoError::KeyRingExhausted)
+        );
+        assert_eq!(ring.current_version(), None);
+    }
+
+    #[test]
+    fn test_sign_and_verify_by_version() {
+        let mut ring = KeyRing::new();
+        ring.add_key(1, SecretKey::from_seed(1));
+        ring.add_key(2, SecretKey::from_seed(2));
+
+        let sig = ring.sign_with_version(2, b"payload").unwrap();
+        assert_eq!(ring.verify_any(b"payload", &sig), Some(2));
+        assert_eq!(ring.verify_any(b"tampered", &sig), None);
+        assert_eq!(
+            ring.sign_with_version(3, b"payload"),
+            Err(CryptoError::UnknownKey("3".to_string()))
+        );
+    }
+}
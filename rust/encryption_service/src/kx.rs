@@ -0,0 +1,220 @@
+//! X25519 key agreement (requires the `kx` feature).
+//!
+//! Groundwork for encrypted transport between the exec adapter and a venue
+//! gateway: each side derives a keypair, computes the shared secret from its
+//! own secret and the peer's public key, then splits it into directional
+//! session keys. The client's send key is the server's receive key and vice
+//! versa, so the two directions never share a key.
+
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::kdf::hkdf_sha256;
+use crate::{keygen_ctx, CryptoError};
+
+/// Size of X25519 secrets, public keys and shared secrets in bytes.
+pub const KX_KEY_SIZE: usize = 32;
+
+/// HKDF label for keys protecting client-to-server traffic.
+const CLIENT_TO_SERVER: &[u8] = b"tinywindow kx client->server";
+
+/// HKDF label for keys protecting server-to-client traffic.
+const SERVER_TO_CLIENT: &[u8] = b"tinywindow kx server->client";
+
+/// Which end of the connection is deriving session keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// The side that initiated the connection (the exec adapter).
+    Client,
+    /// The side that accepted it (the venue gateway).
+    Server,
+}
+
+/// Deterministically derive an X25519 keypair from a seed.
+///
+/// # Arguments
+/// * `seed` - Seed value, domain-separated from signing keys
+///
+/// # Returns
+/// `(secret, public)`, each [`KX_KEY_SIZE`] bytes
+pub fn kx_keypair_from_seed(seed: u64) -> (Vec<u8>, Vec<u8>) {
+    let bytes: [u8; KX_KEY_SIZE] = keygen_ctx(seed, "x25519")
+        .try_into()
+        .expect("keygen_ctx returns KX_KEY_SIZE bytes");
+    let secret = StaticSecret::from(bytes);
+    let public = PublicKey::from(&secret);
+    (secret.to_bytes().to_vec(), public.as_bytes().to_vec())
+}
+
+/// Compute the X25519 shared secret.
+///
+/// # Arguments
+/// * `my_secret` - Our secret key
+/// * `their_public` - The peer's public key
+///
+/// # Returns
+/// * `Ok(Vec<u8>)` - The [`KX_KEY_SIZE`]-byte shared secret
+/// * `Err(CryptoError::InvalidLength)` - If either key is not [`KX_KEY_SIZE`] bytes
+/// * `Err(CryptoError::InvalidPublicKey)` - If the public key is all zeros or a
+///   low-order point that would yield a predictable shared secret
+pub fn kx_shared_secret(my_secret: &[u8], their_public: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let secret = StaticSecret::from(to_key_array(my_secret)?);
+    let public = to_key_array(their_public)?;
+    if public.iter().all(|&b| b == 0) {
+        return Err(CryptoError::InvalidPublicKey);
+    }
+    let shared = secret.diffie_hellman(&PublicKey::from(public));
+    if !shared.was_contributory() {
+        return Err(CryptoError::InvalidPublicKey);
+    }
+    Ok(shared.as_bytes().to_vec())
+}
+
+/// Split a shared secret into directional session keys.
+///
+/// # Arguments
+/// * `shared` - Output of [`kx_shared_secret`]
+/// * `role` - Which side of the connection we are
+///
+/// # Returns
+/// `(send_key, recv_key)`; the client's send key equals the server's receive key
+pub fn kx_session_keys(shared: &[u8], role: Role) -> (Vec<u8>, Vec<u8>) {
+    let c2s = hkdf_sha256(shared, b"", CLIENT_TO_SERVER, KX_KEY_SIZE);
+    let s2c = hkdf_sha256(shared, b"", SERVER_TO_CLIENT, KX_KEY_SIZE);
+    match role {
+        Role::Client => (c2s, s2c),
+        Role::Server => (s2c, c2s),
+    }
+}
+
+fn to_key_array(bytes: &[u8]) -> Result<[u8; KX_KEY_SIZE], CryptoError> {
+    bytes.try_into().map_err(|_| CryptoError::InvalidLength {
+        expected: KX_KEY_SIZE,
+        actual: bytes.len(),
+    })
+}
+
+/// Derive an X25519 keypair from a seed (Python binding).
+#[pyfunction]
+#[pyo3(name = "kx_keypair_from_seed")]
+pub(crate) fn py_kx_keypair_from_seed(
+    py: Python<'_>,
+    seed: u64,
+) -> (Bound<'_, PyBytes>, Bound<'_, PyBytes>) {
+    let (secret, public) = kx_keypair_from_seed(seed);
+    (
+        PyBytes::new_bound(py, &secret),
+        PyBytes::new_bound(py, &public),
+    )
+}
+
+/// Compute the X25519 shared secret (Python binding).
+///
+/// Raises `ValueError` for malformed or low-order public keys.
+#[pyfunction]
+#[pyo3(name = "kx_shared_secret")]
+pub(crate) fn py_kx_shared_secret<'py>(
+    py: Python<'py>,
+    my_secret: Vec<u8>,
+    their_public: Vec<u8>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let shared = kx_shared_secret(&my_secret, &their_public)?;
+    Ok(PyBytes::new_bound(py, &shared))
+}
+
+/// Derive `(send_key, recv_key)` for `role` (`"client"` or `"server"`) (Python binding).
+#[pyfunction]
+#[pyo3(name = "kx_session_keys")]
+pub(crate) fn py_kx_session_keys<'py>(
+    py: Python<'py>,
+    shared: Vec<u8>,
+    role: &str,
+) -> PyResult<(Bound<'py, PyBytes>, Bound<'py, PyBytes>)> {
+    let role = match role {
+        "client" => Role::Client,
+        "server" => Role::Server,
+        other => return Err(CryptoError::Unsupported(other.to_string()).into()),
+    };
+    let (send, recv) = kx_session_keys(&shared, role);
+    Ok((PyBytes::new_bound(py, &send), PyBytes::new_bound(py, &recv)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keypair_is_deterministic() {
+        assert_eq!(kx_keypair_from_seed(7), kx_keypair_from_seed(7));
+        assert_ne!(kx_keypair_from_seed(7).1, kx_keypair_from_seed(8).1);
+    }
+
+    #[test]
+    fn test_both_sides_agree_on_shared_secret() {
+        let (client_secret, client_public) = kx_keypair_from_seed(1);
+        let (server_secret, server_public) = kx_keypair_from_seed(2);
+
+        let client_shared = kx_shared_secret(&client_secret, &server_public).unwrap();
+        let server_shared = kx_shared_secret(&server_secret, &client_public).unwrap();
+        assert_eq!(client_shared, server_shared);
+    }
+
+    #[test]
+    fn test_session_keys_match_and_are_directional() {
+        let (client_secret, client_public) = kx_keypair_from_seed(1);
+        let (server_secret, server_public) = kx_keypair_from_seed(2);
+        let client_shared = kx_shared_secret(&client_secret, &server_public).unwrap();
+        let server_shared = kx_shared_secret(&server_secret, &client_public).unwrap();
+
+        let (client_send, client_recv) = kx_session_keys(&client_shared, Role::Client);
+        let (server_send, server_recv) = kx_session_keys(&server_shared, Role::Server);
+
+        assert_eq!(client_send, server_recv);
+        assert_eq!(client_recv, server_send);
+        assert_ne!(
+            client_send, client_recv,
+            "directions must use distinct keys"
+        );
+        assert_ne!(
+            client_send, client_shared,
+            "session keys must not be the raw secret"
+        );
+    }
+
+    #[test]
+    fn test_rejects_all_zero_public_key() {
+        let (secret, _) = kx_keypair_from_seed(1);
+        assert_eq!(
+            kx_shared_secret(&secret, &[0u8; KX_KEY_SIZE]),
+            Err(CryptoError::InvalidPublicKey)
+        );
+    }
+
+    #[test]
+    fn test_rejects_low_order_public_key() {
+        let (secret, _) = kx_keypair_from_seed(1);
+        let mut one = [0u8; KX_KEY_SIZE];
+        one[0] = 1;
+        assert_eq!(
+            kx_shared_secret(&secret, &one),
+            Err(CryptoError::InvalidPublicKey)
+        );
+    }
+
+    #[test]
+    fn test_rejects_wrong_length_keys() {
+        let (secret, public) = kx_keypair_from_seed(1);
+        assert_eq!(
+            kx_shared_secret(&secret, &public[..31]),
+            Err(CryptoError::InvalidLength {
+                expected: KX_KEY_SIZE,
+                actual: 31,
+            })
+        );
+        assert!(matches!(
+            kx_shared_secret(&secret[..16], &public),
+            Err(CryptoError::InvalidLength { .. })
+        ));
+    }
+}
@@ -8,18 +8,76 @@
 //! TODO: Replace with liboqs/rust-oqs after external crypto audit.
 //! DO NOT ship PQC in production without an external crypto audit.
 
+// pyo3 0.22's `#[pyfunction]` expansion for `PyResult` returns trips this lint.
+#![allow(clippy::useless_conversion)]
+
 use hmac::{Hmac, Mac};
 use pyo3::prelude::*;
 use pyo3::types::PyBytes;
 use rand::SeedableRng;
 use rand_chacha::ChaCha20Rng;
-use sha2::Sha256;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+#[cfg(feature = "aead")]
+mod aead;
+mod audit;
+mod batch;
+mod chain;
+mod dual;
+mod envelope;
+mod error;
+mod fuzz;
+mod kdf;
+mod keyring;
+#[cfg(feature = "kx")]
+mod kx;
+#[cfg(feature = "telemetry")]
+mod metrics;
+mod multi;
+mod nonce;
+mod registry;
+mod secret;
+mod vectors;
+
+#[cfg(feature = "aead")]
+pub use aead::{decrypt, encrypt, AEAD_KEY_SIZE, AEAD_NONCE_SIZE, AEAD_TAG_SIZE};
+pub use audit::AuditLog;
+pub use batch::verify_batch;
+#[cfg(feature = "parallel")]
+pub use batch::verify_parallel;
+pub use chain::KeyChain;
+pub use dual::{sign_dual, verify_dual, DualPolicy, DualSignature, SigAlgorithm};
+pub use envelope::{migrate_signature, sign_envelope, verify_compat, ENVELOPE_MAGIC, ENVELOPE_V1};
+pub use error::CryptoError;
+pub use fuzz::fuzz_verify;
+pub use kdf::{
+    derive_epoch_key, hkdf_sha256, keygen_from_passphrase, prf, MAX_KDF_OUTPUT,
+    MIN_PBKDF2_ITERATIONS,
+};
+pub use keyring::{CountedKey, KeyRing};
+#[cfg(feature = "kx")]
+pub use kx::{kx_keypair_from_seed, kx_session_keys, kx_shared_secret, Role, KX_KEY_SIZE};
+pub use multi::{verify_multi, AlgoKeys, TAG_ED25519, TAG_HMAC_SHA256};
+pub use nonce::NonceGenerator;
+pub use registry::{KeyMeta, KeyRegistry};
+pub use secret::SecretKey;
+pub use vectors::{
+    generate_test_vectors, test_vectors_json, TestVector, DEFAULT_TEST_VECTOR_COUNT,
+    TEST_VECTOR_PAYLOAD,
+};
 
 type HmacSha256 = Hmac<Sha256>;
 
 /// Key size in bytes (256-bit key)
 const KEY_SIZE: usize = 32;
 
+/// Signature size in bytes (HMAC-SHA256 output)
+pub const SIG_SIZE: usize = 32;
+
+/// Shortest MAC accepted by [`sign_truncated`] and [`verify_truncated`].
+pub const MIN_TRUNCATED_SIG_SIZE: usize = 8;
+
 /// Generate a deterministic key from a seed.
 ///
 /// Given the same seed, this function will always produce the same key.
@@ -37,6 +95,84 @@ pub fn keygen(seed: u64) -> Vec<u8> {
     key
 }
 
+/// Generate a deterministic key from a seed bound to a context string.
+///
+/// The ChaCha20 RNG is seeded from `SHA-256(context || seed_le_bytes)`, so
+/// services that share a small integer seed but use different contexts
+/// (e.g. "prod" vs "staging") get unrelated keys.
+///
+/// An empty context is *not* equivalent to [`keygen`]: the two derivations
+/// are domain-separated, and callers migrating to contexts must re-key.
+///
+/// # Arguments
+/// * `seed` - A 64-bit unsigned integer seed
+/// * `context` - A context string such as an environment or service name
+///
+/// # Returns
+/// A 32-byte key as Vec<u8>
+pub fn keygen_ctx(seed: u64, context: &str) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(context.as_bytes());
+    hasher.update(seed.to_le_bytes());
+    let mut rng = ChaCha20Rng::from_seed(hasher.finalize().into());
+    let mut key = vec![0u8; KEY_SIZE];
+    rand::Rng::fill(&mut rng, &mut key[..]);
+    key
+}
+
+/// Parse a key from its hex encoding.
+///
+/// Upper- and lowercase digits are accepted; surrounding whitespace is
+/// ignored so values read from files or environment variables work as-is.
+///
+/// # Arguments
+/// * `hex` - `2 * KEY_SIZE` hex digits
+///
+/// # Returns
+/// * `Ok(Vec<u8>)` - The 32-byte key
+/// * `Err(CryptoError::InvalidEncoding)` - If `hex` has odd length or a non-hex digit
+/// * `Err(CryptoError::InvalidLength)` - If the decoded key is not 32 bytes
+pub fn keygen_from_hex(hex: &str) -> Result<Vec<u8>, CryptoError> {
+    let hex = hex.trim();
+    let pairs = hex.as_bytes().chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return Err(CryptoError::InvalidEncoding(format!(
+            "hex key has odd length {}",
+            hex.len()
+        )));
+    }
+    let key = pairs
+        .map(|pair| {
+            let digit = |b: u8| char::from(b).to_digit(16);
+            match (digit(pair[0]), digit(pair[1])) {
+                (Some(high), Some(low)) => Ok((high << 4 | low) as u8),
+                _ => Err(CryptoError::InvalidEncoding(
+                    "hex key has a non-hex digit".into(),
+                )),
+            }
+        })
+        .collect::<Result<Vec<u8>, _>>()?;
+    if key.len() != KEY_SIZE {
+        return Err(CryptoError::InvalidLength {
+            expected: KEY_SIZE,
+            actual: key.len(),
+        });
+    }
+    Ok(key)
+}
+
+/// Load a hex-encoded key from the environment variable `var`.
+///
+/// # Returns
+/// * `Ok(Vec<u8>)` - The 32-byte key
+/// * `Err(CryptoError::MissingKey)` - If `var` is unset or not valid Unicode
+/// * `Err(CryptoError)` - Any error from [`keygen_from_hex`]
+pub fn load_key_from_env(var: &str) -> Result<Vec<u8>, CryptoError> {
+    let hex = std::env::var(var)
+        .map_err(|_| CryptoError::MissingKey(format!("environment variable {var}")))?;
+    keygen_from_hex(&hex)
+}
+
 /// Sign a payload with the given key.
 ///
 /// Uses HMAC-SHA256 for deterministic signatures.
@@ -49,9 +185,17 @@ pub fn keygen(seed: u64) -> Vec<u8> {
 /// # Returns
 /// A 32-byte signature as Vec<u8>
 pub fn sign(key: &[u8], payload: &[u8]) -> Vec<u8> {
+    #[cfg(feature = "telemetry")]
+    let start = std::time::Instant::now();
+
     let mut mac = HmacSha256::new_from_slice(key).expect("HMAC can take key of any size");
     mac.update(payload);
-    mac.finalize().into_bytes().to_vec()
+    let sig = mac.finalize().into_bytes().to_vec();
+
+    #[cfg(feature = "telemetry")]
+    metrics::record_sign(start);
+
+    sig
 }
 
 /// Verify a signature against a payload using the given key.
@@ -62,11 +206,164 @@ pub fn sign(key: &[u8], payload: &[u8]) -> Vec<u8> {
 /// * `sig` - The signature to verify
 ///
 /// # Returns
-/// `true` if the signature is valid, `false` otherwise
+/// `true` if the signature is valid, `false` otherwise. Never panics: keys,
+/// payloads and signatures of any length (empty included) are accepted, and
+/// a signature that is not [`SIG_SIZE`] bytes is simply invalid.
 pub fn verify(key: &[u8], payload: &[u8], sig: &[u8]) -> bool {
     let mut mac = HmacSha256::new_from_slice(key).expect("HMAC can take key of any size");
     mac.update(payload);
-    mac.verify_slice(sig).is_ok()
+    let valid = mac.verify_slice(sig).is_ok();
+
+    #[cfg(feature = "telemetry")]
+    metrics::record_verify(valid);
+
+    valid
+}
+
+/// Compute the HMAC-SHA256 MAC of a payload.
+///
+/// Same as [`sign`]; the name makes call sites that compute a MAC for
+/// comparison or debugging read as such.
+pub fn compute_mac(key: &[u8], payload: &[u8]) -> Vec<u8> {
+    sign(key, payload)
+}
+
+/// Verify a signature and also return the MAC it was compared against.
+///
+/// For debugging mismatches in the field. The validity check is the same
+/// constant-time comparison as [`verify`].
+///
+/// # Security
+/// The returned MAC is a valid signature for `payload`. Never log it or
+/// return it to a caller in production: anyone who sees it can replay it.
+///
+/// # Arguments
+/// * `key` - The verification key
+/// * `payload` - The data that was signed
+/// * `sig` - The signature to verify
+///
+/// # Returns
+/// `(valid, expected)` where `valid` matches [`verify`] and `expected` is
+/// the freshly computed 32-byte MAC
+pub fn verify_with_expected(key: &[u8], payload: &[u8], sig: &[u8]) -> (bool, Vec<u8>) {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC can take key of any size");
+    mac.update(payload);
+    let expected = mac.finalize().into_bytes().to_vec();
+    let valid = constant_time_eq(&expected, sig);
+
+    #[cfg(feature = "telemetry")]
+    metrics::record_verify(valid);
+
+    (valid, expected)
+}
+
+/// Sign a payload, keeping only the first `len` bytes of the MAC.
+///
+/// # Security
+/// A truncated MAC is only as strong as its length: an attacker can forge a
+/// `len`-byte tag with probability 2^-(8 * len) per attempt, so 16 bytes
+/// gives 128-bit forgery resistance and the 8-byte minimum only 64 bits.
+/// Use the shortest length your format needs and never go below 16 bytes
+/// where the attacker can make unlimited online guesses.
+///
+/// # Arguments
+/// * `key` - The signing key
+/// * `payload` - The data to sign
+/// * `len` - Tag length in bytes, [`MIN_TRUNCATED_SIG_SIZE`]`..=`[`SIG_SIZE`]
+///
+/// # Returns
+/// * `Ok(Vec<u8>)` - The `len`-byte tag
+/// * `Err(CryptoError::InvalidTruncation)` - If `len` is out of range
+pub fn sign_truncated(key: &[u8], payload: &[u8], len: usize) -> Result<Vec<u8>, CryptoError> {
+    check_truncation(len)?;
+    let mut sig = sign(key, payload);
+    sig.truncate(len);
+    Ok(sig)
+}
+
+/// Verify a tag produced by [`sign_truncated`] with the same `len`.
+///
+/// The tag length is fixed by the caller, never taken from `sig`: otherwise
+/// an attacker could strip a tag down to the minimum length and only have
+/// to forge that many bytes. The leading `len` bytes of the MAC are
+/// compared in constant time.
+///
+/// # Arguments
+/// * `key` - The signing key
+/// * `payload` - The signed data
+/// * `sig` - The tag to check
+/// * `len` - The tag length the payload was signed with
+///
+/// # Returns
+/// `true` if `sig` is a valid `len`-byte tag, `false` otherwise (including
+/// when `sig` is not `len` bytes long or `len` is out of range)
+pub fn verify_truncated(key: &[u8], payload: &[u8], sig: &[u8], len: usize) -> bool {
+    if check_truncation(len).is_err() || sig.len() != len {
+        return false;
+    }
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC can take key of any size");
+    mac.update(payload);
+    mac.verify_truncated_left(sig).is_ok()
+}
+
+fn check_truncation(len: usize) -> Result<(), CryptoError> {
+    if !(MIN_TRUNCATED_SIG_SIZE..=SIG_SIZE).contains(&len) {
+        return Err(CryptoError::InvalidTruncation {
+            len,
+            min: MIN_TRUNCATED_SIG_SIZE,
+            max: SIG_SIZE,
+        });
+    }
+    Ok(())
+}
+
+/// Compare two byte slices in constant time.
+///
+/// Use this instead of `==` when comparing secrets such as tokens, keys,
+/// or signatures, so the comparison time does not reveal how many leading
+/// bytes matched.
+///
+/// # Guarantees
+/// - For inputs of equal length, every byte is examined regardless of where
+///   the first difference occurs.
+/// - A length mismatch returns `false` without inspecting content. The
+///   lengths themselves are *not* hidden; callers must not rely on this
+///   function to conceal the length of a secret.
+/// - Constant-time behavior is best-effort at the source level (via the
+///   `subtle` crate); it is not a guarantee against every compiler or CPU
+///   optimization.
+///
+/// # Arguments
+/// * `a` - First byte slice
+/// * `b` - Second byte slice
+///
+/// # Returns
+/// `true` if both slices have the same length and contents, `false` otherwise
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.ct_eq(b).into()
+}
+
+/// Compare two keys in constant time.
+///
+/// Intended for checks such as confirming a rotation completed. Keys of the
+/// same length are compared in full with `subtle::ConstantTimeEq`, so the
+/// time taken does not depend on where they differ; a length mismatch
+/// returns `false` right away.
+///
+/// # Arguments
+/// * `a` - First key
+/// * `b` - Second key
+///
+/// # Returns
+/// `true` if the keys are identical, `false` otherwise
+pub fn keys_equal(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.ct_eq(b).into()
 }
 
 // PyO3 bindings for Python interop
@@ -80,6 +377,22 @@ fn py_keygen<'py>(py: Python<'py>, seed: u64) -> Bound<'py, PyBytes> {
     PyBytes::new_bound(py, &key)
 }
 
+/// Generate a deterministic key from a seed and context (Python binding).
+#[pyfunction]
+#[pyo3(name = "keygen_ctx")]
+fn py_keygen_ctx<'py>(py: Python<'py>, seed: u64, context: &str) -> Bound<'py, PyBytes> {
+    let key = keygen_ctx(seed, context);
+    PyBytes::new_bound(py, &key)
+}
+
+/// Parse a hex-encoded key; raises `ValueError` if invalid (Python binding).
+#[pyfunction]
+#[pyo3(name = "keygen_from_hex")]
+fn py_keygen_from_hex<'py>(py: Python<'py>, hex: &str) -> PyResult<Bound<'py, PyBytes>> {
+    let key = keygen_from_hex(hex)?;
+    Ok(PyBytes::new_bound(py, &key))
+}
+
 /// Sign a payload with the given key (Python binding).
 #[pyfunction]
 #[pyo3(name = "sign")]
@@ -95,12 +408,89 @@ fn py_verify(key: Vec<u8>, payload: Vec<u8>, sig: Vec<u8>) -> bool {
     verify(&key, &payload, &sig)
 }
 
+/// Verify a signature, returning `(valid, expected_mac)`; never log the
+/// MAC in production (Python binding).
+#[pyfunction]
+#[pyo3(name = "verify_with_expected")]
+fn py_verify_with_expected<'py>(
+    py: Python<'py>,
+    key: Vec<u8>,
+    payload: Vec<u8>,
+    sig: Vec<u8>,
+) -> (bool, Bound<'py, PyBytes>) {
+    let (valid, expected) = verify_with_expected(&key, &payload, &sig);
+    (valid, PyBytes::new_bound(py, &expected))
+}
+
+/// Sign a payload with a truncated MAC (Python binding).
+#[pyfunction]
+#[pyo3(name = "sign_truncated")]
+fn py_sign_truncated<'py>(
+    py: Python<'py>,
+    key: Vec<u8>,
+    payload: Vec<u8>,
+    len: usize,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let sig = sign_truncated(&key, &payload, len)?;
+    Ok(PyBytes::new_bound(py, &sig))
+}
+
+/// Verify a truncated MAC of the expected length (Python binding).
+#[pyfunction]
+#[pyo3(name = "verify_truncated")]
+fn py_verify_truncated(key: Vec<u8>, payload: Vec<u8>, sig: Vec<u8>, len: usize) -> bool {
+    verify_truncated(&key, &payload, &sig, len)
+}
+
+/// Compare two byte strings in constant time (Python binding).
+#[pyfunction]
+#[pyo3(name = "constant_time_eq")]
+fn py_constant_time_eq(a: Vec<u8>, b: Vec<u8>) -> bool {
+    constant_time_eq(&a, &b)
+}
+
+/// Compare two keys in constant time (Python binding).
+#[pyfunction]
+#[pyo3(name = "keys_equal")]
+fn py_keys_equal(a: Vec<u8>, b: Vec<u8>) -> bool {
+    keys_equal(&a, &b)
+}
+
 /// Python module for TinyWindow Rust encryption primitives.
 #[pymodule]
 fn tinywindow_rust_encryption(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(py_keygen, m)?)?;
+    m.add_function(wrap_pyfunction!(py_keygen_ctx, m)?)?;
+    m.add_function(wrap_pyfunction!(py_keygen_from_hex, m)?)?;
     m.add_function(wrap_pyfunction!(py_sign, m)?)?;
     m.add_function(wrap_pyfunction!(py_verify, m)?)?;
+    m.add_function(wrap_pyfunction!(py_verify_with_expected, m)?)?;
+    m.add_function(wrap_pyfunction!(py_sign_truncated, m)?)?;
+    m.add_function(wrap_pyfunction!(py_verify_truncated, m)?)?;
+    m.add_function(wrap_pyfunction!(py_constant_time_eq, m)?)?;
+    m.add_function(wrap_pyfunction!(py_keys_equal, m)?)?;
+    m.add_function(wrap_pyfunction!(kdf::py_derive_epoch_key, m)?)?;
+    m.add_function(wrap_pyfunction!(kdf::py_prf, m)?)?;
+    m.add_function(wrap_pyfunction!(kdf::py_keygen_from_passphrase, m)?)?;
+    m.add_function(wrap_pyfunction!(envelope::py_sign_envelope, m)?)?;
+    m.add_function(wrap_pyfunction!(envelope::py_verify_compat, m)?)?;
+    m.add_function(wrap_pyfunction!(envelope::py_migrate_signature, m)?)?;
+    m.add_function(wrap_pyfunction!(dual::py_sign_dual, m)?)?;
+    m.add_function(wrap_pyfunction!(dual::py_verify_dual, m)?)?;
+    m.add_function(wrap_pyfunction!(multi::py_verify_multi, m)?)?;
+    m.add_function(wrap_pyfunction!(vectors::py_test_vectors_json, m)?)?;
+    m.add_class::<registry::PyKeyRegistry>()?;
+    #[cfg(feature = "aead")]
+    {
+        m.add_function(wrap_pyfunction!(aead::py_encrypt, m)?)?;
+        m.add_function(wrap_pyfunction!(aead::py_decrypt, m)?)?;
+    }
+    #[cfg(feature = "kx")]
+    {
+        m.add_function(wrap_pyfunction!(kx::py_kx_keypair_from_seed, m)?)?;
+        m.add_function(wrap_pyfunction!(kx::py_kx_shared_secret, m)?)?;
+        m.add_function(wrap_pyfunction!(kx::py_kx_session_keys, m)?)?;
+    }
     Ok(())
 }
 
@@ -124,6 +514,92 @@ mod tests {
         assert_ne!(key1, key2, "different seeds should produce different keys");
     }
 
+    #[test]
+    fn test_keygen_ctx_deterministic_per_context() {
+        assert_eq!(keygen_ctx(1, "prod"), keygen_ctx(1, "prod"));
+        assert_eq!(keygen_ctx(1, "prod").len(), KEY_SIZE);
+    }
+
+    #[test]
+    fn test_keygen_ctx_contexts_diverge() {
+        let prod = keygen_ctx(1, "prod");
+        let staging = keygen_ctx(1, "staging");
+        assert_ne!(prod, staging, "same seed in different contexts must differ");
+        assert_ne!(keygen_ctx(1, "prod"), keygen_ctx(2, "prod"));
+    }
+
+    #[test]
+    fn test_keygen_ctx_empty_context_is_domain_separated() {
+        assert_ne!(
+            keygen_ctx(42, ""),
+            keygen(42),
+            "empty context must not alias the legacy keygen output"
+        );
+    }
+
+    #[test]
+    fn test_keygen_ctx_test_vector() {
+        let key = keygen_ctx(1, "prod");
+        let hex: String = key.iter().map(|b| format!("{b:02x}")).collect();
+        assert_eq!(
+            hex,
+            "fc4cac80863546c496db6e44c11660d995df2b26172cd4690118e97f1b480d5b"
+        );
+    }
+
+    #[test]
+    fn test_keygen_from_hex_valid_key() {
+        let key = keygen(42);
+        let hex: String = key.iter().map(|b| format!("{b:02X}")).collect();
+        assert_eq!(keygen_from_hex(&hex).unwrap(), key);
+        assert_eq!(keygen_from_hex(&format!(" {hex}\n")).unwrap(), key);
+    }
+
+    #[test]
+    fn test_keygen_from_hex_rejects_odd_length_and_bad_digits() {
+        assert!(matches!(
+            keygen_from_hex("abc"),
+            Err(CryptoError::InvalidEncoding(_))
+        ));
+        assert!(matches!(
+            keygen_from_hex(&"zz".repeat(KEY_SIZE)),
+            Err(CryptoError::InvalidEncoding(_))
+        ));
+        assert!(matches!(
+            keygen_from_hex(&"+f".repeat(KEY_SIZE)),
+            Err(CryptoError::InvalidEncoding(_))
+        ));
+        assert!(matches!(
+            keygen_from_hex(&"é".repeat(KEY_SIZE)),
+            Err(CryptoError::InvalidEncoding(_))
+        ));
+    }
+
+    #[test]
+    fn test_keygen_from_hex_rejects_wrong_key_length() {
+        assert_eq!(
+            keygen_from_hex(&"ab".repeat(16)),
+            Err(CryptoError::InvalidLength {
+                expected: KEY_SIZE,
+                actual: 16,
+            })
+        );
+    }
+
+    #[test]
+    fn test_load_key_from_env() {
+        let var = "TINYWINDOW_TEST_SIGNING_KEY";
+        assert_eq!(
+            load_key_from_env(var),
+            Err(CryptoError::MissingKey(format!(
+                "environment variable {var}"
+            )))
+        );
+        std::env::set_var(var, "11".repeat(KEY_SIZE));
+        assert_eq!(load_key_from_env(var).unwrap(), vec![0x11; KEY_SIZE]);
+        std::env::remove_var(var);
+    }
+
     #[test]
     fn test_sign_deterministic() {
         let key = keygen(42);
@@ -134,7 +610,7 @@ mod tests {
             sig1, sig2,
             "sign must be deterministic for the same key and payload"
         );
-        assert_eq!(sig1.len(), 32); // HMAC-SHA256 produces 32-byte signatures
+        assert_eq!(sig1.len(), SIG_SIZE); // HMAC-SHA256 produces 32-byte signatures
     }
 
     #[test]
@@ -183,4 +659,96 @@ mod tests {
             "verify should fail with tampered signature"
         );
     }
+
+    #[test]
+    fn test_verify_with_expected_matches_sign_and_verify() {
+        let key = keygen(42);
+        let sig = sign(&key, b"payload");
+        assert_eq!(compute_mac(&key, b"payload"), sig);
+
+        for candidate in [sig.clone(), sign(&key, b"other"), sig[..16].to_vec()] {
+            let (valid, expected) = verify_with_expected(&key, b"payload", &candidate);
+            assert_eq!(valid, verify(&key, b"payload", &candidate));
+            assert_eq!(expected, sig);
+        }
+    }
+
+    #[test]
+    fn test_sign_truncated_roundtrip() {
+        let key = keygen(42);
+        let tag = sign_truncated(&key, b"payload", 16).unwrap();
+        assert_eq!(tag, sign(&key, b"payload")[..16]);
+        assert!(verify_truncated(&key, b"payload", &tag, 16));
+        assert!(!verify_truncated(&key, b"other payload", &tag, 16));
+        assert!(!verify_truncated(&keygen(43), b"payload", &tag, 16));
+    }
+
+    #[test]
+    fn test_verify_truncated_rejects_shorter_tag() {
+        let key = keygen(42);
+        let tag = sign_truncated(&key, b"payload", 16).unwrap();
+        // A valid 8-byte prefix must not pass where 16 bytes are expected.
+        assert!(verify_truncated(&key, b"payload", &tag[..8], 8));
+        assert!(!verify_truncated(&key, b"payload", &tag[..8], 16));
+        assert!(!verify_truncated(&key, b"payload", &tag, 8));
+    }
+
+    #[test]
+    fn test_sign_truncated_rejects_out_of_range_length() {
+        let key = keygen(42);
+        for len in [0, MIN_TRUNCATED_SIG_SIZE - 1, SIG_SIZE + 1] {
+            assert_eq!(
+                sign_truncated(&key, b"payload", len),
+                Err(CryptoError::InvalidTruncation {
+                    len,
+                    min: MIN_TRUNCATED_SIG_SIZE,
+                    max: SIG_SIZE,
+                })
+            );
+        }
+        let full = sign(&key, b"payload");
+        assert!(!verify_truncated(&key, b"payload", &full[..4], 4));
+    }
+
+    #[test]
+    fn test_constant_time_eq_equal() {
+        let key = keygen(42);
+        assert!(constant_time_eq(&key, &key.clone()));
+        assert!(constant_time_eq(b"", b""));
+    }
+
+    #[test]
+    fn test_constant_time_eq_different_length() {
+        assert!(!constant_time_eq(b"token", b"token-extended"));
+        assert!(!constant_time_eq(b"", b"x"));
+    }
+
+    #[test]
+    fn test_constant_time_eq_differs_in_last_byte() {
+        let a = sign(&keygen(42), b"payload");
+        let mut b = a.clone();
+        let last = b.len() - 1;
+        b[last] ^= 0x01;
+        assert!(
+            !constant_time_eq(&a, &b),
+            "a difference in the final byte must be detected"
+        );
+    }
+
+    #[test]
+    fn test_keys_equal_identical_keys() {
+        assert!(keys_equal(&keygen(42), &keygen(42)));
+    }
+
+    #[test]
+    fn test_keys_equal_different_keys_same_length() {
+        assert!(!keys_equal(&keygen(42), &keygen(43)));
+    }
+
+    #[test]
+    fn test_keys_equal_different_lengths() {
+        let key = keygen(42);
+        assert!(!keys_equal(&key, &key[..KEY_SIZE - 1]));
+        assert!(!keys_equal(&key, &[]));
+    }
 }
@@ -0,0 +1,63 @@
+//! Sign/verify counters backed by the shared telemetry registry.
+//!
+//! Only compiled with the `telemetry` feature (or its `metrics` alias);
+//! without it the encryption crate has no dependency on the telemetry crate
+//! and no instrumentation.
+//!
+//! Every [`verify`](crate::verify) (and
+//! [`verify_with_expected`](crate::verify_with_expected)) counts in
+//! `signature_verify_total` and, when it fails,
+//! `signature_verify_failures_total`, so the failure rate is their ratio.
+//! These replace `crypto_verify_total` and `crypto_verify_failures_total`;
+//! dashboards and alerts on the old names need to switch to the new ones.
+
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use telemetry::prometheus::IntCounter;
+use tinywindow_rust_telemetry as telemetry;
+
+struct CryptoCounters {
+    sign_total: IntCounter,
+    verify_total: IntCounter,
+    verify_failures_total: IntCounter,
+}
+
+fn counters() -> &'static CryptoCounters {
+    static COUNTERS: OnceLock<CryptoCounters> = OnceLock::new();
+    COUNTERS.get_or_init(|| CryptoCounters {
+        sign_total: register("crypto_sign_total", "Total number of sign operations"),
+        verify_total: register(
+            "signature_verify_total",
+            "Total number of signature verification attempts",
+        ),
+        verify_failures_total: register(
+            "signature_verify_failures_total",
+            "Total number of failed signature verifications",
+        ),
+    })
+}
+
+/// Register a counter, falling back to an unregistered one (counted but
+/// never scraped) if the registry refuses it, so crypto calls never panic.
+fn register(name: &str, help: &str) -> IntCounter {
+    telemetry::register_counter(name, help).unwrap_or_else(|err| {
+        log::warn!("not exporting {name}: {err}");
+        IntCounter::new(name, help).expect("crypto metric definitions are valid")
+    })
+}
+
+/// Count a sign call and record its latency since `start`.
+pub(crate) fn record_sign(start: Instant) {
+    counters().sign_total.inc();
+    telemetry::record_latency("crypto_sign", start.elapsed().as_secs_f64() * 1e6);
+}
+
+/// Count a verify call and, if it failed, a verification failure.
+pub(crate) fn record_verify(valid: bool) {
+    let counters = counters();
+    counters.verify_total.inc();
+    if !valid {
+        counters.verify_failures_total.inc();
+    }
+}
@@ -0,0 +1,200 @@
+//! Verification of algorithm-tagged signatures.
+//!
+//! While senders migrate from HMAC-SHA256 to Ed25519, a message may be
+//! signed with either. A tagged signature is the signature prefixed with
+//! one byte naming its algorithm:
+//!
+//! | Tag | Algorithm | Signature |
+//! |-----|-----------|-----------|
+//! | [`TAG_HMAC_SHA256`] (`0x01`) | HMAC-SHA256 (see [`sign`](crate::sign)) | 32 bytes |
+//! | [`TAG_ED25519`] (`0x02`) | Ed25519 (requires the `ed25519` feature) | 64 bytes |
+//!
+//! [`verify_multi`] reads the tag and checks the rest with the matching key
+//! from an [`AlgoKeys`]. Unknown tags, and tags whose key is missing, do
+//! not verify.
+
+#[cfg(feature = "ed25519")]
+use ed25519_dalek::{Signature, VerifyingKey};
+use pyo3::prelude::*;
+
+#[cfg(feature = "ed25519")]
+use crate::CryptoError;
+use crate::SecretKey;
+
+/// Tag of an HMAC-SHA256 signature.
+pub const TAG_HMAC_SHA256: u8 = 0x01;
+
+/// Tag of an Ed25519 signature.
+pub const TAG_ED25519: u8 = 0x02;
+
+/// The verification key for each algorithm [`verify_multi`] accepts.
+///
+/// Start from [`AlgoKeys::new`], which accepts nothing, and add a key per
+/// algorithm the sender may use.
+#[derive(Debug, Default)]
+pub struct AlgoKeys {
+    hmac: Option<SecretKey>,
+    #[cfg(feature = "ed25519")]
+    ed25519: Option<VerifyingKey>,
+}
+
+impl AlgoKeys {
+    /// Keys for no algorithm.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept HMAC-SHA256 signatures made with `key`.
+    pub fn with_hmac(mut self, key: SecretKey) -> Self {
+        self.hmac = Some(key);
+        self
+    }
+
+    /// Accept Ed25519 signatures made with the secret half of `public_key`
+    /// (requires the `ed25519` feature).
+    ///
+    /// # Returns
+    /// * `Ok(AlgoKeys)` - The keys, now accepting Ed25519
+    /// * `Err(CryptoError::InvalidLength)` - If `public_key` is not 32 bytes
+    /// * `Err(CryptoError::InvalidPublicKey)` - If it is not a valid point
+    #[cfg(feature = "ed25519")]
+    pub fn with_ed25519(mut self, public_key: &[u8]) -> Result<Self, CryptoError> {
+        let bytes: &[u8; 32] = public_key
+            .try_into()
+            .map_err(|_| CryptoError::InvalidLength {
+                expected: 32,
+                actual: public_key.len(),
+            })?;
+        let key = VerifyingKey::from_bytes(bytes).map_err(|_| CryptoError::InvalidPublicKey)?;
+        self.ed25519 = Some(key);
+        Ok(self)
+    }
+}
+
+/// Verify a signature prefixed with its algorithm tag.
+///
+/// HMAC signatures are compared in constant time. Ed25519 signatures are
+/// checked with `verify_strict`, which also rejects weak public keys and
+/// malleable signatures.
+///
+/// # Arguments
+/// * `keys` - The key for each accepted algorithm
+/// * `payload` - The data that was signed
+/// * `tagged_sig` - One tag byte followed by the signature
+///
+/// # Returns
+/// `true` only if the tag is known, `keys` has a key for it, and the
+/// signature verifies; `false` for an empty `tagged_sig`
+pub fn verify_multi(keys: &AlgoKeys, payload: &[u8], tagged_sig: &[u8]) -> bool {
+    let Some((&tag, sig)) = tagged_sig.split_first() else {
+        return false;
+    };
+    match tag {
+        TAG_HMAC_SHA256 => keys
+            .hmac
+            .as_ref()
+            .is_some_and(|key| key.verify(payload, sig)),
+        #[cfg(feature = "ed25519")]
+        TAG_ED25519 => keys.ed25519.is_some_and(|key| {
+            Signature::from_slice(sig).is_ok_and(|sig| key.verify_strict(payload, &sig).is_ok())
+        }),
+        _ => false,
+    }
+}
+
+/// Verify a tagged signature (Python binding).
+///
+/// Pass the key of each algorithm the sender may use; `ed25519_public_key`
+/// is ignored unless built with the `ed25519` feature. Raises `ValueError`
+/// on a malformed key.
+#[pyfunction]
+#[pyo3(
+    name = "verify_multi",
+    signature = (payload, tagged_sig, hmac_key = None, ed25519_public_key = None)
+)]
+pub(crate) fn py_verify_multi(
+    payload: Vec<u8>,
+    tagged_sig: Vec<u8>,
+    hmac_key: Option<Vec<u8>>,
+    ed25519_public_key: Option<Vec<u8>>,
+) -> PyResult<bool> {
+    let mut keys = AlgoKeys::new();
+    if let Some(key) = hmac_key {
+        keys = keys.with_hmac(SecretKey::from_slice(&key)?);
+    }
+    #[cfg(feature = "ed25519")]
+    if let Some(public_key) = ed25519_public_key {
+        keys = keys.with_ed25519(&public_key)?;
+    }
+    #[cfg(not(feature = "ed25519"))]
+    let _ = ed25519_public_key;
+    Ok(verify_multi(&keys, &payload, &tagged_sig))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sign;
+
+    const PAYLOAD: &[u8] = b"BUY 100 AAPL";
+
+    fn tagged(tag: u8, sig: &[u8]) -> Vec<u8> {
+        [&[tag][..], sig].concat()
+    }
+
+    #[test]
+    fn test_hmac_tagged_signature_verifies() {
+        let key = SecretKey::from_seed(7);
+        let sig = tagged(TAG_HMAC_SHA256, &sign(key.as_bytes(), PAYLOAD));
+        let keys = AlgoKeys::new().with_hmac(SecretKey::from_seed(7));
+        assert!(verify_multi(&keys, PAYLOAD, &sig));
+        assert!(!verify_multi(&keys, b"SELL 100 AAPL", &sig));
+        assert!(!verify_multi(&AlgoKeys::new(), PAYLOAD, &sig));
+        let other = AlgoKeys::new().with_hmac(SecretKey::from_seed(8));
+        assert!(!verify_multi(&other, PAYLOAD, &sig));
+    }
+
+    #[test]
+    fn test_unknown_tag_is_rejected() {
+        let key = SecretKey::from_seed(7);
+        let hmac = sign(key.as_bytes(), PAYLOAD);
+        let keys = AlgoKeys::new().with_hmac(key);
+        for tag in [0x00, 0x03, 0xff] {
+            assert!(!verify_multi(&keys, PAYLOAD, &tagged(tag, &hmac)), "{tag}");
+        }
+        assert!(!verify_multi(&keys, PAYLOAD, &[]));
+        assert!(!verify_multi(&keys, PAYLOAD, &[TAG_HMAC_SHA256]));
+    }
+
+    #[cfg(feature = "ed25519")]
+    #[test]
+    fn test_ed25519_tagged_signature_verifies() {
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let signing = SigningKey::from_bytes(&[9; 32]);
+        let public = signing.verifying_key().to_bytes();
+        let sig = tagged(TAG_ED25519, &signing.sign(PAYLOAD).to_bytes());
+        let keys = AlgoKeys::new()
+            .with_hmac(SecretKey::from_seed(7))
+            .with_ed25519(&public)
+            .unwrap();
+        assert!(verify_multi(&keys, PAYLOAD, &sig));
+        assert!(!verify_multi(&keys, b"SELL 100 AAPL", &sig));
+        // The tag selects the verifier: the same bytes as HMAC fail.
+        assert!(!verify_multi(
+            &keys,
+            PAYLOAD,
+            &tagged(TAG_HMAC_SHA256, &sig[1..])
+        ));
+        assert!(!verify_multi(&AlgoKeys::new(), PAYLOAD, &sig));
+        assert!(!verify_multi(&keys, PAYLOAD, &sig[..40]));
+
+        assert_eq!(
+            AlgoKeys::new().with_ed25519(&public[..31]).unwrap_err(),
+            CryptoError::InvalidLength {
+                expected: 32,
+                actual: 31
+            }
+        );
+    }
+}
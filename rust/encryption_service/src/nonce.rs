@@ -0,0 +1,76 @@
+//! Reproducible AEAD nonces for tests.
+//!
+//! A [`NonceGenerator`] draws 96-bit nonces from a ChaCha20 RNG, so tests
+//! and fixtures can encrypt with nonces that look random but come out the
+//! same on every run. Its RNG is seeded from `SHA-256(NONCE_CONTEXT ||
+//! seed_le_bytes)`, not from the seed directly, so a generator and
+//! [`keygen`](crate::keygen) given the same seed produce unrelated bytes.
+//!
+//! This is for tests and other deterministic use only. Two processes with
+//! the same seed produce the same nonces, so reusing a seed under one key
+//! reuses nonces, which breaks ChaCha20-Poly1305. Production code should
+//! draw nonces from the OS RNG or use a per-key counter.
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use sha2::{Digest, Sha256};
+
+/// Domain separator hashed with the seed.
+const NONCE_CONTEXT: &[u8] = b"tinywindow-nonce";
+
+/// Nonce size in bytes (96 bits, as ChaCha20-Poly1305 takes).
+const NONCE_SIZE: usize = 12;
+
+/// Deterministic sequence of 12-byte nonces.
+///
+/// Two generators created from the same seed produce the same nonces in the
+/// same order. Consecutive nonces are independent random draws, so a repeat
+/// within one sequence is as unlikely as a 96-bit collision.
+pub struct NonceGenerator {
+    rng: ChaCha20Rng,
+}
+
+impl NonceGenerator {
+    /// Start the sequence for `seed`.
+    pub fn new(seed: u64) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(NONCE_CONTEXT);
+        hasher.update(seed.to_le_bytes());
+        Self {
+            rng: ChaCha20Rng::from_seed(hasher.finalize().into()),
+        }
+    }
+
+    /// The next nonce of the sequence.
+    pub fn next_nonce(&mut self) -> [u8; NONCE_SIZE] {
+        let mut nonce = [0u8; NONCE_SIZE];
+        self.rng.fill(&mut nonce[..]);
+        nonce
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen;
+
+    #[test]
+    fn test_same_seed_gives_same_sequence() {
+        let mut first = NonceGenerator::new(42);
+        let mut second = NonceGenerator::new(42);
+        let nonces: Vec<_> = (0..100).map(|_| first.next_nonce()).collect();
+        for nonce in &nonces {
+            assert_eq!(*nonce, second.next_nonce());
+        }
+        for pair in nonces.windows(2) {
+            assert_ne!(pair[0], pair[1]);
+        }
+        assert_ne!(NonceGenerator::new(43).next_nonce(), nonces[0]);
+    }
+
+    #[test]
+    fn test_nonces_are_unrelated_to_keygen() {
+        let nonce = NonceGenerator::new(42).next_nonce();
+        assert_ne!(nonce[..], keygen(42)[..NONCE_SIZE]);
+    }
+}
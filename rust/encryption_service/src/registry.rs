@@ -0,0 +1,292 @@
+//! Labelled key registry.
+//!
+//! Operators refer to keys by human-readable labels such as
+//! `"orders-prod-2024"`. A [`KeyRegistry`] maps each label to a
+//! [`SecretKey`] and its [`KeyMeta`]. Metadata can be exported as JSON for
+//! audits; key bytes never leave the registry that way.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use serde::Serialize;
+
+use crate::{CryptoError, SecretKey, SigAlgorithm};
+
+/// Descriptive information about a registered key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KeyMeta {
+    /// Creation time in seconds since the Unix epoch
+    pub created_at: u64,
+    /// Algorithm the key signs with
+    pub algorithm: SigAlgorithm,
+    /// Free-form operator tags, e.g. `["prod", "orders"]`
+    pub tags: Vec<String>,
+}
+
+impl KeyMeta {
+    /// Metadata for a key created now.
+    pub fn new(algorithm: SigAlgorithm, tags: Vec<String>) -> Self {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        Self {
+            created_at,
+            algorithm,
+            tags,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    key: SecretKey,
+    meta: KeyMeta,
+}
+
+/// Keys and their metadata, looked up by label.
+#[derive(Debug, Default)]
+pub struct KeyRegistry {
+    entries: HashMap<String, Entry>,
+}
+
+impl KeyRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `key` under `label`.
+    ///
+    /// # Arguments
+    /// * `label` - Human-readable key name
+    /// * `key` - The key
+    /// * `meta` - Key metadata
+    /// * `replace` - Whether an existing key under `label` may be overwritten
+    ///
+    /// # Returns
+    /// * `Ok(())` - The key was registered
+    /// * `Err(CryptoError::DuplicateLabel)` - If `label` exists and `replace` is false
+    pub fn insert(
+        &mut self,
+        label: &str,
+        key: SecretKey,
+        meta: KeyMeta,
+        replace: bool,
+    ) -> Result<(), CryptoError> {
+        if !replace && self.entries.contains_key(label) {
+            return Err(CryptoError::DuplicateLabel(label.to_string()));
+        }
+        self.entries.insert(label.to_string(), Entry { key, meta });
+        Ok(())
+    }
+
+    /// The key registered under `label`.
+    pub fn get(&self, label: &str) -> Option<&SecretKey> {
+        self.entries.get(label).map(|entry| &entry.key)
+    }
+
+    /// The metadata of the key registered under `label`.
+    pub fn meta(&self, label: &str) -> Option<&KeyMeta> {
+        self.entries.get(label).map(|entry| &entry.meta)
+    }
+
+    /// Sign `payload` with the key under `label`, using its registered algorithm.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<u8>)` - The signature
+    /// * `Err(CryptoError::UnknownKey)` - If no key is registered under `label`
+    pub fn sign_as(&self, label: &str, payload: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let entry = self
+            .entries
+            .get(label)
+            .ok_or_else(|| CryptoError::UnknownKey(label.to_string()))?;
+        Ok(entry.meta.algorithm.sign(entry.key.as_bytes(), payload))
+    }
+
+    /// All registered labels in sorted order.
+    pub fn list_labels(&self) -> Vec<&str> {
+        let mut labels: Vec<&str> = self.entries.keys().map(String::as_str).collect();
+        labels.sort_unstable();
+        labels
+    }
+
+    /// Number of registered keys.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the registry holds no keys.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Export metadata as a JSON object keyed by label.
+    ///
+    /// Only [`KeyMeta`] fields are included; key bytes are never exported.
+    pub fn metadata_json(&self) -> String {
+        let metadata: BTreeMap<&str, &KeyMeta> = self
+            .entries
+            .iter()
+            .map(|(label, entry)| (label.as_str(), &entry.meta))
+            .collect();
+        serde_json::to_string(&metadata).expect("key metadata always serializes")
+    }
+}
+
+/// Python wrapper around [`KeyRegistry`].
+#[pyclass(name = "KeyRegistry")]
+#[derive(Debug, Default)]
+pub(crate) struct PyKeyRegistry {
+    inner: KeyRegistry,
+}
+
+#[pymethods]
+impl PyKeyRegistry {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a key; raises `ValueError` on a duplicate label unless `replace`.
+    #[pyo3(signature = (label, key, algorithm = "hmac-sha256", tags = None, replace = false))]
+    fn insert(
+        &mut self,
+        label: &str,
+        key: Vec<u8>,
+        algorithm: &str,
+        tags: Option<Vec<String>>,
+        replace: bool,
+    ) -> PyResult<()> {
+        let meta = KeyMeta::new(algorithm.parse()?, tags.unwrap_or_default());
+        let key = SecretKey::from_slice(&key)?;
+        Ok(self.inner.insert(label, key, meta, replace)?)
+    }
+
+    /// Sign with the key under `label`; raises `ValueError` for unknown labels.
+    fn sign_as<'py>(
+        &self,
+        py: Python<'py>,
+        label: &str,
+        payload: Vec<u8>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let sig = self.inner.sign_as(label, &payload)?;
+        Ok(PyBytes::new_bound(py, &sig))
+    }
+
+    fn list_labels(&self) -> Vec<String> {
+        self.inner
+            .list_labels()
+            .into_iter()
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn metadata_json(&self) -> String {
+        self.inner.metadata_json()
+    }
+
+    fn __contains__(&self, label: &str) -> bool {
+        self.inner.get(label).is_some()
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{keygen, verify};
+
+    fn meta() -> KeyMeta {
+        KeyMeta {
+            created_at: 1_700_000_000,
+            algorithm: SigAlgorithm::HmacSha256,
+            tags: vec!["prod".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_lookup_and_sign_by_label() {
+        let mut registry = KeyRegistry::new();
+        registry
+            .insert("orders-prod-2024", SecretKey::from_seed(1), meta(), false)
+            .unwrap();
+        registry
+            .insert("orders-dev", SecretKey::from_seed(2), meta(), false)
+            .unwrap();
+
+        assert_eq!(
+            registry.list_labels(),
+            vec!["orders-dev", "orders-prod-2024"]
+        );
+        assert_eq!(
+            registry.get("orders-prod-2024").unwrap().as_bytes(),
+            keygen(1).as_slice()
+        );
+        assert_eq!(registry.meta("orders-dev"), Some(&meta()));
+
+        let sig = registry.sign_as("orders-prod-2024", b"payload").unwrap();
+        assert!(verify(&keygen(1), b"payload", &sig));
+        assert_eq!(
+            registry.sign_as("missing", b"payload"),
+            Err(CryptoError::UnknownKey("missing".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_sign_as_uses_registered_algorithm() {
+        let mut registry = KeyRegistry::new();
+        let meta = KeyMeta {
+            algorithm: SigAlgorithm::HmacSha512,
+            ..meta()
+        };
+        registry
+            .insert("wide", SecretKey::from_seed(1), meta, false)
+            .unwrap();
+        assert_eq!(registry.sign_as("wide", b"payload").unwrap().len(), 64);
+    }
+
+    #[test]
+    fn test_duplicate_label_requires_replace() {
+        let mut registry = KeyRegistry::new();
+        registry
+            .insert("k", SecretKey::from_seed(1), meta(), false)
+            .unwrap();
+
+        assert_eq!(
+            registry.insert("k", SecretKey::from_seed(2), meta(), false),
+            Err(CryptoError::DuplicateLabel("k".to_string()))
+        );
+        assert_eq!(registry.get("k").unwrap().as_bytes(), keygen(1).as_slice());
+
+        registry
+            .insert("k", SecretKey::from_seed(2), meta(), true)
+            .unwrap();
+        assert_eq!(registry.get("k").unwrap().as_bytes(), keygen(2).as_slice());
+        assert_eq!(registry.len(), 1);
+    }
+
+    #[test]
+    fn test_metadata_json_contains_no_key_material() {
+        let mut registry = KeyRegistry::new();
+        registry
+            .insert("k", SecretKey::from_seed(1), meta(), false)
+            .unwrap();
+
+        let json = registry.metadata_json();
+        assert_eq!(
+            json,
+            r#"{"k":{"created_at":1700000000,"algorithm":"hmac-sha256","tags":["prod"]}}"#
+        );
+
+        let key = keygen(1);
+        let key_hex: String = key.iter().map(|b| format!("{b:02x}")).collect();
+        assert!(!json.contains(&key_hex));
+        let key_array = format!("{key:?}").replace(' ', "");
+        assert!(!json.contains(&key_array[1..key_array.len() - 1]));
+    }
+}
@@ -0,0 +1,191 @@
+//! Owned secret key material.
+//!
+//! [`SecretKey`] holds a 32-byte key on the heap, redacts it from `Debug`
+//! output, and zeroizes it on drop. With the `secure-mem` feature on Unix
+//! the key lives in its own page-aligned allocation that is locked with
+//! `mlock` so it is never written to swap.
+//!
+//! Locking can fail (e.g. `RLIMIT_MEMLOCK` exhausted, unsupported platform).
+//! In that case the key is still fully usable; a warning is logged and
+//! [`SecretKey::is_locked`] reports `false`.
+
+use std::fmt;
+
+use zeroize::Zeroize;
+
+use crate::{keygen, sign, verify, CryptoError, KEY_SIZE};
+
+/// Key storage. Under `secure-mem` each key occupies a whole page so that
+/// unlocking one key never unlocks another that shares its page.
+#[cfg_attr(all(feature = "secure-mem", unix), repr(C, align(4096)))]
+struct KeyBytes([u8; KEY_SIZE]);
+
+/// A 32-byte signing key that is zeroized on drop.
+pub struct SecretKey {
+    bytes: Box<KeyBytes>,
+    locked: bool,
+}
+
+impl SecretKey {
+    /// Wrap raw key bytes.
+    pub fn from_bytes(mut bytes: [u8; KEY_SIZE]) -> Self {
+        let key = Self::new_with_lock(&bytes, lock_memory);
+        bytes.zeroize();
+        key
+    }
+
+    /// Copy key bytes from a slice.
+    ///
+    /// # Returns
+    /// * `Ok(SecretKey)` - If `bytes` is exactly `KEY_SIZE` long
+    /// * `Err(CryptoError::InvalidLength)` - Otherwise
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, CryptoError> {
+        if bytes.len() != KEY_SIZE {
+            return Err(CryptoError::InvalidLength {
+                expected: KEY_SIZE,
+                actual: bytes.len(),
+            });
+        }
+        Ok(Self::new_with_lock(bytes, lock_memory))
+    }
+
+    /// Derive a key deterministically from a seed (see [`keygen`]).
+    pub fn from_seed(seed: u64) -> Self {
+        let mut bytes = keygen(seed);
+        let key = Self::new_with_lock(&bytes, lock_memory);
+        bytes.zeroize();
+        key
+    }
+
+    /// Allocate storage, try to lock it, then copy the key in.
+    fn new_with_lock(bytes: &[u8], lock: impl FnOnce(&mut [u8]) -> bool) -> Self {
+        let mut storage = Box::new(KeyBytes([0u8; KEY_SIZE]));
+        let locked = lock(&mut storage.0);
+        if !locked && cfg!(feature = "secure-mem") {
+            log::warn!("mlock failed; secret key memory may be swapped");
+        }
+        storage.0.copy_from_slice(bytes);
+        Self {
+            bytes: storage,
+            locked,
+        }
+    }
+
+    /// Raw key bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes.0
+    }
+
+    /// Whether the key memory is page-locked.
+    ///
+    /// Always `false` without the `secure-mem` feature.
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Sign a payload with this key (see [`sign`]).
+    pub fn sign(&self, payload: &[u8]) -> Vec<u8> {
+        sign(self.as_bytes(), payload)
+    }
+
+    /// Verify a signature with this key (see [`verify`]).
+    pub fn verify(&self, payload: &[u8], sig: &[u8]) -> bool {
+        verify(self.as_bytes(), payload, sig)
+    }
+}
+
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        self.bytes.0.zeroize();
+        if self.locked {
+            unlock_memory(&mut self.bytes.0);
+        }
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretKey")
+            .field("bytes", &"<redacted>")
+            .field("locked", &self.locked)
+            .finish()
+    }
+}
+
+#[cfg(all(feature = "secure-mem", unix))]
+fn lock_memory(buf: &mut [u8]) -> bool {
+    // SAFETY: `buf` is a valid, exclusively borrowed allocation of `buf.len()` bytes.
+    unsafe { memsec::mlock(buf.as_mut_ptr(), buf.len()) }
+}
+
+#[cfg(not(all(feature = "secure-mem", unix)))]
+fn lock_memory(_buf: &mut [u8]) -> bool {
+    false
+}
+
+#[cfg(all(feature = "secure-mem", unix))]
+fn unlock_memory(buf: &mut [u8]) {
+    // SAFETY: `buf` was locked by `lock_memory` and is still exclusively owned.
+    unsafe {
+        memsec::munlock(buf.as_mut_ptr(), buf.len());
+    }
+}
+
+#[cfg(not(all(feature = "secure-mem", unix)))]
+fn unlock_memory(_buf: &mut [u8]) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_key_matches_keygen() {
+        let key = SecretKey::from_seed(42);
+        assert_eq!(key.as_bytes(), keygen(42).as_slice());
+    }
+
+    #[test]
+    fn test_secret_key_rejects_wrong_length() {
+        assert_eq!(
+            SecretKey::from_slice(&[0u8; 16]).unwrap_err(),
+            CryptoError::InvalidLength {
+                expected: KEY_SIZE,
+                actual: 16,
+            }
+        );
+    }
+
+    #[test]
+    fn test_secret_key_debug_is_redacted() {
+        let key = SecretKey::from_bytes([0xab; KEY_SIZE]);
+        let debug = format!("{key:?}");
+        assert!(debug.contains("<redacted>"));
+        assert!(!debug.contains("171"), "key bytes must not be printed");
+    }
+
+    #[test]
+    fn test_lock_failure_falls_back_to_usable_key() {
+        let bytes = keygen(7);
+        let key = SecretKey::new_with_lock(&bytes, |_| false);
+        assert!(!key.is_locked());
+
+        let sig = key.sign(b"payload");
+        assert_eq!(sig, sign(&bytes, b"payload"));
+        assert!(key.verify(b"payload", &sig));
+    }
+
+    #[cfg(all(feature = "secure-mem", unix))]
+    #[test]
+    fn test_secure_mem_key_is_usable_whether_or_not_locked() {
+        let key = SecretKey::from_seed(42);
+        let sig = key.sign(b"payload");
+        assert!(key.verify(b"payload", &sig));
+        drop(key);
+    }
+
+    #[cfg(not(feature = "secure-mem"))]
+    #[test]
+    fn test_keys_are_unlocked_without_secure_mem() {
+        assert!(!SecretKey::from_seed(42).is_locked());
+    }
+}
@@ -0,0 +1,100 @@
+//! Canonical test vectors for [`keygen`] and [`sign`].
+//!
+//! Other implementations (the Python bindings' callers, future ports)
+//! cross-check themselves against these. Any change to the output is a
+//! breaking change to key derivation or signing.
+
+use pyo3::prelude::*;
+use serde::Serialize;
+
+use crate::{keygen, sign};
+
+/// Payload signed by every test vector.
+pub const TEST_VECTOR_PAYLOAD: &str = "tinywindow test vector";
+
+/// Number of vectors exported by [`test_vectors_json`].
+pub const DEFAULT_TEST_VECTOR_COUNT: usize = 16;
+
+/// One keygen/sign test vector.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TestVector {
+    /// Seed passed to [`keygen`]
+    pub seed: u64,
+    /// Derived key, lowercase hex
+    pub key: String,
+    /// Signed payload ([`TEST_VECTOR_PAYLOAD`])
+    pub payload: String,
+    /// Expected signature, lowercase hex
+    pub signature: String,
+}
+
+/// Generate vectors for seeds `0..count`.
+pub fn generate_test_vectors(count: usize) -> Vec<TestVector> {
+    (0..count as u64)
+        .map(|seed| {
+            let key = keygen(seed);
+            TestVector {
+                seed,
+                key: to_hex(&key),
+                payload: TEST_VECTOR_PAYLOAD.to_string(),
+                signature: to_hex(&sign(&key, TEST_VECTOR_PAYLOAD.as_bytes())),
+            }
+        })
+        .collect()
+}
+
+/// The first [`DEFAULT_TEST_VECTOR_COUNT`] vectors as a JSON array.
+pub fn test_vectors_json() -> String {
+    serde_json::to_string_pretty(&generate_test_vectors(DEFAULT_TEST_VECTOR_COUNT))
+        .expect("test vectors always serialize")
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Export the canonical test vectors as JSON (Python binding).
+#[pyfunction]
+#[pyo3(name = "test_vectors_json")]
+pub(crate) fn py_test_vectors_json() -> String {
+    test_vectors_json()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vectors_are_stable() {
+        let vectors = generate_test_vectors(2);
+        assert_eq!(
+            vectors[0],
+            TestVector {
+                seed: 0,
+                key: "b2f7f581d6de3c06a822fd6e7e8265fbc00f8401696a5bdc34f5a6d2ff3f922f".to_string(),
+                payload: TEST_VECTOR_PAYLOAD.to_string(),
+                signature: "a5ed132d401ced2382f16925ad4303b1a42115f959a004a3ad7fce6ff8863342"
+                    .to_string(),
+            }
+        );
+        assert_eq!(
+            vectors[1],
+            TestVector {
+                seed: 1,
+                key: "9a3744504560639ec670b7a17d492b273e077b0a96bef58ba7760779e544546e".to_string(),
+                payload: TEST_VECTOR_PAYLOAD.to_string(),
+                signature: "a4e1cae3a9cb313ac91af777813af375828ee8cdbe4c77908f3ffa57a3f227ae"
+                    .to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_vectors_json_is_deterministic() {
+        let json = test_vectors_json();
+        assert_eq!(json, test_vectors_json());
+        let parsed: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.len(), DEFAULT_TEST_VECTOR_COUNT);
+        assert_eq!(parsed[3]["seed"], 3);
+    }
+}
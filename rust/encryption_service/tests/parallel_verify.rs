@@ -0,0 +1,40 @@
+//! Serial vs parallel verification throughput on 1M items.
+//!
+//! Ignored by default; run with
+//! `cargo test --release -p encryption_service --features parallel --test parallel_verify -- --ignored --nocapture`.
+
+#![cfg(feature = "parallel")]
+
+use std::time::Instant;
+
+use tinywindow_rust_encryption::{keygen, sign, verify_batch, verify_parallel};
+
+const ITEMS: usize = 1_000_000;
+
+#[test]
+#[ignore = "benchmark; run in release mode"]
+fn bench_verify_parallel_vs_serial() {
+    let key = keygen(42);
+    let items: Vec<(Vec<u8>, Vec<u8>)> = (0..ITEMS)
+        .map(|i| {
+            let payload = format!("order-{i}").into_bytes();
+            let sig = sign(&key, &payload);
+            (payload, sig)
+        })
+        .collect();
+
+    let start = Instant::now();
+    let serial = verify_batch(&key, &items);
+    let serial_elapsed = start.elapsed();
+
+    let start = Instant::now();
+    let parallel = verify_parallel(&key, &items);
+    let parallel_elapsed = start.elapsed();
+
+    assert_eq!(serial, parallel);
+    println!(
+        "{ITEMS} items: serial {serial_elapsed:?}, parallel {parallel_elapsed:?} ({:.1}x on {} threads)",
+        serial_elapsed.as_secs_f64() / parallel_elapsed.as_secs_f64(),
+        rayon::current_num_threads(),
+    );
+}
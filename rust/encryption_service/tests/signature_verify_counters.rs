@@ -0,0 +1,23 @@
+//! Verification attempts and failures reach `signature_verify_*_total`.
+//!
+//! Runs in its own process so the counts are exact.
+
+#![cfg(feature = "metrics")]
+
+use tinywindow_rust_encryption::{keygen, sign, verify};
+use tinywindow_rust_telemetry::get_counter_value;
+
+#[test]
+fn test_good_and_bad_verify_are_counted() {
+    let key = keygen(42);
+    let sig = sign(&key, b"payload");
+    assert!(verify(&key, b"payload", &sig));
+    assert!(!verify(&key, b"tampered", &sig));
+
+    assert_eq!(get_counter_value("signature_verify_total"), Some(2.0));
+    assert_eq!(
+        get_counter_value("signature_verify_failures_total"),
+        Some(1.0)
+    );
+    assert_eq!(get_counter_value("crypto_verify_total"), None);
+}
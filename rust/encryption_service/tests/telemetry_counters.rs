@@ -0,0 +1,34 @@
+//! Cross-crate check that sign/verify counters reach the telemetry registry.
+
+#![cfg(feature = "telemetry")]
+
+use tinywindow_rust_encryption::{keygen, sign, verify};
+use tinywindow_rust_telemetry::get_metrics;
+
+/// Read an unlabeled sample value from the Prometheus text output.
+fn sample(output: &str, name: &str) -> Option<f64> {
+    output.lines().find_map(|line| {
+        let (metric, value) = line.split_once(' ')?;
+        (metric == name).then(|| value.parse().ok())?
+    })
+}
+
+#[test]
+fn test_sign_and_failed_verify_are_counted() {
+    let key = keygen(42);
+    let payload = b"telemetry payload";
+
+    let sig = sign(&key, payload);
+    let mut bad_sig = sig.clone();
+    bad_sig[0] ^= 0xff;
+    assert!(!verify(&key, payload, &bad_sig));
+
+    let output = get_metrics();
+    assert!(sample(&output, "crypto_sign_total").unwrap() >= 1.0);
+    assert!(sample(&output, "signature_verify_total").unwrap() >= 1.0);
+    assert_eq!(
+        sample(&output, "signature_verify_failures_total"),
+        Some(1.0)
+    );
+    assert!(output.contains("latency_seconds_count{operation=\"crypto_sign\"}"));
+}
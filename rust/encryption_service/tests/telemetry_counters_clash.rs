@@ -0,0 +1,19 @@
+//! A name clash on a crypto counter does not break signing.
+//!
+//! Runs in its own process so the clashing metric is registered first.
+
+#![cfg(feature = "telemetry")]
+
+use tinywindow_rust_encryption::{keygen, sign, verify};
+use tinywindow_rust_telemetry::{get_metrics, register_counter_vec};
+
+#[test]
+fn test_sign_survives_unregistrable_counter() {
+    register_counter_vec("crypto_sign_total", "Taken by the application", &["key"]).unwrap();
+
+    let key = keygen(42);
+    let sig = sign(&key, b"payload");
+    assert!(verify(&key, b"payload", &sig));
+    // The other counters are still exported.
+    assert!(get_metrics().contains("\nsignature_verify_total 1\n"));
+}
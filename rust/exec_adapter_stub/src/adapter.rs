@@ -0,0 +1,287 @@
+//! Configured order submission.
+//!
+//! An [`ExecAdapter`] bundles a backend with the policies applied to every
+//! order sent through it: an ack timeout, retries of connection failures
+//! and a rate limit. Build one with [`ExecAdapterBuilder`]; the default
+//! configuration sends through the free [`send_order`](crate::send_order)
+//! function with no policies, so it behaves exactly like it.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::time::Instant;
+
+use crate::backend::ExecutionBackend;
+use crate::{send_order, ExecError, OrderAck};
+
+/// Backend of a default [`ExecAdapter`]: the free [`send_order`] function,
+/// with its process-wide order IDs and metrics.
+struct FreeFunctionBackend;
+
+#[async_trait]
+impl ExecutionBackend for FreeFunctionBackend {
+    async fn submit(&self, order: Vec<u8>) -> Result<OrderAck, ExecError> {
+        send_order(order).await
+    }
+}
+
+/// Spaces submissions at least `interval` apart.
+struct RateLimiter {
+    interval: Duration,
+    next_slot: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    /// Reserve the next free slot and wait for it.
+    async fn acquire(&self) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let now = Instant::now();
+            let slot = next_slot.map_or(now, |next| next.max(now));
+            *next_slot = Some(slot + self.interval);
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+/// Builder for [`ExecAdapter`].
+///
+/// Every setting is optional; see the setters for the defaults.
+#[derive(Default)]
+pub struct ExecAdapterBuilder {
+    backend: Option<Box<dyn ExecutionBackend>>,
+    timeout: Option<Duration>,
+    max_retries: u32,
+    rate_limit: Option<u32>,
+}
+
+impl ExecAdapterBuilder {
+    /// A builder with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Submit through `backend` instead of the free
+    /// [`send_order`](crate::send_order) function.
+    ///
+    /// The adapter does not connect it; connect it before sending.
+    pub fn backend(mut self, backend: impl ExecutionBackend + 'static) -> Self {
+        self.backend = Some(Box::new(backend));
+        self
+    }
+
+    /// Fail an attempt with [`ExecError::Timeout`] if its ack takes longer
+    /// than `timeout`. No timeout by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Retry an order up to `max_retries` more times after an
+    /// [`ExecError::ConnectionError`]. No retries by default.
+    ///
+    /// Timeouts are not retried: the venue may have received the order, and
+    /// sending it again could fill it twice.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Send at most `orders_per_second` orders per second, spaced evenly;
+    /// orders over the limit wait for their turn. Unlimited by default.
+    ///
+    /// A limit of 0 is treated as 1.
+    pub fn rate_limit(mut self, orders_per_second: u32) -> Self {
+        self.rate_limit = Some(orders_per_second.max(1));
+        self
+    }
+
+    /// Build the adapter.
+    pub fn build(self) -> ExecAdapter {
+        ExecAdapter {
+            backend: self
+                .backend
+                .unwrap_or_else(|| Box::new(FreeFunctionBackend)),
+            timeout: self.timeout,
+            max_retries: self.max_retries,
+            rate_limiter: self.rate_limit.map(|orders_per_second| RateLimiter {
+                interval: Duration::from_secs(1) / orders_per_second,
+                next_slot: Mutex::new(None),
+            }),
+        }
+    }
+}
+
+/// A backend plus the policies applied to each order sent through it.
+///
+/// The timeout and rate limit use `tokio::time`, so tests should run with
+/// paused time and advance the clock explicitly, as for
+/// [`StubBackend::with_latency`](crate::StubBackend::with_latency).
+pub struct ExecAdapter {
+    backend: Box<dyn ExecutionBackend>,
+    timeout: Option<Duration>,
+    max_retries: u32,
+    rate_limiter: Option<RateLimiter>,
+}
+
+impl Default for ExecAdapter {
+    fn default() -> Self {
+        ExecAdapterBuilder::new().build()
+    }
+}
+
+impl ExecAdapter {
+    /// Start configuring an adapter.
+    pub fn builder() -> ExecAdapterBuilder {
+        ExecAdapterBuilder::new()
+    }
+
+    /// The backend orders are submitted to.
+    pub fn backend(&self) -> &dyn ExecutionBackend {
+        self.backend.as_ref()
+    }
+
+    /// Send an order, applying the configured policies.
+    ///
+    /// Waits for a slot under the rate limit, then submits, retrying
+    /// connection errors up to the retry limit; each attempt is subject to
+    /// the timeout.
+    ///
+    /// # Arguments
+    /// * `order` - The order payload as bytes
+    ///
+    /// # Returns
+    /// * `Ok(OrderAck)` - Order acknowledgment with status
+    /// * `Err(ExecError::Timeout)` - If an attempt timed out
+    /// * `Err(ExecError)` - The backend's error, after the last retry for
+    ///   connection errors
+    pub async fn send_order(&self, order: Vec<u8>) -> Result<OrderAck, ExecError> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire().await;
+        }
+        let mut retries_left = self.max_retries;
+        loop {
+            let result = self.submit_once(order.clone()).await;
+            match result {
+                Err(ExecError::ConnectionError(_)) if retries_left > 0 => retries_left -= 1,
+                result => return result,
+            }
+        }
+    }
+
+    async fn submit_once(&self, order: Vec<u8>) -> Result<OrderAck, ExecError> {
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.backend.submit(order))
+                .await
+                .unwrap_or(Err(ExecError::Timeout)),
+            None => self.backend.submit(order).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+    use crate::{RejectReason, StubBackend, STUB_ORDER_QUANTITY};
+
+    /// Fails the first `failures` submissions with a connection error.
+    struct FlakyBackend {
+        failures: u32,
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl ExecutionBackend for FlakyBackend {
+        async fn submit(&self, _order: Vec<u8>) -> Result<OrderAck, ExecError> {
+            let call = self.calls.fetch_add(1, Ordering::Relaxed);
+            if call < self.failures {
+                Err(ExecError::ConnectionError("refused".to_string()))
+            } else {
+                Ok(OrderAck::filled(u64::from(call), 1))
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_adapter_behaves_like_send_order() {
+        let adapter = ExecAdapter::default();
+        let ack = adapter.send_order(b"BUY 1 AAPL".to_vec()).await.unwrap();
+        let legacy = send_order(b"BUY 1 AAPL".to_vec()).await.unwrap();
+        assert!(ack.accepted && legacy.accepted);
+        assert!(ack.is_fully_filled());
+        assert_eq!(ack.filled_quantity, STUB_ORDER_QUANTITY);
+        assert_eq!(ack.reason, legacy.reason);
+
+        assert_eq!(
+            adapter.send_order(Vec::new()).await,
+            Err(ExecError::ValidationFailed(RejectReason::EmptyOrder))
+        );
+        assert_eq!(
+            adapter.send_order(Vec::new()).await,
+            send_order(Vec::new()).await
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tiny_timeout_times_out() {
+        let backend = StubBackend::with_latency(Duration::from_millis(50));
+        backend.connect().await.unwrap();
+        let adapter = ExecAdapter::builder()
+            .backend(backend)
+            .timeout(Duration::from_millis(1))
+            .max_retries(3)
+            .build();
+
+        let start = Instant::now();
+        assert_eq!(
+            adapter.send_order(b"BUY 1 AAPL".to_vec()).await,
+            Err(ExecError::Timeout)
+        );
+        // Timeouts are not retried.
+        assert_eq!(start.elapsed(), Duration::from_millis(1));
+    }
+
+    #[tokio::test]
+    async fn test_connection_errors_are_retried() {
+        let adapter = ExecAdapter::builder()
+            .backend(FlakyBackend {
+                failures: 2,
+                calls: AtomicU32::new(0),
+            })
+            .max_retries(2)
+            .build();
+        assert_eq!(adapter.send_order(b"x".to_vec()).await.unwrap().order_id, 2);
+
+        let adapter = ExecAdapter::builder()
+            .backend(FlakyBackend {
+                failures: 2,
+                calls: AtomicU32::new(0),
+            })
+            .max_retries(1)
+            .build();
+        assert!(matches!(
+            adapter.send_order(b"x".to_vec()).await,
+            Err(ExecError::ConnectionError(_))
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limit_spaces_orders() {
+        let adapter = ExecAdapter::builder()
+            .backend(FlakyBackend {
+                failures: 0,
+                calls: AtomicU32::new(0),
+            })
+            .rate_limit(10)
+            .build();
+        let start = Instant::now();
+        for _ in 0..3 {
+            adapter.send_order(b"x".to_vec()).await.unwrap();
+        }
+        assert_eq!(start.elapsed(), Duration::from_millis(200));
+    }
+}
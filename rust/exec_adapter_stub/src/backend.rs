@@ -0,0 +1,758 @@
+//! Pluggable execution backends.
+//!
+//! An [`ExecutionBackend`] is anything that can accept an order payload and
+//! produce an [`OrderAck`]. Wrappers such as the circuit breaker implement
+//! the same trait so they can be stacked in front of a real venue connection.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use tokio::sync::mpsc;
+
+use crate::{
+    pre_trade_check, Book, BookSnapshot, ExecError, FillEvent, Order, OrderAck, OrderIdGenerator,
+    OrderStatus, STUB_ORDER_QUANTITY,
+};
+
+/// Buffer size of each fill subscription channel.
+pub const FILL_CHANNEL_CAPACITY: usize = 64;
+
+/// Seeded coin flips deciding which submissions a [`StubBackend`] rejects.
+#[derive(Debug)]
+struct RejectInjector {
+    rate: f64,
+    rng: Mutex<ChaCha20Rng>,
+}
+
+impl RejectInjector {
+    /// Whether to reject the next submission.
+    fn reject(&self) -> bool {
+        // `gen` is in `0.0..1.0`, so a rate of 1.0 rejects every submission.
+        self.rng.lock().unwrap().gen::<f64>() < self.rate
+    }
+}
+
+/// Latest acknowledgment and status of an order submitted to a [`StubBackend`].
+#[derive(Debug)]
+struct TrackedOrder {
+    ack: OrderAck,
+    status: OrderStatus,
+}
+
+/// An order submission backend.
+#[async_trait]
+pub trait ExecutionBackend: Send + Sync {
+    /// Submit an order payload and wait for its acknowledgment.
+    ///
+    /// # Arguments
+    /// * `order` - The order payload as bytes
+    ///
+    /// # Returns
+    /// * `Ok(OrderAck)` - Order acknowledgment with status
+    /// * `Err(ExecError)` - Error if order could not be processed
+    async fn submit(&self, order: Vec<u8>) -> Result<OrderAck, ExecError>;
+
+    /// Establish the connection to the venue.
+    ///
+    /// Backends without a connection lifecycle succeed immediately.
+    async fn connect(&self) -> Result<(), ExecError> {
+        Ok(())
+    }
+
+    /// Close the connection to the venue.
+    ///
+    /// Backends without a connection lifecycle succeed immediately.
+    async fn disconnect(&self) -> Result<(), ExecError> {
+        Ok(())
+    }
+
+    /// Whether the backend is currently able to accept orders.
+    fn is_connected(&self) -> bool {
+        true
+    }
+
+    /// Check that the venue is still reachable.
+    ///
+    /// Used by the heartbeat task (see [`HeartbeatExt`](crate::HeartbeatExt)).
+    async fn ping(&self) -> Result<(), ExecError> {
+        Ok(())
+    }
+
+    /// Subscribe to fill notifications for orders submitted after this call.
+    ///
+    /// Dropping the receiver unsubscribes. Backends that never report fills
+    /// return a receiver that is already closed.
+    fn subscribe_fills(&self) -> mpsc::Receiver<FillEvent> {
+        mpsc::channel(1).1
+    }
+}
+
+/// Deterministic in-process backend used for tests and local development.
+///
+/// Behaves exactly like the free [`send_order`](crate::send_order) function:
+/// empty orders are rejected and everything else is accepted. Each accepted
+/// order also produces a simulated [`FillEvent`] on every fill subscription.
+///
+/// Order IDs come from the backend's own [`OrderIdGenerator`], so two stub
+/// backends number their orders independently.
+///
+/// The stub starts disconnected; call [`connect`](ExecutionBackend::connect)
+/// before submitting. Pings succeed while connected unless
+/// [`set_ping_failure`](Self::set_ping_failure) is enabled.
+///
+/// A backend built with [`with_latency`](Self::with_latency) waits before
+/// every response. The wait uses `tokio::time`, so tests should run with
+/// paused time (`#[tokio::test(start_paused = true)]` or
+/// `tokio::time::pause()`) and advance the clock explicitly for
+/// deterministic timing.
+///
+/// A backend built with [`with_fill_ratio`](Self::with_fill_ratio) parses
+/// each payload as an [`Order`] and fills only part of its quantity.
+///
+/// A backend built with [`with_book`](Self::with_book) instead keeps a
+/// [`Book`] per symbol: limit orders rest until matched and market orders
+/// trade against them.
+///
+/// A backend built with [`with_reject_rate`](Self::with_reject_rate)
+/// fails a seeded random share of submissions with
+/// [`ExecError::ConnectionError`], for chaos testing.
+///
+/// Every accepted order is tracked: [`order_status`](Self::order_status)
+/// reports where it stands and [`cancel_all`](Self::cancel_all) cancels
+/// whatever is still open.
+#[derive(Debug, Default)]
+pub struct StubBackend {
+    connected: AtomicBool,
+    fail_pings: AtomicBool,
+    latency: Duration,
+    fill_ratio: Option<f64>,
+    books: Option<Mutex<HashMap<String, Book>>>,
+    orders: Mutex<HashMap<u64, TrackedOrder>>,
+    order_ids: Arc<OrderIdGenerator>,
+    fill_subscribers: Mutex<Vec<mpsc::Sender<FillEvent>>>,
+    rejects: Option<RejectInjector>,
+}
+
+impl StubBackend {
+    /// Create a new stub backend with its own ID sequence starting at 1.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a stub backend drawing order IDs from a shared generator.
+    pub fn with_order_ids(order_ids: Arc<OrderIdGenerator>) -> Self {
+        Self {
+            order_ids,
+            ..Self::default()
+        }
+    }
+
+    /// Create a stub backend that waits `latency` before answering each submission.
+    pub fn with_latency(latency: Duration) -> Self {
+        Self {
+            latency,
+            ..Self::default()
+        }
+    }
+
+    /// Create a stub backend that fills `ratio` of each order's quantity.
+    ///
+    /// Payloads must parse as an [`Order`]; malformed ones are rejected. Each
+    /// accepted order is filled `floor(quantity * ratio)` with the remainder
+    /// left open. `ratio` is clamped to `0.0..=1.0`.
+    pub fn with_fill_ratio(ratio: f64) -> Self {
+        Self {
+            fill_ratio: Some(ratio.clamp(0.0, 1.0)),
+            ..Self::default()
+        }
+    }
+
+    /// Create a stub backend that rejects about `rate` of its submissions,
+    /// for chaos testing of callers' error handling.
+    ///
+    /// Each submission made while connected draws from an RNG seeded with
+    /// `seed` and is rejected with [`ExecError::ConnectionError`] before
+    /// any other check, so the same seed rejects the same submissions, by
+    /// position, on every run. `rate` is clamped to `0.0..=1.0`; NaN
+    /// rejects nothing.
+    pub fn with_reject_rate(rate: f64, seed: u64) -> Self {
+        Self {
+            rejects: Some(RejectInjector {
+                rate: rate.clamp(0.0, 1.0),
+                rng: Mutex::new(ChaCha20Rng::seed_from_u64(seed)),
+            }),
+            ..Self::default()
+        }
+    }
+
+    /// Create a stub backend that matches orders against a simulated order book.
+    ///
+    /// Payloads must parse as an [`Order`]; malformed ones are rejected.
+    /// Every trade is published to fill subscribers for both the resting
+    /// and the incoming order.
+    pub fn with_book() -> Self {
+        Self {
+            books: Some(Mutex::default()),
+            ..Self::default()
+        }
+    }
+
+    /// Top-of-book and depth for `symbol`.
+    ///
+    /// Empty if the backend has no book or nothing ever rested on `symbol`.
+    pub fn book_snapshot(&self, symbol: &str) -> BookSnapshot {
+        let Some(books) = &self.books else {
+            return BookSnapshot::default();
+        };
+        books
+            .lock()
+            .unwrap()
+            .get(&symbol.to_ascii_uppercase())
+            .map(Book::snapshot)
+            .unwrap_or_default()
+    }
+
+    /// Current status of an order submitted to this backend.
+    ///
+    /// # Returns
+    /// `None` if `order_id` was never accepted by this backend
+    pub fn order_status(&self, order_id: u64) -> Option<OrderStatus> {
+        self.orders
+            .lock()
+            .unwrap()
+            .get(&order_id)
+            .map(|order| order.status)
+    }
+
+    /// Cancel every open order, e.g. for a risk kill switch.
+    ///
+    /// Resting orders leave the book and report [`OrderStatus::Cancelled`].
+    ///
+    /// # Returns
+    /// One [`OrderAck::cancelled`] per cancelled order, by ascending order
+    /// ID; empty if nothing was open
+    pub fn cancel_all(&self) -> Vec<OrderAck> {
+        let mut orders = self.orders.lock().unwrap();
+        if let Some(books) = &self.books {
+            books.lock().unwrap().values_mut().for_each(Book::clear);
+        }
+        let mut acks: Vec<OrderAck> = orders
+            .values_mut()
+            .filter(|order| order.status.is_open())
+            .map(|order| {
+                order.status = OrderStatus::Cancelled;
+                order.ack = OrderAck::cancelled(order.ack.order_id, order.ack.filled_quantity);
+                order.ack.clone()
+            })
+            .collect();
+        acks.sort_by_key(|ack| ack.order_id);
+        acks
+    }
+
+    /// Simulated round-trip latency of each submission.
+    pub fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    /// Make subsequent pings fail (or succeed again), simulating a dead link.
+    pub fn set_ping_failure(&self, fail: bool) {
+        self.fail_pings.store(fail, Ordering::SeqCst);
+    }
+
+    /// The generator this backend draws order IDs from.
+    pub fn order_ids(&self) -> &Arc<OrderIdGenerator> {
+        &self.order_ids
+    }
+
+    /// Deliver `fills` in order to all live subscribers from a background
+    /// task, pruning subscriptions whose receiver has been dropped.
+    ///
+    /// When the submission is not polled on a Tokio runtime there is no task
+    /// to wait for room in, so fills go straight into the channels and a
+    /// fill that does not fit is dropped.
+    fn publish_fills(&self, fills: Vec<FillEvent>) {
+        let subscribers = {
+            let mut guard = self.fill_subscribers.lock().unwrap();
+            guard.retain(|tx| !tx.is_closed());
+            guard.clone()
+        };
+        if subscribers.is_empty() || fills.is_empty() {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            for fill in &fills {
+                for tx in &subscribers {
+                    if let Err(mpsc::error::TrySendError::Full(_)) = tx.try_send(fill.clone()) {
+                        log::warn!("dropping fill of order {}: channel full", fill.order_id);
+                    }
+                }
+            }
+            return;
+        };
+        runtime.spawn(async move {
+            for fill in fills {
+                for tx in &subscribers {
+                    // A receiver dropped in the meantime is not an error.
+                    let _ = tx.send(fill.clone()).await;
+                }
+            }
+        });
+    }
+
+    /// The stub's submission logic; `submit` wraps it to count the result.
+    async fn submit_inner(&self, order: Vec<u8>) -> Result<OrderAck, ExecError> {
+        if !self.is_connected() {
+            return Err(ExecError::ConnectionError("not connected".to_string()));
+        }
+        if self.rejects.as_ref().is_some_and(RejectInjector::reject) {
+            return Err(ExecError::ConnectionError("injected reject".to_string()));
+        }
+        if !self.latency.is_zero() {
+            tokio::time::sleep(self.latency).await;
+        }
+        pre_trade_check(&order)?;
+
+        if let Some(books) = &self.books {
+            let order = Order::parse(&order).map_err(ExecError::ValidationFailed)?;
+            // Lock order (orders, then books) matches `cancel_all`.
+            let mut orders = self.orders.lock().unwrap();
+            let (ack, fills) = books
+                .lock()
+                .unwrap()
+                .entry(order.symbol.clone())
+                .or_default()
+                .submit(self.order_ids.next_id(), &order);
+            for fill in &fills {
+                if let Some(resting) = orders.get_mut(&fill.order_id) {
+                    resting.ack.apply_fill(fill);
+                    resting.status = OrderStatus::of(&resting.ack);
+                }
+            }
+            // The unfilled rest of a market order does not rest in the book.
+            let status = match OrderStatus::of(&ack) {
+                status if status.is_open() && order.price.is_none() => OrderStatus::Cancelled,
+                status => status,
+            };
+            orders.insert(
+                ack.order_id,
+                TrackedOrder {
+                    ack: ack.clone(),
+                    status,
+                },
+            );
+            drop(orders);
+            self.publish_fills(fills);
+            return Ok(ack);
+        }
+
+        let ack = match self.fill_ratio {
+            None => OrderAck::filled(self.order_ids.next_id(), STUB_ORDER_QUANTITY),
+            Some(ratio) => {
+                let quantity = Order::parse(&order)
+                    .map_err(ExecError::ValidationFailed)?
+                    .quantity;
+                let filled = (quantity as f64 * ratio).floor() as u64;
+                OrderAck::partially_filled(self.order_ids.next_id(), filled, quantity)
+            }
+        };
+        self.orders.lock().unwrap().insert(
+            ack.order_id,
+            TrackedOrder {
+                ack: ack.clone(),
+                status: OrderStatus::of(&ack),
+            },
+        );
+        if ack.filled_quantity > 0 {
+            self.publish_fills(vec![FillEvent {
+                order_id: ack.order_id,
+                fill_quantity: ack.filled_quantity,
+                remaining_quantity: ack.remaining_quantity,
+            }]);
+        }
+        Ok(ack)
+    }
+}
+
+#[async_trait]
+impl ExecutionBackend for StubBackend {
+    async fn submit(&self, order: Vec<u8>) -> Result<OrderAck, ExecError> {
+        let result = self.submit_inner(order).await;
+        #[cfg(feature = "metrics")]
+        if crate::metrics::self_counting() {
+            match &result {
+                Ok(_) => crate::metrics::record_order(),
+                Err(err) => crate::metrics::record_error(err),
+            }
+        }
+        result
+    }
+
+    async fn connect(&self) -> Result<(), ExecError> {
+        self.connected.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<(), ExecError> {
+        self.connected.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
+    async fn ping(&self) -> Result<(), ExecError> {
+        if !self.is_connected() {
+            return Err(ExecError::ConnectionError("not connected".to_string()));
+        }
+        if self.fail_pings.load(Ordering::SeqCst) {
+            return Err(ExecError::ConnectionError("ping failed".to_string()));
+        }
+        Ok(())
+    }
+
+    fn subscribe_fills(&self) -> mpsc::Receiver<FillEvent> {
+        let (tx, rx) = mpsc::channel(FILL_CHANNEL_CAPACITY);
+        self.fill_subscribers.lock().unwrap().push(tx);
+        rx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn connected_stub() -> StubBackend {
+        let backend = StubBackend::new();
+        backend.connect().await.unwrap();
+        backend
+    }
+
+    #[tokio::test]
+    async fn test_stub_backend_rejects_orders_before_connect() {
+        let backend = StubBackend::new();
+        assert!(!backend.is_connected());
+        assert_eq!(
+            backend.submit(b"order".to_vec()).await,
+            Err(ExecError::ConnectionError("not connected".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stub_backend_connect_then_send() {
+        let backend = StubBackend::new();
+        backend.connect().await.unwrap();
+        assert!(backend.is_connected());
+        assert!(backend.submit(b"order".to_vec()).await.unwrap().accepted);
+    }
+
+    #[tokio::test]
+    async fn test_stub_backend_disconnect_stops_orders() {
+        let backend = connected_stub().await;
+        backend.disconnect().await.unwrap();
+        assert!(!backend.is_connected());
+        assert_eq!(
+            backend.submit(b"order".to_vec()).await,
+            Err(ExecError::ConnectionError("not connected".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reject_rate_is_deterministic_per_seed() {
+        async fn outcomes(rate: f64, seed: u64) -> Vec<bool> {
+            let backend = StubBackend::with_reject_rate(rate, seed);
+            backend.connect().await.unwrap();
+            let mut accepted = Vec::new();
+            for _ in 0..100 {
+                match backend.submit(b"order".to_vec()).await {
+                    Ok(ack) => accepted.push(ack.accepted),
+                    Err(err) => {
+                        assert_eq!(
+                            err,
+                            ExecError::ConnectionError("injected reject".to_string())
+                        );
+                        accepted.push(false);
+                    }
+                }
+            }
+            accepted
+        }
+
+        assert!(outcomes(1.0, 7).await.iter().all(|accepted| !accepted));
+        assert!(outcomes(0.0, 7).await.iter().all(|accepted| *accepted));
+
+        let first = outcomes(0.3, 7).await;
+        assert_eq!(first, outcomes(0.3, 7).await);
+        assert_ne!(first, outcomes(0.3, 8).await);
+        let rejected = first.iter().filter(|accepted| !**accepted).count();
+        assert!((15..=45).contains(&rejected), "{rejected} of 100 rejected");
+    }
+
+    #[tokio::test]
+    async fn test_stub_backend_accepts_valid_order() {
+        let backend = connected_stub().await;
+        let ack = backend.submit(b"stub order".to_vec()).await.unwrap();
+        assert!(ack.accepted);
+        assert!(ack.order_id > 0);
+    }
+
+    #[tokio::test]
+    async fn test_stub_backend_rejects_empty_order() {
+        let backend = connected_stub().await;
+        let result = backend.submit(vec![]).await;
+        assert_eq!(
+            result,
+            Err(ExecError::ValidationFailed(crate::RejectReason::EmptyOrder))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backends_have_independent_order_ids() {
+        let first = connected_stub().await;
+        let second = connected_stub().await;
+
+        let mut first_ids = Vec::new();
+        let mut second_ids = Vec::new();
+        for _ in 0..3 {
+            first_ids.push(first.submit(b"a".to_vec()).await.unwrap().order_id);
+            second_ids.push(second.submit(b"b".to_vec()).await.unwrap().order_id);
+        }
+
+        assert_eq!(first_ids, vec![1, 2, 3]);
+        assert_eq!(second_ids, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_backends_can_share_a_generator() {
+        let ids = Arc::new(OrderIdGenerator::new());
+        let first = StubBackend::with_order_ids(Arc::clone(&ids));
+        let second = StubBackend::with_order_ids(ids);
+        first.connect().await.unwrap();
+        second.connect().await.unwrap();
+
+        assert_eq!(first.submit(b"a".to_vec()).await.unwrap().order_id, 1);
+        assert_eq!(second.submit(b"b".to_vec()).await.unwrap().order_id, 2);
+    }
+
+    #[tokio::test]
+    async fn test_stub_backend_emits_fill_event() {
+        let backend = connected_stub().await;
+        let mut fills = backend.subscribe_fills();
+
+        let ack = backend.submit(b"fill me".to_vec()).await.unwrap();
+        let fill = fills.recv().await.expect("fill event");
+
+        assert_eq!(fill.order_id, ack.order_id);
+        assert_eq!(fill.fill_quantity, ack.filled_quantity);
+        assert_eq!(fill.remaining_quantity, 0);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_fill_subscription_is_pruned() {
+        let backend = connected_stub().await;
+        drop(backend.subscribe_fills());
+        let mut live = backend.subscribe_fills();
+
+        let ack = backend.submit(b"order".to_vec()).await.unwrap();
+        assert_eq!(live.recv().await.unwrap().order_id, ack.order_id);
+        assert_eq!(backend.fill_subscribers.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_latency_delays_ack_exactly() {
+        let backend = Arc::new(StubBackend::with_latency(Duration::from_millis(100)));
+        backend.connect().await.unwrap();
+
+        let submitter = Arc::clone(&backend);
+        let ack = tokio::spawn(async move { submitter.submit(b"slow".to_vec()).await });
+
+        tokio::time::advance(Duration::from_millis(99)).await;
+        tokio::task::yield_now().await;
+        assert!(
+            !ack.is_finished(),
+            "ack resolved before the latency elapsed"
+        );
+
+        tokio::time::advance(Duration::from_millis(1)).await;
+        let ack = ack.await.unwrap().unwrap();
+        assert!(ack.accepted);
+    }
+
+    #[tokio::test]
+    async fn test_fill_ratio_fills_part_of_order() {
+        let backend = StubBackend::with_fill_ratio(0.5);
+        backend.connect().await.unwrap();
+        let mut fills = backend.subscribe_fills();
+
+        let ack = backend.submit(b"BUY AAPL 10 15025".to_vec()).await.unwrap();
+        assert_eq!(ack.filled_quantity, 5);
+        assert_eq!(ack.remaining_quantity, 5);
+
+        let fill = fills.recv().await.expect("fill event");
+        assert_eq!(fill.fill_quantity, 5);
+        assert_eq!(fill.remaining_quantity, 5);
+
+        let odd = backend.submit(b"SELL MSFT 7".to_vec()).await.unwrap();
+        assert_eq!((odd.filled_quantity, odd.remaining_quantity), (3, 4));
+    }
+
+    #[tokio::test]
+    async fn test_fill_ratio_rejects_unparseable_order() {
+        let backend = StubBackend::with_fill_ratio(0.5);
+        backend.connect().await.unwrap();
+        assert!(matches!(
+            backend.submit(b"not an order".to_vec()).await,
+            Err(ExecError::ValidationFailed(
+                crate::RejectReason::MalformedOrder(_)
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_book_snapshot_shows_resting_limit_orders() {
+        let backend = StubBackend::with_book();
+        backend.connect().await.unwrap();
+        for payload in ["BUY AAPL 10 15020", "BUY AAPL 5 15025", "SELL AAPL 8 15040"] {
+            let ack = backend.submit(payload.as_bytes().to_vec()).await.unwrap();
+            assert_eq!(ack.filled_quantity, 0);
+        }
+        backend.submit(b"SELL MSFT 1 100".to_vec()).await.unwrap();
+
+        let snapshot = backend.book_snapshot("aapl");
+        assert_eq!(
+            snapshot.best_bid,
+            Some(crate::Level {
+                price: 15025,
+                quantity: 5
+            })
+        );
+        assert_eq!(
+            snapshot.best_ask,
+            Some(crate::Level {
+                price: 15040,
+                quantity: 8
+            })
+        );
+        assert_eq!(snapshot.bids.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_book_market_order_fills_against_resting_order() {
+        let backend = StubBackend::with_book();
+        backend.connect().await.unwrap();
+        let resting = backend.submit(b"SELL AAPL 8 15040".to_vec()).await.unwrap();
+        let mut fills = backend.subscribe_fills();
+
+        let ack = backend.submit(b"BUY AAPL 3".to_vec()).await.unwrap();
+        assert_eq!((ack.filled_quantity, ack.remaining_quantity), (3, 0));
+
+        let maker = fills.recv().await.expect("resting fill");
+        assert_eq!(
+            (maker.order_id, maker.remaining_quantity),
+            (resting.order_id, 5)
+        );
+        assert_eq!(
+            fills.recv().await.expect("incoming fill").order_id,
+            ack.order_id
+        );
+        assert_eq!(backend.book_snapshot("AAPL").asks[0].quantity, 5);
+    }
+
+    #[test]
+    fn test_fills_are_delivered_outside_a_tokio_runtime() {
+        // Poll the way a non-Tokio executor would, with no runtime entered.
+        fn ready<F: std::future::Future>(future: F) -> F::Output {
+            let mut context = std::task::Context::from_waker(std::task::Waker::noop());
+            match std::pin::pin!(future).poll(&mut context) {
+                std::task::Poll::Ready(output) => output,
+                std::task::Poll::Pending => panic!("stub submissions complete at once"),
+            }
+        }
+
+        let backend = StubBackend::with_book();
+        ready(backend.connect()).unwrap();
+        let mut fills = backend.subscribe_fills();
+        let resting = ready(backend.submit(b"SELL AAPL 8 15040".to_vec())).unwrap();
+        let ack = ready(backend.submit(b"BUY AAPL 3".to_vec())).unwrap();
+
+        assert_eq!(fills.try_recv().unwrap().order_id, resting.order_id);
+        assert_eq!(fills.try_recv().unwrap().order_id, ack.order_id);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_all_cancels_every_open_order() {
+        let backend = StubBackend::with_book();
+        backend.connect().await.unwrap();
+        assert!(backend.cancel_all().is_empty());
+
+        let mut ids = Vec::new();
+        for payload in ["BUY AAPL 10 15020", "SELL AAPL 5 15040", "BUY MSFT 3 41000"] {
+            ids.push(
+                backend
+                    .submit(payload.as_bytes().to_vec())
+                    .await
+                    .unwrap()
+                    .order_id,
+            );
+        }
+        backend.submit(b"SELL AAPL 4".to_vec()).await.unwrap();
+        assert_eq!(
+            backend.order_status(ids[0]),
+            Some(OrderStatus::PartiallyFilled)
+        );
+
+        let acks = backend.cancel_all();
+        assert_eq!(acks.iter().map(|ack| ack.order_id).collect::<Vec<_>>(), ids);
+        assert_eq!(acks[0], OrderAck::cancelled(ids[0], 4));
+        for id in &ids {
+            assert_eq!(backend.order_status(*id), Some(OrderStatus::Cancelled));
+        }
+        assert_eq!(backend.book_snapshot("AAPL"), BookSnapshot::default());
+        assert!(backend.cancel_all().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_order_status_tracks_fills() {
+        let backend = StubBackend::with_fill_ratio(0.5);
+        backend.connect().await.unwrap();
+        let partial = backend.submit(b"BUY AAPL 10".to_vec()).await.unwrap();
+        let empty = backend.submit(b"BUY AAPL 1".to_vec()).await.unwrap();
+
+        assert_eq!(
+            backend.order_status(partial.order_id),
+            Some(OrderStatus::PartiallyFilled)
+        );
+        assert_eq!(
+            backend.order_status(empty.order_id),
+            Some(OrderStatus::Open)
+        );
+        assert_eq!(backend.order_status(999), None);
+
+        let stub = connected_stub().await;
+        let filled = stub.submit(b"order".to_vec()).await.unwrap();
+        assert_eq!(
+            stub.order_status(filled.order_id),
+            Some(OrderStatus::Filled)
+        );
+        assert!(stub.cancel_all().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_default_subscription_is_closed() {
+        struct Silent;
+
+        #[async_trait]
+        impl ExecutionBackend for Silent {
+            async fn submit(&self, _order: Vec<u8>) -> Result<OrderAck, ExecError> {
+                Err(ExecError::Timeout)
+            }
+        }
+
+        assert!(Silent.subscribe_fills().recv().await.is_none());
+    }
+}
@@ -0,0 +1,305 @@
+//! Simulated limit order book.
+//!
+//! A [`Book`] holds the resting limit orders of one symbol, bids and asks
+//! each sorted by price. Incoming orders first trade against the opposite
+//! side with price-time priority (best price first, then earliest arrival;
+//! see [`Book::match_order`]); whatever is left of a limit order then rests
+//! at its price, while the unfilled rest of a market order is discarded.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::{FillEvent, Order, OrderAck, Side};
+
+/// Aggregated quantity resting at one price.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Level {
+    /// Price in ticks
+    pub price: u64,
+    /// Total resting quantity at this price
+    pub quantity: u64,
+}
+
+/// Point-in-time view of a [`Book`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BookSnapshot {
+    /// Highest bid, if any
+    pub best_bid: Option<Level>,
+    /// Lowest ask, if any
+    pub best_ask: Option<Level>,
+    /// Every bid level, best (highest) first
+    pub bids: Vec<Level>,
+    /// Every ask level, best (lowest) first
+    pub asks: Vec<Level>,
+}
+
+/// A limit order waiting in the book.
+#[derive(Debug, Clone, Copy)]
+struct Resting {
+    order_id: u64,
+    remaining: u64,
+}
+
+/// Resting orders of one symbol, queued by arrival within each price.
+#[derive(Debug, Default)]
+pub struct Book {
+    bids: BTreeMap<u64, VecDeque<Resting>>,
+    asks: BTreeMap<u64, VecDeque<Resting>>,
+}
+
+impl Book {
+    /// Create an empty book.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Execute `order` against the book and rest any limit remainder.
+    ///
+    /// # Arguments
+    /// * `order_id` - ID assigned to the incoming order
+    /// * `order` - The incoming order; its symbol is not checked
+    ///
+    /// # Returns
+    /// The acknowledgment for `order` and one [`FillEvent`] per side of
+    /// every trade, resting order first. A market order's
+    /// `remaining_quantity` is what found no liquidity; it does not rest.
+    pub fn submit(&mut self, order_id: u64, order: &Order) -> (OrderAck, Vec<FillEvent>) {
+        let mut remaining = order.quantity;
+        let mut fills = Vec::new();
+        for maker in self.match_order(order) {
+            remaining -= maker.fill_quantity;
+            let taker = FillEvent {
+                order_id,
+                fill_quantity: maker.fill_quantity,
+                remaining_quantity: remaining,
+            };
+            fills.push(maker);
+            fills.push(taker);
+        }
+
+        if let (Some(price), true) = (order.price, remaining > 0) {
+            let side = match order.side {
+                Side::Buy => &mut self.bids,
+                Side::Sell => &mut self.asks,
+            };
+            side.entry(price).or_default().push_back(Resting {
+                order_id,
+                remaining,
+            });
+        }
+
+        let ack = OrderAck::partially_filled(order_id, order.quantity - remaining, order.quantity);
+        (ack, fills)
+    }
+
+    /// Trade `order` against resting liquidity with price-time priority.
+    ///
+    /// Walks the opposite side from the best price while `order` is
+    /// marketable (any price for market orders, up to its limit otherwise),
+    /// filling resting orders at each price in arrival order. Nothing of
+    /// `order` is rested; see [`submit`](Self::submit) for that.
+    ///
+    /// # Returns
+    /// One [`FillEvent`] per resting order traded against, in execution
+    /// order, carrying that resting order's ID and what it has left.
+    pub fn match_order(&mut self, order: &Order) -> Vec<FillEvent> {
+        let mut remaining = order.quantity;
+        let mut fills = Vec::new();
+
+        while remaining > 0 {
+            let opposite = match order.side {
+                Side::Buy => self.asks.first_entry(),
+                Side::Sell => self.bids.last_entry(),
+            };
+            let Some(mut level) = opposite else { break };
+            let crosses = match (order.side, order.price) {
+                (_, None) => true,
+                (Side::Buy, Some(limit)) => *level.key() <= limit,
+                (Side::Sell, Some(limit)) => *level.key() >= limit,
+            };
+            if !crosses {
+                break;
+            }
+
+            let queue = level.get_mut();
+            while remaining > 0 {
+                let Some(maker) = queue.front_mut() else {
+                    break;
+                };
+                let quantity = maker.remaining.min(remaining);
+                maker.remaining -= quantity;
+                remaining -= quantity;
+                fills.push(FillEvent {
+                    order_id: maker.order_id,
+                    fill_quantity: quantity,
+                    remaining_quantity: maker.remaining,
+                });
+                if maker.remaining == 0 {
+                    queue.pop_front();
+                }
+            }
+            if queue.is_empty() {
+                level.remove();
+            }
+        }
+        fills
+    }
+
+    /// Remove every resting order.
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+    }
+
+    /// Top-of-book and full depth of the book.
+    pub fn snapshot(&self) -> BookSnapshot {
+        let bids: Vec<Level> = self.bids.iter().rev().map(aggregate).collect();
+        let asks: Vec<Level> = self.asks.iter().map(aggregate).collect();
+        BookSnapshot {
+            best_bid: bids.first().copied(),
+            best_ask: asks.first().copied(),
+            bids,
+            asks,
+        }
+    }
+}
+
+fn aggregate((price, queue): (&u64, &VecDeque<Resting>)) -> Level {
+    Level {
+        price: *price,
+        quantity: queue.iter().map(|resting| resting.remaining).sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(payload: &str) -> Order {
+        Order::parse(payload.as_bytes()).unwrap()
+    }
+
+    fn level(price: u64, quantity: u64) -> Level {
+        Level { price, quantity }
+    }
+
+    fn fill(order_id: u64, fill_quantity: u64, remaining_quantity: u64) -> FillEvent {
+        FillEvent {
+            order_id,
+            fill_quantity,
+            remaining_quantity,
+        }
+    }
+
+    #[test]
+    fn test_limit_orders_rest_sorted_by_price() {
+        let mut book = Book::new();
+        book.submit(1, &order("BUY AAPL 10 100"));
+        book.submit(2, &order("BUY AAPL 5 101"));
+        book.submit(3, &order("SELL AAPL 7 105"));
+        book.submit(4, &order("SELL AAPL 3 103"));
+        book.submit(5, &order("BUY AAPL 2 100"));
+
+        let snapshot = book.snapshot();
+        assert_eq!(snapshot.best_bid, Some(level(101, 5)));
+        assert_eq!(snapshot.best_ask, Some(level(103, 3)));
+        assert_eq!(snapshot.bids, vec![level(101, 5), level(100, 12)]);
+        assert_eq!(snapshot.asks, vec![level(103, 3), level(105, 7)]);
+    }
+
+    #[test]
+    fn test_market_order_sweeps_resting_liquidity() {
+        let mut book = Book::new();
+        book.submit(1, &order("SELL AAPL 4 103"));
+        book.submit(2, &order("SELL AAPL 10 105"));
+
+        let (ack, fills) = book.submit(3, &order("BUY AAPL 6"));
+        assert_eq!((ack.filled_quantity, ack.remaining_quantity), (6, 0));
+        assert_eq!(
+            fills,
+            vec![fill(1, 4, 0), fill(3, 4, 2), fill(2, 2, 8), fill(3, 2, 0)]
+        );
+        assert_eq!(book.snapshot().asks, vec![level(105, 8)]);
+    }
+
+    #[test]
+    fn test_market_order_remainder_does_not_rest() {
+        let mut book = Book::new();
+        book.submit(1, &order("BUY AAPL 3 100"));
+
+        let (ack, _) = book.submit(2, &order("SELL AAPL 5"));
+        assert_eq!((ack.filled_quantity, ack.remaining_quantity), (3, 2));
+        assert_eq!(book.snapshot(), BookSnapshot::default());
+    }
+
+    #[test]
+    fn test_crossing_limit_order_trades_then_rests() {
+        let mut book = Book::new();
+        book.submit(1, &order("SELL AAPL 4 103"));
+
+        let (ack, fills) = book.submit(2, &order("BUY AAPL 6 104"));
+        assert_eq!((ack.filled_quantity, ack.remaining_quantity), (4, 2));
+        assert_eq!(fills.len(), 2);
+
+        let snapshot = book.snapshot();
+        assert_eq!(snapshot.best_bid, Some(level(104, 2)));
+        assert_eq!(snapshot.best_ask, None);
+    }
+
+    #[test]
+    fn test_match_order_fully_fills_one_resting_order() {
+        let mut book = Book::new();
+        book.submit(1, &order("SELL AAPL 5 103"));
+
+        assert_eq!(book.match_order(&order("BUY AAPL 5")), vec![fill(1, 5, 0)]);
+        assert_eq!(book.snapshot(), BookSnapshot::default());
+    }
+
+    #[test]
+    fn test_match_order_partially_fills_resting_order() {
+        let mut book = Book::new();
+        book.submit(1, &order("BUY AAPL 10 100"));
+
+        assert_eq!(book.match_order(&order("SELL AAPL 4")), vec![fill(1, 4, 6)]);
+        assert_eq!(book.snapshot().best_bid, Some(level(100, 6)));
+    }
+
+    #[test]
+    fn test_match_order_walks_price_levels() {
+        let mut book = Book::new();
+        book.submit(1, &order("SELL AAPL 2 105"));
+        book.submit(2, &order("SELL AAPL 3 103"));
+        book.submit(3, &order("SELL AAPL 4 104"));
+
+        assert_eq!(
+            book.match_order(&order("BUY AAPL 8")),
+            vec![fill(2, 3, 0), fill(3, 4, 0), fill(1, 1, 1)]
+        );
+        assert_eq!(book.snapshot().asks, vec![level(105, 1)]);
+    }
+
+    #[test]
+    fn test_match_order_breaks_ties_by_arrival() {
+        let mut book = Book::new();
+        book.submit(7, &order("BUY AAPL 2 100"));
+        book.submit(3, &order("BUY AAPL 2 100"));
+        book.submit(5, &order("BUY AAPL 2 100"));
+
+        assert_eq!(
+            book.match_order(&order("SELL AAPL 3")),
+            vec![fill(7, 2, 0), fill(3, 1, 1)]
+        );
+    }
+
+    #[test]
+    fn test_match_order_stops_at_limit_price() {
+        let mut book = Book::new();
+        book.submit(1, &order("SELL AAPL 2 103"));
+        book.submit(2, &order("SELL AAPL 2 105"));
+
+        assert_eq!(
+            book.match_order(&order("BUY AAPL 5 104")),
+            vec![fill(1, 2, 0)]
+        );
+        assert_eq!(book.snapshot().asks, vec![level(105, 2)]);
+    }
+}
@@ -0,0 +1,327 @@
+//! Circuit breaker for execution backends.
+//!
+//! Stops hammering a backend that keeps failing at the connection level.
+//! After `failure_threshold` consecutive [`ExecError::ConnectionError`]s or
+//! [`ExecError::Timeout`]s the breaker opens and rejects submissions
+//! immediately for `cooldown`. Once the cooldown elapses a single trial
+//! submission is let through (half-open); a success closes the breaker
+//! again, another connection failure or timeout re-opens it. A trial that is
+//! cancelled before it finishes frees the half-open slot for the next one.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+use crate::backend::ExecutionBackend;
+use crate::{ExecError, FillEvent, OrderAck};
+
+/// Current state of a [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Submissions flow through to the backend.
+    Closed,
+    /// Submissions are short-circuited until the cooldown elapses.
+    Open,
+    /// A single trial submission is in flight to probe recovery.
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct BreakerState {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+}
+
+/// Backend wrapper that trips after consecutive connection failures.
+#[derive(Debug)]
+pub struct CircuitBreaker<B> {
+    inner: B,
+    failure_threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+impl<B: ExecutionBackend> CircuitBreaker<B> {
+    /// Wrap `inner` with a circuit breaker.
+    ///
+    /// # Arguments
+    /// * `inner` - The backend to protect
+    /// * `failure_threshold` - Consecutive connection errors before opening
+    /// * `cooldown` - How long to stay open before probing recovery
+    pub fn new(inner: B, failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            inner,
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            state: Mutex::new(BreakerState {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                opened_at: None,
+            }),
+        }
+    }
+
+    /// Current breaker state.
+    ///
+    /// An open breaker whose cooldown has elapsed still reports `Open` until
+    /// the next submission moves it to `HalfOpen`.
+    pub fn state(&self) -> CircuitState {
+        self.state.lock().unwrap().state
+    }
+
+    /// Access the wrapped backend.
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    /// Decide whether a submission may proceed, transitioning Open -> HalfOpen
+    /// once the cooldown has elapsed.
+    fn try_acquire(&self) -> Result<ProbeGuard<'_, B>, ExecError> {
+        let mut guard = self.state.lock().unwrap();
+        match guard.state {
+            CircuitState::Closed => Ok(ProbeGuard { breaker: None }),
+            CircuitState::HalfOpen => Err(circuit_open()),
+            CircuitState::Open => {
+                let cooled_down = guard
+                    .opened_at
+                    .is_some_and(|opened| opened.elapsed() >= self.cooldown);
+                if cooled_down {
+                    guard.state = CircuitState::HalfOpen;
+                    Ok(ProbeGuard {
+                        breaker: Some(self),
+                    })
+                } else {
+                    Err(circuit_open())
+                }
+            }
+        }
+    }
+
+    fn record(&self, result: &Result<OrderAck, ExecError>) {
+        let mut guard = self.state.lock().unwrap();
+        match result {
+            Err(ExecError::ConnectionError(_) | ExecError::Timeout) => {
+                guard.consecutive_failures = guard.consecutive_failures.saturating_add(1);
+                if guard.state == CircuitState::HalfOpen
+                    || guard.consecutive_failures >= self.failure_threshold
+                {
+                    guard.state = CircuitState::Open;
+                    guard.opened_at = Some(Instant::now());
+                }
+            }
+            // Any other outcome means the backend answered, so it is reachable.
+            _ => {
+                guard.state = CircuitState::Closed;
+                guard.consecutive_failures = 0;
+                guard.opened_at = None;
+            }
+        }
+    }
+}
+
+/// Holds the half-open slot while a trial submission is in flight.
+///
+/// If the submission future is dropped before its outcome is recorded, the
+/// breaker goes back to `Open` with its original cooldown, so the next
+/// submission probes again instead of being rejected forever.
+struct ProbeGuard<'a, B> {
+    /// The breaker whose slot is held; `None` outside half-open.
+    breaker: Option<&'a CircuitBreaker<B>>,
+}
+
+impl<B> ProbeGuard<'_, B> {
+    /// The outcome was recorded; keep the state it left.
+    fn disarm(mut self) {
+        self.breaker = None;
+    }
+}
+
+impl<B> Drop for ProbeGuard<'_, B> {
+    fn drop(&mut self) {
+        if let Some(breaker) = self.breaker {
+            let mut guard = breaker.state.lock().unwrap();
+            if guard.state == CircuitState::HalfOpen {
+                guard.state = CircuitState::Open;
+            }
+        }
+    }
+}
+
+fn circuit_open() -> ExecError {
+    ExecError::ConnectionError("circuit open".to_string())
+}
+
+#[async_trait]
+impl<B: ExecutionBackend> ExecutionBackend for CircuitBreaker<B> {
+    async fn submit(&self, order: Vec<u8>) -> Result<OrderAck, ExecError> {
+        let probe = self.try_acquire()?;
+        let result = self.inner.submit(order).await;
+        self.record(&result);
+        probe.disarm();
+        result
+    }
+
+    async fn connect(&self) -> Result<(), ExecError> {
+        self.inner.connect().await
+    }
+
+    async fn disconnect(&self) -> Result<(), ExecError> {
+        self.inner.disconnect().await
+    }
+
+    fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+
+    async fn ping(&self) -> Result<(), ExecError> {
+        self.inner.ping().await
+    }
+
+    fn subscribe_fills(&self) -> mpsc::Receiver<FillEvent> {
+        self.inner.subscribe_fills()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+    /// Backend that fails with a connection error while `failing` is set,
+    /// times out while `timing_out` is set and never answers while
+    /// `hanging` is set.
+    #[derive(Default)]
+    struct FlakyBackend {
+        failing: AtomicBool,
+        timing_out: AtomicBool,
+        hanging: AtomicBool,
+        calls: AtomicU64,
+    }
+
+    #[async_trait]
+    impl ExecutionBackend for FlakyBackend {
+        async fn submit(&self, _order: Vec<u8>) -> Result<OrderAck, ExecError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            if self.hanging.load(Ordering::SeqCst) {
+                std::future::pending::<()>().await;
+            }
+            if self.timing_out.load(Ordering::SeqCst) {
+                return Err(ExecError::Timeout);
+            }
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(ExecError::ConnectionError("venue down".to_string()));
+            }
+            Ok(OrderAck::filled(call, 1))
+        }
+    }
+
+    fn failing_breaker() -> CircuitBreaker<FlakyBackend> {
+        let backend = FlakyBackend::default();
+        backend.failing.store(true, Ordering::SeqCst);
+        CircuitBreaker::new(backend, 3, Duration::from_secs(5))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_breaker_opens_after_consecutive_failures() {
+        let breaker = failing_breaker();
+
+        for _ in 0..3 {
+            let result = breaker.submit(b"order".to_vec()).await;
+            assert_eq!(
+                result,
+                Err(ExecError::ConnectionError("venue down".to_string()))
+            );
+        }
+        assert_eq!(breaker.state(), CircuitState::Open);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_open_breaker_short_circuits() {
+        let breaker = failing_breaker();
+        for _ in 0..3 {
+            let _ = breaker.submit(b"order".to_vec()).await;
+        }
+
+        let result = breaker.submit(b"order".to_vec()).await;
+        assert_eq!(result, Err(circuit_open()));
+        assert_eq!(
+            breaker.inner().calls.load(Ordering::SeqCst),
+            3,
+            "open breaker must not reach the backend"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_breaker_closes_after_successful_probe() {
+        let breaker = failing_breaker();
+        for _ in 0..3 {
+            let _ = breaker.submit(b"order".to_vec()).await;
+        }
+        breaker.inner().failing.store(false, Ordering::SeqCst);
+
+        // Still cooling down.
+        tokio::time::advance(Duration::from_secs(4)).await;
+        assert_eq!(breaker.submit(b"order".to_vec()).await, Err(circuit_open()));
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        let ack = breaker.submit(b"order".to_vec()).await.unwrap();
+        assert!(ack.accepted);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        assert!(breaker.submit(b"order".to_vec()).await.is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failed_probe_reopens_breaker() {
+        let breaker = failing_breaker();
+        for _ in 0..3 {
+            let _ = breaker.submit(b"order".to_vec()).await;
+        }
+
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert!(breaker.submit(b"order".to_vec()).await.is_err());
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert_eq!(breaker.submit(b"order".to_vec()).await, Err(circuit_open()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_timed_out_probe_reopens_breaker() {
+        let breaker = failing_breaker();
+        for _ in 0..3 {
+            let _ = breaker.submit(b"order".to_vec()).await;
+        }
+        breaker.inner().failing.store(false, Ordering::SeqCst);
+        breaker.inner().timing_out.store(true, Ordering::SeqCst);
+
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert_eq!(
+            breaker.submit(b"order".to_vec()).await,
+            Err(ExecError::Timeout)
+        );
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert_eq!(breaker.submit(b"order".to_vec()).await, Err(circuit_open()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cancelled_probe_releases_half_open_slot() {
+        let breaker = failing_breaker();
+        for _ in 0..3 {
+            let _ = breaker.submit(b"order".to_vec()).await;
+        }
+        breaker.inner().failing.store(false, Ordering::SeqCst);
+        breaker.inner().hanging.store(true, Ordering::SeqCst);
+
+        tokio::time::advance(Duration::from_secs(5)).await;
+        let probe = tokio::time::timeout(Duration::from_secs(1), breaker.submit(b"order".to_vec()));
+        assert!(probe.await.is_err(), "the probe never answers");
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        breaker.inner().hanging.store(false, Ordering::SeqCst);
+        assert!(breaker.submit(b"order".to_vec()).await.is_ok());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+}
@@ -0,0 +1,120 @@
+//! Dry-run wrapper for staging.
+//!
+//! [`DryRunBackend`] runs the pre-trade checks on every order and answers
+//! with a simulated acknowledgment, but never forwards anything to the
+//! wrapped backend. Lifecycle calls are not forwarded either, so a dry run
+//! cannot open connections or change the real backend's state.
+
+use async_trait::async_trait;
+
+use crate::backend::ExecutionBackend;
+use crate::{pre_trade_check, ExecError, OrderAck, OrderIdGenerator, STUB_ORDER_QUANTITY};
+
+/// `reason` attached to every dry-run acknowledgment.
+pub const DRY_RUN_REASON: &str = "dry-run";
+
+/// Backend wrapper that validates orders without submitting them.
+#[derive(Debug)]
+pub struct DryRunBackend<B> {
+    inner: B,
+    order_ids: OrderIdGenerator,
+}
+
+impl<B: ExecutionBackend> DryRunBackend<B> {
+    /// Wrap `inner`; it will never receive a call from this wrapper.
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            order_ids: OrderIdGenerator::new(),
+        }
+    }
+
+    /// Access the wrapped backend.
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    /// Unwrap, returning the untouched inner backend.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<B: ExecutionBackend> ExecutionBackend for DryRunBackend<B> {
+    /// Validate `order` and return an accepted, unfilled acknowledgment whose
+    /// `reason` is [`DRY_RUN_REASON`]. Order IDs come from the wrapper's own
+    /// sequence so the real backend's IDs are not consumed.
+    async fn submit(&self, order: Vec<u8>) -> Result<OrderAck, ExecError> {
+        let checked = pre_trade_check(&order);
+        #[cfg(feature = "metrics")]
+        if let Err(err) = &checked {
+            if crate::metrics::self_counting() {
+                crate::metrics::record_error(err);
+            }
+        }
+        checked?;
+
+        let mut ack = OrderAck::partially_filled(self.order_ids.next_id(), 0, STUB_ORDER_QUANTITY);
+        ack.reason = Some(DRY_RUN_REASON.to_string());
+        Ok(ack)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RejectReason;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// Backend that counts every call it receives.
+    #[derive(Default)]
+    struct CountingBackend {
+        calls: AtomicU64,
+    }
+
+    #[async_trait]
+    impl ExecutionBackend for CountingBackend {
+        async fn submit(&self, _order: Vec<u8>) -> Result<OrderAck, ExecError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(OrderAck::filled(1, 1))
+        }
+
+        async fn connect(&self) -> Result<(), ExecError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_returns_simulated_ack() {
+        let backend = DryRunBackend::new(CountingBackend::default());
+        let ack = backend.submit(b"BUY AAPL 10".to_vec()).await.unwrap();
+
+        assert!(ack.accepted);
+        assert_eq!(ack.reason.as_deref(), Some(DRY_RUN_REASON));
+        assert_eq!(ack.filled_quantity, 0);
+        assert_eq!(ack.order_id, 1);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_never_calls_inner_backend() {
+        let backend = DryRunBackend::new(CountingBackend::default());
+        backend.connect().await.unwrap();
+        for _ in 0..3 {
+            backend.submit(b"order".to_vec()).await.unwrap();
+        }
+        let _ = backend.submit(vec![]).await;
+
+        assert_eq!(backend.inner().calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_still_runs_pre_trade_checks() {
+        let backend = DryRunBackend::new(CountingBackend::default());
+        assert_eq!(
+            backend.submit(vec![]).await,
+            Err(ExecError::ValidationFailed(RejectReason::EmptyOrder))
+        );
+    }
+}
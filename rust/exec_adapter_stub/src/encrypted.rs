@@ -0,0 +1,120 @@
+//! Encrypted order submission (requires the `encryption` feature).
+//!
+//! The adapter seals each order payload with the encryption service's AEAD
+//! before it leaves the process. On the wire a sealed order is the nonce
+//! followed by the ciphertext:
+//!
+//! ```text
+//! +------------------+-----------------------------+
+//! | nonce (12 bytes) | ciphertext + tag (n+16)     |
+//! +------------------+-----------------------------+
+//! ```
+//!
+//! [`submit_sealed_order`] is the stub venue's receiving end: it opens the
+//! payload and runs it through [`send_order`]. Any decryption failure is
+//! reported as a connection error, since it means the transport cannot be
+//! trusted.
+
+use tinywindow_rust_encryption::{decrypt, encrypt, AEAD_NONCE_SIZE};
+
+use crate::{send_order, ExecError, OrderAck, RejectReason};
+
+/// Encrypt an order and submit it through the stub venue.
+///
+/// # Arguments
+/// * `key` - 32-byte transport key shared with the venue
+/// * `nonce` - 12-byte nonce, unique per order for this key
+/// * `order` - The plaintext order payload
+///
+/// # Returns
+/// * `Ok(OrderAck)` - Order acknowledgment with status
+/// * `Err(ExecError::ValidationFailed)` - If the key or nonce is malformed, or
+///   the decrypted order fails validation
+/// * `Err(ExecError::ConnectionError)` - If the venue cannot decrypt the payload
+pub async fn send_encrypted_order(
+    key: &[u8],
+    nonce: &[u8],
+    order: Vec<u8>,
+) -> Result<OrderAck, ExecError> {
+    let sealed = seal_order(key, nonce, &order)?;
+    submit_sealed_order(key, &sealed).await
+}
+
+/// Receive a sealed order at the stub venue: decrypt it, then submit it.
+///
+/// # Returns
+/// * `Ok(OrderAck)` - Order acknowledgment with status
+/// * `Err(ExecError::ConnectionError)` - If the payload does not decrypt
+/// * `Err(ExecError::ValidationFailed)` - If the decrypted order fails validation
+pub async fn submit_sealed_order(key: &[u8], sealed: &[u8]) -> Result<OrderAck, ExecError> {
+    let order = open_order(key, sealed)?;
+    send_order(order).await
+}
+
+fn seal_order(key: &[u8], nonce: &[u8], order: &[u8]) -> Result<Vec<u8>, ExecError> {
+    let ciphertext = encrypt(key, nonce, order)
+        .map_err(|err| ExecError::ValidationFailed(RejectReason::Other(err.to_string())))?;
+    let mut sealed = Vec::with_capacity(nonce.len() + ciphertext.len());
+    sealed.extend_from_slice(nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+fn open_order(key: &[u8], sealed: &[u8]) -> Result<Vec<u8>, ExecError> {
+    if sealed.len() < AEAD_NONCE_SIZE {
+        return Err(decryption_failed("sealed order shorter than nonce"));
+    }
+    let (nonce, ciphertext) = sealed.split_at(AEAD_NONCE_SIZE);
+    decrypt(key, nonce, ciphertext).map_err(|err| decryption_failed(&err.to_string()))
+}
+
+fn decryption_failed(detail: &str) -> ExecError {
+    ExecError::ConnectionError(format!("could not decrypt order: {detail}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tinywindow_rust_encryption::keygen;
+
+    const NONCE: [u8; AEAD_NONCE_SIZE] = [1; AEAD_NONCE_SIZE];
+
+    #[tokio::test]
+    async fn test_encrypted_order_roundtrip() {
+        let key = keygen(42);
+        let ack = send_encrypted_order(&key, &NONCE, b"BUY AAPL 10".to_vec())
+            .await
+            .unwrap();
+        assert!(ack.accepted);
+    }
+
+    #[tokio::test]
+    async fn test_sealed_order_hides_plaintext() {
+        let key = keygen(42);
+        let sealed = seal_order(&key, &NONCE, b"BUY AAPL 10").unwrap();
+        assert!(!sealed.windows(4).any(|w| w == b"AAPL"));
+        assert_eq!(open_order(&key, &sealed).unwrap(), b"BUY AAPL 10");
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_ciphertext_is_rejected() {
+        let key = keygen(42);
+        let mut sealed = seal_order(&key, &NONCE, b"BUY AAPL 10").unwrap();
+        *sealed.last_mut().unwrap() ^= 0xff;
+
+        let result = submit_sealed_order(&key, &sealed).await;
+        assert!(matches!(result, Err(ExecError::ConnectionError(_))));
+
+        let truncated = submit_sealed_order(&key, &sealed[..4]).await;
+        assert!(matches!(truncated, Err(ExecError::ConnectionError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_wrong_key_is_rejected() {
+        let sealed = seal_order(&keygen(42), &NONCE, b"BUY AAPL 10").unwrap();
+        assert!(matches!(
+            submit_sealed_order(&keygen(43), &sealed).await,
+            Err(ExecError::ConnectionError(_))
+        ));
+    }
+}
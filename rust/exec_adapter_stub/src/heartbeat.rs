@@ -0,0 +1,121 @@
+//! Connection keepalive.
+//!
+//! A connection can die without either side noticing until the next order
+//! fails. The heartbeat task pings the backend periodically and disconnects
+//! it after `max_missed` consecutive failed pings, so
+//! [`is_connected`](ExecutionBackend::is_connected) reflects reality.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+use crate::backend::ExecutionBackend;
+
+/// Spawns a heartbeat task for a shared backend.
+pub trait HeartbeatExt {
+    /// Ping the backend every `interval` until it is disconnected.
+    ///
+    /// Each failed ping increments a failure counter and each successful one
+    /// resets it. Once `max_missed` consecutive pings fail the backend is
+    /// disconnected and the task ends. The task also ends if the backend is
+    /// disconnected by someone else.
+    ///
+    /// # Arguments
+    /// * `interval` - Time between pings; the first ping happens after one interval
+    /// * `max_missed` - Consecutive failures that mark the backend disconnected
+    fn spawn_heartbeat(&self, interval: Duration, max_missed: u32) -> JoinHandle<()>;
+}
+
+impl<B: ExecutionBackend + 'static> HeartbeatExt for Arc<B> {
+    fn spawn_heartbeat(&self, interval: Duration, max_missed: u32) -> JoinHandle<()> {
+        let backend = Arc::clone(self);
+        let max_missed = max_missed.max(1);
+        tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            let mut missed = 0u32;
+            loop {
+                ticker.tick().await;
+                if !backend.is_connected() {
+                    return;
+                }
+                match backend.ping().await {
+                    Ok(()) => missed = 0,
+                    Err(err) => {
+                        missed += 1;
+                        log::warn!("heartbeat ping failed ({missed}/{max_missed}): {err}");
+                    }
+                }
+                if missed >= max_missed {
+                    // Already unreachable; a failed disconnect changes nothing.
+                    let _ = backend.disconnect().await;
+                    return;
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StubBackend;
+
+    async fn connected_stub() -> Arc<StubBackend> {
+        let backend = Arc::new(StubBackend::new());
+        backend.connect().await.unwrap();
+        backend
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failing_pings_mark_backend_disconnected() {
+        let backend = connected_stub().await;
+        backend.set_ping_failure(true);
+
+        let heartbeat = backend.spawn_heartbeat(Duration::from_secs(1), 3);
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        assert!(backend.is_connected(), "two missed pings are tolerated");
+
+        heartbeat.await.unwrap();
+        assert!(!backend.is_connected());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_healthy_pings_keep_backend_connected() {
+        let backend = connected_stub().await;
+        let heartbeat = backend.spawn_heartbeat(Duration::from_secs(1), 3);
+
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert!(backend.is_connected());
+        assert!(!heartbeat.is_finished());
+        heartbeat.abort();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_successful_ping_resets_missed_count() {
+        let backend = connected_stub().await;
+        backend.set_ping_failure(true);
+        let heartbeat = backend.spawn_heartbeat(Duration::from_secs(1), 3);
+
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        backend.set_ping_failure(false);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        backend.set_ping_failure(true);
+        tokio::time::sleep(Duration::from_secs(2)).await;
+
+        assert!(backend.is_connected());
+        heartbeat.await.unwrap();
+        assert!(!backend.is_connected());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_heartbeat_stops_after_manual_disconnect() {
+        let backend = connected_stub().await;
+        let heartbeat = backend.spawn_heartbeat(Duration::from_secs(1), 3);
+        backend.disconnect().await.unwrap();
+        heartbeat.await.unwrap();
+    }
+}
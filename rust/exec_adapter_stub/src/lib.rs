@@ -8,10 +8,46 @@
 //! - Integrates with: telemetry and KMS/HSM boundaries
 //! - Participates in: system feedback loops (Layer 1..7)
 
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::fmt;
+
+pub mod adapter;
+pub mod backend;
+pub mod book;
+pub mod circuit_breaker;
+pub mod dry_run;
+#[cfg(feature = "encryption")]
+pub mod encrypted;
+pub mod heartbeat;
+#[cfg(feature = "metrics")]
+pub mod metered;
+#[cfg(feature = "metrics")]
+mod metrics;
+pub mod order;
+pub mod order_id;
+#[cfg(feature = "python")]
+mod python;
+pub mod queue;
+
+pub use adapter::{ExecAdapter, ExecAdapterBuilder};
+pub use backend::{ExecutionBackend, StubBackend, FILL_CHANNEL_CAPACITY};
+pub use book::{Book, BookSnapshot, Level};
+pub use circuit_breaker::{CircuitBreaker, CircuitState};
+pub use dry_run::{DryRunBackend, DRY_RUN_REASON};
+#[cfg(feature = "encryption")]
+pub use encrypted::{send_encrypted_order, submit_sealed_order};
+pub use heartbeat::HeartbeatExt;
+#[cfg(feature = "metrics")]
+pub use metered::MeteredBackend;
+pub use order::{Order, Side};
+pub use order_id::{OrderIdGenerator, NODE_ID_SHIFT};
+pub use queue::{PendingAck, SubmissionQueue};
+
+/// `reason` attached to the acknowledgment of a cancelled order.
+pub const CANCELLED_REASON: &str = "cancelled";
 
 /// Order acknowledgment result
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OrderAck {
     /// Unique order ID
     pub order_id: u64,
@@ -19,30 +55,278 @@ pub struct OrderAck {
     pub accepted: bool,
     /// Optional rejection reason
     pub reason: Option<String>,
+    /// Quantity executed so far
+    pub filled_quantity: u64,
+    /// Quantity still open
+    pub remaining_quantity: u64,
+}
+
+impl OrderAck {
+    /// Build an accepted acknowledgment for a fully filled order.
+    pub fn filled(order_id: u64, quantity: u64) -> Self {
+        Self::partially_filled(order_id, quantity, quantity)
+    }
+
+    /// Build an accepted acknowledgment with `filled` out of `quantity` executed.
+    ///
+    /// `filled` is clamped to `quantity`.
+    pub fn partially_filled(order_id: u64, filled: u64, quantity: u64) -> Self {
+        let filled = filled.min(quantity);
+        Self {
+            order_id,
+            accepted: true,
+            reason: None,
+            filled_quantity: filled,
+            remaining_quantity: quantity - filled,
+        }
+    }
+
+    /// Build the acknowledgment for cancelling an order after `filled` executed.
+    ///
+    /// Nothing remains open; `reason` is [`CANCELLED_REASON`].
+    pub fn cancelled(order_id: u64, filled: u64) -> Self {
+        Self {
+            order_id,
+            accepted: true,
+            reason: Some(CANCELLED_REASON.to_string()),
+            filled_quantity: filled,
+            remaining_quantity: 0,
+        }
+    }
+
+    /// Total order quantity (filled plus remaining).
+    pub fn total_quantity(&self) -> u64 {
+        self.filled_quantity + self.remaining_quantity
+    }
+
+    /// Whether nothing remains open.
+    pub fn is_fully_filled(&self) -> bool {
+        self.remaining_quantity == 0
+    }
+
+    /// Apply an incremental fill to this acknowledgment.
+    ///
+    /// Fills for other orders are ignored. The fill quantity is clamped to
+    /// what remains open.
+    ///
+    /// # Returns
+    /// `true` if the fill belonged to this order and was applied
+    pub fn apply_fill(&mut self, fill: &FillEvent) -> bool {
+        if fill.order_id != self.order_id {
+            return false;
+        }
+        let quantity = fill.fill_quantity.min(self.remaining_quantity);
+        self.filled_quantity += quantity;
+        self.remaining_quantity -= quantity;
+        true
+    }
+}
+
+/// Lifecycle state of an order tracked by a backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum OrderStatus {
+    /// Accepted with nothing executed yet
+    Open,
+    /// Part of the quantity executed, the rest still open
+    PartiallyFilled,
+    /// The whole quantity executed
+    Filled,
+    /// Cancelled; nothing remains open
+    Cancelled,
+}
+
+impl OrderStatus {
+    /// Status implied by an acknowledgment's quantities.
+    pub fn of(ack: &OrderAck) -> Self {
+        if ack.is_fully_filled() {
+            OrderStatus::Filled
+        } else if ack.filled_quantity > 0 {
+            OrderStatus::PartiallyFilled
+        } else {
+            OrderStatus::Open
+        }
+    }
+
+    /// Whether the order can still execute.
+    pub fn is_open(self) -> bool {
+        matches!(self, OrderStatus::Open | OrderStatus::PartiallyFilled)
+    }
+}
+
+/// Incremental fill notification for a previously acknowledged order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FillEvent {
+    /// Order the fill belongs to
+    pub order_id: u64,
+    /// Quantity executed by this fill
+    pub fill_quantity: u64,
+    /// Quantity still open after this fill
+    pub remaining_quantity: u64,
+}
+
+/// Why an order was rejected
+///
+/// With the `serde` feature it serializes as `{"kind": <label>}`, plus a
+/// `detail` string for the variants that carry one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "kind", content = "detail", rename_all = "snake_case")
+)]
+pub enum RejectReason {
+    /// Order payload was empty
+    EmptyOrder,
+    /// Order payload exceeded the size limit
+    SizeExceeded,
+    /// Order referenced an unknown symbol
+    UnknownSymbol,
+    /// Order would breach a risk limit
+    RiskLimit,
+    /// Order signature did not verify
+    BadSignature,
+    /// Submission queue was at capacity
+    QueueFull,
+    /// Order payload could not be parsed
+    MalformedOrder(String),
+    /// Any other reason
+    Other(String),
+}
+
+impl RejectReason {
+    /// Stable identifier used as a metric label value.
+    pub fn label(&self) -> &'static str {
+        match self {
+            RejectReason::EmptyOrder => "empty_order",
+            RejectReason::SizeExceeded => "size_exceeded",
+            RejectReason::UnknownSymbol => "unknown_symbol",
+            RejectReason::RiskLimit => "risk_limit",
+            RejectReason::BadSignature => "bad_signature",
+            RejectReason::QueueFull => "queue_full",
+            RejectReason::MalformedOrder(_) => "malformed_order",
+            RejectReason::Other(_) => "other",
+        }
+    }
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RejectReason::EmptyOrder => write!(f, "Order payload cannot be empty"),
+            RejectReason::SizeExceeded => write!(f, "Order payload too large"),
+            RejectReason::UnknownSymbol => write!(f, "Unknown symbol"),
+            RejectReason::RiskLimit => write!(f, "Risk limit exceeded"),
+            RejectReason::BadSignature => write!(f, "Bad order signature"),
+            RejectReason::QueueFull => write!(f, "Queue full"),
+            RejectReason::MalformedOrder(detail) => write!(f, "Malformed order: {detail}"),
+            RejectReason::Other(reason) => write!(f, "{reason}"),
+        }
+    }
 }
 
 /// Execution error types
+///
+/// With the `serde` feature it serializes as an object whose `type` names
+/// the variant, e.g. `{"type": "connection_error", "message": "refused"}`
+/// or `{"type": "validation_failed", "reason": {"kind": "empty_order"}}`.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "ExecErrorRepr", from = "ExecErrorRepr")
+)]
 pub enum ExecError {
     /// Order validation failed
-    ValidationFailed(String),
+    ValidationFailed(RejectReason),
     /// Connection error
     ConnectionError(String),
     /// Timeout waiting for ack
     Timeout,
 }
 
-/// Counter for generating deterministic order IDs in tests
-static ORDER_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecError::ValidationFailed(reason) => write!(f, "validation failed: {reason}"),
+            ExecError::ConnectionError(msg) => write!(f, "connection error: {msg}"),
+            ExecError::Timeout => write!(f, "timed out waiting for ack"),
+        }
+    }
+}
+
+impl std::error::Error for ExecError {}
+
+/// Serialized form of [`ExecError`]: tuple variants cannot be internally
+/// tagged, so their payloads get field names here.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ExecErrorRepr {
+    ValidationFailed { reason: RejectReason },
+    ConnectionError { message: String },
+    Timeout,
+}
+
+#[cfg(feature = "serde")]
+impl From<ExecError> for ExecErrorRepr {
+    fn from(err: ExecError) -> Self {
+        match err {
+            ExecError::ValidationFailed(reason) => ExecErrorRepr::ValidationFailed { reason },
+            ExecError::ConnectionError(message) => ExecErrorRepr::ConnectionError { message },
+            ExecError::Timeout => ExecErrorRepr::Timeout,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<ExecErrorRepr> for ExecError {
+    fn from(repr: ExecErrorRepr) -> Self {
+        match repr {
+            ExecErrorRepr::ValidationFailed { reason } => ExecError::ValidationFailed(reason),
+            ExecErrorRepr::ConnectionError { message } => ExecError::ConnectionError(message),
+            ExecErrorRepr::Timeout => ExecError::Timeout,
+        }
+    }
+}
+
+/// Quantity the stub assigns to an order it does not parse.
+///
+/// Only a backend with a fill ratio (see [`StubBackend::with_fill_ratio`])
+/// reads the quantity from the payload; everywhere else each order counts
+/// as a single unit.
+pub const STUB_ORDER_QUANTITY: u64 = 1;
+
+/// Process-wide generator used by the free [`send_order`] functions
+static ORDER_ID_COUNTER: OrderIdGenerator = OrderIdGenerator::new();
 
 /// Reset the order ID counter (for deterministic testing)
+///
+/// Only affects the free [`send_order`] functions; each backend owns its
+/// own [`OrderIdGenerator`]. The node ID set by [`configure_node_id`] is
+/// kept.
 pub fn reset_order_id_counter() {
-    ORDER_ID_COUNTER.store(1, Ordering::SeqCst);
+    ORDER_ID_COUNTER.reset();
+}
+
+/// Embed `node_id` in the order IDs of the free [`send_order`] functions.
+///
+/// IDs become `(node_id as u64) << NODE_ID_SHIFT | counter`, unique across
+/// nodes with distinct IDs and increasing within a node. Call it once at
+/// startup. Backends configure their own generator through
+/// [`OrderIdGenerator::set_node_id`].
+pub fn configure_node_id(node_id: u16) {
+    ORDER_ID_COUNTER.set_node_id(node_id);
 }
 
 /// Get the next order ID (deterministic within a test run)
 fn next_order_id() -> u64 {
-    ORDER_ID_COUNTER.fetch_add(1, Ordering::SeqCst)
+    ORDER_ID_COUNTER.next_id()
 }
 
 /// Send an order asynchronously and receive an acknowledgment.
@@ -61,24 +345,44 @@ fn next_order_id() -> u64 {
 /// # Determinism
 /// This function is deterministic for testing:
 /// - Empty orders are rejected
-/// - Non-empty orders are accepted with sequential IDs
+/// - Non-empty orders are accepted with sequential IDs and fully filled
 pub async fn send_order(order: Vec<u8>) -> Result<OrderAck, ExecError> {
+    send_order_partial(order, STUB_ORDER_QUANTITY, STUB_ORDER_QUANTITY).await
+}
+
+/// Send an order and receive a partially filled acknowledgment.
+///
+/// Stub variant of [`send_order`] that lets callers exercise partial-fill
+/// handling before a real venue is wired in.
+///
+/// # Arguments
+/// * `order` - The order payload as bytes
+/// * `quantity` - Total order quantity
+/// * `filled` - Quantity to report as executed (clamped to `quantity`)
+///
+/// # Returns
+/// * `Ok(OrderAck)` - Acknowledgment with the requested fill split
+/// * `Err(ExecError)` - Error if order could not be processed
+pub async fn send_order_partial(
+    order: Vec<u8>,
+    quantity: u64,
+    filled: u64,
+) -> Result<OrderAck, ExecError> {
     // Validate order (stub: reject empty orders)
-    if order.is_empty() {
-        return Err(ExecError::ValidationFailed(
-            "Order payload cannot be empty".to_string(),
-        ));
+    let checked = pre_trade_check(&order);
+    #[cfg(feature = "metrics")]
+    if let Err(err) = &checked {
+        metrics::record_error(err);
     }
+    checked?;
 
     // Simulate order processing (in production, this would be a real network call)
     // For MVP, we use a deterministic mock that always accepts valid orders
     let order_id = next_order_id();
+    #[cfg(feature = "metrics")]
+    metrics::record_order();
 
-    Ok(OrderAck {
-        order_id,
-        accepted: true,
-        reason: None,
-    })
+    Ok(OrderAck::partially_filled(order_id, filled, quantity))
 }
 
 /// Pre-trade check stub.
@@ -94,14 +398,34 @@ pub async fn send_order(order: Vec<u8>) -> Result<OrderAck, ExecError> {
 /// * `Err(ExecError)` - Order fails pre-trade checks
 pub fn pre_trade_check(order: &[u8]) -> Result<(), ExecError> {
     if order.is_empty() {
-        return Err(ExecError::ValidationFailed(
-            "Order payload cannot be empty".to_string(),
-        ));
+        return Err(ExecError::ValidationFailed(RejectReason::EmptyOrder));
     }
     // TODO: Add real pre-trade risk checks (position limits, margin checks, etc.)
     Ok(())
 }
 
+/// [`pre_trade_check`] plus a cap on the payload size.
+///
+/// An oversized payload usually means a bug upstream or a hostile client,
+/// so it is rejected before any parsing.
+///
+/// # Arguments
+/// * `order` - The order payload as bytes
+/// * `max_bytes` - Largest accepted payload, inclusive
+///
+/// # Returns
+/// * `Ok(())` - Order passes pre-trade checks
+/// * `Err(ExecError::ValidationFailed(RejectReason::EmptyOrder))` - If the payload is empty
+/// * `Err(ExecError::ValidationFailed(RejectReason::SizeExceeded))` - If the
+///   payload is longer than `max_bytes`
+pub fn pre_trade_check_sized(order: &[u8], max_bytes: usize) -> Result<(), ExecError> {
+    pre_trade_check(order)?;
+    if order.len() > max_bytes {
+        return Err(ExecError::ValidationFailed(RejectReason::SizeExceeded));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -115,6 +439,58 @@ mod tests {
         assert!(ack.accepted);
         assert!(ack.order_id > 0); // IDs are positive and sequential
         assert!(ack.reason.is_none());
+        assert!(ack.is_fully_filled());
+        assert_eq!(ack.filled_quantity, STUB_ORDER_QUANTITY);
+    }
+
+    #[test]
+    fn test_partially_filled_ack_remaining_quantity() {
+        let mut ack = OrderAck::partially_filled(7, 40, 100);
+        assert!(ack.accepted);
+        assert_eq!(ack.filled_quantity, 40);
+        assert_eq!(ack.remaining_quantity, 60);
+        assert_eq!(ack.total_quantity(), 100);
+        assert!(!ack.is_fully_filled());
+
+        let fill = FillEvent {
+            order_id: 7,
+            fill_quantity: 25,
+            remaining_quantity: 35,
+        };
+        assert!(ack.apply_fill(&fill));
+        assert_eq!(ack.filled_quantity, 65);
+        assert_eq!(ack.remaining_quantity, fill.remaining_quantity);
+
+        // Overfills are clamped and fills for other orders are ignored.
+        let other = FillEvent {
+            order_id: 8,
+            fill_quantity: 10,
+            remaining_quantity: 0,
+        };
+        assert!(!ack.apply_fill(&other));
+        let overfill = FillEvent {
+            order_id: 7,
+            fill_quantity: 1_000,
+            remaining_quantity: 0,
+        };
+        assert!(ack.apply_fill(&overfill));
+        assert!(ack.is_fully_filled());
+        assert_eq!(ack.total_quantity(), 100);
+    }
+
+    #[tokio::test]
+    async fn test_send_order_partial() {
+        let ack = send_order_partial(b"partial".to_vec(), 10, 4)
+            .await
+            .unwrap();
+        assert_eq!(ack.filled_quantity, 4);
+        assert_eq!(ack.remaining_quantity, 6);
+
+        let clamped = send_order_partial(b"partial".to_vec(), 10, 50)
+            .await
+            .unwrap();
+        assert!(clamped.is_fully_filled());
+        assert_eq!(clamped.filled_quantity, 10);
     }
 
     #[tokio::test]
@@ -123,8 +499,8 @@ mod tests {
         let result = send_order(order).await;
         assert!(result.is_err());
         match result.unwrap_err() {
-            ExecError::ValidationFailed(msg) => {
-                assert!(msg.contains("empty"));
+            ExecError::ValidationFailed(reason) => {
+                assert_eq!(reason, RejectReason::EmptyOrder);
             }
             _ => panic!("Expected ValidationFailed error"),
         }
@@ -146,6 +522,26 @@ mod tests {
         assert!(ack3.order_id > ack2.order_id);
     }
 
+    #[test]
+    fn test_reject_reason_labels_are_distinct() {
+        let reasons = [
+            RejectReason::EmptyOrder,
+            RejectReason::SizeExceeded,
+            RejectReason::UnknownSymbol,
+            RejectReason::RiskLimit,
+            RejectReason::BadSignature,
+            RejectReason::QueueFull,
+            RejectReason::MalformedOrder("x".to_string()),
+            RejectReason::Other("y".to_string()),
+        ];
+        let labels: std::collections::HashSet<_> = reasons.iter().map(|r| r.label()).collect();
+        assert_eq!(labels.len(), reasons.len());
+        assert_eq!(
+            RejectReason::MalformedOrder("anything".to_string()).label(),
+            "malformed_order"
+        );
+    }
+
     #[test]
     fn test_pre_trade_check_valid_order() {
         let order = b"valid order";
@@ -156,6 +552,81 @@ mod tests {
     fn test_pre_trade_check_empty_order() {
         let order: &[u8] = &[];
         let result = pre_trade_check(order);
-        assert!(result.is_err());
+        assert_eq!(
+            result,
+            Err(ExecError::ValidationFailed(RejectReason::EmptyOrder))
+        );
+    }
+
+    #[test]
+    fn test_pre_trade_check_sized() {
+        assert_eq!(pre_trade_check_sized(&[1; 64], 64), Ok(()));
+        assert_eq!(
+            pre_trade_check_sized(&[1; 65], 64),
+            Err(ExecError::ValidationFailed(RejectReason::SizeExceeded))
+        );
+        assert_eq!(
+            pre_trade_check_sized(&[], 64),
+            Err(ExecError::ValidationFailed(RejectReason::EmptyOrder))
+        );
+        assert_eq!(
+            ExecError::ValidationFailed(RejectReason::SizeExceeded).to_string(),
+            "validation failed: Order payload too large"
+        );
+    }
+
+    #[test]
+    fn test_exec_error_display() {
+        let err = ExecError::ValidationFailed(RejectReason::EmptyOrder);
+        assert_eq!(
+            err.to_string(),
+            "validation failed: Order payload cannot be empty"
+        );
+        let other = ExecError::ValidationFailed(RejectReason::Other("halted".to_string()));
+        assert_eq!(other.to_string(), "validation failed: halted");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_order_ack_json_round_trip() {
+        let mut ack = OrderAck::partially_filled(7, 2, 5);
+        ack.reason = Some("partial".to_string());
+        let json = serde_json::to_string(&ack).unwrap();
+        assert_eq!(
+            json,
+            r#"{"order_id":7,"accepted":true,"reason":"partial","filled_quantity":2,"remaining_quantity":3}"#
+        );
+        assert_eq!(serde_json::from_str::<OrderAck>(&json).unwrap(), ack);
+        assert_eq!(
+            serde_json::to_string(&OrderStatus::PartiallyFilled).unwrap(),
+            r#""partially_filled""#
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_exec_error_json_shape() {
+        let cases = [
+            (
+                ExecError::ValidationFailed(RejectReason::EmptyOrder),
+                serde_json::json!({"type": "validation_failed", "reason": {"kind": "empty_order"}}),
+            ),
+            (
+                ExecError::ValidationFailed(RejectReason::MalformedOrder("no side".to_string())),
+                serde_json::json!({
+                    "type": "validation_failed",
+                    "reason": {"kind": "malformed_order", "detail": "no side"},
+                }),
+            ),
+            (
+                ExecError::ConnectionError("refused".to_string()),
+                serde_json::json!({"type": "connection_error", "message": "refused"}),
+            ),
+            (ExecError::Timeout, serde_json::json!({"type": "timeout"})),
+        ];
+        for (err, expected) in cases {
+            assert_eq!(serde_json::to_value(&err).unwrap(), expected);
+            assert_eq!(serde_json::from_value::<ExecError>(expected).unwrap(), err);
+        }
     }
 }
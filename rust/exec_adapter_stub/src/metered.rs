@@ -0,0 +1,82 @@
+//! Metrics wrapper for any backend.
+//!
+//! [`MeteredBackend`] measures submissions to any backend: stack it in
+//! front of a venue connection (or any other wrapper) instead of
+//! instrumenting each backend. The built-in backends still count their own
+//! rejections and accepted orders when used bare, but leave that to a
+//! `MeteredBackend` above them, so nothing is counted twice. Only compiled
+//! with the `metrics` feature.
+
+use std::time::Instant;
+
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use crate::backend::ExecutionBackend;
+use crate::{metrics, ExecError, FillEvent, OrderAck};
+
+/// Backend wrapper that records every submission in the telemetry registry.
+///
+/// Each [`submit`](ExecutionBackend::submit) is timed as
+/// `latency_seconds{operation="order_submit"}`, whatever its outcome.
+/// Accepted orders increment `orders_total` and feed `orders_per_second`;
+/// validation failures increment `order_rejects_total{reason}`. Every
+/// other trait method is forwarded untouched.
+///
+/// A `MeteredBackend` wrapped in another one leaves the recording to the
+/// outer one. [`ExecutionBackend`] has no cancel operation, so nothing is
+/// recorded under `order_cancel`.
+#[derive(Debug)]
+pub struct MeteredBackend<B> {
+    inner: B,
+}
+
+impl<B: ExecutionBackend> MeteredBackend<B> {
+    /// Wrap `inner`.
+    pub fn new(inner: B) -> Self {
+        Self { inner }
+    }
+
+    /// Access the wrapped backend.
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    /// Unwrap, returning the inner backend.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<B: ExecutionBackend> ExecutionBackend for MeteredBackend<B> {
+    async fn submit(&self, order: Vec<u8>) -> Result<OrderAck, ExecError> {
+        if !metrics::self_counting() {
+            return self.inner.submit(order).await;
+        }
+        let start = Instant::now();
+        let result = metrics::metered(self.inner.submit(order)).await;
+        metrics::record_submit(start.elapsed(), &result);
+        result
+    }
+
+    async fn connect(&self) -> Result<(), ExecError> {
+        self.inner.connect().await
+    }
+
+    async fn disconnect(&self) -> Result<(), ExecError> {
+        self.inner.disconnect().await
+    }
+
+    fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+
+    async fn ping(&self) -> Result<(), ExecError> {
+        self.inner.ping().await
+    }
+
+    fn subscribe_fills(&self) -> mpsc::Receiver<FillEvent> {
+        self.inner.subscribe_fills()
+    }
+}
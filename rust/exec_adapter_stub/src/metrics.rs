@@ -0,0 +1,77 @@
+//! Rejection counters, submission latency and the order rate, backed by
+//! the shared telemetry registry.
+//!
+//! Only compiled with the `metrics` feature; without it the adapter has no
+//! dependency on the telemetry crate and no instrumentation.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use telemetry::prometheus::{CounterVec, Opts};
+use tinywindow_rust_telemetry as telemetry;
+
+use crate::{ExecError, OrderAck};
+
+/// `operation` label of backend submissions in `latency_seconds`.
+const SUBMIT_OPERATION: &str = "order_submit";
+
+tokio::task_local! {
+    /// Set while a [`MeteredBackend`](crate::MeteredBackend) awaits the
+    /// backend it wraps.
+    static METERED: ();
+}
+
+/// Run a [`MeteredBackend`](crate::MeteredBackend) submission, during which
+/// the backends below it leave the counting to it.
+pub(crate) async fn metered<F: std::future::Future>(submit: F) -> F::Output {
+    METERED.scope((), submit).await
+}
+
+/// Whether a backend should count its own result: `false` when a
+/// [`MeteredBackend`](crate::MeteredBackend) above it already does.
+pub(crate) fn self_counting() -> bool {
+    METERED.try_with(|_| ()).is_err()
+}
+
+fn order_rejects_total() -> &'static CounterVec {
+    static COUNTER: OnceLock<CounterVec> = OnceLock::new();
+    COUNTER.get_or_init(|| {
+        let (name, help) = (
+            "order_rejects_total",
+            "Total number of rejected orders by reason",
+        );
+        telemetry::register_counter_vec(name, help, &["reason"]).unwrap_or_else(|err| {
+            // Keep counting into a counter no scrape sees rather than panic
+            // on every rejection.
+            log::warn!("not exporting {name}: {err}");
+            CounterVec::new(Opts::new(name, help), &["reason"])
+                .expect("order_rejects_total metric definition is valid")
+        })
+    })
+}
+
+/// Feed an accepted order into the `orders_per_second` gauge.
+pub(crate) fn record_order() {
+    telemetry::record_order();
+}
+
+/// Record a backend submission that took `elapsed` and ended in `result`.
+pub(crate) fn record_submit(elapsed: Duration, result: &Result<OrderAck, ExecError>) {
+    telemetry::record_duration(SUBMIT_OPERATION, elapsed);
+    match result {
+        Ok(_) => {
+            telemetry::emit_metric("orders_total", 1.0);
+            record_order();
+        }
+        Err(err) => record_error(err),
+    }
+}
+
+/// Count `err` if it is a validation rejection.
+pub(crate) fn record_error(err: &ExecError) {
+    if let ExecError::ValidationFailed(reason) = err {
+        order_rejects_total()
+            .with_label_values(&[reason.label()])
+            .inc();
+    }
+}
@@ -0,0 +1,170 @@
+//! Order payload parsing.
+//!
+//! Orders travel as ASCII text of the form
+//!
+//! ```text
+//! SIDE SYMBOL QTY [PRICE]
+//! ```
+//!
+//! where `SIDE` is `BUY` or `SELL` (case-insensitive), `SYMBOL` is an
+//! alphanumeric ticker, `QTY` is a positive integer and the optional `PRICE`
+//! is a positive integer in price ticks. A missing price means a market order.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::RejectReason;
+
+/// Maximum length of a symbol.
+const MAX_SYMBOL_LEN: usize = 16;
+
+/// Order direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// Buy order
+    Buy,
+    /// Sell order
+    Sell,
+}
+
+/// A parsed order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Order {
+    /// Order direction
+    pub side: Side,
+    /// Instrument symbol, upper-cased
+    pub symbol: String,
+    /// Order quantity (always positive)
+    pub quantity: u64,
+    /// Limit price in ticks; `None` for market orders
+    pub price: Option<u64>,
+}
+
+impl Order {
+    /// Parse an order from its wire payload.
+    ///
+    /// # Returns
+    /// * `Ok(Order)` - The parsed order
+    /// * `Err(RejectReason::EmptyOrder)` - If the payload is empty
+    /// * `Err(RejectReason::MalformedOrder)` - If the payload does not match the format
+    pub fn parse(payload: &[u8]) -> Result<Self, RejectReason> {
+        if payload.is_empty() {
+            return Err(RejectReason::EmptyOrder);
+        }
+        std::str::from_utf8(payload)
+            .map_err(|_| malformed("payload is not UTF-8"))?
+            .parse()
+    }
+
+    /// Encode the order as its wire payload.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_string().into_bytes()
+    }
+}
+
+impl FromStr for Order {
+    type Err = RejectReason;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_ascii_whitespace().collect();
+        let (side, symbol, quantity, price) = match fields.as_slice() {
+            [] => return Err(RejectReason::EmptyOrder),
+            [side, symbol, quantity] => (side, symbol, quantity, None),
+            [side, symbol, quantity, price] => (side, symbol, quantity, Some(price)),
+            _ => return Err(malformed("expected SIDE SYMBOL QTY [PRICE]")),
+        };
+
+        let side = if side.eq_ignore_ascii_case("BUY") {
+            Side::Buy
+        } else if side.eq_ignore_ascii_case("SELL") {
+            Side::Sell
+        } else {
+            return Err(malformed("side must be BUY or SELL"));
+        };
+
+        if symbol.len() > MAX_SYMBOL_LEN || !symbol.bytes().all(|b| b.is_ascii_alphanumeric()) {
+            return Err(malformed("invalid symbol"));
+        }
+
+        Ok(Order {
+            side,
+            symbol: symbol.to_ascii_uppercase(),
+            quantity: parse_positive(quantity, "quantity")?,
+            price: price.map(|p| parse_positive(p, "price")).transpose()?,
+        })
+    }
+}
+
+impl fmt::Display for Order {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = match self.side {
+            Side::Buy => "BUY",
+            Side::Sell => "SELL",
+        };
+        write!(f, "{side} {} {}", self.symbol, self.quantity)?;
+        if let Some(price) = self.price {
+            write!(f, " {price}")?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_positive(field: &str, name: &str) -> Result<u64, RejectReason> {
+    match field.parse::<u64>() {
+        Ok(value) if value > 0 => Ok(value),
+        _ => Err(malformed(&format!("{name} must be a positive integer"))),
+    }
+}
+
+fn malformed(detail: &str) -> RejectReason {
+    RejectReason::MalformedOrder(detail.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_limit_and_market_orders() {
+        assert_eq!(
+            Order::parse(b"BUY AAPL 10 15025").unwrap(),
+            Order {
+                side: Side::Buy,
+                symbol: "AAPL".to_string(),
+                quantity: 10,
+                price: Some(15025),
+            }
+        );
+        let market = Order::parse(b"sell msft 3").unwrap();
+        assert_eq!(market.side, Side::Sell);
+        assert_eq!(market.symbol, "MSFT");
+        assert_eq!(market.price, None);
+    }
+
+    #[test]
+    fn test_roundtrip_through_bytes() {
+        let order = Order::parse(b"BUY AAPL 10 15025").unwrap();
+        assert_eq!(Order::parse(&order.to_bytes()).unwrap(), order);
+    }
+
+    #[test]
+    fn test_rejects_malformed_orders() {
+        assert_eq!(Order::parse(b""), Err(RejectReason::EmptyOrder));
+        for bad in [
+            &b"HOLD AAPL 10"[..],
+            b"BUY AAPL",
+            b"BUY AAPL 0",
+            b"BUY AAPL -5",
+            b"BUY AAPL 10 0",
+            b"BUY AA-PL 10",
+            b"BUY AAPL 10 100 extra",
+            b"\xff\xfe",
+        ] {
+            assert!(
+                matches!(Order::parse(bad), Err(RejectReason::MalformedOrder(_))),
+                "{:?} should be malformed",
+                String::from_utf8_lossy(bad)
+            );
+        }
+    }
+}
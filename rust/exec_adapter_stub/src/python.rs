@@ -0,0 +1,146 @@
+//! Python bindings (requires the `python` feature).
+//!
+//! Exposed as the `tinywindow_rust_exec` module. [`send_order`] is an
+//! awaitable coroutine: the order runs on a Tokio runtime owned by this
+//! module, so it can be awaited from any asyncio event loop. Each
+//! [`ExecError`](crate::ExecError) variant maps to its own exception type,
+//! all deriving from `tinywindow_rust_exec.ExecError` and carrying a
+//! `reason` attribute: the [`RejectReason`](crate::RejectReason) label for
+//! `ExecValidationError`, `"connection_error"` or `"timeout"` otherwise.
+//! `OrderRejected` remains as an alias of `ExecValidationError`.
+//!
+//! [`send_order`]: crate::send_order
+
+// pyo3 0.22's `create_exception!` checks a `gil-refs` feature this crate
+// doesn't declare, and its `#[pyfunction]` expansion for `PyResult` returns
+// trips `useless_conversion`.
+#![allow(unexpected_cfgs, clippy::useless_conversion)]
+
+use std::sync::OnceLock;
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use tokio::runtime::Runtime;
+
+use crate::OrderAck;
+
+create_exception!(
+    tinywindow_rust_exec,
+    ExecError,
+    PyException,
+    "Base class for execution adapter errors."
+);
+create_exception!(
+    tinywindow_rust_exec,
+    ExecValidationError,
+    ExecError,
+    "The order failed validation; `reason` (also `args[1]`) is the reject reason label."
+);
+create_exception!(
+    tinywindow_rust_exec,
+    ExecConnectionError,
+    ExecError,
+    "The execution backend could not be reached."
+);
+create_exception!(
+    tinywindow_rust_exec,
+    ExecTimeoutError,
+    ExecError,
+    "No acknowledgment arrived in time."
+);
+
+impl From<crate::ExecError> for PyErr {
+    fn from(err: crate::ExecError) -> PyErr {
+        let message = err.to_string();
+        let (err, reason) = match err {
+            crate::ExecError::ValidationFailed(reason) => (
+                ExecValidationError::new_err((message, reason.label())),
+                reason.label(),
+            ),
+            crate::ExecError::ConnectionError(_) => {
+                (ExecConnectionError::new_err(message), "connection_error")
+            }
+            crate::ExecError::Timeout => (ExecTimeoutError::new_err(message), "timeout"),
+        };
+        Python::with_gil(|py| match err.value_bound(py).setattr("reason", reason) {
+            Ok(()) => err,
+            Err(setattr_failed) => setattr_failed,
+        })
+    }
+}
+
+/// Runtime that drives orders submitted from Python.
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| Runtime::new().expect("failed to start the exec adapter runtime"))
+}
+
+fn ack_to_dict<'py>(py: Python<'py>, ack: &OrderAck) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    dict.set_item("order_id", ack.order_id)?;
+    dict.set_item("accepted", ack.accepted)?;
+    dict.set_item("reason", ack.reason.as_deref())?;
+    dict.set_item("filled_quantity", ack.filled_quantity)?;
+    dict.set_item("remaining_quantity", ack.remaining_quantity)?;
+    Ok(dict)
+}
+
+/// Send an order; resolves to the ack as a dict (Python binding).
+///
+/// Raises `ExecValidationError`, `ExecConnectionError` or `ExecTimeoutError`.
+#[pyfunction]
+#[pyo3(name = "send_order")]
+async fn py_send_order(order: Vec<u8>) -> PyResult<Py<PyDict>> {
+    let ack = runtime()
+        .spawn(crate::send_order(order))
+        .await
+        .map_err(|err| ExecError::new_err(format!("order task failed: {err}")))??;
+    Python::with_gil(|py| Ok(ack_to_dict(py, &ack)?.unbind()))
+}
+
+/// Run the pre-trade checks; raises `ExecValidationError` on failure (Python
+/// binding).
+#[pyfunction]
+#[pyo3(name = "pre_trade_check")]
+fn py_pre_trade_check(order: Vec<u8>) -> PyResult<()> {
+    Ok(crate::pre_trade_check(&order)?)
+}
+
+/// Run the pre-trade checks with a payload size cap (Python binding).
+#[pyfunction]
+#[pyo3(name = "pre_trade_check_sized")]
+fn py_pre_trade_check_sized(order: Vec<u8>, max_bytes: usize) -> PyResult<()> {
+    Ok(crate::pre_trade_check_sized(&order, max_bytes)?)
+}
+
+/// Embed a node ID in the order IDs of `send_order` (Python binding).
+#[pyfunction]
+#[pyo3(name = "configure_node_id")]
+fn py_configure_node_id(node_id: u16) {
+    crate::configure_node_id(node_id);
+}
+
+/// Python module for the TinyWindow execution adapter.
+#[pymodule]
+#[pyo3(name = "tinywindow_rust_exec")]
+fn tinywindow_rust_exec(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add("ExecError", py.get_type_bound::<ExecError>())?;
+    m.add(
+        "ExecValidationError",
+        py.get_type_bound::<ExecValidationError>(),
+    )?;
+    m.add("OrderRejected", py.get_type_bound::<ExecValidationError>())?;
+    m.add(
+        "ExecConnectionError",
+        py.get_type_bound::<ExecConnectionError>(),
+    )?;
+    m.add("ExecTimeoutError", py.get_type_bound::<ExecTimeoutError>())?;
+    m.add_function(wrap_pyfunction!(py_send_order, m)?)?;
+    m.add_function(wrap_pyfunction!(py_pre_trade_check, m)?)?;
+    m.add_function(wrap_pyfunction!(py_pre_trade_check_sized, m)?)?;
+    m.add_function(wrap_pyfunction!(py_configure_node_id, m)?)?;
+    Ok(())
+}
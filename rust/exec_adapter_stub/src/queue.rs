@@ -0,0 +1,179 @@
+//! Bounded order submission queue.
+//!
+//! Instead of spawning a task per order, callers push orders into a bounded
+//! channel that a single worker drains into the backend. When the channel is
+//! full, [`SubmissionQueue::try_enqueue`] rejects immediately and
+//! [`SubmissionQueue::enqueue`] waits for room, giving callers backpressure.
+
+use std::sync::Arc;
+
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+use crate::backend::ExecutionBackend;
+use crate::{ExecError, OrderAck, RejectReason};
+
+/// An order waiting for the worker, with the channel its result goes back on.
+struct Submission {
+    order: Vec<u8>,
+    reply: oneshot::Sender<Result<OrderAck, ExecError>>,
+}
+
+/// Handle to a queued order's eventual acknowledgment.
+#[derive(Debug)]
+pub struct PendingAck {
+    rx: oneshot::Receiver<Result<OrderAck, ExecError>>,
+}
+
+impl PendingAck {
+    /// Wait for the backend's response to the queued order.
+    ///
+    /// # Returns
+    /// * `Ok(OrderAck)` - The backend's acknowledgment
+    /// * `Err(ExecError)` - The backend's error, or a connection error if the
+    ///   queue shut down before the order was submitted
+    pub async fn wait(self) -> Result<OrderAck, ExecError> {
+        self.rx.await.unwrap_or_else(|_| Err(queue_closed()))
+    }
+}
+
+/// Bounded queue feeding a single backend worker.
+#[derive(Debug)]
+pub struct SubmissionQueue {
+    tx: mpsc::Sender<Submission>,
+    worker: JoinHandle<()>,
+}
+
+impl SubmissionQueue {
+    /// Create a queue holding at most `capacity` waiting orders and spawn its worker.
+    ///
+    /// # Arguments
+    /// * `backend` - Backend the worker submits to, one order at a time
+    /// * `capacity` - Maximum number of orders waiting in the queue (at least 1)
+    pub fn new<B: ExecutionBackend + 'static>(backend: Arc<B>, capacity: usize) -> Self {
+        let (tx, mut rx) = mpsc::channel::<Submission>(capacity.max(1));
+        let worker = tokio::spawn(async move {
+            while let Some(submission) = rx.recv().await {
+                let result = backend.submit(submission.order).await;
+                // The caller may have dropped its PendingAck; that is fine.
+                let _ = submission.reply.send(result);
+            }
+        });
+        Self { tx, worker }
+    }
+
+    /// Queue an order without waiting.
+    ///
+    /// # Returns
+    /// * `Ok(PendingAck)` - The order was queued
+    /// * `Err(ExecError::ValidationFailed(RejectReason::QueueFull))` - The queue is at capacity
+    pub fn try_enqueue(&self, order: Vec<u8>) -> Result<PendingAck, ExecError> {
+        let (submission, pending) = submission(order);
+        if let Err(err) = self.tx.try_send(submission) {
+            let err = match err {
+                mpsc::error::TrySendError::Full(_) => {
+                    ExecError::ValidationFailed(RejectReason::QueueFull)
+                }
+                mpsc::error::TrySendError::Closed(_) => queue_closed(),
+            };
+            #[cfg(feature = "metrics")]
+            crate::metrics::record_error(&err);
+            return Err(err);
+        }
+        Ok(pending)
+    }
+
+    /// Queue an order, waiting for room if the queue is at capacity.
+    pub async fn enqueue(&self, order: Vec<u8>) -> Result<PendingAck, ExecError> {
+        let (submission, pending) = submission(order);
+        self.tx.send(submission).await.map_err(|_| queue_closed())?;
+        Ok(pending)
+    }
+
+    /// Free slots left in the queue.
+    pub fn available(&self) -> usize {
+        self.tx.capacity()
+    }
+
+    /// Stop accepting orders and wait for the worker to drain what is queued.
+    pub async fn shutdown(self) {
+        drop(self.tx);
+        // The worker only ends by draining the channel; a panic in the
+        // backend has already been reported by the runtime.
+        let _ = self.worker.await;
+    }
+}
+
+fn submission(order: Vec<u8>) -> (Submission, PendingAck) {
+    let (reply, rx) = oneshot::channel();
+    (Submission { order, reply }, PendingAck { rx })
+}
+
+fn queue_closed() -> ExecError {
+    ExecError::ConnectionError("submission queue closed".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StubBackend;
+    use std::time::Duration;
+
+    async fn queue(capacity: usize) -> SubmissionQueue {
+        let backend = Arc::new(StubBackend::new());
+        backend.connect().await.unwrap();
+        SubmissionQueue::new(backend, capacity)
+    }
+
+    #[tokio::test]
+    async fn test_try_enqueue_rejects_when_full() {
+        let queue = queue(2).await;
+
+        // The single-threaded test runtime does not run the worker until we
+        // yield, so nothing is drained between these calls.
+        let first = queue.try_enqueue(b"one".to_vec()).unwrap();
+        let second = queue.try_enqueue(b"two".to_vec()).unwrap();
+        assert_eq!(queue.available(), 0);
+        assert_eq!(
+            queue.try_enqueue(b"three".to_vec()).unwrap_err(),
+            ExecError::ValidationFailed(RejectReason::QueueFull)
+        );
+
+        assert_eq!(first.wait().await.unwrap().order_id, 1);
+        assert_eq!(second.wait().await.unwrap().order_id, 2);
+        assert!(queue.try_enqueue(b"four".to_vec()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_waits_for_room() {
+        let queue = queue(1).await;
+        let first = queue.try_enqueue(b"one".to_vec()).unwrap();
+
+        let blocked = queue.enqueue(b"two".to_vec());
+        let second = tokio::time::timeout(Duration::from_secs(1), blocked)
+            .await
+            .expect("worker frees a slot")
+            .unwrap();
+
+        assert!(first.wait().await.unwrap().accepted);
+        assert!(second.wait().await.unwrap().accepted);
+    }
+
+    #[tokio::test]
+    async fn test_backend_errors_are_returned_to_caller() {
+        let queue = queue(4).await;
+        let pending = queue.enqueue(vec![]).await.unwrap();
+        assert_eq!(
+            pending.wait().await,
+            Err(ExecError::ValidationFailed(RejectReason::EmptyOrder))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_queued_orders() {
+        let queue = queue(4).await;
+        let pending = queue.try_enqueue(b"order".to_vec()).unwrap();
+        queue.shutdown().await;
+        assert!(pending.wait().await.unwrap().accepted);
+    }
+}
@@ -0,0 +1,54 @@
+//! `MeteredBackend` records latency, accepted orders and rejections.
+//!
+//! Runs in its own process so the counts are exact.
+
+#![cfg(feature = "metrics")]
+
+use std::time::Duration;
+
+use exec_adapter_stub::{DryRunBackend, ExecutionBackend, MeteredBackend, StubBackend};
+use tinywindow_rust_telemetry::{
+    get_counter_value, get_histogram_count, get_metrics, latency_percentile,
+};
+
+fn submit_samples() -> Option<u64> {
+    get_histogram_count("latency_seconds", &[("operation", "order_submit")])
+}
+
+fn orders_total() -> f64 {
+    get_counter_value("orders_total").unwrap_or(0.0)
+}
+
+#[tokio::test]
+async fn test_submit_is_timed_and_counted() {
+    let backend = MeteredBackend::new(StubBackend::with_latency(Duration::from_millis(2)));
+    assert!(!backend.is_connected());
+    backend.connect().await.unwrap();
+    assert!(backend.is_connected());
+    assert!(backend.inner().is_connected());
+
+    let ack = backend.submit(b"BUY AAPL 1".to_vec()).await.unwrap();
+    assert!(ack.accepted);
+    assert_eq!(submit_samples(), Some(1));
+    assert_eq!(orders_total(), 1.0);
+    let max_us = latency_percentile("order_submit", 1.0).unwrap();
+    assert!(max_us >= 2_000.0, "{max_us}");
+
+    // Rejections are timed too, but count as rejects rather than orders.
+    assert!(backend.submit(vec![]).await.is_err());
+    assert_eq!(submit_samples(), Some(2));
+    assert_eq!(orders_total(), 1.0);
+    assert!(get_metrics().contains("order_rejects_total{reason=\"empty_order\"} 1"));
+
+    backend.disconnect().await.unwrap();
+    assert!(backend.submit(b"BUY AAPL 1".to_vec()).await.is_err());
+    assert_eq!(submit_samples(), Some(3));
+    assert_eq!(orders_total(), 1.0);
+
+    // Other wrappers can be metered too; lifecycle calls still pass through.
+    let dry_run = MeteredBackend::new(DryRunBackend::new(StubBackend::new()));
+    dry_run.submit(b"BUY AAPL 1".to_vec()).await.unwrap();
+    assert_eq!(submit_samples(), Some(4));
+    assert_eq!(orders_total(), 2.0);
+    assert!(!dry_run.into_inner().into_inner().is_connected());
+}
@@ -0,0 +1,26 @@
+//! Accepted orders feed the `orders_per_second` gauge.
+//!
+//! Runs in its own process so no other test records orders.
+
+#![cfg(feature = "metrics")]
+
+use exec_adapter_stub::{send_order, ExecutionBackend, StubBackend};
+use tinywindow_rust_telemetry::{get_metrics, orders_per_second};
+
+#[tokio::test]
+async fn test_orders_per_second_tracks_accepted_orders() {
+    assert_eq!(orders_per_second(), 0.0);
+    assert!(send_order(vec![]).await.is_err());
+    assert_eq!(orders_per_second(), 0.0);
+
+    let backend = StubBackend::new();
+    backend.connect().await.unwrap();
+    for _ in 0..5 {
+        send_order(b"BUY AAPL 1".to_vec()).await.unwrap();
+        backend.submit(b"BUY AAPL 1".to_vec()).await.unwrap();
+    }
+
+    let rate = orders_per_second();
+    assert!(rate > 0.0 && rate <= 10.0, "{rate}");
+    assert!(get_metrics().contains("\norders_per_second "));
+}
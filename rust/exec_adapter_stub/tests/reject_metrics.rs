@@ -0,0 +1,35 @@
+//! Rejections are counted by reason in the telemetry registry.
+//!
+//! Runs in its own process so the counts are exact.
+
+#![cfg(feature = "metrics")]
+
+use exec_adapter_stub::{send_order, ExecutionBackend, StubBackend};
+use tinywindow_rust_telemetry::get_metrics;
+
+/// Read the `order_rejects_total` sample for `reason`, if present.
+fn rejects(reason: &str) -> Option<f64> {
+    let series = format!("order_rejects_total{{reason=\"{reason}\"}} ");
+    get_metrics()
+        .lines()
+        .find_map(|line| line.strip_prefix(&series)?.parse().ok())
+}
+
+#[tokio::test]
+async fn test_rejections_are_counted_by_reason() {
+    let before = rejects("empty_order").unwrap_or(0.0);
+    assert!(send_order(vec![]).await.is_err());
+    assert_eq!(rejects("empty_order"), Some(before + 1.0));
+
+    let backend = StubBackend::with_fill_ratio(0.5);
+    backend.connect().await.unwrap();
+    assert!(backend.submit(b"garbage".to_vec()).await.is_err());
+    assert_eq!(rejects("malformed_order"), Some(1.0));
+
+    // Accepted orders and connection errors are not rejections.
+    assert!(send_order(b"BUY AAPL 1".to_vec()).await.is_ok());
+    backend.disconnect().await.unwrap();
+    assert!(backend.submit(b"BUY AAPL 1".to_vec()).await.is_err());
+    assert_eq!(rejects("empty_order"), Some(before + 1.0));
+    assert_eq!(rejects("malformed_order"), Some(1.0));
+}
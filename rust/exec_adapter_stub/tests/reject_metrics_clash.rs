@@ -0,0 +1,21 @@
+//! A name clash on `order_rejects_total` does not break order submission.
+//!
+//! Runs in its own process so the clashing metric is registered first.
+
+#![cfg(feature = "metrics")]
+
+use exec_adapter_stub::{send_order, ExecError, RejectReason};
+use tinywindow_rust_telemetry::{get_metrics, register_counter};
+
+#[tokio::test]
+async fn test_rejections_survive_unregistrable_counter() {
+    register_counter("order_rejects_total", "Taken by the application").unwrap();
+
+    for _ in 0..2 {
+        assert_eq!(
+            send_order(vec![]).await,
+            Err(ExecError::ValidationFailed(RejectReason::EmptyOrder))
+        );
+    }
+    assert!(get_metrics().contains("# HELP order_rejects_total Taken by the application"));
+}
@@ -0,0 +1,320 @@
+//! CSV rendering of the registry for offline analysis (e.g. pandas).
+//!
+//! Every sample is one row of `timestamp,metric,labels,value_kind,value`:
+//!
+//! ```text
+//! timestamp,metric,labels,value_kind,value
+//! 1700000000123,orders_total,,value,3
+//! 1700000000123,latency_seconds,"operation=order_send,le=0.0005",bucket,1
+//! 1700000000123,latency_seconds,operation=order_send,count,1
+//! 1700000000123,latency_seconds,operation=order_send,sum,0.00025
+//! ```
+//!
+//! `timestamp` is the Unix time of the dump in milliseconds, the same for
+//! every row of one dump. `labels` is `name=value` pairs joined by commas,
+//! with the bucket bound as `le` and the summary quantile as `quantile`,
+//! as in the Prometheus text format. `value_kind` is `value` for counters,
+//! gauges and untyped metrics and `bucket`, `quantile`, `count` or `sum`
+//! for the samples of histograms and summaries. Fields are quoted per
+//! RFC 4180 where needed. Series are sorted by metric, then labels, so a
+//! dump of a given registry state is stable apart from its timestamp.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use prometheus::proto::{Metric, MetricFamily, MetricType};
+use pyo3::prelude::*;
+
+use crate::{init_metrics, TelemetryError, DEFAULT};
+
+/// Header row of [`get_metrics_csv`] output.
+pub const CSV_HEADER: &str = "timestamp,metric,labels,value_kind,value";
+
+/// Render all registered metrics as CSV, header row included.
+pub fn get_metrics_csv() -> String {
+    init_metrics();
+    DEFAULT.get_metrics_csv()
+}
+
+/// Write [`get_metrics_csv`] to a file.
+///
+/// # Arguments
+/// * `path` - File to write
+/// * `append` - Add the rows to the end of an existing file instead of
+///   replacing it; the header is only written if the file is new or empty
+///
+/// # Returns
+/// * `Ok(())` - The rows were written
+/// * `Err(TelemetryError::Io)` - If the file could not be opened or written
+pub fn dump_metrics_csv(path: &Path, append: bool) -> Result<(), TelemetryError> {
+    init_metrics();
+    DEFAULT.dump_metrics_csv(path, append)
+}
+
+/// Write the rows of `families` to `path`, see [`dump_metrics_csv`].
+pub(crate) fn dump(
+    families: &[MetricFamily],
+    path: &Path,
+    append: bool,
+) -> Result<(), TelemetryError> {
+    let describe = |err: std::io::Error| TelemetryError::Io(format!("{}: {err}", path.display()));
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .map_err(describe)?;
+    let header = file.metadata().map_err(describe)?.len() == 0;
+    file.write_all(render(families, now_ms(), header).as_bytes())
+        .map_err(describe)
+}
+
+/// The current Unix time in milliseconds.
+pub(crate) fn now_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or_default()
+}
+
+/// Render `families` as CSV rows stamped `timestamp`, preceded by
+/// [`CSV_HEADER`] if `header` is set.
+///
+/// Series are sorted; the rows of one series keep their natural order
+/// (buckets ascending, then count and sum).
+pub(crate) fn render(families: &[MetricFamily], timestamp: u128, header: bool) -> String {
+    let mut series: Vec<(&str, MetricType, Vec<String>, &Metric)> = families
+        .iter()
+        .flat_map(|family| {
+            family.get_metric().iter().map(|metric| {
+                let labels = metric
+                    .get_label()
+                    .iter()
+                    .map(|label| format!("{}={}", label.get_name(), label.get_value()))
+                    .collect();
+                (family.get_name(), family.get_field_type(), labels, metric)
+            })
+        })
+        .collect();
+    series.sort_by(|a, b| (a.0, &a.2).cmp(&(b.0, &b.2)));
+
+    let mut out = String::new();
+    if header {
+        out.push_str(CSV_HEADER);
+        out.push('\n');
+    }
+    for row in series
+        .iter()
+        .flat_map(|(name, kind, labels, metric)| rows(name, *kind, labels, metric))
+    {
+        out.push_str(&format!(
+            "{timestamp},{},{},{},{}\n",
+            quote(row.metric),
+            quote(&row.labels),
+            row.kind,
+            row.value
+        ));
+    }
+    out
+}
+
+struct Row<'a> {
+    metric: &'a str,
+    labels: String,
+    kind: &'static str,
+    value: f64,
+}
+
+/// The rows of one series, whose labels are `labels`.
+fn rows<'a>(
+    metric_name: &'a str,
+    kind: MetricType,
+    labels: &[String],
+    metric: &Metric,
+) -> Vec<Row<'a>> {
+    let row = |extra: Option<(&str, f64)>, kind: &'static str, value: f64| {
+        let mut labels = labels.to_vec();
+        if let Some((name, bound)) = extra {
+            if bound.is_infinite() {
+                labels.push(format!("{name}=+Inf"));
+            } else {
+                labels.push(format!("{name}={bound}"));
+            }
+        }
+        Row {
+            metric: metric_name,
+            labels: labels.join(","),
+            kind,
+            value,
+        }
+    };
+
+    match kind {
+        MetricType::COUNTER => vec![row(None, "value", metric.get_counter().get_value())],
+        MetricType::GAUGE => vec![row(None, "value", metric.get_gauge().get_value())],
+        MetricType::UNTYPED => vec![row(None, "value", metric.get_untyped().get_value())],
+        MetricType::HISTOGRAM => {
+            let histogram = metric.get_histogram();
+            let count = histogram.get_sample_count() as f64;
+            let mut rows: Vec<Row> = histogram
+                .get_bucket()
+                .iter()
+                .map(|bucket| {
+                    row(
+                        Some(("le", bucket.get_upper_bound())),
+                        "bucket",
+                        bucket.get_cumulative_count() as f64,
+                    )
+                })
+                .collect();
+            rows.push(row(Some(("le", f64::INFINITY)), "bucket", count));
+            rows.push(row(None, "count", count));
+            rows.push(row(None, "sum", histogram.get_sample_sum()));
+            rows
+        }
+        MetricType::SUMMARY => {
+            let summary = metric.get_summary();
+            let mut rows: Vec<Row> = summary
+                .get_quantile()
+                .iter()
+                .map(|q| {
+                    row(
+                        Some(("quantile", q.get_quantile())),
+                        "quantile",
+                        q.get_value(),
+                    )
+                })
+                .collect();
+            rows.push(row(None, "count", summary.get_sample_count() as f64));
+            rows.push(row(None, "sum", summary.get_sample_sum()));
+            rows
+        }
+    }
+}
+
+/// Quote `field` if it contains a comma, quote or line break.
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Render metrics as CSV (Python binding).
+#[pyfunction]
+#[pyo3(name = "get_metrics_csv")]
+pub(crate) fn py_get_metrics_csv(py: Python<'_>) -> String {
+    py.allow_threads(get_metrics_csv)
+}
+
+/// Write metrics as CSV to `path`, appending if `append` is true (Python
+/// binding).
+///
+/// Raises `ValueError` if the file cannot be written.
+#[pyfunction]
+#[pyo3(name = "dump_metrics_csv", signature = (path, append = false))]
+pub(crate) fn py_dump_metrics_csv(py: Python<'_>, path: PathBuf, append: bool) -> PyResult<()> {
+    Ok(py.allow_threads(|| dump_metrics_csv(&path, append))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TelemetryHandle;
+
+    /// A path in the temp directory unique to this process and test.
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("tinywindow-{}-{name}.csv", std::process::id()))
+    }
+
+    /// Parse CSV `text` into rows of fields, honouring quotes.
+    fn parse(text: &str) -> Vec<Vec<String>> {
+        text.lines()
+            .map(|line| {
+                let mut fields = vec![String::new()];
+                let mut quoted = false;
+                let mut chars = line.chars().peekable();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' if quoted && chars.peek() == Some(&'"') => {
+                            chars.next();
+                            fields.last_mut().unwrap().push('"');
+                        }
+                        '"' => quoted = !quoted,
+                        ',' if !quoted => fields.push(String::new()),
+                        c => fields.last_mut().unwrap().push(c),
+                    }
+                }
+                fields
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_dump_and_append() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.record_latency("order_send", 250.0);
+        handle
+            .emit_counter("fills_total", &[("venue", "nyse")], 2.0)
+            .unwrap();
+        let path = temp_path("dump");
+
+        handle.dump_metrics_csv(&path, false).unwrap();
+        let rows = parse(&std::fs::read_to_string(&path).unwrap());
+        assert_eq!(rows[0].join(","), CSV_HEADER);
+        assert!(rows.iter().all(|row| row.len() == 5));
+        let latency: Vec<&Vec<String>> = rows
+            .iter()
+            .filter(|row| row[1] == "latency_seconds")
+            .collect();
+        // One row per finite bucket, plus +Inf, count and sum.
+        assert_eq!(latency.len(), crate::LATENCY_BUCKETS.len() + 3);
+        assert!(latency
+            .iter()
+            .any(|row| row[2..] == ["operation=order_send,le=0.0005", "bucket", "1"]));
+        assert!(latency
+            .iter()
+            .any(|row| row[2..] == ["operation=order_send,le=+Inf", "bucket", "1"]));
+        assert!(rows
+            .iter()
+            .any(|row| row[1..] == ["fills_total", "venue=nyse", "value", "2"]));
+        let first_dump = rows.len();
+
+        handle.dump_metrics_csv(&path, true).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text.matches(CSV_HEADER).count(), 1);
+        assert_eq!(parse(&text).len(), 2 * first_dump - 1);
+
+        handle.dump_metrics_csv(&path, false).unwrap();
+        assert_eq!(
+            parse(&std::fs::read_to_string(&path).unwrap()).len(),
+            first_dump
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_labels_with_commas_are_quoted() {
+        let handle = TelemetryHandle::new_isolated();
+        handle
+            .emit_counter("fills_total", &[("venue", "nyse"), ("side", "buy")], 1.0)
+            .unwrap();
+        let text = handle.get_metrics_csv();
+        assert!(text.contains(",fills_total,\"side=buy,venue=nyse\",value,1\n"));
+        assert_eq!(quote("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(quote("plain"), "plain");
+    }
+
+    #[test]
+    fn test_unwritable_path_is_an_error() {
+        let path = temp_path("missing").join("metrics.csv");
+        assert!(matches!(
+            dump_metrics_csv(&path, true),
+            Err(TelemetryError::Io(_))
+        ));
+    }
+}
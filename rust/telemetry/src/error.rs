@@ -0,0 +1,194 @@
+//! Error type for the fallible telemetry APIs.
+
+use std::fmt;
+
+use pyo3::PyErr;
+
+/// Errors returned by the telemetry crate.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TelemetryError {
+    /// A metric value was NaN or infinite, or a counter increment was
+    /// negative (or fractional, for integer counters).
+    InvalidValue {
+        /// Metric name
+        name: String,
+        /// The rejected value
+        value: f64,
+    },
+    /// A metric name does not follow the Prometheus naming rules.
+    InvalidMetricName(String),
+    /// A new counter name was dropped because the counter limit was reached.
+    TooManyMetrics {
+        /// The configured limit
+        limit: usize,
+    },
+    /// A label key or value contains disallowed characters or is too long.
+    InvalidLabel(String),
+    /// A labeled metric was used with different label keys than it was created with.
+    LabelMismatch {
+        /// Metric name
+        name: String,
+        /// Label keys fixed at registration
+        expected: Vec<String>,
+        /// Label keys supplied
+        actual: Vec<String>,
+    },
+    /// Histogram buckets were empty, non-finite or not strictly increasing.
+    InvalidBuckets(String),
+    /// A histogram was registered again with different buckets.
+    BucketMismatch {
+        /// Metric name
+        name: String,
+        /// Buckets of the existing histogram
+        existing: Vec<f64>,
+        /// Buckets requested by the new registration
+        requested: Vec<f64>,
+    },
+    /// Summary objectives were empty or outside `0.0..=1.0`.
+    InvalidObjectives(String),
+    /// A summary was registered again with different objectives.
+    ObjectiveMismatch {
+        /// Metric name
+        name: String,
+        /// Objectives of the existing summary
+        existing: Vec<(f64, f64)>,
+        /// Objectives requested by the new registration
+        requested: Vec<(f64, f64)>,
+    },
+    /// The latency histogram was already created, so its buckets are fixed.
+    LatencyInUse,
+    /// Global labels were already set, or the registry already exists.
+    GlobalLabelsFrozen,
+    /// The namespace was already set, or the registry already exists.
+    NamespaceFrozen,
+    /// No metric of the required kind is registered under this name.
+    UnknownMetric(String),
+    /// A built-in metric such as `orders_total` cannot be removed.
+    BuiltinMetric(String),
+    /// A Pushgateway could not be reached (bad URL, connection failure or timeout).
+    PushConnection(String),
+    /// A Pushgateway answered a push with a non-2xx status.
+    PushRejected {
+        /// HTTP status code
+        status: u16,
+        /// Response body
+        body: String,
+    },
+    /// The metrics HTTP server could not start.
+    Server(String),
+    /// StatsD mirroring could not be enabled (bad address or prefix).
+    Statsd(String),
+    /// An OTLP exporter could not start or a collector export failed.
+    Otlp(String),
+    /// An InfluxDB write was invalid, could not be sent or was rejected.
+    Influx(String),
+    /// A metrics file could not be read or written.
+    Io(String),
+    /// The Prometheus registry rejected a metric (e.g. the name is taken).
+    Registry(String),
+    /// The registry could not be rendered in an exposition format.
+    EncodeFailed(String),
+}
+
+impl fmt::Display for TelemetryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TelemetryError::InvalidValue { name, value } => write!(
+                f,
+                "invalid value {value} for metric {name:?}: must be finite (and a non-negative whole number for counters)"
+            ),
+            TelemetryError::InvalidMetricName(name) => write!(f, "invalid metric name {name:?}"),
+            TelemetryError::TooManyMetrics { limit } => {
+                write!(f, "counter limit of {limit} distinct names reached")
+            }
+            TelemetryError::InvalidLabel(label) => write!(f, "invalid label {label:?}"),
+            TelemetryError::LabelMismatch {
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "metric {name:?} has label keys {expected:?}, got {actual:?}"
+            ),
+            TelemetryError::InvalidBuckets(msg) => write!(f, "invalid histogram buckets: {msg}"),
+            TelemetryError::BucketMismatch {
+                name,
+                existing,
+                requested,
+            } => write!(
+                f,
+                "histogram {name:?} already registered with buckets {existing:?}, got {requested:?}"
+            ),
+            TelemetryError::InvalidObjectives(msg) => write!(f, "invalid summary objectives: {msg}"),
+            TelemetryError::ObjectiveMismatch {
+                name,
+                existing,
+                requested,
+            } => write!(
+                f,
+                "summary {name:?} already registered with objectives {existing:?}, got {requested:?}"
+            ),
+            TelemetryError::LatencyInUse => write!(
+                f,
+                "latency_seconds already exists; configure_latency_buckets must be called \
+                 before the first record_latency"
+            ),
+            TelemetryError::GlobalLabelsFrozen => write!(
+                f,
+                "global labels are fixed; set_global_labels must be called once, \
+                 before any other telemetry call"
+            ),
+            TelemetryError::NamespaceFrozen => write!(
+                f,
+                "metric namespace is fixed; set_namespace must be called once, \
+                 before any other telemetry call"
+            ),
+            TelemetryError::UnknownMetric(name) => write!(f, "unknown metric {name:?}"),
+            TelemetryError::BuiltinMetric(name) => {
+                write!(f, "built-in metric {name:?} cannot be removed")
+            }
+            TelemetryError::PushConnection(msg) => write!(f, "pushgateway unreachable: {msg}"),
+            TelemetryError::PushRejected { status, body } => {
+                write!(f, "pushgateway rejected push with status {status}: {body}")
+            }
+            TelemetryError::Server(msg) => write!(f, "metrics server error: {msg}"),
+            TelemetryError::Statsd(msg) => write!(f, "StatsD error: {msg}"),
+            TelemetryError::Otlp(msg) => write!(f, "OTLP export error: {msg}"),
+            TelemetryError::Influx(msg) => write!(f, "InfluxDB write error: {msg}"),
+            TelemetryError::Io(msg) => write!(f, "I/O error: {msg}"),
+            TelemetryError::Registry(msg) => write!(f, "registry error: {msg}"),
+            TelemetryError::EncodeFailed(msg) => write!(f, "failed to encode metrics: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for TelemetryError {}
+
+impl From<prometheus::Error> for TelemetryError {
+    fn from(err: prometheus::Error) -> Self {
+        TelemetryError::Registry(err.to_string())
+    }
+}
+
+/// Python exception types, registered in the `tinywindow_rust_telemetry` module.
+pub(crate) mod py {
+    // pyo3 0.22's `create_exception!` checks a `gil-refs` feature this crate
+    // doesn't declare.
+    #![allow(unexpected_cfgs)]
+
+    use pyo3::create_exception;
+    use pyo3::exceptions::PyValueError;
+
+    create_exception!(
+        tinywindow_rust_telemetry,
+        TelemetryError,
+        PyValueError,
+        "A telemetry call was rejected."
+    );
+}
+
+impl From<TelemetryError> for PyErr {
+    fn from(err: TelemetryError) -> PyErr {
+        py::TelemetryError::new_err(err.to_string())
+    }
+}
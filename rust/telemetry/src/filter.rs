@@ -0,0 +1,255 @@
+//! Filtered scrapes.
+//!
+//! A [`MetricFilter`] selects metric families by name prefix or exact name,
+//! and series by label values, before encoding, so a consumer that only
+//! needs `latency_seconds` does not pay for rendering everything else.
+//! The built-in endpoint (see [`serve_metrics`](crate::serve_metrics))
+//! takes the same filter as query parameters:
+//!
+//! ```text
+//! /metrics?prefix[]=latency_&name[]=orders_total&label[]=operation=order_send
+//! ```
+
+use std::collections::{BTreeSet, HashMap};
+
+use prometheus::proto::MetricFamily;
+use pyo3::prelude::*;
+
+use crate::{init_metrics, DEFAULT};
+
+/// Which metric families and series to render.
+///
+/// A family is kept if its name starts with one of the prefixes or is one
+/// of the names; with neither set, every family is. Within a kept family,
+/// a series is kept if it carries every label constraint, and families
+/// left without series are dropped. Names are matched as rendered,
+/// including any [`set_namespace`](crate::set_namespace) prefix.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetricFilter {
+    prefixes: Vec<String>,
+    names: BTreeSet<String>,
+    labels: Vec<(String, String)>,
+}
+
+impl MetricFilter {
+    /// A filter that keeps everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also keep families whose name starts with `prefix`.
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefixes.push(prefix.to_string());
+        self
+    }
+
+    /// Also keep the family called exactly `name`.
+    pub fn name(mut self, name: &str) -> Self {
+        self.names.insert(name.to_string());
+        self
+    }
+
+    /// Only keep series whose label `key` equals `value`.
+    pub fn label(mut self, key: &str, value: &str) -> Self {
+        self.labels.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Whether this filter keeps everything.
+    pub fn is_empty(&self) -> bool {
+        self.prefixes.is_empty() && self.names.is_empty() && self.labels.is_empty()
+    }
+
+    fn keeps_name(&self, name: &str) -> bool {
+        (self.prefixes.is_empty() && self.names.is_empty())
+            || self.names.contains(name)
+            || self.prefixes.iter().any(|prefix| name.starts_with(prefix))
+    }
+
+    /// The families and series of `families` this filter keeps.
+    pub(crate) fn apply(&self, families: Vec<MetricFamily>) -> Vec<MetricFamily> {
+        families
+            .into_iter()
+            .filter(|family| self.keeps_name(family.get_name()))
+            .filter_map(|mut family| {
+                if !self.labels.is_empty() {
+                    let mut metrics = family.take_metric().into_vec();
+                    metrics.retain(|metric| {
+                        self.labels.iter().all(|(key, value)| {
+                            metric
+                                .get_label()
+                                .iter()
+                                .any(|pair| pair.get_name() == key && pair.get_value() == value)
+                        })
+                    });
+                    family.set_metric(metrics.into());
+                }
+                // The encoders reject families without series.
+                (!family.get_metric().is_empty()).then_some(family)
+            })
+            .collect()
+    }
+
+    /// Parse the `prefix[]`, `name[]` and `label[]=key=value` parameters of
+    /// a URL query string, ignoring any others.
+    pub(crate) fn from_query(query: &str) -> Self {
+        let mut filter = Self::new();
+        for pair in query.split('&') {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = percent_decode(value);
+            match percent_decode(key).as_str() {
+                "prefix[]" => filter = filter.prefix(&value),
+                "name[]" => filter = filter.name(&value),
+                "label[]" => {
+                    if let Some((key, value)) = value.split_once('=') {
+                        filter = filter.label(key, value);
+                    }
+                }
+                _ => {}
+            }
+        }
+        filter
+    }
+}
+
+/// Decode `%XX` escapes and `+` (as a space) in a query component;
+/// malformed escapes are kept as they are.
+fn percent_decode(text: &str) -> String {
+    let hex = |byte: u8| (byte as char).to_digit(16);
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                (Some(high), Some(low)) => {
+                    out.push((high * 16 + low) as u8);
+                    i += 2;
+                }
+                _ => out.push(b'%'),
+            },
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Render the metrics `filter` keeps in the Prometheus text format.
+///
+/// An empty result is an empty string, which is valid exposition text.
+pub fn get_metrics_filtered(filter: &MetricFilter) -> String {
+    init_metrics();
+    DEFAULT.get_metrics_filtered(filter)
+}
+
+/// Render the metrics whose names start with one of `prefixes`, optionally
+/// also those in `names`, keeping only series with every label in `labels`
+/// (Python binding).
+///
+/// Releases the GIL while rendering.
+#[pyfunction]
+#[pyo3(name = "get_metrics_filtered", signature = (prefixes, names = None, labels = None))]
+pub(crate) fn py_get_metrics_filtered(
+    py: Python<'_>,
+    prefixes: Vec<String>,
+    names: Option<Vec<String>>,
+    labels: Option<HashMap<String, String>>,
+) -> String {
+    let mut filter = MetricFilter::new();
+    for prefix in &prefixes {
+        filter = filter.prefix(prefix);
+    }
+    for name in names.iter().flatten() {
+        filter = filter.name(name);
+    }
+    for (key, value) in labels.iter().flatten() {
+        filter = filter.label(key, value);
+    }
+    py.allow_threads(|| get_metrics_filtered(&filter))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TelemetryHandle;
+
+    fn handle() -> TelemetryHandle {
+        let handle = TelemetryHandle::new_isolated();
+        handle.emit_metric("orders_total", 2.0);
+        handle.record_latency("order_send", 250.0);
+        handle.record_latency("order_cancel", 250.0);
+        // Above the top bucket, so `latency_overflow_total` has a series.
+        handle.record_latency("order_slow", 20_000_000.0);
+        handle
+            .emit_counter("fills_total", &[("venue", "nyse")], 1.0)
+            .unwrap();
+        handle
+    }
+
+    #[test]
+    fn test_prefix_filter() {
+        let output = handle().get_metrics_filtered(&MetricFilter::new().prefix("latency_"));
+        assert!(output.contains("latency_seconds_count{operation=\"order_send\"} 1"));
+        assert!(output.contains("latency_overflow_total"));
+        assert!(!output.contains("orders_total"));
+        assert!(!output.contains("fills_total"));
+    }
+
+    #[test]
+    fn test_exact_name_filter() {
+        let handle = handle();
+        let output = handle.get_metrics_filtered(&MetricFilter::new().name("latency_seconds"));
+        assert!(output.contains("# TYPE latency_seconds histogram"));
+        assert!(!output.contains("latency_overflow_total"));
+
+        let output = handle
+            .get_metrics_filtered(&MetricFilter::new().name("orders_total").name("fills_total"));
+        assert!(output.contains("orders_total 2"));
+        assert!(output.contains("fills_total{venue=\"nyse\"} 1"));
+        assert!(!output.contains("latency_seconds"));
+
+        assert_eq!(
+            handle.get_metrics_filtered(&MetricFilter::new().name("latency")),
+            ""
+        );
+        assert_eq!(
+            handle.get_metrics_filtered(&MetricFilter::new()),
+            handle.get_metrics()
+        );
+    }
+
+    #[test]
+    fn test_label_filter() {
+        let output = handle().get_metrics_filtered(
+            &MetricFilter::new()
+                .name("latency_seconds")
+                .label("operation", "order_send"),
+        );
+        assert!(output.contains("latency_seconds_count{operation=\"order_send\"} 1"));
+        assert!(!output.contains("order_cancel"));
+
+        // Families without a matching series disappear entirely.
+        let output = handle().get_metrics_filtered(&MetricFilter::new().label("venue", "nyse"));
+        assert!(output.contains("fills_total{venue=\"nyse\"} 1"));
+        assert!(!output.contains("orders_total"));
+        assert!(!output.contains("latency_seconds"));
+    }
+
+    #[test]
+    fn test_from_query() {
+        let filter = MetricFilter::from_query(
+            "prefix%5B%5D=latency_&name[]=orders_total&label[]=operation%3Dorder_send&x=1",
+        );
+        assert_eq!(
+            filter,
+            MetricFilter::new()
+                .prefix("latency_")
+                .name("orders_total")
+                .label("operation", "order_send")
+        );
+        assert!(MetricFilter::from_query("").is_empty());
+        assert_eq!(percent_decode("a+b%2"), "a b%2");
+    }
+}
@@ -0,0 +1,1856 @@
+//! Self-contained metric state.
+//!
+//! A [`TelemetryHandle`] owns a registry plus every name-keyed map the
+//! free functions use. The free functions delegate to a process-wide
+//! handle over [`REGISTRY`]; tests can build their own with
+//! [`TelemetryHandle::new_isolated`] and assert exact values without
+//! seeing metrics from other tests.
+//!
+//! [`REGISTRY`]: crate::REGISTRY
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Once, OnceLock, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use prometheus::core::Collector;
+use prometheus::proto::{Metric, MetricFamily};
+use prometheus::{
+    Counter, CounterVec, Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounter, IntCounterVec,
+    Opts, ProtobufEncoder, Registry, TextEncoder,
+};
+
+use crate::csv;
+use crate::filter::MetricFilter;
+use crate::health::{HealthReport, HealthThresholds};
+use crate::influx;
+use crate::introspect::{self, MetricInfo};
+use crate::local::ThreadLocalRecorder;
+use crate::openmetrics::{self, Exemplar, Exemplars};
+use crate::operations::Operations;
+use crate::rate::RateGauge;
+use crate::resettable::ResettableCounter;
+use crate::snapshot::{self, Sample, Snapshot};
+use crate::summary::Summary;
+use crate::{
+    global_label_map, is_valid_metric_name, json, label_values, validate_buckets, validate_labels,
+    TelemetryError, DEFAULT_COUNTER_LIMIT, DEFAULT_LABEL_VALUE_LIMIT, LATENCY_BUCKETS,
+    LATENCY_LABELS, OTHER_LABEL_VALUE, RESULT_ERROR_KIND,
+};
+
+/// Metrics every handle creates itself; they cannot be unregistered.
+const BUILTIN_METRICS: &[&str] = &[
+    "orders_total",
+    "orders_per_second",
+    "errors_total",
+    "latency_seconds",
+    "labeled_latency_seconds",
+    "latency_overflow_total",
+    "latency_samples_skipped_total",
+    "telemetry_dropped_metrics_total",
+    "telemetry_unknown_metric_total",
+    "telemetry_label_overflow_total",
+];
+
+/// A counter family created by `emit_counter`, with its label keys in
+/// registration order.
+#[derive(Clone)]
+struct LabeledCounter {
+    counter: IntCounterVec,
+    keys: Vec<String>,
+}
+
+/// A gauge family created by `set_gauge`, with its label keys in
+/// registration order.
+#[derive(Clone)]
+struct LabeledGauge {
+    gauge: GaugeVec,
+    keys: Vec<String>,
+}
+
+/// Bucket layout for `latency_seconds`, and whether it has been created yet.
+struct LatencyConfig {
+    buckets: Vec<f64>,
+    in_use: bool,
+}
+
+/// Deterministic 1-in-`every` sampling of one operation's latency samples.
+struct Sampler {
+    /// Record every `every`-th call; 0 records nothing
+    every: u64,
+    calls: AtomicU64,
+}
+
+impl Sampler {
+    /// Whether the current call should be observed.
+    fn admit(&self) -> bool {
+        let call = self.calls.fetch_add(1, Ordering::Relaxed);
+        self.every != 0 && call.is_multiple_of(self.every)
+    }
+}
+
+/// Convert a counter increment, already checked to be finite and
+/// non-negative, for an integer counter.
+///
+/// Integer counters keep counting past 2^53, where an `f64` counter stops
+/// registering increments of 1, but only take whole increments.
+fn whole(name: &str, value: f64) -> Result<u64, TelemetryError> {
+    if value.fract() != 0.0 || value >= u64::MAX as f64 {
+        return Err(TelemetryError::InvalidValue {
+            name: name.to_string(),
+            value,
+        });
+    }
+    Ok(value as u64)
+}
+
+/// The last text rendering kept by `get_metrics_cached`.
+struct CachedRender {
+    rendered_at: Instant,
+    text: String,
+}
+
+/// A histogram created by `register_histogram`, with the buckets it was
+/// registered with. Held as a label-less vec so [`TelemetryHandle::reset`]
+/// can clear it.
+struct NamedHistogram {
+    histogram: HistogramVec,
+    buckets: Vec<f64>,
+}
+
+/// A registry with its own counters, gauges, histograms and summaries.
+///
+/// Methods mirror the crate's free functions (see those for details); the
+/// free functions act on the default handle backing
+/// [`REGISTRY`](crate::REGISTRY).
+pub struct TelemetryHandle {
+    registry: Registry,
+    /// Namespace of every metric the handle creates.
+    namespace: Option<String>,
+    /// Const labels the registry adds to every series when gathering.
+    global_labels: HashMap<String, String>,
+    orders_total: IntCounter,
+    dropped: Counter,
+    unknown: Counter,
+    errors: CounterVec,
+    skipped: CounterVec,
+    /// `latency_overflow_total{operation}`, samples above `latency_top_bound`.
+    latency_overflow: CounterVec,
+    label_overflow: IntCounter,
+    counter_limit: AtomicUsize,
+    strict: AtomicBool,
+    counters: RwLock<HashMap<String, IntCounter>>,
+    resettable_counters: RwLock<HashMap<String, ResettableCounter>>,
+    labeled_counters: RwLock<HashMap<String, LabeledCounter>>,
+    /// Families from `register_counter_vec`, kept so they can be unregistered.
+    counter_vecs: RwLock<HashMap<String, CounterVec>>,
+    gauges: RwLock<HashMap<String, LabeledGauge>>,
+    histograms: RwLock<HashMap<String, NamedHistogram>>,
+    summaries: RwLock<HashMap<String, Summary>>,
+    latency_config: Mutex<LatencyConfig>,
+    latency: OnceLock<HistogramVec>,
+    /// Largest finite bucket bound of `latency`, set when it is created.
+    latency_top_bound: OnceLock<f64>,
+    /// Operation names admitted to `latency`.
+    operations: Arc<Operations>,
+    /// Whether `samplers` is non-empty, so unsampled calls skip its lock.
+    sampling: AtomicBool,
+    samplers: RwLock<HashMap<String, Sampler>>,
+    labeled_latency: OnceLock<HistogramVec>,
+    label_value_limit: AtomicUsize,
+    /// Distinct values seen per `labeled_latency_seconds` label.
+    label_values_seen: Mutex<HashMap<&'static str, HashSet<String>>>,
+    exemplars: Mutex<Exemplars>,
+    local_recorder: OnceLock<ThreadLocalRecorder>,
+    orders_rate: RateGauge,
+    /// Registers `orders_rate` on the first recorded order.
+    orders_rate_registered: Once,
+    metrics_cache: Mutex<Option<CachedRender>>,
+    /// Number of text renderings, for testing the cache.
+    renders: AtomicU64,
+    health: RwLock<HealthThresholds>,
+    /// When the text or protobuf format was last rendered.
+    last_scrape: Mutex<Option<(Instant, SystemTime)>>,
+}
+
+impl TelemetryHandle {
+    /// Create a handle with a fresh registry, unrelated to [`REGISTRY`].
+    ///
+    /// It starts with the built-in `orders_total`,
+    /// `telemetry_dropped_metrics_total` and `telemetry_unknown_metric_total`
+    /// counters at zero, in lenient mode.
+    ///
+    /// [`REGISTRY`]: crate::REGISTRY
+    pub fn new_isolated() -> Self {
+        Self::with_registry(Registry::new(), None, HashMap::new())
+    }
+
+    /// Create an isolated handle whose metric names are prefixed with `ns`.
+    ///
+    /// `ns` is validated as for [`set_namespace`](crate::set_namespace).
+    pub fn with_namespace(ns: &str) -> Result<Self, TelemetryError> {
+        if !is_valid_metric_name(ns) {
+            return Err(TelemetryError::InvalidMetricName(ns.to_string()));
+        }
+        Ok(Self::with_registry(
+            Registry::new(),
+            Some(ns.to_string()),
+            HashMap::new(),
+        ))
+    }
+
+    /// Create an isolated handle whose every series carries `labels`.
+    ///
+    /// The labels are validated as for
+    /// [`set_global_labels`](crate::set_global_labels).
+    pub fn with_global_labels(labels: &[(&str, &str)]) -> Result<Self, TelemetryError> {
+        Self::labeled(None, labels)
+    }
+
+    /// Create an isolated handle in `namespace` whose every series carries
+    /// `labels`.
+    pub(crate) fn labeled(
+        namespace: Option<&str>,
+        labels: &[(&str, &str)],
+    ) -> Result<Self, TelemetryError> {
+        let labels = global_label_map(labels)?;
+        let registry = Registry::new_custom(None, Some(labels.clone()))?;
+        Ok(Self::with_registry(
+            registry,
+            namespace.map(str::to_string),
+            labels,
+        ))
+    }
+
+    /// Create a handle over `registry` and register the built-in counters.
+    ///
+    /// Every metric the handle creates is put in `namespace`.
+    /// `global_labels` must be the const labels `registry` was created with.
+    pub(crate) fn with_registry(
+        registry: Registry,
+        namespace: Option<String>,
+        global_labels: HashMap<String, String>,
+    ) -> Self {
+        let opts = |name: &str, help: &str| namespaced_opts(namespace.as_deref(), name, help);
+        let orders_total =
+            IntCounter::with_opts(opts("orders_total", "Total number of orders processed"))
+                .expect("orders_total metric definition is valid");
+        let dropped = Counter::with_opts(opts(
+            "telemetry_dropped_metrics_total",
+            "New counter names dropped because the counter limit was reached",
+        ))
+        .expect("telemetry_dropped_metrics_total metric definition is valid");
+        let unknown = Counter::with_opts(opts(
+            "telemetry_unknown_metric_total",
+            "Counter names emitted without being registered first",
+        ))
+        .expect("telemetry_unknown_metric_total metric definition is valid");
+        let errors = CounterVec::new(
+            opts("errors_total", "Failed operations by operation and kind"),
+            &["operation", "kind"],
+        )
+        .expect("errors_total metric definition is valid");
+        registry
+            .register(Box::new(orders_total.clone()))
+            .expect("orders_total registers once");
+        registry
+            .register(Box::new(dropped.clone()))
+            .expect("telemetry_dropped_metrics_total registers once");
+        registry
+            .register(Box::new(unknown.clone()))
+            .expect("telemetry_unknown_metric_total registers once");
+        let skipped = CounterVec::new(
+            opts(
+                "latency_samples_skipped_total",
+                "Latency samples not recorded because of sampling",
+            ),
+            &["operation"],
+        )
+        .expect("latency_samples_skipped_total metric definition is valid");
+        registry
+            .register(Box::new(errors.clone()))
+            .expect("errors_total registers once");
+        registry
+            .register(Box::new(skipped.clone()))
+            .expect("latency_samples_skipped_total registers once");
+        let latency_overflow = CounterVec::new(
+            opts(
+                "latency_overflow_total",
+                "Latency samples above the largest finite latency_seconds bucket",
+            ),
+            &["operation"],
+        )
+        .expect("latency_overflow_total metric definition is valid");
+        registry
+            .register(Box::new(latency_overflow.clone()))
+            .expect("latency_overflow_total registers once");
+        let label_overflow = IntCounter::with_opts(opts(
+            "telemetry_label_overflow_total",
+            "Latency samples recorded as operation=\"overflow\" because the operation limit was reached",
+        ))
+        .expect("telemetry_label_overflow_total metric definition is valid");
+        registry
+            .register(Box::new(label_overflow.clone()))
+            .expect("telemetry_label_overflow_total registers once");
+
+        let orders_rate = RateGauge::new(opts(
+            "orders_per_second",
+            "Orders recorded per second over a sliding window",
+        ))
+        .expect("orders_per_second metric definition is valid");
+
+        let counters = HashMap::from([("orders_total".to_string(), orders_total.clone())]);
+        Self {
+            registry,
+            namespace,
+            global_labels,
+            orders_total,
+            dropped,
+            unknown,
+            errors,
+            skipped,
+            latency_overflow,
+            label_overflow: label_overflow.clone(),
+            counter_limit: AtomicUsize::new(DEFAULT_COUNTER_LIMIT),
+            strict: AtomicBool::new(false),
+            counters: RwLock::new(counters),
+            resettable_counters: RwLock::default(),
+            labeled_counters: RwLock::default(),
+            counter_vecs: RwLock::default(),
+            gauges: RwLock::default(),
+            histograms: RwLock::default(),
+            summaries: RwLock::default(),
+            latency_config: Mutex::new(LatencyConfig {
+                buckets: LATENCY_BUCKETS.to_vec(),
+                in_use: false,
+            }),
+            latency: OnceLock::new(),
+            latency_top_bound: OnceLock::new(),
+            operations: Arc::new(Operations::new(label_overflow)),
+            sampling: AtomicBool::new(false),
+            samplers: RwLock::default(),
+            labeled_latency: OnceLock::new(),
+            label_value_limit: AtomicUsize::new(DEFAULT_LABEL_VALUE_LIMIT),
+            label_values_seen: Mutex::default(),
+            exemplars: Mutex::default(),
+            local_recorder: OnceLock::new(),
+            orders_rate,
+            orders_rate_registered: Once::new(),
+            metrics_cache: Mutex::default(),
+            renders: AtomicU64::new(0),
+            health: RwLock::default(),
+            last_scrape: Mutex::default(),
+        }
+    }
+
+    /// The registry this handle registers into.
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// The built-in `orders_total` counter.
+    pub fn orders_total(&self) -> &IntCounter {
+        &self.orders_total
+    }
+
+    /// The built-in `telemetry_dropped_metrics_total` counter.
+    pub fn dropped_metrics_total(&self) -> &Counter {
+        &self.dropped
+    }
+
+    /// The built-in `telemetry_unknown_metric_total` counter.
+    pub fn unknown_metrics_total(&self) -> &Counter {
+        &self.unknown
+    }
+
+    /// The built-in `errors_total{operation, kind}` counter.
+    pub fn errors_total(&self) -> &CounterVec {
+        &self.errors
+    }
+
+    /// See [`set_strict_mode`](crate::set_strict_mode).
+    pub fn set_strict_mode(&self, strict: bool) {
+        self.strict.store(strict, Ordering::Relaxed);
+    }
+
+    /// See [`strict_mode`](crate::strict_mode).
+    pub fn strict_mode(&self) -> bool {
+        self.strict.load(Ordering::Relaxed)
+    }
+
+    /// See [`set_counter_limit`](crate::set_counter_limit).
+    pub fn set_counter_limit(&self, limit: usize) {
+        self.counter_limit.store(limit, Ordering::Relaxed);
+    }
+
+    /// See [`register_counter`](crate::register_counter).
+    pub fn register_counter(&self, name: &str, help: &str) -> prometheus::Result<IntCounter> {
+        // Held across registration so a concurrent `counter_for` cannot
+        // miss the name and register it a second time.
+        let mut counters = self.counters.write().unwrap();
+        let counter = IntCounter::with_opts(self.opts(name, help))?;
+        self.registry.register(Box::new(counter.clone()))?;
+        counters.insert(name.to_string(), counter.clone());
+        Ok(counter)
+    }
+
+    /// See [`register_resettable_counter`](crate::register_resettable_counter).
+    pub fn register_resettable_counter(
+        &self,
+        name: &str,
+        help: &str,
+    ) -> Result<(), TelemetryError> {
+        if !is_valid_metric_name(name) {
+            return Err(TelemetryError::InvalidMetricName(name.to_string()));
+        }
+        let mut resettable = self.resettable_counters.write().unwrap();
+        let counter = ResettableCounter::new(self.opts(name, help))?;
+        self.registry.register(Box::new(counter.clone()))?;
+        resettable.insert(name.to_string(), counter);
+        Ok(())
+    }
+
+    /// See [`take_and_reset_counter`](crate::take_and_reset_counter).
+    pub fn take_and_reset_counter(&self, name: &str) -> Option<f64> {
+        self.resettable_counters
+            .read()
+            .unwrap()
+            .get(name)
+            .map(ResettableCounter::take)
+    }
+
+    /// See [`register_counter_vec`](crate::register_counter_vec).
+    pub fn register_counter_vec(
+        &self,
+        name: &str,
+        help: &str,
+        label_names: &[&str],
+    ) -> prometheus::Result<CounterVec> {
+        let mut families = self.counter_vecs.write().unwrap();
+        let counter = CounterVec::new(self.opts(name, help), label_names)?;
+        self.registry.register(Box::new(counter.clone()))?;
+        families.insert(name.to_string(), counter.clone());
+        Ok(counter)
+    }
+
+    /// See [`unregister_counter`](crate::unregister_counter).
+    pub fn unregister_counter(&self, name: &str) -> Result<(), TelemetryError> {
+        if BUILTIN_METRICS.contains(&name) {
+            return Err(TelemetryError::BuiltinMetric(name.to_string()));
+        }
+        if let Some(counter) = self.counters.write().unwrap().remove(name) {
+            self.registry.unregister(Box::new(counter))?;
+            return Ok(());
+        }
+        if let Some(family) = self.labeled_counters.write().unwrap().remove(name) {
+            self.registry.unregister(Box::new(family.counter))?;
+            return Ok(());
+        }
+        if let Some(counter) = self.resettable_counters.write().unwrap().remove(name) {
+            self.registry.unregister(Box::new(counter))?;
+            return Ok(());
+        }
+        if let Some(family) = self.counter_vecs.write().unwrap().remove(name) {
+            self.registry.unregister(Box::new(family))?;
+            return Ok(());
+        }
+        Err(TelemetryError::UnknownMetric(name.to_string()))
+    }
+
+    /// See [`unregister_metric`](crate::unregister_metric).
+    pub fn unregister_metric(&self, name: &str) -> bool {
+        if BUILTIN_METRICS.contains(&name) {
+            return false;
+        }
+        let collector: Box<dyn Collector> =
+            if let Some(counter) = self.counters.write().unwrap().remove(name) {
+                Box::new(counter)
+            } else if let Some(family) = self.labeled_counters.write().unwrap().remove(name) {
+                Box::new(family.counter)
+            } else if let Some(counter) = self.resettable_counters.write().unwrap().remove(name) {
+                Box::new(counter)
+            } else if let Some(family) = self.counter_vecs.write().unwrap().remove(name) {
+                Box::new(family)
+            } else if let Some(family) = self.gauges.write().unwrap().remove(name) {
+                Box::new(family.gauge)
+            } else if let Some(named) = self.histograms.write().unwrap().remove(name) {
+                Box::new(named.histogram)
+            } else if let Some(summary) = self.summaries.write().unwrap().remove(name) {
+                Box::new(summary)
+            } else {
+                return false;
+            };
+        self.registry.unregister(collector).is_ok()
+    }
+
+    /// Look up the counter called `name`, creating and registering it if needed.
+    fn counter_for(&self, name: &str) -> Result<IntCounter, TelemetryError> {
+        if let Some(counter) = self.counters.read().unwrap().get(name) {
+            return Ok(counter.clone());
+        }
+        if !is_valid_metric_name(name) {
+            return Err(TelemetryError::InvalidMetricName(name.to_string()));
+        }
+
+        let mut counters = self.counters.write().unwrap();
+        // Another thread may have created it while we waited for the lock.
+        if let Some(counter) = counters.get(name) {
+            return Ok(counter.clone());
+        }
+        if self.strict_mode() {
+            return Err(TelemetryError::UnknownMetric(name.to_string()));
+        }
+        let limit = self.counter_limit.load(Ordering::Relaxed);
+        if counters.len() >= limit {
+            self.dropped.inc();
+            return Err(TelemetryError::TooManyMetrics { limit });
+        }
+        let counter =
+            IntCounter::with_opts(self.opts(name, &format!("Counter {name} emitted by name")))?;
+        self.registry.register(Box::new(counter.clone()))?;
+        // Counted once created, so a name dropped at the limit only shows
+        // up in `telemetry_dropped_metrics_total`.
+        self.unknown.inc();
+        counters.insert(name.to_string(), counter.clone());
+        Ok(counter)
+    }
+
+    /// See [`emit_metric_checked`](crate::emit_metric_checked).
+    pub fn emit_metric_checked(&self, name: &str, value: f64) -> Result<(), TelemetryError> {
+        if !value.is_finite() || value < 0.0 {
+            return Err(TelemetryError::InvalidValue {
+                name: name.to_string(),
+                value,
+            });
+        }
+        if let Some(counter) = self.resettable_counters.read().unwrap().get(name) {
+            counter.inc_by(value);
+            return Ok(());
+        }
+        let value = whole(name, value)?;
+        self.counter_for(name)?.inc_by(value);
+        Ok(())
+    }
+
+    /// See [`emit_metric`](crate::emit_metric).
+    pub fn emit_metric(&self, name: &str, value: f64) {
+        // Rejected samples are documented as dropped.
+        let _ = self.emit_metric_checked(name, value);
+    }
+
+    /// See [`emit_counter`](crate::emit_counter).
+    pub fn emit_counter(
+        &self,
+        name: &str,
+        labels: &[(&str, &str)],
+        value: f64,
+    ) -> Result<(), TelemetryError> {
+        if !value.is_finite() || value < 0.0 {
+            return Err(TelemetryError::InvalidValue {
+                name: name.to_string(),
+                value,
+            });
+        }
+        let value = whole(name, value)?;
+        validate_labels(labels)?;
+
+        let family = self.labeled_counter_for(name, labels)?;
+        let values = label_values(name, &family.keys, labels)?;
+        family.counter.with_label_values(&values).inc_by(value);
+        Ok(())
+    }
+
+    /// Look up the labeled counter called `name`, creating it with the keys
+    /// of `labels` if needed.
+    fn labeled_counter_for(
+        &self,
+        name: &str,
+        labels: &[(&str, &str)],
+    ) -> Result<LabeledCounter, TelemetryError> {
+        if let Some(family) = self.labeled_counters.read().unwrap().get(name) {
+            return Ok(family.clone());
+        }
+        if !is_valid_metric_name(name) {
+            return Err(TelemetryError::InvalidMetricName(name.to_string()));
+        }
+
+        let mut families = self.labeled_counters.write().unwrap();
+        if let Some(family) = families.get(name) {
+            return Ok(family.clone());
+        }
+        let keys: Vec<&str> = labels.iter().map(|(key, _)| *key).collect();
+        let counter = IntCounterVec::new(
+            self.opts(name, &format!("Counter {name} emitted by name")),
+            &keys,
+        )?;
+        self.registry.register(Box::new(counter.clone()))?;
+        let family = LabeledCounter {
+            counter,
+            keys: keys.into_iter().map(str::to_string).collect(),
+        };
+        families.insert(name.to_string(), family.clone());
+        Ok(family)
+    }
+
+    /// See [`set_gauge`](crate::set_gauge).
+    pub fn set_gauge(
+        &self,
+        name: &str,
+        labels: &[(&str, &str)],
+        value: f64,
+    ) -> Result<(), TelemetryError> {
+        if !value.is_finite() {
+            return Err(TelemetryError::InvalidValue {
+                name: name.to_string(),
+                value,
+            });
+        }
+        validate_labels(labels)?;
+
+        let family = self.gauge_for(name, labels)?;
+        let values = label_values(name, &family.keys, labels)?;
+        family.gauge.with_label_values(&values).set(value);
+        Ok(())
+    }
+
+    /// Look up the gauge called `name`, creating it with the keys of
+    /// `labels` if needed.
+    fn gauge_for(
+        &self,
+        name: &str,
+        labels: &[(&str, &str)],
+    ) -> Result<LabeledGauge, TelemetryError> {
+        if let Some(family) = self.gauges.read().unwrap().get(name) {
+            return Ok(family.clone());
+        }
+        if !is_valid_metric_name(name) {
+            return Err(TelemetryError::InvalidMetricName(name.to_string()));
+        }
+
+        let mut families = self.gauges.write().unwrap();
+        if let Some(family) = families.get(name) {
+            return Ok(family.clone());
+        }
+        let keys: Vec<&str> = labels.iter().map(|(key, _)| *key).collect();
+        let gauge = GaugeVec::new(self.opts(name, &format!("Gauge {name} set by name")), &keys)?;
+        self.registry.register(Box::new(gauge.clone()))?;
+        let family = LabeledGauge {
+            gauge,
+            keys: keys.into_iter().map(str::to_string).collect(),
+        };
+        families.insert(name.to_string(), family.clone());
+        Ok(family)
+    }
+
+    /// See [`register_histogram`](crate::register_histogram).
+    pub fn register_histogram(&self, name: &str, buckets: &[f64]) -> Result<(), TelemetryError> {
+        if !is_valid_metric_name(name) {
+            return Err(TelemetryError::InvalidMetricName(name.to_string()));
+        }
+        validate_buckets(buckets)?;
+
+        let mut histograms = self.histograms.write().unwrap();
+        if let Some(existing) = histograms.get(name) {
+            if existing.buckets == buckets {
+                return Ok(());
+            }
+            return Err(TelemetryError::BucketMismatch {
+                name: name.to_string(),
+                existing: existing.buckets.clone(),
+                requested: buckets.to_vec(),
+            });
+        }
+        let histogram = HistogramVec::new(
+            HistogramOpts::from(self.opts(name, &format!("Histogram {name} registered by name")))
+                .buckets(buckets.to_vec()),
+            &[],
+        )?;
+        // Export the empty series right away, like a plain histogram would.
+        histogram.with_label_values(&[]);
+        self.registry.register(Box::new(histogram.clone()))?;
+        histograms.insert(
+            name.to_string(),
+            NamedHistogram {
+                histogram,
+                buckets: buckets.to_vec(),
+            },
+        );
+        Ok(())
+    }
+
+    /// See [`observe_histogram`](crate::observe_histogram).
+    pub fn observe_histogram(&self, name: &str, value: f64) -> Result<(), TelemetryError> {
+        if !value.is_finite() {
+            return Err(TelemetryError::InvalidValue {
+                name: name.to_string(),
+                value,
+            });
+        }
+        match self.histograms.read().unwrap().get(name) {
+            Some(named) => {
+                named.histogram.with_label_values(&[]).observe(value);
+                Ok(())
+            }
+            None => Err(TelemetryError::UnknownMetric(name.to_string())),
+        }
+    }
+
+    /// See [`register_summary`](crate::register_summary).
+    pub fn register_summary(
+        &self,
+        name: &str,
+        objectives: &[(f64, f64)],
+    ) -> Result<(), TelemetryError> {
+        if !is_valid_metric_name(name) {
+            return Err(TelemetryError::InvalidMetricName(name.to_string()));
+        }
+        if objectives.is_empty() {
+            return Err(TelemetryError::InvalidObjectives(
+                "no objectives given".to_string(),
+            ));
+        }
+        if objectives.iter().any(|(quantile, error)| {
+            !(0.0..=1.0).contains(quantile) || !(0.0..=1.0).contains(error)
+        }) {
+            return Err(TelemetryError::InvalidObjectives(format!(
+                "quantiles and errors must be within 0..=1, got {objectives:?}"
+            )));
+        }
+
+        let mut summaries = self.summaries.write().unwrap();
+        if let Some(existing) = summaries.get(name) {
+            if existing.objectives() == objectives {
+                return Ok(());
+            }
+            return Err(TelemetryError::ObjectiveMismatch {
+                name: name.to_string(),
+                existing: existing.objectives().to_vec(),
+                requested: objectives.to_vec(),
+            });
+        }
+        let summary = Summary::new(
+            self.opts(name, &format!("Summary {name} registered by name")),
+            objectives,
+        )?;
+        self.registry.register(Box::new(summary.clone()))?;
+        summaries.insert(name.to_string(), summary);
+        Ok(())
+    }
+
+    /// See [`observe_summary`](crate::observe_summary).
+    pub fn observe_summary(&self, name: &str, value: f64) -> Result<(), TelemetryError> {
+        if !value.is_finite() {
+            return Err(TelemetryError::InvalidValue {
+                name: name.to_string(),
+                value,
+            });
+        }
+        match self.summaries.read().unwrap().get(name) {
+            Some(summary) => {
+                summary.observe(value);
+                Ok(())
+            }
+            None => Err(TelemetryError::UnknownMetric(name.to_string())),
+        }
+    }
+
+    /// See [`configure_latency_buckets`](crate::configure_latency_buckets).
+    pub fn configure_latency_buckets(&self, buckets: &[f64]) -> Result<(), TelemetryError> {
+        validate_buckets(buckets)?;
+        let mut config = self.latency_config.lock().unwrap();
+        if config.in_use {
+            return Err(TelemetryError::LatencyInUse);
+        }
+        config.buckets = buckets.to_vec();
+        Ok(())
+    }
+
+    /// See [`latency_buckets`](crate::latency_buckets).
+    pub fn latency_buckets(&self) -> Vec<f64> {
+        self.latency_config.lock().unwrap().buckets.clone()
+    }
+
+    /// The `latency_seconds` histogram, created with the configured buckets
+    /// on first use.
+    pub fn latency(&self) -> &HistogramVec {
+        self.latency.get_or_init(|| {
+            let mut config = self.latency_config.lock().unwrap();
+            config.in_use = true;
+            // Buckets are validated as increasing and finite.
+            if let Some(top) = config.buckets.last() {
+                let _ = self.latency_top_bound.set(*top);
+            }
+            let latency = HistogramVec::new(
+                HistogramOpts::from(self.opts("latency_seconds", "Operation latency in seconds"))
+                    .buckets(config.buckets.clone()),
+                &["operation"],
+            )
+            .expect("latency_seconds metric definition is valid");
+            self.registry
+                .register(Box::new(latency.clone()))
+                .expect("latency_seconds registers once");
+            latency
+        })
+    }
+
+    /// See [`record_latency`](crate::record_latency).
+    pub fn record_latency(&self, operation: &str, duration_us: f64) {
+        if let Err(err) = self.try_record_latency(operation, duration_us) {
+            log::warn!("dropping latency sample: {err}");
+        }
+    }
+
+    /// See [`try_record_latency`](crate::try_record_latency).
+    pub fn try_record_latency(
+        &self,
+        operation: &str,
+        duration_us: f64,
+    ) -> Result<(), TelemetryError> {
+        validate_labels(&[("operation", operation)])?;
+        self.observe_latency(operation, duration_us / 1e6);
+        Ok(())
+    }
+
+    /// See [`record_latency_with_exemplar`](crate::record_latency_with_exemplar).
+    pub fn record_latency_with_exemplar(&self, operation: &str, duration_us: f64, trace_id: &str) {
+        match self.try_record_latency(operation, duration_us) {
+            Ok(()) => self.attach_exemplar(operation, duration_us / 1e6, trace_id),
+            Err(err) => log::warn!("dropping latency sample: {err}"),
+        }
+    }
+
+    /// Remember `trace_id` as the exemplar of the `latency_seconds` bucket
+    /// holding `seconds`, an observation already recorded for `operation`.
+    pub(crate) fn attach_exemplar(&self, operation: &str, seconds: f64, trace_id: &str) {
+        if !openmetrics::is_valid_trace_id(trace_id) {
+            log::warn!("dropping exemplar with invalid trace ID {trace_id:?}");
+            return;
+        }
+        let operation = self.operations.label_of(operation);
+        let bucket = {
+            let config = self.latency_config.lock().unwrap();
+            config
+                .buckets
+                .iter()
+                .position(|bound| seconds <= *bound)
+                .unwrap_or(config.buckets.len())
+        };
+        self.exemplars.lock().unwrap().insert(
+            (operation.to_string(), bucket),
+            Exemplar::new(trace_id, seconds),
+        );
+    }
+
+    /// See [`record_duration`](crate::record_duration).
+    pub fn record_duration(&self, operation: &str, duration: Duration) {
+        if let Err(err) = self.try_record_duration(operation, duration) {
+            log::warn!("dropping latency sample: {err}");
+        }
+    }
+
+    /// See [`try_record_duration`](crate::try_record_duration).
+    pub fn try_record_duration(
+        &self,
+        operation: &str,
+        duration: Duration,
+    ) -> Result<(), TelemetryError> {
+        validate_labels(&[("operation", operation)])?;
+        self.observe_latency(operation, duration.as_secs_f64());
+        Ok(())
+    }
+
+    /// Observe `seconds` for a validated `operation`, unless sampled out,
+    /// under the overflow label if `operation` is over the limit, and count
+    /// it in `latency_overflow_total` if it is above the top bucket.
+    fn observe_latency(&self, operation: &str, seconds: f64) {
+        if self.sampling.load(Ordering::Relaxed) {
+            if let Some(sampler) = self.samplers.read().unwrap().get(operation) {
+                if !sampler.admit() {
+                    self.skipped.with_label_values(&[operation]).inc();
+                    return;
+                }
+            }
+        }
+        let operation = self.operations.admit(operation);
+        self.latency()
+            .with_label_values(&[operation])
+            .observe(seconds);
+        if self
+            .latency_top_bound
+            .get()
+            .is_some_and(|top| seconds > *top)
+        {
+            self.latency_overflow.with_label_values(&[operation]).inc();
+        }
+    }
+
+    /// See [`set_operation_limit`](crate::set_operation_limit).
+    pub fn set_operation_limit(&self, limit: usize) {
+        self.operations.set_limit(limit);
+    }
+
+    /// See [`remove_operation`](crate::remove_operation).
+    pub fn remove_operation(&self, operation: &str) -> bool {
+        let tracked = self.operations.remove(operation);
+        self.remove_operation_series(operation);
+        tracked
+    }
+
+    /// See [`remove_latency_series`](crate::remove_latency_series).
+    pub fn remove_latency_series(&self, operation: &str) -> bool {
+        self.operations.remove(operation);
+        self.remove_operation_series(operation)
+    }
+
+    /// See [`prune_idle_operations`](crate::prune_idle_operations).
+    pub fn prune_idle_operations(&self, max_idle: Duration) -> Vec<String> {
+        let pruned = self.operations.prune_idle(max_idle);
+        for operation in &pruned {
+            self.remove_operation_series(operation);
+        }
+        pruned
+    }
+
+    /// See [`configure_health`](crate::configure_health).
+    pub fn configure_health(&self, thresholds: HealthThresholds) -> Result<(), TelemetryError> {
+        thresholds.validate()?;
+        *self.health.write().unwrap() = thresholds;
+        Ok(())
+    }
+
+    /// See [`health_report`](crate::health_report).
+    pub fn health_report(&self) -> HealthReport {
+        self.health_report_at(Instant::now())
+    }
+
+    /// [`health_report`](Self::health_report) as of `now`.
+    pub(crate) fn health_report_at(&self, now: Instant) -> HealthReport {
+        let last_scrape = self
+            .last_scrape
+            .lock()
+            .unwrap()
+            .map(|(at, time)| (now.saturating_duration_since(at), time));
+        HealthReport::new(
+            &self.health.read().unwrap(),
+            self.registry.gather().len(),
+            self.operations.idle_at(now),
+            last_scrape,
+        )
+    }
+
+    /// Note that the registry was scraped, for the health report.
+    fn scraped(&self) {
+        *self.last_scrape.lock().unwrap() = Some((Instant::now(), SystemTime::now()));
+    }
+
+    /// Delete `operation`'s `latency_seconds` and `latency_overflow_total`
+    /// series, its exemplars and any samples still buffered for it,
+    /// returning whether the latency series existed.
+    fn remove_operation_series(&self, operation: &str) -> bool {
+        if let Some(recorder) = self.local_recorder.get() {
+            recorder.forget(operation);
+        }
+        let _ = self.latency_overflow.remove_label_values(&[operation]);
+        self.exemplars
+            .lock()
+            .unwrap()
+            .retain(|(label, _), _| label != operation);
+        // An error only means the series does not exist.
+        self.latency
+            .get()
+            .is_some_and(|latency| latency.remove_label_values(&[operation]).is_ok())
+    }
+
+    /// See [`record_order`](crate::record_order).
+    pub fn record_order(&self) {
+        self.orders_rate_registered.call_once(|| {
+            // The gauge keeps counting unregistered, so a name clash only
+            // hides it from scrapes.
+            if let Err(err) = self.registry.register(Box::new(self.orders_rate.clone())) {
+                log::warn!("not exporting orders_per_second: {err}");
+            }
+        });
+        self.orders_rate.record();
+    }
+
+    /// See [`orders_per_second`](crate::orders_per_second).
+    pub fn orders_per_second(&self) -> f64 {
+        self.orders_rate.rate()
+    }
+
+    /// See [`set_orders_rate_window`](crate::set_orders_rate_window).
+    pub fn set_orders_rate_window(&self, window: Duration) -> Result<(), TelemetryError> {
+        if window.is_zero() {
+            return Err(TelemetryError::InvalidValue {
+                name: "orders_per_second".to_string(),
+                value: 0.0,
+            });
+        }
+        self.orders_rate.set_window(window);
+        Ok(())
+    }
+
+    /// See [`thread_local_recorder`](crate::thread_local_recorder).
+    pub fn thread_local_recorder(&self) -> ThreadLocalRecorder {
+        self.local_recorder
+            .get_or_init(|| {
+                ThreadLocalRecorder::new(self.latency().clone(), Arc::clone(&self.operations))
+            })
+            .clone()
+    }
+
+    /// See [`flush_thread_local`](crate::flush_thread_local).
+    pub fn flush_thread_local(&self) {
+        if let Some(recorder) = self.local_recorder.get() {
+            recorder.flush();
+        }
+    }
+
+    /// See [`set_sampling`](crate::set_sampling).
+    pub fn set_sampling(&self, operation: &str, rate: f64) -> Result<(), TelemetryError> {
+        validate_labels(&[("operation", operation)])?;
+        if !(0.0..=1.0).contains(&rate) {
+            return Err(TelemetryError::InvalidValue {
+                name: operation.to_string(),
+                value: rate,
+            });
+        }
+        let mut samplers = self.samplers.write().unwrap();
+        if rate == 1.0 {
+            samplers.remove(operation);
+        } else {
+            let every = if rate == 0.0 {
+                0
+            } else {
+                (1.0 / rate).round() as u64
+            };
+            samplers.insert(
+                operation.to_string(),
+                Sampler {
+                    every,
+                    calls: AtomicU64::new(0),
+                },
+            );
+        }
+        self.sampling.store(!samplers.is_empty(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// See [`sampled_count`](crate::sampled_count).
+    pub fn sampled_count(&self, operation: &str) -> u64 {
+        self.skipped
+            .get_metric_with_label_values(&[operation])
+            .map(|counter| counter.get() as u64)
+            .unwrap_or(0)
+    }
+
+    /// The `labeled_latency_seconds` histogram, created with the configured
+    /// latency buckets on first use.
+    pub fn labeled_latency(&self) -> &HistogramVec {
+        self.labeled_latency.get_or_init(|| {
+            let mut config = self.latency_config.lock().unwrap();
+            config.in_use = true;
+            let latency = HistogramVec::new(
+                HistogramOpts::from(self.opts(
+                    "labeled_latency_seconds",
+                    "Operation latency in seconds by venue and symbol",
+                ))
+                .buckets(config.buckets.clone()),
+                &LATENCY_LABELS,
+            )
+            .expect("labeled_latency_seconds metric definition is valid");
+            self.registry
+                .register(Box::new(latency.clone()))
+                .expect("labeled_latency_seconds registers once");
+            latency
+        })
+    }
+
+    /// See [`set_label_value_limit`](crate::set_label_value_limit).
+    pub fn set_label_value_limit(&self, limit: usize) {
+        self.label_value_limit.store(limit, Ordering::Relaxed);
+    }
+
+    /// See [`record_latency_labeled`](crate::record_latency_labeled).
+    pub fn record_latency_labeled(
+        &self,
+        operation: &str,
+        extra_labels: &[(&str, &str)],
+        duration_us: f64,
+    ) -> Result<(), TelemetryError> {
+        let mut values = [operation, "", ""];
+        for (key, value) in extra_labels {
+            let Some(slot) = LATENCY_LABELS[1..].iter().position(|label| label == key) else {
+                return Err(TelemetryError::InvalidLabel(format!("{key}={value}")));
+            };
+            values[slot + 1] = value;
+        }
+        for (key, value) in LATENCY_LABELS.iter().zip(values) {
+            if !value.is_empty() || *key == "operation" {
+                validate_labels(&[(key, value)])?;
+            }
+        }
+
+        let limit = self.label_value_limit.load(Ordering::Relaxed);
+        let mut seen = self.label_values_seen.lock().unwrap();
+        for (key, value) in LATENCY_LABELS.iter().zip(values.iter_mut()) {
+            if value.is_empty() || *value == OTHER_LABEL_VALUE {
+                continue;
+            }
+            let seen = seen.entry(key).or_default();
+            if !seen.contains(*value) {
+                if seen.len() >= limit {
+                    *value = OTHER_LABEL_VALUE;
+                } else {
+                    seen.insert(value.to_string());
+                }
+            }
+        }
+        drop(seen);
+
+        self.labeled_latency()
+            .with_label_values(&values)
+            .observe(duration_us / 1e6);
+        Ok(())
+    }
+
+    /// See [`record_latency_ns`](crate::record_latency_ns).
+    pub fn record_latency_ns(&self, operation: &str, duration_ns: u64) {
+        self.record_duration(operation, Duration::from_nanos(duration_ns));
+    }
+
+    /// See [`record_error`](crate::record_error).
+    pub fn record_error(&self, operation: &str, kind: &str) {
+        if let Err(err) = self.try_record_error(operation, kind) {
+            log::warn!("dropping error sample: {err}");
+        }
+    }
+
+    /// See [`try_record_error`](crate::try_record_error).
+    pub fn try_record_error(&self, operation: &str, kind: &str) -> Result<(), TelemetryError> {
+        validate_labels(&[("operation", operation), ("kind", kind)])?;
+        self.errors.with_label_values(&[operation, kind]).inc();
+        Ok(())
+    }
+
+    /// See [`record_result`](crate::record_result).
+    pub fn record_result(&self, operation: &str, duration_us: f64, ok: bool) {
+        self.record_latency(operation, duration_us);
+        if !ok {
+            self.record_error(operation, RESULT_ERROR_KIND);
+        }
+    }
+
+    /// See [`latency_percentile`](crate::latency_percentile).
+    pub fn latency_percentile(&self, operation: &str, quantile: f64) -> Option<f64> {
+        // Don't create the histogram (and freeze its buckets) just to query it.
+        let latency = self.latency.get()?;
+        if quantile.is_nan() {
+            return None;
+        }
+        let families = latency.collect();
+        let metric = families.first()?.get_metric().iter().find(|m| {
+            m.get_label()
+                .iter()
+                .any(|label| label.get_name() == "operation" && label.get_value() == operation)
+        })?;
+        let histogram = metric.get_histogram();
+        let count = histogram.get_sample_count();
+        if count == 0 {
+            return None;
+        }
+
+        let rank = quantile.clamp(0.0, 1.0) * count as f64;
+        Some(introspect::estimate(histogram, rank) * 1e6)
+    }
+
+    /// Reset every counter created through this handle to zero.
+    pub(crate) fn reset_counters(&self) {
+        for counter in self.counters.read().unwrap().values() {
+            counter.reset();
+        }
+        for family in self.labeled_counters.read().unwrap().values() {
+            family.counter.reset();
+        }
+        for counter in self.resettable_counters.read().unwrap().values() {
+            counter.take();
+        }
+    }
+
+    /// See [`reset_metrics`](crate::reset_metrics).
+    pub fn reset(&self) {
+        self.reset_counters();
+        self.dropped.reset();
+        self.unknown.reset();
+        self.errors.reset();
+        self.skipped.reset();
+        self.latency_overflow.reset();
+        self.label_overflow.reset();
+        for sampler in self.samplers.read().unwrap().values() {
+            sampler.calls.store(0, Ordering::Relaxed);
+        }
+        for family in self.gauges.read().unwrap().values() {
+            family.gauge.reset();
+        }
+        for named in self.histograms.read().unwrap().values() {
+            named.histogram.reset();
+            named.histogram.with_label_values(&[]);
+        }
+        for summary in self.summaries.read().unwrap().values() {
+            summary.clear();
+        }
+        if let Some(recorder) = self.local_recorder.get() {
+            recorder.discard();
+        }
+        if let Some(latency) = self.latency.get() {
+            latency.reset();
+        }
+        self.operations.clear();
+        if let Some(latency) = self.labeled_latency.get() {
+            latency.reset();
+        }
+        self.label_values_seen.lock().unwrap().clear();
+        self.exemplars.lock().unwrap().clear();
+        self.orders_rate.clear();
+    }
+
+    /// See [`snapshot_to_file`](crate::snapshot_to_file).
+    pub fn snapshot_to_file(&self, path: &Path) -> Result<(), TelemetryError> {
+        snapshot::write(&self.snapshot(), path)
+    }
+
+    /// The non-zero counters and all gauges created by name, saved under
+    /// the names they were created with rather than the namespaced ones.
+    fn snapshot(&self) -> Snapshot {
+        let named = |name: &String, collector: &dyn Collector| {
+            collector
+                .collect()
+                .into_iter()
+                .map(|family| (name.clone(), family))
+                .collect::<Vec<_>>()
+        };
+        let samples = |families: Vec<(String, MetricFamily)>, value: fn(&Metric) -> f64| {
+            let mut samples = Vec::new();
+            for (name, family) in families {
+                for metric in family.get_metric() {
+                    samples.push(Sample {
+                        name: name.clone(),
+                        labels: metric
+                            .get_label()
+                            .iter()
+                            .map(|label| {
+                                (label.get_name().to_string(), label.get_value().to_string())
+                            })
+                            .collect(),
+                        value: value(metric),
+                    });
+                }
+            }
+            samples
+        };
+
+        let mut counters = samples(
+            self.counters
+                .read()
+                .unwrap()
+                .iter()
+                .flat_map(|(name, counter)| named(name, counter))
+                .chain(
+                    self.labeled_counters
+                        .read()
+                        .unwrap()
+                        .iter()
+                        .flat_map(|(name, family)| named(name, &family.counter)),
+                )
+                .collect(),
+            |metric| metric.get_counter().get_value(),
+        );
+        counters.retain(|sample| sample.value > 0.0);
+        let gauges = samples(
+            self.gauges
+                .read()
+                .unwrap()
+                .iter()
+                .flat_map(|(name, family)| named(name, &family.gauge))
+                .collect(),
+            |metric| metric.get_gauge().get_value(),
+        );
+        Snapshot { counters, gauges }
+    }
+
+    /// See [`restore_from_file`](crate::restore_from_file).
+    pub fn restore_from_file(&self, path: &Path) -> bool {
+        let Some(snapshot) = snapshot::read(path) else {
+            return false;
+        };
+        for sample in &snapshot.counters {
+            let restored = if sample.labels.is_empty()
+                && !self
+                    .labeled_counters
+                    .read()
+                    .unwrap()
+                    .contains_key(&sample.name)
+            {
+                self.emit_metric_checked(&sample.name, sample.value)
+            } else {
+                self.emit_counter(&sample.name, &sample.labels(), sample.value)
+            };
+            if let Err(err) = restored {
+                log::warn!("not restoring counter {}: {err}", sample.name);
+            }
+        }
+        for sample in &snapshot.gauges {
+            if let Err(err) = self.set_gauge(&sample.name, &sample.labels(), sample.value) {
+                log::warn!("not restoring gauge {}: {err}", sample.name);
+            }
+        }
+        true
+    }
+
+    /// See [`get_metrics`](crate::get_metrics).
+    pub fn get_metrics(&self) -> String {
+        self.try_get_metrics().unwrap_or_else(|err| {
+            log::error!("{err}");
+            String::new()
+        })
+    }
+
+    /// See [`try_get_metrics`](crate::try_get_metrics).
+    pub fn try_get_metrics(&self) -> Result<String, TelemetryError> {
+        self.renders.fetch_add(1, Ordering::Relaxed);
+        self.scraped();
+        encode_text(&self.registry.gather())
+    }
+
+    /// See [`get_metrics_filtered`](crate::get_metrics_filtered).
+    pub fn get_metrics_filtered(&self, filter: &MetricFilter) -> String {
+        self.scraped();
+        encode_text(&filter.apply(self.registry.gather())).unwrap_or_else(|err| {
+            log::error!("{err}");
+            String::new()
+        })
+    }
+
+    /// [`get_metrics_protobuf`](Self::get_metrics_protobuf) of the metrics
+    /// `filter` keeps.
+    pub(crate) fn get_metrics_protobuf_filtered(&self, filter: &MetricFilter) -> Vec<u8> {
+        self.scraped();
+        encode_protobuf(&filter.apply(self.registry.gather())).unwrap_or_else(|err| {
+            log::error!("{err}");
+            Vec::new()
+        })
+    }
+
+    /// See [`get_metrics_cached`](crate::get_metrics_cached).
+    pub fn get_metrics_cached(&self, max_age: Duration) -> String {
+        // Held while rendering, so concurrent callers wait for one
+        // rendering instead of each doing their own.
+        let mut cache = self.metrics_cache.lock().unwrap();
+        if let Some(cached) = cache.as_ref() {
+            if cached.rendered_at.elapsed() <= max_age {
+                self.scraped();
+                return cached.text.clone();
+            }
+        }
+        let rendered_at = Instant::now();
+        let text = self.get_metrics();
+        *cache = Some(CachedRender {
+            rendered_at,
+            text: text.clone(),
+        });
+        text
+    }
+
+    /// How many times the text format has been rendered.
+    #[cfg(test)]
+    pub(crate) fn render_count(&self) -> u64 {
+        self.renders.load(Ordering::Relaxed)
+    }
+
+    /// See [`get_metrics_protobuf`](crate::get_metrics_protobuf).
+    pub fn get_metrics_protobuf(&self) -> Vec<u8> {
+        self.try_get_metrics_protobuf().unwrap_or_else(|err| {
+            log::error!("{err}");
+            Vec::new()
+        })
+    }
+
+    /// See [`try_get_metrics_protobuf`](crate::try_get_metrics_protobuf).
+    pub fn try_get_metrics_protobuf(&self) -> Result<Vec<u8>, TelemetryError> {
+        self.scraped();
+        encode_protobuf(&self.registry.gather())
+    }
+
+    /// See [`get_metrics_openmetrics`](crate::get_metrics_openmetrics).
+    pub fn get_metrics_openmetrics(&self) -> String {
+        let exemplars = self.exemplars.lock().unwrap();
+        openmetrics::render(
+            &self.registry.gather(),
+            &self.exposed_name("latency_seconds"),
+            &exemplars,
+        )
+    }
+
+    /// See [`get_metrics_json`](crate::get_metrics_json).
+    pub fn get_metrics_json(&self) -> String {
+        json::render(&self.registry.gather())
+    }
+
+    /// See [`get_metrics_influx`](crate::get_metrics_influx).
+    pub fn get_metrics_influx(&self, timestamp_ns: Option<u64>) -> String {
+        influx::render(&self.registry.gather(), timestamp_ns)
+    }
+
+    /// See [`get_metrics_csv`](crate::get_metrics_csv).
+    pub fn get_metrics_csv(&self) -> String {
+        csv::render(&self.registry.gather(), csv::now_ms(), true)
+    }
+
+    /// See [`dump_metrics_csv`](crate::dump_metrics_csv).
+    pub fn dump_metrics_csv(&self, path: &Path, append: bool) -> Result<(), TelemetryError> {
+        csv::dump(&self.registry.gather(), path, append)
+    }
+
+    /// See [`list_metrics`](crate::list_metrics).
+    pub fn list_metrics(&self) -> Vec<MetricInfo> {
+        introspect::list(&self.gather_own())
+    }
+
+    /// See [`get_counter_value`](crate::get_counter_value).
+    pub fn get_counter_value(&self, name: &str) -> Option<f64> {
+        introspect::counter_value(&self.gather_own(), name)
+    }
+
+    /// See [`get_histogram_count`](crate::get_histogram_count).
+    pub fn get_histogram_count(&self, name: &str, labels: &[(&str, &str)]) -> Option<u64> {
+        introspect::histogram(&self.gather_own(), name, labels).map(|h| h.get_sample_count())
+    }
+
+    /// See [`histogram_quantile`](crate::histogram_quantile).
+    pub fn histogram_quantile(&self, name: &str, labels: &[(&str, &str)], q: f64) -> Option<f64> {
+        introspect::quantile(introspect::histogram(&self.gather_own(), name, labels)?, q)
+    }
+
+    /// See [`latency_quantile`](crate::latency_quantile).
+    pub fn latency_quantile(&self, operation: &str, q: f64) -> Option<f64> {
+        self.histogram_quantile(
+            &self.exposed_name("latency_seconds"),
+            &[("operation", operation)],
+            q,
+        )
+    }
+
+    /// See [`get_histogram_sum`](crate::get_histogram_sum).
+    pub fn get_histogram_sum(&self, name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+        introspect::histogram(&self.gather_own(), name, labels).map(|h| h.get_sample_sum())
+    }
+
+    /// `Opts` for a metric called `name` in the handle's namespace.
+    fn opts(&self, name: &str, help: &str) -> Opts {
+        namespaced_opts(self.namespace.as_deref(), name, help)
+    }
+
+    /// `name` as it appears in the exposition, with the namespace prefix.
+    fn exposed_name(&self, name: &str) -> String {
+        self.opts(name, "").fq_name()
+    }
+
+    /// Gather the registry without the global labels, so introspection
+    /// callers name series by the labels they recorded them with.
+    pub(crate) fn gather_own(&self) -> Vec<MetricFamily> {
+        let mut families = self.registry.gather();
+        if self.global_labels.is_empty() {
+            return families;
+        }
+        for metric in families
+            .iter_mut()
+            .flat_map(|family| family.mut_metric().iter_mut())
+        {
+            let mut labels = metric.take_label().into_vec();
+            labels.retain(|pair| !self.global_labels.contains_key(pair.get_name()));
+            metric.set_label(labels.into());
+        }
+        families
+    }
+}
+
+/// `Opts` for a metric called `name`, in `namespace` if there is one.
+pub(crate) fn namespaced_opts(namespace: Option<&str>, name: &str, help: &str) -> Opts {
+    Opts::new(name, help).namespace(namespace.unwrap_or_default())
+}
+
+/// Encode `families` in the Prometheus text format.
+fn encode_text(families: &[MetricFamily]) -> Result<String, TelemetryError> {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(families, &mut buffer)
+        .map_err(|err| TelemetryError::EncodeFailed(err.to_string()))?;
+    String::from_utf8(buffer).map_err(|err| TelemetryError::EncodeFailed(err.to_string()))
+}
+
+/// Encode `families` as length-delimited `MetricFamily` messages.
+fn encode_protobuf(families: &[MetricFamily]) -> Result<Vec<u8>, TelemetryError> {
+    let mut buffer = Vec::new();
+    ProtobufEncoder::new()
+        .encode(families, &mut buffer)
+        .map_err(|err| TelemetryError::EncodeFailed(err.to_string()))?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::health::HealthStatus;
+    use crate::OVERFLOW_OPERATION;
+
+    #[test]
+    fn test_isolated_handles_do_not_share_metrics() {
+        let first = TelemetryHandle::new_isolated();
+        let second = TelemetryHandle::new_isolated();
+
+        first.emit_metric("orders_total", 2.0);
+        first.emit_metric("handle_fills_total", 1.0);
+        second.record_latency("order_send", 250.0);
+
+        assert_eq!(first.orders_total().get(), 2);
+        assert_eq!(second.orders_total().get(), 0);
+        assert!(first.get_metrics().contains("handle_fills_total 1"));
+        assert!(!second.get_metrics().contains("handle_fills_total"));
+        assert!(second
+            .get_metrics()
+            .contains("latency_seconds_count{operation=\"order_send\"} 1"));
+        assert!(!first.get_metrics().contains("latency_seconds"));
+    }
+
+    #[test]
+    fn test_isolated_handle_does_not_touch_global_registry() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.emit_metric("handle_private_total", 1.0);
+        assert!(!crate::get_metrics().contains("handle_private_total"));
+    }
+
+    #[test]
+    fn test_strict_mode_rejects_unregistered_names() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.register_counter("fills_total", "Fills").unwrap();
+        handle.set_strict_mode(true);
+
+        assert_eq!(
+            handle.emit_metric_checked("order_total", 1.0),
+            Err(TelemetryError::UnknownMetric("order_total".to_string()))
+        );
+        handle.emit_metric_checked("orders_total", 1.0).unwrap();
+        handle.emit_metric_checked("fills_total", 2.0).unwrap();
+
+        let output = handle.get_metrics();
+        assert!(!output.contains("order_total "));
+        assert!(output.contains("fills_total 2"));
+        assert_eq!(handle.unknown_metrics_total().get(), 0.0);
+    }
+
+    #[test]
+    fn test_lenient_mode_counts_unregistered_names() {
+        let handle = TelemetryHandle::new_isolated();
+        assert!(!handle.strict_mode());
+
+        handle.emit_metric("order_total", 1.0);
+        handle.emit_metric("order_total", 1.0);
+        handle.emit_metric("orders_total", 1.0);
+
+        assert_eq!(handle.unknown_metrics_total().get(), 1.0);
+        assert!(handle.get_metrics().contains("order_total 2"));
+    }
+
+    #[test]
+    fn test_dropped_name_is_not_counted_as_unknown() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.set_counter_limit(1);
+
+        handle.emit_metric("fills_total", 1.0);
+
+        assert_eq!(handle.dropped_metrics_total().get(), 1.0);
+        assert_eq!(handle.unknown_metrics_total().get(), 0.0);
+    }
+
+    #[test]
+    fn test_reset_zeroes_counters_and_clears_observations() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.emit_metric("orders_total", 3.0);
+        handle.emit_metric("handle_reset_total", 5.0);
+        handle
+            .emit_counter("handle_reset_fills_total", &[("venue", "nyse")], 1.0)
+            .unwrap();
+        handle.set_gauge("handle_reset_depth", &[], 4.0).unwrap();
+        handle
+            .register_histogram("handle_reset_seconds", &[1.0])
+            .unwrap();
+        handle
+            .observe_histogram("handle_reset_seconds", 0.5)
+            .unwrap();
+        handle
+            .register_summary("handle_reset_fill", &[(0.5, 0.05)])
+            .unwrap();
+        handle.observe_summary("handle_reset_fill", 2.0).unwrap();
+        handle.record_latency("order_send", 100.0);
+
+        handle.reset();
+
+        let output = handle.get_metrics();
+        assert!(output.contains("orders_total 0"));
+        assert!(output.contains("handle_reset_total 0"));
+        assert!(!output.contains("handle_reset_fills_total{"));
+        assert!(!output.contains("handle_reset_depth "));
+        assert!(output.contains("handle_reset_seconds_count 0"));
+        assert!(output.contains("handle_reset_fill_count 0"));
+        assert!(!output.contains("latency_seconds_count"));
+        assert_eq!(handle.latency_percentile("order_send", 0.5), None);
+
+        // Everything keeps working after a reset.
+        handle.emit_metric("handle_reset_total", 1.0);
+        assert!(handle.get_metrics().contains("handle_reset_total 1"));
+    }
+
+    #[test]
+    fn test_counters_count_past_f64_precision() {
+        // 2^53 + 1 is the first integer an f64 cannot hold, so an f64
+        // counter at 2^53 ignores every increment of 1.
+        const BOUNDARY: f64 = 9_007_199_254_740_992.0;
+        let handle = TelemetryHandle::new_isolated();
+        let ticks = handle.register_counter("ticks_total", "Ticks").unwrap();
+        handle.emit_metric_checked("ticks_total", BOUNDARY).unwrap();
+        handle.emit_metric_checked("ticks_total", 1.0).unwrap();
+        assert_eq!(ticks.get(), (1 << 53) + 1);
+        handle.emit_metric_checked("ticks_total", 1.0).unwrap();
+        assert_eq!(
+            handle.get_counter_value("ticks_total"),
+            Some(BOUNDARY + 2.0)
+        );
+        assert!(handle
+            .get_metrics()
+            .contains("\nticks_total 9007199254740994\n"));
+    }
+
+    #[test]
+    fn test_counters_reject_fractional_increments() {
+        let handle = TelemetryHandle::new_isolated();
+        for value in [0.5, 1.25] {
+            assert_eq!(
+                handle.emit_metric_checked("fills_total", value),
+                Err(TelemetryError::InvalidValue {
+                    name: "fills_total".to_string(),
+                    value,
+                })
+            );
+            assert!(matches!(
+                handle.emit_counter("venue_fills_total", &[("venue", "nyse")], value),
+                Err(TelemetryError::InvalidValue { .. })
+            ));
+        }
+        assert_eq!(handle.get_counter_value("fills_total"), None);
+
+        // Resettable counters are f64 and still take fractions.
+        handle
+            .register_resettable_counter("notional_total", "Notional")
+            .unwrap();
+        handle.emit_metric_checked("notional_total", 0.5).unwrap();
+        assert_eq!(handle.take_and_reset_counter("notional_total"), Some(0.5));
+    }
+
+    #[test]
+    fn test_orders_per_second_reflects_recent_orders() {
+        let handle = TelemetryHandle::new_isolated();
+        assert!(!handle.get_metrics().contains("orders_per_second"));
+        for _ in 0..5 {
+            handle.record_order();
+        }
+        let rate = handle.orders_per_second();
+        assert!(rate > 0.0 && rate <= 5.0, "{rate}");
+        assert!(handle
+            .get_metrics()
+            .contains("# TYPE orders_per_second gauge"));
+
+        assert!(handle.set_orders_rate_window(Duration::ZERO).is_err());
+        handle.reset();
+        assert_eq!(handle.orders_per_second(), 0.0);
+    }
+
+    #[test]
+    fn test_orders_per_second_survives_name_clash() {
+        let handle = TelemetryHandle::new_isolated();
+        handle
+            .registry
+            .register(Box::new(
+                prometheus::Gauge::new("orders_per_second", "Taken").unwrap(),
+            ))
+            .unwrap();
+        handle.record_order();
+        handle.record_order();
+        assert!(handle.orders_per_second() > 0.0);
+        assert!(handle
+            .get_metrics()
+            .contains("# HELP orders_per_second Taken"));
+    }
+
+    #[test]
+    fn test_get_metrics_cached_reuses_fresh_rendering() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.emit_metric("orders_total", 1.0);
+        let first = handle.get_metrics_cached(Duration::from_secs(60));
+        let renders = handle.render_count();
+
+        handle.emit_metric("orders_total", 1.0);
+        let second = handle.get_metrics_cached(Duration::from_secs(60));
+        assert_eq!(second, first);
+        assert_eq!(handle.render_count(), renders);
+
+        std::thread::sleep(Duration::from_millis(5));
+        let stale = handle.get_metrics_cached(Duration::from_millis(1));
+        assert_eq!(handle.render_count(), renders + 1);
+        assert!(stale.contains("\norders_total 2\n"));
+    }
+
+    #[test]
+    fn test_operation_limit_overflows_and_prunes() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.set_operation_limit(2);
+        for operation in ["order_send", "order_cancel", "uuid-1", "uuid-2"] {
+            handle.record_latency(operation, 100.0);
+        }
+        let count =
+            |operation| handle.get_histogram_count("latency_seconds", &[("operation", operation)]);
+        assert_eq!(count("order_send"), Some(1));
+        assert_eq!(count("uuid-1"), None);
+        assert_eq!(count(OVERFLOW_OPERATION), Some(2));
+        assert_eq!(
+            handle.get_counter_value("telemetry_label_overflow_total"),
+            Some(2.0)
+        );
+
+        assert!(handle.remove_operation("order_cancel"));
+        assert_eq!(count("order_cancel"), None);
+        handle.record_latency("uuid-2", 100.0);
+        assert_eq!(count("uuid-2"), Some(1));
+
+        std::thread::sleep(Duration::from_millis(5));
+        handle.record_latency("order_send", 100.0);
+        let mut pruned = handle.prune_idle_operations(Duration::from_millis(2));
+        pruned.sort();
+        assert_eq!(pruned, [OVERFLOW_OPERATION, "uuid-2"]);
+        assert_eq!(count("order_send"), Some(2));
+        assert_eq!(count(OVERFLOW_OPERATION), None);
+    }
+
+    #[test]
+    fn test_removed_series_vanish_and_can_be_recreated() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.record_latency("strategy_a", 100.0);
+        handle.record_latency("strategy_b", 100.0);
+        handle
+            .set_gauge("handle_depth", &[("venue", "nyse")], 3.0)
+            .unwrap();
+        handle.emit_metric("handle_fills_total", 2.0);
+
+        assert!(handle.remove_latency_series("strategy_a"));
+        assert!(!handle.remove_latency_series("strategy_a"));
+        assert!(handle.unregister_metric("handle_depth"));
+        assert!(handle.unregister_metric("handle_fills_total"));
+        assert!(!handle.unregister_metric("handle_fills_total"));
+        let output = handle.get_metrics();
+        assert!(!output.contains("strategy_a"));
+        assert!(output.contains("latency_seconds_count{operation=\"strategy_b\"} 1"));
+        assert!(!output.contains("handle_depth"));
+        assert!(!output.contains("handle_fills_total"));
+
+        handle.record_latency("strategy_a", 100.0);
+        handle
+            .set_gauge("handle_depth", &[("venue", "arca")], 1.0)
+            .unwrap();
+        handle.emit_metric("handle_fills_total", 1.0);
+        let output = handle.get_metrics();
+        assert!(output.contains("latency_seconds_count{operation=\"strategy_a\"} 1"));
+        assert!(output.contains("handle_depth{venue=\"arca\"} 1"));
+        assert!(output.contains("handle_fills_total 1"));
+
+        // Built-in metrics stay.
+        handle.emit_metric("orders_total", 1.0);
+        for name in ["orders_total", "latency_seconds", "errors_total"] {
+            assert!(!handle.unregister_metric(name));
+        }
+        let output = handle.get_metrics();
+        assert!(output.contains("orders_total 1"));
+        assert!(output.contains("latency_seconds_count{operation=\"strategy_a\"} 1"));
+    }
+
+    #[test]
+    fn test_latency_above_top_bucket_counts_as_overflow() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.record_latency("order_send", 10_000_000.0);
+        handle.record_latency("order_send", 10_000_001.0);
+        handle.record_latency("order_send", 250.0);
+        assert!(handle
+            .get_metrics()
+            .contains("latency_overflow_total{operation=\"order_send\"} 1\n"));
+
+        let handle = TelemetryHandle::new_isolated();
+        handle.configure_latency_buckets(&[0.01, 0.1]).unwrap();
+        handle.record_latency("order_send", 10_000_000.0);
+        handle.record_latency("order_cancel", 100_000.0);
+        let output = handle.get_metrics();
+        assert!(output.contains("latency_overflow_total{operation=\"order_send\"} 1\n"));
+        assert!(!output.contains("latency_overflow_total{operation=\"order_cancel\"}"));
+
+        assert!(handle.remove_latency_series("order_send"));
+        assert!(!handle.get_metrics().contains("latency_overflow_total{"));
+    }
+
+    #[test]
+    fn test_protobuf_export_decodes_to_current_values() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.emit_metric("orders_total", 3.0);
+        handle.record_latency("order_send", 250.0);
+
+        let families = crate::tests::decode_protobuf(&handle.get_metrics_protobuf());
+        let family = |name: &str| families.iter().find(|family| family.get_name() == name);
+
+        let orders = family("orders_total").unwrap();
+        assert_eq!(
+            orders.get_field_type(),
+            prometheus::proto::MetricType::COUNTER
+        );
+        assert_eq!(orders.get_metric()[0].get_counter().get_value(), 3.0);
+        let latency = family("latency_seconds").unwrap();
+        assert_eq!(
+            latency.get_metric()[0].get_histogram().get_sample_count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_health_degrades_as_operations_go_idle() {
+        let handle = TelemetryHandle::new_isolated();
+        handle
+            .configure_health(HealthThresholds {
+                degraded_after: Duration::from_secs(10),
+                stale_after: Duration::from_secs(60),
+                max_scrape_age: Some(Duration::from_secs(30)),
+            })
+            .unwrap();
+        handle.record_latency("order_send", 250.0);
+        handle.record_latency("order_cancel", 250.0);
+        let start = Instant::now();
+        let report = handle.health_report_at(start);
+        assert_eq!(report.status, HealthStatus::Healthy);
+        assert_eq!(report.seconds_since_scrape, None);
+        assert_eq!(report.seconds_since_latency.len(), 2);
+
+        handle.get_metrics();
+        let report = handle.health_report_at(start + Duration::from_secs(20));
+        assert_eq!(report.status, HealthStatus::Degraded);
+        assert!(report.seconds_since_latency["order_cancel"] > 19.0);
+        assert!(report.seconds_since_scrape.unwrap() > 19.0);
+
+        let report = handle.health_report_at(start + Duration::from_secs(120));
+        assert_eq!(report.status, HealthStatus::Stale);
+    }
+}
@@ -0,0 +1,293 @@
+//! Telemetry health summary.
+//!
+//! [`health_report`] answers "is telemetry alive and are the key series
+//! moving" in one call: how many metric families the registry holds, how
+//! long ago each known operation last recorded a latency, and when the
+//! registry was last scraped, summed up as a [`HealthStatus`] against the
+//! thresholds set with [`configure_health`]. The built-in endpoint (see
+//! [`serve_metrics`](crate::serve_metrics)) serves the report as JSON at
+//! `/healthz`.
+
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use serde::Serialize;
+
+use crate::{init_metrics, TelemetryError, DEFAULT};
+
+/// Default [`HealthThresholds::degraded_after`].
+pub const DEFAULT_DEGRADED_AFTER: Duration = Duration::from_secs(60);
+
+/// Default [`HealthThresholds::stale_after`].
+pub const DEFAULT_STALE_AFTER: Duration = Duration::from_secs(300);
+
+/// Overall state of a [`HealthReport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum HealthStatus {
+    /// Every known operation recorded recently, and scrapes are on time.
+    Healthy,
+    /// Some operation has been idle past `degraded_after`, or the last
+    /// scrape is older than `max_scrape_age`.
+    Degraded,
+    /// Every known operation has been idle past `stale_after`.
+    Stale,
+}
+
+impl HealthStatus {
+    /// The variant name, as serialized.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Healthy => "Healthy",
+            Self::Degraded => "Degraded",
+            Self::Stale => "Stale",
+        }
+    }
+}
+
+/// When a [`HealthReport`] stops being [`HealthStatus::Healthy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthThresholds {
+    /// Idle time after which one operation makes the report degraded.
+    pub degraded_after: Duration,
+    /// Idle time after which, once every operation reaches it, the report
+    /// is stale. At least `degraded_after`.
+    pub stale_after: Duration,
+    /// Age of the last scrape after which the report is degraded; `None`
+    /// ignores scrapes.
+    pub max_scrape_age: Option<Duration>,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        Self {
+            degraded_after: DEFAULT_DEGRADED_AFTER,
+            stale_after: DEFAULT_STALE_AFTER,
+            max_scrape_age: None,
+        }
+    }
+}
+
+impl HealthThresholds {
+    pub(crate) fn validate(&self) -> Result<(), TelemetryError> {
+        let invalid = |name: &str, value: Duration| TelemetryError::InvalidValue {
+            name: name.to_string(),
+            value: value.as_secs_f64(),
+        };
+        if self.degraded_after.is_zero() {
+            return Err(invalid("degraded_after", self.degraded_after));
+        }
+        if self.stale_after < self.degraded_after {
+            return Err(invalid("stale_after", self.stale_after));
+        }
+        match self.max_scrape_age {
+            Some(age) if age.is_zero() => Err(invalid("max_scrape_age", age)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A point-in-time summary of the registry's health.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthReport {
+    /// Overall state, per the configured [`HealthThresholds`]
+    pub status: HealthStatus,
+    /// Number of metric families in the registry
+    pub metric_count: usize,
+    /// Seconds since each known operation last recorded a latency
+    pub seconds_since_latency: BTreeMap<String, f64>,
+    /// Unix time, in seconds, of the last text or protobuf scrape; `None`
+    /// if there was none
+    pub last_scrape_unix_secs: Option<f64>,
+    /// Seconds since that scrape
+    pub seconds_since_scrape: Option<f64>,
+}
+
+impl HealthReport {
+    /// Summarize a registry of `metric_count` families whose operations
+    /// have been idle for `idle`, last scraped `last_scrape` ago at the
+    /// given wall-clock time.
+    pub(crate) fn new(
+        thresholds: &HealthThresholds,
+        metric_count: usize,
+        idle: BTreeMap<String, Duration>,
+        last_scrape: Option<(Duration, SystemTime)>,
+    ) -> Self {
+        let status = if !idle.is_empty() && idle.values().all(|&i| i > thresholds.stale_after) {
+            HealthStatus::Stale
+        } else if idle.values().any(|&i| i > thresholds.degraded_after)
+            || thresholds
+                .max_scrape_age
+                .zip(last_scrape)
+                .is_some_and(|(max, (age, _))| age > max)
+        {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Healthy
+        };
+        Self {
+            status,
+            metric_count,
+            seconds_since_latency: idle
+                .into_iter()
+                .map(|(operation, idle)| (operation, idle.as_secs_f64()))
+                .collect(),
+            last_scrape_unix_secs: last_scrape.map(|(_, at)| {
+                at.duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs_f64()
+            }),
+            seconds_since_scrape: last_scrape.map(|(age, _)| age.as_secs_f64()),
+        }
+    }
+
+    /// The report as a JSON object, as served at `/healthz`.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("health reports always serialize")
+    }
+}
+
+/// Summarize the health of the default registry.
+///
+/// Known operations are those with a `latency_seconds` series that were
+/// not pruned (see [`prune_idle_operations`](crate::prune_idle_operations));
+/// with none, only the scrape age counts. The registry is not scraped by
+/// this call.
+pub fn health_report() -> HealthReport {
+    init_metrics();
+    DEFAULT.health_report()
+}
+
+/// Set the thresholds [`health_report`] judges the registry by.
+///
+/// # Returns
+/// * `Ok(())` - Later reports use `thresholds`
+/// * `Err(TelemetryError::InvalidValue)` - If `degraded_after` or
+///   `max_scrape_age` is zero, or `stale_after` is below `degraded_after`
+pub fn configure_health(thresholds: HealthThresholds) -> Result<(), TelemetryError> {
+    init_metrics();
+    DEFAULT.configure_health(thresholds)
+}
+
+/// Summarize telemetry health as a dict with `status` (`"Healthy"`,
+/// `"Degraded"` or `"Stale"`), `metric_count`, `seconds_since_latency`,
+/// `last_scrape_unix_secs` and `seconds_since_scrape` keys (Python binding).
+#[pyfunction]
+#[pyo3(name = "health_report")]
+pub(crate) fn py_health_report(py: Python<'_>) -> PyResult<Py<PyDict>> {
+    let report = py.allow_threads(health_report);
+    let dict = PyDict::new_bound(py);
+    dict.set_item("status", report.status.as_str())?;
+    dict.set_item("metric_count", report.metric_count)?;
+    dict.set_item("seconds_since_latency", report.seconds_since_latency)?;
+    dict.set_item("last_scrape_unix_secs", report.last_scrape_unix_secs)?;
+    dict.set_item("seconds_since_scrape", report.seconds_since_scrape)?;
+    Ok(dict.unbind())
+}
+
+/// Set the health thresholds in seconds; raises `ValueError` on invalid
+/// ones (Python binding).
+#[pyfunction]
+#[pyo3(
+    name = "configure_health",
+    signature = (degraded_after_secs, stale_after_secs, max_scrape_age_secs = None)
+)]
+pub(crate) fn py_configure_health(
+    degraded_after_secs: f64,
+    stale_after_secs: f64,
+    max_scrape_age_secs: Option<f64>,
+) -> PyResult<()> {
+    let duration = |name: &str, secs: f64| {
+        Duration::try_from_secs_f64(secs).map_err(|_| TelemetryError::InvalidValue {
+            name: name.to_string(),
+            value: secs,
+        })
+    };
+    let thresholds = HealthThresholds {
+        degraded_after: duration("degraded_after", degraded_after_secs)?,
+        stale_after: duration("stale_after", stale_after_secs)?,
+        max_scrape_age: max_scrape_age_secs
+            .map(|secs| duration("max_scrape_age", secs))
+            .transpose()?,
+    };
+    Ok(configure_health(thresholds)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn idle(operations: &[(&str, u64)]) -> BTreeMap<String, Duration> {
+        operations
+            .iter()
+            .map(|(operation, secs)| (operation.to_string(), SECOND * *secs as u32))
+            .collect()
+    }
+
+    #[test]
+    fn test_status_follows_idle_operations_and_scrapes() {
+        let thresholds = HealthThresholds {
+            degraded_after: SECOND * 10,
+            stale_after: SECOND * 60,
+            max_scrape_age: Some(SECOND * 30),
+        };
+        let status = |operations, scrape_age: Option<u64>| {
+            let scrape = scrape_age.map(|secs| (SECOND * secs as u32, SystemTime::now()));
+            HealthReport::new(&thresholds, 5, idle(operations), scrape).status
+        };
+        assert_eq!(status(&[], None), HealthStatus::Healthy);
+        assert_eq!(
+            status(&[("a", 1), ("b", 10)], Some(30)),
+            HealthStatus::Healthy
+        );
+        assert_eq!(status(&[("a", 1), ("b", 11)], None), HealthStatus::Degraded);
+        assert_eq!(status(&[("a", 1)], Some(31)), HealthStatus::Degraded);
+        assert_eq!(status(&[("a", 1), ("b", 61)], None), HealthStatus::Degraded);
+        assert_eq!(status(&[("a", 61), ("b", 61)], None), HealthStatus::Stale);
+    }
+
+    #[test]
+    fn test_report_serializes_to_json() {
+        let scraped_at = UNIX_EPOCH + SECOND * 1_700_000_000;
+        let report = HealthReport::new(
+            &HealthThresholds::default(),
+            3,
+            idle(&[("order_send", 2)]),
+            Some((SECOND * 4, scraped_at)),
+        );
+        assert_eq!(
+            report.to_json(),
+            "{\"status\":\"Healthy\",\"metric_count\":3,\
+             \"seconds_since_latency\":{\"order_send\":2.0},\
+             \"last_scrape_unix_secs\":1700000000.0,\"seconds_since_scrape\":4.0}"
+        );
+    }
+
+    #[test]
+    fn test_invalid_thresholds_are_rejected() {
+        let valid = HealthThresholds::default();
+        assert_eq!(valid.validate(), Ok(()));
+        for invalid in [
+            HealthThresholds {
+                degraded_after: Duration::ZERO,
+                ..valid
+            },
+            HealthThresholds {
+                stale_after: SECOND,
+                ..valid
+            },
+            HealthThresholds {
+                max_scrape_age: Some(Duration::ZERO),
+                ..valid
+            },
+        ] {
+            assert!(matches!(
+                invalid.validate(),
+                Err(TelemetryError::InvalidValue { .. })
+            ));
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Minimal blocking HTTP/1.1 client for pushing metrics.
+//!
+//! Only plain `http://` URLs are supported. Each request is a single
+//! exchange over a fresh [`TcpStream`], with the timeout applied to
+//! connecting, writing and reading.
+
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// A parsed `http://host:port/path` URL.
+pub(crate) struct HttpUrl {
+    /// `host:port`
+    pub(crate) host: String,
+    /// Path without a trailing slash; empty for the root
+    pub(crate) path: String,
+}
+
+impl HttpUrl {
+    /// Parse `url`, which must start with `http://`.
+    pub(crate) fn parse(url: &str) -> Result<Self, String> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("unsupported URL {url:?}: need http://"))?;
+        let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+        if host.is_empty() {
+            return Err(format!("URL {url:?} has no host"));
+        }
+        let path = path.trim_end_matches('/');
+        Ok(Self {
+            host: host.to_string(),
+            path: if path.is_empty() {
+                String::new()
+            } else {
+                format!("/{path}")
+            },
+        })
+    }
+}
+
+/// A response status and body.
+pub(crate) struct HttpResponse {
+    pub(crate) status: u16,
+    pub(crate) body: String,
+}
+
+/// Send one request and read the whole response.
+///
+/// Errors are connection-level failures (unreachable host, timeout,
+/// malformed response) described as strings; HTTP error statuses are
+/// returned as responses.
+pub(crate) fn send(
+    method: &str,
+    host: &str,
+    path: &str,
+    content_type: &str,
+    body: &[u8],
+    timeout: Duration,
+) -> Result<HttpResponse, String> {
+    let describe = |err: std::io::Error| format!("{host}: {err}");
+    let addr = host
+        .to_socket_addrs()
+        .map_err(describe)?
+        .next()
+        .ok_or_else(|| format!("{host}: no address"))?;
+    let mut stream = TcpStream::connect_timeout(&addr, timeout).map_err(describe)?;
+    stream
+        .set_write_timeout(Some(timeout))
+        .and_then(|()| stream.set_read_timeout(Some(timeout)))
+        .map_err(describe)?;
+
+    let head = format!(
+        "{method} {path} HTTP/1.1\r\n\
+         Host: {host}\r\n\
+         Content-Type: {content_type}\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).map_err(describe)?;
+    stream.write_all(body).map_err(describe)?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).map_err(describe)?;
+
+    let response = String::from_utf8_lossy(&response);
+    let status = response
+        .split_ascii_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| format!("malformed response from {host}"))?;
+    let body = response
+        .split_once("\r\n\r\n")
+        .map_or("", |(_, body)| body)
+        .trim()
+        .to_string();
+    Ok(HttpResponse { status, body })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_url() {
+        let url = HttpUrl::parse("http://gateway:9091/prefix/").unwrap();
+        assert_eq!(url.host, "gateway:9091");
+        assert_eq!(url.path, "/prefix");
+        assert_eq!(HttpUrl::parse("http://gateway:9091").unwrap().path, "");
+        assert!(HttpUrl::parse("https://gateway:9091").is_err());
+        assert!(HttpUrl::parse("http:///metrics").is_err());
+    }
+}
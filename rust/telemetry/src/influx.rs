@@ -0,0 +1,287 @@
+//! InfluxDB line-protocol rendering and push.
+//!
+//! Each series becomes one line: the metric name is the measurement, its
+//! labels are the tags and its value (or values) the fields, laid out the
+//! way Telegraf's Prometheus input writes them so existing dashboards work:
+//!
+//! ```text
+//! orders_total,venue=nyse value=3 1700000000000000000
+//! latency_seconds,operation=order_send count=1,sum=0.00025,0.0001=0,0.0005=1,... 1700000000000000000
+//! ```
+//!
+//! Histogram buckets are fields keyed by their upper bound, summary
+//! quantiles fields keyed by their quantile. Lines are sorted by
+//! measurement, then by tags, so the output for a given registry state is
+//! byte-for-byte stable. Only plain `http://` servers are supported by
+//! [`push_influx`].
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use prometheus::proto::{MetricFamily, MetricType};
+use pyo3::prelude::*;
+
+use crate::http::{self, HttpUrl};
+use crate::{init_metrics, is_valid_operation, TelemetryError, DEFAULT, PUSH_TIMEOUT};
+
+/// Content type of [`get_metrics_influx`] output.
+pub const INFLUX_CONTENT_TYPE: &str = "text/plain; charset=utf-8";
+
+/// Timestamp precisions accepted by [`push_influx`], finest first, with the
+/// number of nanoseconds in one unit.
+const PRECISIONS: [(&str, u128); 4] = [
+    ("ns", 1),
+    ("u", 1_000),
+    ("ms", 1_000_000),
+    ("s", 1_000_000_000),
+];
+
+/// Render all registered metrics in the InfluxDB line protocol.
+///
+/// # Arguments
+/// * `timestamp_ns` - Appended to every line if given; otherwise InfluxDB
+///   stamps the points with its own clock on write
+///
+/// Counters, gauges and untyped metrics have a single `value` field;
+/// histograms have `count`, `sum` and one cumulative field per bucket
+/// bound (`+Inf` is `count`); summaries have `count`, `sum` and one field
+/// per quantile. Non-finite values (e.g. the quantiles of an empty
+/// summary) are left out, since InfluxDB cannot store them.
+pub fn get_metrics_influx(timestamp_ns: Option<u64>) -> String {
+    init_metrics();
+    DEFAULT.get_metrics_influx(timestamp_ns)
+}
+
+/// Render `families` as line protocol, see [`get_metrics_influx`].
+pub(crate) fn render(families: &[MetricFamily], timestamp: Option<u64>) -> String {
+    let mut families: Vec<&MetricFamily> = families.iter().collect();
+    families.sort_by(|a, b| a.get_name().cmp(b.get_name()));
+
+    let mut out = String::new();
+    for family in families {
+        let measurement = escape(family.get_name(), &[',', ' ']);
+        let mut lines: Vec<(BTreeMap<String, String>, String)> = family
+            .get_metric()
+            .iter()
+            .map(|metric| {
+                let tags = metric
+                    .get_label()
+                    .iter()
+                    .map(|label| (escape_key(label.get_name()), escape_key(label.get_value())))
+                    .collect();
+                (tags, fields(family.get_field_type(), metric))
+            })
+            .collect();
+        lines.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (tags, fields) in lines {
+            if fields.is_empty() {
+                continue;
+            }
+            out.push_str(&measurement);
+            for (name, value) in &tags {
+                let _ = write!(out, ",{name}={value}");
+            }
+            let _ = write!(out, " {fields}");
+            if let Some(timestamp) = timestamp {
+                let _ = write!(out, " {timestamp}");
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// The field set of one series, without its non-finite values.
+fn fields(kind: MetricType, metric: &prometheus::proto::Metric) -> String {
+    let mut fields: Vec<(String, f64)> = Vec::new();
+    match kind {
+        MetricType::COUNTER => fields.push(("value".into(), metric.get_counter().get_value())),
+        MetricType::GAUGE => fields.push(("value".into(), metric.get_gauge().get_value())),
+        MetricType::UNTYPED => fields.push(("value".into(), metric.get_untyped().get_value())),
+        MetricType::HISTOGRAM => {
+            let histogram = metric.get_histogram();
+            fields.push(("count".into(), histogram.get_sample_count() as f64));
+            fields.push(("sum".into(), histogram.get_sample_sum()));
+            for bucket in histogram.get_bucket() {
+                fields.push((
+                    bucket.get_upper_bound().to_string(),
+                    bucket.get_cumulative_count() as f64,
+                ));
+            }
+        }
+        MetricType::SUMMARY => {
+            let summary = metric.get_summary();
+            fields.push(("count".into(), summary.get_sample_count() as f64));
+            fields.push(("sum".into(), summary.get_sample_sum()));
+            for quantile in summary.get_quantile() {
+                fields.push((quantile.get_quantile().to_string(), quantile.get_value()));
+            }
+        }
+    }
+    fields
+        .iter()
+        .filter(|(_, value)| value.is_finite())
+        .map(|(name, value)| format!("{}={value}", escape_key(name)))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Escape a tag key, tag value or field key.
+fn escape_key(text: &str) -> String {
+    escape(text, &[',', '=', ' '])
+}
+
+/// Backslash-escape every character of `text` in `special`.
+fn escape(text: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Write every registered metric to an InfluxDB server, stamped with the
+/// current time.
+///
+/// Uses the `/write` endpoint of InfluxDB 1.x, which 2.x also serves for
+/// compatibility (with `db` mapped to a bucket).
+///
+/// # Arguments
+/// * `url` - Server base URL, e.g. `"http://influxdb:8086"`
+/// * `db` - Database to write to
+/// * `precision` - Timestamp precision: `"ns"`, `"u"`, `"ms"` or `"s"`
+///
+/// # Returns
+/// * `Ok(())` - The server accepted the write
+/// * `Err(TelemetryError::Influx)` - If `db` or `precision` is invalid, the
+///   server is unreachable or the URL is unusable, or the server answered
+///   with a non-2xx status
+pub fn push_influx(url: &str, db: &str, precision: &str) -> Result<(), TelemetryError> {
+    if !is_valid_operation(db) {
+        return Err(TelemetryError::Influx(format!("invalid database {db:?}")));
+    }
+    let unit = PRECISIONS
+        .iter()
+        .find(|(name, _)| *name == precision)
+        .map(|(_, unit)| *unit)
+        .ok_or_else(|| TelemetryError::Influx(format!("unknown precision {precision:?}")))?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default();
+
+    let url = HttpUrl::parse(url).map_err(TelemetryError::Influx)?;
+    let response = http::send(
+        "POST",
+        &url.host,
+        &format!("{}/write?db={db}&precision={precision}", url.path),
+        INFLUX_CONTENT_TYPE,
+        get_metrics_influx(Some((now / unit) as u64)).as_bytes(),
+        PUSH_TIMEOUT,
+    )
+    .map_err(TelemetryError::Influx)?;
+    if !(200..300).contains(&response.status) {
+        return Err(TelemetryError::Influx(format!(
+            "write rejected with status {}: {}",
+            response.status, response.body
+        )));
+    }
+    Ok(())
+}
+
+/// Render metrics in the InfluxDB line protocol (Python binding).
+///
+/// Releases the GIL while rendering.
+#[pyfunction]
+#[pyo3(name = "get_metrics_influx", signature = (timestamp_ns = None))]
+pub(crate) fn py_get_metrics_influx(py: Python<'_>, timestamp_ns: Option<u64>) -> String {
+    py.allow_threads(|| get_metrics_influx(timestamp_ns))
+}
+
+/// Write metrics to an InfluxDB server (Python binding).
+///
+/// Raises `ValueError` on an invalid database or precision, connection
+/// failures or non-2xx responses.
+#[pyfunction]
+#[pyo3(name = "push_influx", signature = (url, db, precision = "ns"))]
+pub(crate) fn py_push_influx(py: Python<'_>, url: &str, db: &str, precision: &str) -> PyResult<()> {
+    Ok(py.allow_threads(|| push_influx(url, db, precision))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TelemetryHandle;
+
+    /// The lines of `handle`'s rendering for one measurement.
+    fn lines(handle: &TelemetryHandle, measurement: &str, timestamp: Option<u64>) -> Vec<String> {
+        handle
+            .get_metrics_influx(timestamp)
+            .lines()
+            .filter(|line| line.starts_with(&format!("{measurement},")))
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_labeled_counter_lines() {
+        let handle = TelemetryHandle::new_isolated();
+        handle
+            .emit_counter("fills_total", &[("venue", "nyse"), ("side", "buy")], 2.0)
+            .unwrap();
+        handle
+            .emit_counter("fills_total", &[("venue", "arca"), ("side", "sell")], 1.0)
+            .unwrap();
+
+        assert_eq!(
+            lines(&handle, "fills_total", Some(1_700_000_000_000_000_000)),
+            [
+                "fills_total,side=buy,venue=nyse value=2 1700000000000000000",
+                "fills_total,side=sell,venue=arca value=1 1700000000000000000",
+            ]
+        );
+        assert_eq!(
+            lines(&handle, "fills_total", None)[0],
+            "fills_total,side=buy,venue=nyse value=2"
+        );
+    }
+
+    #[test]
+    fn test_histogram_line() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.record_latency("order.send", 250.0);
+
+        // Dots need no escaping in line protocol, so the tag keeps them.
+        assert_eq!(
+            lines(&handle, "latency_seconds", Some(42)),
+            ["latency_seconds,operation=order.send count=1,sum=0.00025,\
+              0.000001=0,0.0000025=0,0.000005=0,0.00001=0,0.0001=0,0.0005=1,\
+              0.001=1,0.005=1,0.01=1,0.05=1,0.1=1,0.25=1,0.5=1,1=1,2.5=1,5=1,10=1 42"]
+        );
+    }
+
+    #[test]
+    fn test_escaping() {
+        assert_eq!(escape_key("a b,c=d.e"), "a\\ b\\,c\\=d.e");
+        assert_eq!(escape("a b,c=d", &[',', ' ']), "a\\ b\\,c=d");
+    }
+
+    #[test]
+    fn test_push_rejects_bad_arguments() {
+        for (url, db, precision) in [
+            ("http://127.0.0.1:1", "bad db", "ns"),
+            ("http://127.0.0.1:1", "metrics", "minutes"),
+            ("https://influx", "metrics", "ns"),
+        ] {
+            assert!(matches!(
+                push_influx(url, db, precision),
+                Err(TelemetryError::Influx(_))
+            ));
+        }
+    }
+}
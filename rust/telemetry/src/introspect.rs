@@ -0,0 +1,444 @@
+//! Reading metric values back without parsing the exposition format.
+//!
+//! Everything here works on the registry's gather output, so it sees the
+//! same metrics as [`get_metrics`](crate::get_metrics), including ones
+//! other crates registered, under the same names (including any
+//! [`set_namespace`](crate::set_namespace) prefix). Missing metrics give
+//! `None` (or are simply not listed) rather than an error.
+//!
+//! [`histogram_quantile`] estimates quantiles from histogram buckets the
+//! way PromQL's function of the same name does, so the trading loop can
+//! react to its own latency without a Prometheus server.
+
+use std::collections::{BTreeSet, HashMap};
+
+use prometheus::proto::{Histogram, Metric, MetricFamily, MetricType};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::{init_metrics, json, DEFAULT};
+
+/// Description of one registered metric family.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricInfo {
+    /// Metric name
+    pub name: String,
+    /// `"counter"`, `"gauge"`, `"histogram"`, `"summary"` or `"untyped"`
+    pub metric_type: &'static str,
+    /// Help text
+    pub help: String,
+    /// Label names used by any series, sorted
+    pub label_names: Vec<String>,
+}
+
+/// Describe every metric family with at least one series, sorted by name.
+pub fn list_metrics() -> Vec<MetricInfo> {
+    init_metrics();
+    DEFAULT.list_metrics()
+}
+
+/// Current value of a counter.
+///
+/// For a labeled counter this is the sum over all its series.
+///
+/// # Returns
+/// `None` if no counter called `name` has a series yet
+pub fn get_counter_value(name: &str) -> Option<f64> {
+    init_metrics();
+    DEFAULT.get_counter_value(name)
+}
+
+/// Number of observations in one histogram series.
+///
+/// # Arguments
+/// * `name` - Histogram name, e.g. `"latency_seconds"`
+/// * `labels` - The series' complete label set, in any order, leaving out
+///   global labels (see [`set_global_labels`](crate::set_global_labels));
+///   empty for an unlabeled histogram
+///
+/// # Returns
+/// `None` if there is no histogram `name` or it has no series with exactly
+/// these labels
+pub fn get_histogram_count(name: &str, labels: &[(&str, &str)]) -> Option<u64> {
+    init_metrics();
+    DEFAULT.get_histogram_count(name, labels)
+}
+
+/// Sum of the observations in one histogram series.
+///
+/// Takes the same arguments as [`get_histogram_count`].
+pub fn get_histogram_sum(name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+    init_metrics();
+    DEFAULT.get_histogram_sum(name, labels)
+}
+
+/// Estimate a quantile of one histogram series from its buckets.
+///
+/// Finds the bucket holding rank `q * count` and interpolates linearly
+/// between its bounds (the first bucket starts at 0). A rank beyond the
+/// last finite bucket, i.e. in `+Inf`, reports the largest finite bound.
+///
+/// # Arguments
+/// * `name` - Histogram name
+/// * `labels` - The series' complete label set, as for [`get_histogram_count`]
+/// * `q` - Quantile in `(0, 1]`, e.g. `0.99`
+///
+/// # Returns
+/// * `Some(value)` - The estimate, in the histogram's unit
+/// * `None` - If the series does not exist or is empty, or `q` is outside `(0, 1]`
+pub fn histogram_quantile(name: &str, labels: &[(&str, &str)], q: f64) -> Option<f64> {
+    init_metrics();
+    DEFAULT.histogram_quantile(name, labels, q)
+}
+
+/// [`histogram_quantile`] for `latency_seconds{operation}`, in seconds.
+///
+/// Unlike [`latency_percentile`](crate::latency_percentile) this rejects
+/// `q` outside `(0, 1]` instead of clamping it.
+pub fn latency_quantile(operation: &str, q: f64) -> Option<f64> {
+    init_metrics();
+    DEFAULT.latency_quantile(operation, q)
+}
+
+/// Interpolated `q` quantile of `histogram`; see [`histogram_quantile`].
+pub(crate) fn quantile(histogram: &Histogram, q: f64) -> Option<f64> {
+    let count = histogram.get_sample_count();
+    if !(q > 0.0 && q <= 1.0) || count == 0 {
+        return None;
+    }
+    Some(estimate(histogram, q * count as f64))
+}
+
+/// Value at `rank` (in observations) by linear interpolation within its bucket.
+pub(crate) fn estimate(histogram: &Histogram, rank: f64) -> f64 {
+    let mut lower = 0.0;
+    let mut below = 0;
+    for bucket in histogram.get_bucket() {
+        let upper = bucket.get_upper_bound();
+        let cumulative = bucket.get_cumulative_count();
+        if cumulative > 0 && cumulative as f64 >= rank {
+            let fraction = (rank - below as f64) / (cumulative - below) as f64;
+            return lower + (upper - lower) * fraction.max(0.0);
+        }
+        lower = upper;
+        below = cumulative;
+    }
+    lower
+}
+
+/// [`list_metrics`] over `families`.
+pub(crate) fn list(families: &[MetricFamily]) -> Vec<MetricInfo> {
+    let mut infos: Vec<MetricInfo> = families
+        .iter()
+        .map(|family| {
+            let label_names: BTreeSet<&str> = family
+                .get_metric()
+                .iter()
+                .flat_map(|metric| metric.get_label())
+                .map(|label| label.get_name())
+                .collect();
+            MetricInfo {
+                name: family.get_name().to_string(),
+                metric_type: json::type_name(family.get_field_type()),
+                help: family.get_help().to_string(),
+                label_names: label_names.into_iter().map(str::to_string).collect(),
+            }
+        })
+        .collect();
+    infos.sort_by(|a, b| a.name.cmp(&b.name));
+    infos
+}
+
+/// [`get_counter_value`] over `families`.
+pub(crate) fn counter_value(families: &[MetricFamily], name: &str) -> Option<f64> {
+    let family = find(families, name, MetricType::COUNTER)?;
+    Some(
+        family
+            .get_metric()
+            .iter()
+            .map(|metric| metric.get_counter().get_value())
+            .sum(),
+    )
+}
+
+/// Sum of the counter or gauge series of `name` in `families` that carry
+/// every pair in `labels` (and possibly others), as a PromQL selector
+/// would match them.
+pub(crate) fn selector_value(
+    families: &[MetricFamily],
+    name: &str,
+    labels: &[(&str, &str)],
+) -> Option<f64> {
+    let family = families.iter().find(|family| family.get_name() == name)?;
+    let read = |metric: &Metric| match family.get_field_type() {
+        MetricType::COUNTER => Some(metric.get_counter().get_value()),
+        MetricType::GAUGE => Some(metric.get_gauge().get_value()),
+        _ => None,
+    };
+    family
+        .get_metric()
+        .iter()
+        .filter(|metric| {
+            labels.iter().all(|(key, value)| {
+                metric
+                    .get_label()
+                    .iter()
+                    .any(|pair| pair.get_name() == *key && pair.get_value() == *value)
+            })
+        })
+        .map(read)
+        .sum()
+}
+
+/// The histogram series `name{labels}` in `families`.
+pub(crate) fn histogram<'a>(
+    families: &'a [MetricFamily],
+    name: &str,
+    labels: &[(&str, &str)],
+) -> Option<&'a Histogram> {
+    find(families, name, MetricType::HISTOGRAM)?
+        .get_metric()
+        .iter()
+        .find(|metric| has_labels(metric, labels))
+        .map(Metric::get_histogram)
+}
+
+fn find<'a>(
+    families: &'a [MetricFamily],
+    name: &str,
+    kind: MetricType,
+) -> Option<&'a MetricFamily> {
+    families
+        .iter()
+        .find(|family| family.get_name() == name && family.get_field_type() == kind)
+}
+
+fn has_labels(metric: &Metric, labels: &[(&str, &str)]) -> bool {
+    let pairs = metric.get_label();
+    pairs.len() == labels.len()
+        && labels.iter().all(|(key, value)| {
+            pairs
+                .iter()
+                .any(|pair| pair.get_name() == *key && pair.get_value() == *value)
+        })
+}
+
+fn label_pairs(labels: &Option<HashMap<String, String>>) -> Vec<(&str, &str)> {
+    labels
+        .iter()
+        .flatten()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect()
+}
+
+/// Describe every metric as a list of dicts with `name`, `metric_type`,
+/// `help` and `label_names` keys (Python binding).
+#[pyfunction]
+#[pyo3(name = "list_metrics")]
+pub(crate) fn py_list_metrics(py: Python<'_>) -> PyResult<Vec<Py<PyDict>>> {
+    list_metrics()
+        .into_iter()
+        .map(|info| {
+            let dict = PyDict::new_bound(py);
+            dict.set_item("name", info.name)?;
+            dict.set_item("metric_type", info.metric_type)?;
+            dict.set_item("help", info.help)?;
+            dict.set_item("label_names", info.label_names)?;
+            Ok(dict.unbind())
+        })
+        .collect()
+}
+
+/// Current value of a counter, or `None` (Python binding).
+#[pyfunction]
+#[pyo3(name = "get_counter_value")]
+pub(crate) fn py_get_counter_value(name: &str) -> Option<f64> {
+    get_counter_value(name)
+}
+
+/// Observation count of a histogram series, or `None`; `labels` is a dict
+/// (Python binding).
+#[pyfunction]
+#[pyo3(name = "get_histogram_count", signature = (name, labels = None))]
+pub(crate) fn py_get_histogram_count(
+    name: &str,
+    labels: Option<HashMap<String, String>>,
+) -> Option<u64> {
+    get_histogram_count(name, &label_pairs(&labels))
+}
+
+/// Observation sum of a histogram series, or `None`; `labels` is a dict
+/// (Python binding).
+#[pyfunction]
+#[pyo3(name = "get_histogram_sum", signature = (name, labels = None))]
+pub(crate) fn py_get_histogram_sum(
+    name: &str,
+    labels: Option<HashMap<String, String>>,
+) -> Option<f64> {
+    get_histogram_sum(name, &label_pairs(&labels))
+}
+
+/// Estimate a histogram quantile, or `None`; `labels` is a dict or `None`
+/// (Python binding).
+#[pyfunction]
+#[pyo3(name = "histogram_quantile", signature = (name, labels, q))]
+pub(crate) fn py_histogram_quantile(
+    name: &str,
+    labels: Option<HashMap<String, String>>,
+    q: f64,
+) -> Option<f64> {
+    histogram_quantile(name, &label_pairs(&labels), q)
+}
+
+/// Estimate a latency quantile in seconds, or `None` (Python binding).
+#[pyfunction]
+#[pyo3(name = "latency_quantile")]
+pub(crate) fn py_latency_quantile(operation: &str, q: f64) -> Option<f64> {
+    latency_quantile(operation, q)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::list_metrics;
+    use crate::TelemetryHandle;
+
+    #[test]
+    fn test_reads_back_exact_values() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.emit_metric("orders_total", 3.0);
+        handle
+            .emit_counter("fills_total", &[("venue", "nyse")], 2.0)
+            .unwrap();
+        handle
+            .emit_counter("fills_total", &[("venue", "arca")], 5.0)
+            .unwrap();
+        handle.record_latency("order_send", 500.0);
+        handle.record_latency("order_send", 500.0);
+        handle.record_latency("order_cancel", 100.0);
+
+        assert_eq!(handle.get_counter_value("orders_total"), Some(3.0));
+        assert_eq!(handle.get_counter_value("fills_total"), Some(7.0));
+        assert_eq!(
+            handle.get_histogram_count("latency_seconds", &[("operation", "order_send")]),
+            Some(2)
+        );
+        assert_eq!(
+            handle.get_histogram_sum("latency_seconds", &[("operation", "order_send")]),
+            Some(0.001)
+        );
+        assert_eq!(
+            handle.get_histogram_count("latency_seconds", &[("operation", "order_cancel")]),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_missing_metrics_are_none() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.record_latency("order_send", 250.0);
+
+        assert_eq!(handle.get_counter_value("missing_total"), None);
+        assert_eq!(handle.get_counter_value("latency_seconds"), None);
+        assert_eq!(handle.get_histogram_count("latency_seconds", &[]), None);
+        assert_eq!(
+            handle.get_histogram_sum("latency_seconds", &[("operation", "other")]),
+            None
+        );
+        assert_eq!(handle.get_histogram_count("orders_total", &[]), None);
+    }
+
+    #[test]
+    fn test_list_metrics_describes_families() {
+        let handle = TelemetryHandle::new_isolated();
+        handle
+            .emit_counter("fills_total", &[("venue", "nyse"), ("side", "buy")], 1.0)
+            .unwrap();
+        handle
+            .register_histogram("fill_size", &[1.0, 10.0])
+            .unwrap();
+
+        let metrics = handle.list_metrics();
+        let names: Vec<&str> = metrics.iter().map(|info| info.name.as_str()).collect();
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let fills = metrics
+            .iter()
+            .find(|info| info.name == "fills_total")
+            .unwrap();
+        assert_eq!(fills.metric_type, "counter");
+        assert_eq!(fills.label_names, vec!["side", "venue"]);
+
+        let histogram = metrics
+            .iter()
+            .find(|info| info.name == "fill_size")
+            .unwrap();
+        assert_eq!(histogram.metric_type, "histogram");
+        assert!(histogram.label_names.is_empty());
+    }
+
+    #[test]
+    fn test_list_metrics_includes_registered_counter_and_histogram() {
+        crate::register_counter("test_list_registered_total", "Registered for list_metrics")
+            .unwrap();
+        crate::register_histogram("test_list_sizes", &[1.0, 10.0]).unwrap();
+        crate::observe_histogram("test_list_sizes", 5.0).unwrap();
+
+        let metrics = list_metrics();
+        let find = |name| metrics.iter().find(|info| info.name == name).unwrap();
+        let counter = find("test_list_registered_total");
+        assert_eq!(counter.metric_type, "counter");
+        assert_eq!(counter.help, "Registered for list_metrics");
+        assert_eq!(find("test_list_sizes").metric_type, "histogram");
+    }
+
+    #[test]
+    fn test_histogram_quantile_interpolates_within_bucket() {
+        let handle = TelemetryHandle::new_isolated();
+        handle
+            .register_histogram("fill_size", &[1.0, 2.0, 4.0, 8.0])
+            .unwrap();
+        for (value, times) in [(0.5, 10), (1.5, 40), (3.0, 40), (6.0, 10)] {
+            for _ in 0..times {
+                handle.observe_histogram("fill_size", value).unwrap();
+            }
+        }
+
+        assert_eq!(handle.histogram_quantile("fill_size", &[], 0.1), Some(1.0));
+        assert_eq!(handle.histogram_quantile("fill_size", &[], 0.5), Some(2.0));
+        let p70 = handle.histogram_quantile("fill_size", &[], 0.7).unwrap();
+        assert!(p70 > 2.0 && p70 < 4.0, "p70 = {p70}");
+        let p99 = handle.histogram_quantile("fill_size", &[], 0.99).unwrap();
+        assert!(p99 > 4.0 && p99 <= 8.0, "p99 = {p99}");
+    }
+
+    #[test]
+    fn test_histogram_quantile_edge_cases() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.register_histogram("fill_size", &[1.0, 2.0]).unwrap();
+        assert_eq!(handle.histogram_quantile("fill_size", &[], 0.5), None);
+
+        handle.observe_histogram("fill_size", 0.5).unwrap();
+        handle.observe_histogram("fill_size", 50.0).unwrap();
+        // The second sample only lands in +Inf.
+        assert_eq!(handle.histogram_quantile("fill_size", &[], 1.0), Some(2.0));
+        for q in [0.0, -0.5, 1.5, f64::NAN] {
+            assert_eq!(handle.histogram_quantile("fill_size", &[], q), None);
+        }
+        assert_eq!(handle.histogram_quantile("missing", &[], 0.5), None);
+    }
+
+    #[test]
+    fn test_latency_quantile_reads_operation_series() {
+        let handle = TelemetryHandle::new_isolated();
+        assert_eq!(handle.latency_quantile("order_gen", 0.99), None);
+        for _ in 0..100 {
+            handle.record_latency("order_gen", 300.0);
+        }
+        handle.record_latency("order_cancel", 5_000_000.0);
+
+        // 300μs falls in the (100μs, 500μs] bucket.
+        let p99 = handle.latency_quantile("order_gen", 0.99).unwrap();
+        assert!(p99 > 0.0001 && p99 <= 0.0005, "p99 = {p99}");
+        assert_eq!(handle.latency_quantile("order_cancel", 1.0), Some(5.0));
+    }
+}
@@ -0,0 +1,245 @@
+//! JSON rendering of the registry for tools that don't speak the
+//! Prometheus text format.
+//!
+//! The document is an array of metric families sorted by name; each
+//! family's series are sorted by their labels, so the output for a given
+//! registry state is byte-for-byte stable.
+
+use std::collections::BTreeMap;
+
+use prometheus::proto::{Metric, MetricFamily, MetricType};
+use pyo3::prelude::*;
+use serde::Serialize;
+
+use crate::{init_metrics, REGISTRY};
+
+#[derive(Serialize)]
+struct Family<'a> {
+    name: &'a str,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    help: &'a str,
+    series: Vec<Series<'a>>,
+}
+
+#[derive(Serialize)]
+struct Series<'a> {
+    labels: BTreeMap<&'a str, &'a str>,
+    #[serde(flatten)]
+    data: Data,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Data {
+    Value {
+        value: f64,
+    },
+    Histogram {
+        count: u64,
+        sum: f64,
+        buckets: Vec<Bucket>,
+    },
+    Summary {
+        count: u64,
+        sum: f64,
+        quantiles: Vec<Quantile>,
+    },
+}
+
+#[derive(Serialize)]
+struct Bucket {
+    le: f64,
+    count: u64,
+}
+
+#[derive(Serialize)]
+struct Quantile {
+    quantile: f64,
+    value: f64,
+}
+
+/// Render all registered metrics as a JSON array of metric families.
+///
+/// ```json
+/// [
+///   {
+///     "name": "latency_seconds",
+///     "type": "histogram",
+///     "help": "Operation latency in seconds",
+///     "series": [
+///       {
+///         "labels": {"operation": "order_send"},
+///         "count": 1,
+///         "sum": 0.00025,
+///         "buckets": [{"le": 0.000001, "count": 0}, ...]
+///       }
+///     ]
+///   }
+/// ]
+/// ```
+///
+/// Counters, gauges and untyped metrics carry `"value"`; histograms carry
+/// `"count"`, `"sum"` and cumulative `"buckets"`; summaries carry
+/// `"count"`, `"sum"` and `"quantiles"`.
+pub fn get_metrics_json() -> String {
+    init_metrics();
+    render(&REGISTRY.gather())
+}
+
+/// Render `families` in the [`get_metrics_json`] layout.
+pub(crate) fn render(families: &[MetricFamily]) -> String {
+    let mut document: Vec<Family> = families.iter().map(family).collect();
+    document.sort_by(|a, b| a.name.cmp(b.name));
+    serde_json::to_string(&document).expect("metric families always serialize")
+}
+
+fn family(family: &MetricFamily) -> Family<'_> {
+    let kind = family.get_field_type();
+    let mut series: Vec<Series> = family
+        .get_metric()
+        .iter()
+        .map(|metric| Series {
+            labels: metric
+                .get_label()
+                .iter()
+                .map(|label| (label.get_name(), label.get_value()))
+                .collect(),
+            data: data(kind, metric),
+        })
+        .collect();
+    series.sort_by(|a, b| a.labels.cmp(&b.labels));
+    Family {
+        name: family.get_name(),
+        kind: type_name(kind),
+        help: family.get_help(),
+        series,
+    }
+}
+
+/// Lower-case name of a metric type, as used in the exposition format.
+pub(crate) fn type_name(kind: MetricType) -> &'static str {
+    match kind {
+        MetricType::COUNTER => "counter",
+        MetricType::GAUGE => "gauge",
+        MetricType::HISTOGRAM => "histogram",
+        MetricType::SUMMARY => "summary",
+        MetricType::UNTYPED => "untyped",
+    }
+}
+
+fn data(kind: MetricType, metric: &Metric) -> Data {
+    match kind {
+        MetricType::COUNTER => Data::Value {
+            value: metric.get_counter().get_value(),
+        },
+        MetricType::GAUGE => Data::Value {
+            value: metric.get_gauge().get_value(),
+        },
+        MetricType::UNTYPED => Data::Value {
+            value: metric.get_untyped().get_value(),
+        },
+        MetricType::HISTOGRAM => {
+            let histogram = metric.get_histogram();
+            Data::Histogram {
+                count: histogram.get_sample_count(),
+                sum: histogram.get_sample_sum(),
+                buckets: histogram
+                    .get_bucket()
+                    .iter()
+                    .map(|bucket| Bucket {
+                        le: bucket.get_upper_bound(),
+                        count: bucket.get_cumulative_count(),
+                    })
+                    .collect(),
+            }
+        }
+        MetricType::SUMMARY => {
+            let summary = metric.get_summary();
+            Data::Summary {
+                count: summary.get_sample_count(),
+                sum: summary.get_sample_sum(),
+                quantiles: summary
+                    .get_quantile()
+                    .iter()
+                    .map(|q| Quantile {
+                        quantile: q.get_quantile(),
+                        value: q.get_value(),
+                    })
+                    .collect(),
+            }
+        }
+    }
+}
+
+/// Render metrics as a JSON string (Python binding).
+#[pyfunction]
+#[pyo3(name = "get_metrics_json")]
+pub(crate) fn py_get_metrics_json() -> String {
+    get_metrics_json()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{emit_counter, emit_metric, record_latency};
+    use serde_json::Value;
+
+    fn find_family(document: &Value, name: &str) -> Value {
+        document
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|family| family["name"] == name)
+            .cloned()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_counter_and_latency_paths() {
+        emit_metric("test_json_total", 3.0);
+        record_latency("test_json_op", 250.0);
+        let document: Value = serde_json::from_str(&get_metrics_json()).unwrap();
+
+        let counter = find_family(&document, "test_json_total");
+        assert_eq!(counter["type"], "counter");
+        assert_eq!(counter["series"][0]["labels"], serde_json::json!({}));
+        assert_eq!(counter["series"][0]["value"], 3.0);
+
+        let latency = find_family(&document, "latency_seconds");
+        assert_eq!(latency["type"], "histogram");
+        assert_eq!(latency["help"], "Operation latency in seconds");
+        let series = latency["series"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|series| series["labels"]["operation"] == "test_json_op")
+            .unwrap();
+        assert_eq!(series["count"], 1);
+        assert_eq!(series["sum"], 0.00025);
+        let buckets = series["buckets"].as_array().unwrap();
+        assert_eq!(buckets[4], serde_json::json!({"le": 0.0001, "count": 0}));
+        assert_eq!(buckets[5], serde_json::json!({"le": 0.0005, "count": 1}));
+    }
+
+    #[test]
+    fn test_output_is_sorted() {
+        emit_counter("test_json_sorted_total", &[("venue", "nyse")], 1.0).unwrap();
+        emit_counter("test_json_sorted_total", &[("venue", "arca")], 1.0).unwrap();
+        let document: Value = serde_json::from_str(&get_metrics_json()).unwrap();
+
+        let names: Vec<&str> = document
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|family| family["name"].as_str().unwrap())
+            .collect();
+        let mut sorted = names.clone();
+        sorted.sort_unstable();
+        assert_eq!(names, sorted);
+
+        let family = find_family(&document, "test_json_sorted_total");
+        assert_eq!(family["series"][0]["labels"]["venue"], "arca");
+        assert_eq!(family["series"][1]["labels"]["venue"], "nyse");
+    }
+}
@@ -0,0 +1,2328 @@
+//! Prometheus metrics registry and helpers for TinyWindow.
+//!
+//! This crate owns the process-wide metrics registry shared by the Rust
+//! components (encryption service, execution adapter) and exposes it to
+//! Python as the `tinywindow_rust_telemetry` module.
+//!
+//! # Metrics
+//! - `orders_total` - counter incremented via [`emit_metric`] or [`emit_metric_checked`]
+//! - `orders_per_second` - gauge of the orders passed to [`record_order`]
+//!   over a sliding window (see [`set_orders_rate_window`])
+//! - `latency_seconds{operation}` - histogram fed by [`record_latency`],
+//!   a [`start_timer`] guard (or the [`time_operation!`] macro) or a
+//!   [`thread_local_recorder`]
+//! - `labeled_latency_seconds{operation,venue,symbol}` - histogram fed by
+//!   [`record_latency_labeled`]
+//! - `errors_total{operation,kind}` - counter fed by [`record_error`] or
+//!   [`record_result`]
+//! - `latency_samples_skipped_total{operation}` - latency samples left out
+//!   by [`set_sampling`]
+//! - `latency_overflow_total{operation}` - latency samples above the largest
+//!   finite `latency_seconds` bucket (see [`configure_latency_buckets`]),
+//!   i.e. the ones only the `+Inf` bucket counts
+//! - `telemetry_dropped_metrics_total` - new counter names dropped because
+//!   the counter limit (see [`set_counter_limit`]) was reached
+//! - `telemetry_unknown_metric_total` - counter names [`emit_metric`]
+//!   created on first use instead of finding them registered (see
+//!   [`set_strict_mode`])
+//! - `tinywindow_uptime_seconds` (and `process_*` with the `process`
+//!   feature) - registered by [`init_process_metrics`]; the `process_*`
+//!   metrics alone by [`register_process_metrics`]
+//! - `build_info{version,commit,...}` - constant 1, registered (with the
+//!   uptime gauge) by [`register_build_info`]
+//!
+//! Additional histograms with their own bucket layouts can be created with
+//! [`register_histogram`] and fed with [`observe_histogram`]; summaries with
+//! client-side quantiles with [`register_summary`] and [`observe_summary`].
+//!
+//! Every function here acts on [`REGISTRY`]; a namespace set with
+//! [`set_namespace`] and labels set with [`set_global_labels`] before it is
+//! created appear on all of the metrics this crate puts in it.
+//! Tests that need exact values
+//! can use a [`TelemetryHandle::new_isolated`] registry with the same
+//! methods, or [`reset_metrics`] between cases.
+//!
+//! [`emit_metric`] creates a counter the first time it sees a new name,
+//! [`emit_counter`] does the same for labeled counters and [`set_gauge`]
+//! for gauges. With [`enable_statsd`] these calls (and [`record_latency`])
+//! are also mirrored to a StatsD agent.
+//! Other crates may register their own counters with [`register_counter`];
+//! everything registered here is rendered by [`get_metrics`] in the
+//! Prometheus text exposition format, and can be served over HTTP with
+//! [`serve_metrics`]; [`get_metrics_filtered`] renders a subset.
+//! [`get_metrics_json`] renders the same data as JSON,
+//! [`get_metrics_openmetrics`] as OpenMetrics text with trace exemplars,
+//! [`get_metrics_influx`] as InfluxDB line protocol (see [`push_influx`]),
+//! [`get_metrics_csv`] as CSV rows (see [`dump_metrics_csv`]),
+//! and [`list_metrics`], [`get_counter_value`], [`get_histogram_count`] and
+//! [`get_histogram_sum`] read single values back. Counter and gauge values
+//! can be carried across restarts with [`snapshot_to_file`] (or
+//! [`start_snapshotter`]) and [`restore_from_file`], and
+//! [`register_threshold`] calls back when one rises too fast. With the
+//! `async` feature, `subscribe` returns a `tokio` watch channel that
+//! follows one. [`health_report`] sums up whether operations are still
+//! recording and the registry is being scraped.
+
+// pyo3 0.22's `#[pyfunction]` expansion for `PyResult` returns trips this lint.
+#![allow(clippy::useless_conversion)]
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Once, OnceLock};
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use prometheus::{Counter, CounterVec, HistogramVec, IntCounter, Registry};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+mod csv;
+mod error;
+mod filter;
+mod handle;
+mod health;
+mod http;
+mod influx;
+mod introspect;
+mod json;
+mod local;
+mod openmetrics;
+mod operations;
+#[cfg(feature = "otlp")]
+mod otlp;
+mod process;
+#[cfg(all(feature = "process", target_os = "linux"))]
+mod process_collector;
+mod push;
+mod rate;
+mod resettable;
+mod server;
+mod snapshot;
+mod statsd;
+mod subregistry;
+#[cfg(feature = "async")]
+mod subscribe;
+mod summary;
+mod threshold;
+mod timer;
+
+#[cfg(test)]
+#[path = "../tests/common/mod.rs"]
+mod mock_http;
+
+pub use csv::{dump_metrics_csv, get_metrics_csv, CSV_HEADER};
+pub use error::TelemetryError;
+pub use filter::{get_metrics_filtered, MetricFilter};
+pub use handle::TelemetryHandle;
+pub use health::{
+    configure_health, health_report, HealthReport, HealthStatus, HealthThresholds,
+    DEFAULT_DEGRADED_AFTER, DEFAULT_STALE_AFTER,
+};
+pub use influx::{get_metrics_influx, push_influx, INFLUX_CONTENT_TYPE};
+pub use introspect::{
+    get_counter_value, get_histogram_count, get_histogram_sum, histogram_quantile,
+    latency_quantile, list_metrics, MetricInfo,
+};
+pub use json::get_metrics_json;
+pub use local::{FlusherHandle, ThreadLocalRecorder};
+pub use openmetrics::{get_metrics_openmetrics, MAX_TRACE_ID_LEN, OPENMETRICS_CONTENT_TYPE};
+#[cfg(feature = "otlp")]
+pub use otlp::{start_otlp_exporter, OtlpHandle, OTLP_TIMEOUT};
+pub use process::{init_process_metrics, register_build_info, register_process_metrics};
+pub use push::{push_metrics, push_metrics_and_clear, PUSH_TIMEOUT};
+pub use rate::DEFAULT_RATE_WINDOW;
+pub use server::{serve_metrics, MetricsServerHandle, METRICS_CONTENT_TYPE};
+pub use snapshot::{restore_from_file, snapshot_to_file, start_snapshotter, SnapshotterHandle};
+pub use statsd::{disable_statsd, enable_statsd, statsd_enabled};
+pub use subregistry::{subregistry, SubRegistry, SUBSYSTEM_LABEL};
+#[cfg(feature = "async")]
+pub use subscribe::{set_subscription_interval, subscribe, DEFAULT_SUBSCRIPTION_INTERVAL};
+pub use summary::SUMMARY_WINDOW;
+pub use threshold::{clear_thresholds, register_threshold, ThresholdCallback, ThresholdEvent};
+pub use timer::{start_timer, LatencyTimer};
+
+/// Re-exported so dependents use the same `prometheus` version as the registry.
+pub use prometheus;
+
+/// Default upper bounds (in seconds) of the latency histogram buckets.
+///
+/// Override with [`configure_latency_buckets`] before the first
+/// [`record_latency`].
+pub const LATENCY_BUCKETS: &[f64] = &[
+    0.000001, 0.0000025, 0.000005, 0.00001, 0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.25,
+    0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// `kind` label [`record_result`] uses for failed operations.
+pub const RESULT_ERROR_KIND: &str = "error";
+
+/// Label names of `labeled_latency_seconds`, in registration order.
+pub const LATENCY_LABELS: [&str; 3] = ["operation", "venue", "symbol"];
+
+/// Default maximum number of distinct values per `labeled_latency_seconds` label.
+pub const DEFAULT_LABEL_VALUE_LIMIT: usize = 1000;
+
+/// Value substituted for label values beyond the limit (see [`set_label_value_limit`]).
+pub const OTHER_LABEL_VALUE: &str = "other";
+
+/// Default maximum number of distinct `operation` values of `latency_seconds`
+/// (see [`set_operation_limit`]).
+pub const DEFAULT_OPERATION_LIMIT: usize = 500;
+
+/// Operation samples beyond the limit are recorded under (see [`set_operation_limit`]).
+pub const OVERFLOW_OPERATION: &str = "overflow";
+
+/// Default maximum number of distinct counter names.
+pub const DEFAULT_COUNTER_LIMIT: usize = 256;
+
+/// Maximum length of an operation label value.
+const MAX_OPERATION_LEN: usize = 64;
+
+/// Labels [`REGISTRY`] adds to every series; fixed when it is created.
+static GLOBAL_LABELS: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Namespace of the metrics this crate puts in [`REGISTRY`]; fixed when
+/// the registry is created.
+static NAMESPACE: OnceLock<Option<String>> = OnceLock::new();
+
+lazy_static! {
+    /// Process-wide metrics registry.
+    pub static ref REGISTRY: Registry = {
+        let labels = GLOBAL_LABELS.get_or_init(HashMap::new);
+        NAMESPACE.get_or_init(|| None);
+        Registry::new_custom(None, Some(labels.clone()))
+            .expect("set_global_labels only accepts valid labels")
+    };
+
+    /// The handle behind the free functions, registering into [`REGISTRY`].
+    static ref DEFAULT: TelemetryHandle = TelemetryHandle::with_registry(
+        REGISTRY.clone(),
+        namespace().map(str::to_string),
+        GLOBAL_LABELS.get().cloned().unwrap_or_default(),
+    );
+
+    /// Total number of orders processed.
+    pub static ref ORDERS_TOTAL: IntCounter = DEFAULT.orders_total().clone();
+
+    /// Operation latency in seconds, labeled by operation.
+    ///
+    /// Created with the configured buckets on first use, after which
+    /// [`configure_latency_buckets`] fails.
+    pub static ref LATENCY: HistogramVec = DEFAULT.latency().clone();
+
+    /// New counter names dropped because the counter limit was reached.
+    pub static ref DROPPED_METRICS_TOTAL: Counter = DEFAULT.dropped_metrics_total().clone();
+
+    /// Failed operations, labeled by operation and error kind.
+    pub static ref ERRORS_TOTAL: CounterVec = DEFAULT.errors_total().clone();
+
+    /// Counter names emitted without being registered first.
+    pub static ref UNKNOWN_METRICS_TOTAL: Counter = DEFAULT.unknown_metrics_total().clone();
+}
+
+/// Label names `set_global_labels` refuses because the crate's own metrics
+/// (or the exposition format) already use them.
+const RESERVED_LABEL_NAMES: &[&str] = &["le", "quantile", "operation", "kind", "venue", "symbol"];
+
+/// Tag every series in [`REGISTRY`] with fixed labels, e.g. environment
+/// and instance ID.
+///
+/// The labels are added when the registry is gathered, so they cover the
+/// built-in metrics, counters and gauges created later on the fly, and
+/// metrics registered by other crates, in every exposition format.
+/// Introspection functions such as [`get_histogram_count`] leave them out
+/// of the label sets they match.
+///
+/// # Arguments
+/// * `labels` - `(name, value)` pairs; names must be valid Prometheus label
+///   names not used by this crate's metrics, values follow the rules for
+///   operation names
+///
+/// # Returns
+/// * `Ok(())` - The labels are in effect
+/// * `Err(TelemetryError::InvalidLabel)` - If a name or value is invalid or
+///   a name repeats
+/// * `Err(TelemetryError::GlobalLabelsFrozen)` - If called a second time or
+///   after any other function of this crate (which creates the registry)
+pub fn set_global_labels(labels: &[(&str, &str)]) -> Result<(), TelemetryError> {
+    let labels = global_label_map(labels)?;
+    GLOBAL_LABELS
+        .set(labels)
+        .map_err(|_| TelemetryError::GlobalLabelsFrozen)
+}
+
+/// Put every metric this crate creates in [`REGISTRY`] in namespace `ns`,
+/// e.g. `tw_exec` turns `orders_total` into `tw_exec_orders_total`.
+///
+/// Lets two services on one host export the same metrics without
+/// relabeling. The namespace is set on each metric's `prometheus::Opts`,
+/// so `# HELP` and `# TYPE` lines carry it too, as do [`get_metrics_json`]
+/// and the introspection functions ([`list_metrics`] reports prefixed
+/// names, and [`get_counter_value`] and friends look metrics up by them).
+/// Names passed to [`emit_metric`], [`register_counter`] and the like stay
+/// unprefixed. Metrics an application registers with [`REGISTRY`] itself
+/// keep the names they were created with.
+///
+/// # Returns
+/// * `Ok(())` - Every metric will be prefixed
+/// * `Err(TelemetryError::InvalidMetricName)` - If `ns` is not a valid
+///   metric name
+/// * `Err(TelemetryError::NamespaceFrozen)` - If called a second time or
+///   after any other function of this crate (which creates the registry)
+pub fn set_namespace(ns: &str) -> Result<(), TelemetryError> {
+    if !is_valid_metric_name(ns) {
+        return Err(TelemetryError::InvalidMetricName(ns.to_string()));
+    }
+    NAMESPACE
+        .set(Some(ns.to_string()))
+        .map_err(|_| TelemetryError::NamespaceFrozen)
+}
+
+/// The namespace of the crate's metrics in [`REGISTRY`], once fixed.
+pub(crate) fn namespace() -> Option<&'static str> {
+    NAMESPACE.get().and_then(Option::as_deref)
+}
+
+/// Validate global labels and collect them into the map a registry takes.
+fn global_label_map(labels: &[(&str, &str)]) -> Result<HashMap<String, String>, TelemetryError> {
+    let mut map = HashMap::with_capacity(labels.len());
+    for (key, value) in labels {
+        let valid_key = is_valid_metric_name(key)
+            && !key.contains(':')
+            && !key.starts_with("__")
+            && !RESERVED_LABEL_NAMES.contains(key);
+        if !valid_key
+            || !is_valid_operation(value)
+            || map.insert(key.to_string(), value.to_string()).is_some()
+        {
+            return Err(TelemetryError::InvalidLabel(format!("{key}={value}")));
+        }
+    }
+    Ok(map)
+}
+
+/// Environment variable that enables strict mode when set to `1` or `true`
+/// at [`init_metrics`].
+pub const STRICT_MODE_ENV: &str = "TINYWINDOW_TELEMETRY_STRICT";
+
+static INIT: Once = Once::new();
+
+/// Whether the recording functions do anything; see [`set_enabled`].
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turn every recording function into a no-op, or back on.
+///
+/// For deployments without a metrics backend. While disabled,
+/// [`emit_metric`], [`emit_counter`], [`set_gauge`], the latency and error
+/// recorders (including [`ThreadLocalRecorder`]) and
+/// [`observe_histogram`]/[`observe_summary`] return `Ok(())`
+/// after a single atomic load, before any validation or registry work,
+/// and do not call [`init_metrics`], so a process that never enables
+/// telemetry never builds [`REGISTRY`]'s contents. Samples dropped while
+/// disabled are lost for good. Registration, configuration and exposition
+/// functions keep working.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether telemetry is enabled (see [`set_enabled`]); `true` by default.
+#[inline]
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Register the built-in metrics with [`REGISTRY`].
+///
+/// Safe to call any number of times; registration happens once. All other
+/// functions in this crate call it implicitly.
+pub fn init_metrics() {
+    INIT.call_once(|| {
+        lazy_static::initialize(&process::START);
+        lazy_static::initialize(&DEFAULT);
+        if let Ok(value) = std::env::var(STRICT_MODE_ENV) {
+            DEFAULT.set_strict_mode(value == "1" || value.eq_ignore_ascii_case("true"));
+        }
+    });
+}
+
+/// Make emitting an unregistered counter name an error.
+///
+/// In strict mode [`emit_metric_checked`] fails with
+/// [`TelemetryError::UnknownMetric`] for names not created by
+/// [`register_counter`] (or by an earlier lenient emit), and [`emit_metric`]
+/// drops the sample. In lenient mode, the default, such names are created
+/// on first use and counted in `telemetry_unknown_metric_total`, so a
+/// typo'd name shows up there.
+///
+/// Also enabled by setting [`STRICT_MODE_ENV`] before the first call into
+/// this crate.
+pub fn set_strict_mode(strict: bool) {
+    init_metrics();
+    DEFAULT.set_strict_mode(strict);
+}
+
+/// Whether strict mode is enabled (see [`set_strict_mode`]).
+pub fn strict_mode() -> bool {
+    init_metrics();
+    DEFAULT.strict_mode()
+}
+
+/// Zero every counter and clear every observation in [`REGISTRY`],
+/// including every [`SubRegistry`].
+///
+/// Counters created through this crate drop to zero, labeled series and
+/// gauges disappear until next written, and histograms and summaries
+/// forget their samples. Registrations (names, buckets, objectives) are
+/// kept. Meant for tests; prefer [`TelemetryHandle::new_isolated`] where
+/// the code under test can take a handle.
+pub fn reset_metrics() {
+    init_metrics();
+    DEFAULT.reset();
+    subregistry::reset_all();
+}
+
+/// Set the maximum number of distinct counter names.
+///
+/// Once reached, [`emit_metric`] drops names it has not seen before and
+/// increments `telemetry_dropped_metrics_total`. Existing counters keep
+/// working. Defaults to [`DEFAULT_COUNTER_LIMIT`].
+pub fn set_counter_limit(limit: usize) {
+    init_metrics();
+    DEFAULT.set_counter_limit(limit);
+}
+
+/// Register a counter whose value can be taken and zeroed in one step.
+///
+/// For integrations with pull-and-reset semantics: increment it with
+/// [`emit_metric`] as usual and drain it with [`take_and_reset_counter`].
+/// It is exported like any counter, dropping to zero after each take.
+///
+/// # Returns
+/// * `Ok(())` - The counter is registered at zero
+/// * `Err(TelemetryError::InvalidMetricName)` - If `name` is not a valid metric name
+/// * `Err(TelemetryError::Registry)` - If `name` is already registered
+pub fn register_resettable_counter(name: &str, help: &str) -> Result<(), TelemetryError> {
+    init_metrics();
+    DEFAULT.register_resettable_counter(name, help)
+}
+
+/// Atomically read a resettable counter and set it to zero.
+///
+/// No increment is lost or counted twice, even with concurrent
+/// [`emit_metric`] calls.
+///
+/// # Returns
+/// * `Some(value)` - What the counter held before the reset
+/// * `None` - If `name` was not registered with [`register_resettable_counter`]
+pub fn take_and_reset_counter(name: &str) -> Option<f64> {
+    init_metrics();
+    DEFAULT.take_and_reset_counter(name)
+}
+
+/// Register an additional counter with [`REGISTRY`].
+///
+/// Intended to be called once per counter (e.g. from a `OnceLock`); a second
+/// registration under the same name returns an error.
+///
+/// # Arguments
+/// * `name` - Prometheus metric name
+/// * `help` - Help text shown in the exposition output
+///
+/// # Returns
+/// * `Ok(IntCounter)` - The registered counter
+/// * `Err(prometheus::Error)` - If the name is invalid or already registered
+pub fn register_counter(name: &str, help: &str) -> prometheus::Result<IntCounter> {
+    init_metrics();
+    DEFAULT.register_counter(name, help)
+}
+
+/// Register an additional labeled counter with [`REGISTRY`].
+///
+/// Like [`register_counter`], intended to be called once per metric.
+///
+/// # Arguments
+/// * `name` - Prometheus metric name
+/// * `help` - Help text shown in the exposition output
+/// * `label_names` - Names of the labels every sample carries
+///
+/// # Returns
+/// * `Ok(CounterVec)` - The registered counter family
+/// * `Err(prometheus::Error)` - If a name is invalid or already registered
+pub fn register_counter_vec(
+    name: &str,
+    help: &str,
+    label_names: &[&str],
+) -> prometheus::Result<CounterVec> {
+    init_metrics();
+    DEFAULT.register_counter_vec(name, help, label_names)
+}
+
+/// Remove a counter created through this crate from [`REGISTRY`].
+///
+/// Works for counters from [`emit_metric`], [`emit_counter`],
+/// [`register_counter`] and [`register_counter_vec`], freeing its slot
+/// under the counter limit. The name may be registered again later, but
+/// the registry insists on the same help text and label names. Handles
+/// returned by [`register_counter`] and [`register_counter_vec`] keep
+/// working but are no longer exported.
+///
+/// # Arguments
+/// * `name` - Metric name
+///
+/// # Returns
+/// * `Ok(())` - The counter was removed
+/// * `Err(TelemetryError::UnknownMetric)` - If no counter of that name exists
+/// * `Err(TelemetryError::BuiltinMetric)` - If `name` is a built-in metric
+///   such as `orders_total`
+pub fn unregister_counter(name: &str) -> Result<(), TelemetryError> {
+    init_metrics();
+    DEFAULT.unregister_counter(name)
+}
+
+/// Remove any metric created by name through this crate from [`REGISTRY`].
+///
+/// Like [`unregister_counter`], but also covers gauges from [`set_gauge`],
+/// histograms from [`register_histogram`] and summaries from
+/// [`register_summary`]. All of the metric's series disappear from
+/// scrapes, and the name can be created again later, starting from zero.
+/// The registry insists on the same label names (and, for
+/// [`register_counter`], help text) as before.
+///
+/// # Returns
+/// * `true` - If the metric was removed
+/// * `false` - If no metric of that name was created through this crate, or
+///   it is a built-in metric such as `orders_total` or `latency_seconds`
+pub fn unregister_metric(name: &str) -> bool {
+    init_metrics();
+    DEFAULT.unregister_metric(name)
+}
+
+/// Emit a counter metric, rejecting anything that cannot be recorded.
+///
+/// The first call with a new name creates and registers a counter of that
+/// name (unless strict mode is on, see [`set_strict_mode`]); later calls
+/// increment it by `value`. Zero is accepted and leaves the counter
+/// unchanged.
+///
+/// Counters count in `u64`, so they stay exact past 2^53 where an `f64`
+/// would stop taking increments of 1; `value` must therefore be a whole
+/// number. Counters from [`register_resettable_counter`] are the exception
+/// and take fractional values.
+///
+/// # Arguments
+/// * `name` - Metric name, following Prometheus naming rules
+/// * `value` - Amount to increment by
+///
+/// # Returns
+/// * `Ok(())` - The value was applied
+/// * `Err(TelemetryError::InvalidValue)` - If `value` is negative, NaN,
+///   infinite, or not a whole number
+/// * `Err(TelemetryError::InvalidMetricName)` - If `name` is not a valid metric name
+/// * `Err(TelemetryError::UnknownMetric)` - If `name` is new and strict mode is on
+/// * `Err(TelemetryError::TooManyMetrics)` - If `name` is new and the counter limit is reached
+/// * `Err(TelemetryError::Registry)` - If `name` is already used by a non-counter metric
+pub fn emit_metric_checked(name: &str, value: f64) -> Result<(), TelemetryError> {
+    if !is_enabled() {
+        return Ok(());
+    }
+    init_metrics();
+    DEFAULT.emit_metric_checked(name, value)?;
+    statsd::send(name, value, statsd::Kind::Counter, &[]);
+    Ok(())
+}
+
+/// Emit a counter metric.
+///
+/// Lenient wrapper around [`emit_metric_checked`]: anything it would reject
+/// is dropped.
+///
+/// # Arguments
+/// * `name` - Metric name
+/// * `value` - Amount to increment by
+pub fn emit_metric(name: &str, value: f64) {
+    // Rejected samples are documented as dropped.
+    let _ = emit_metric_checked(name, value);
+}
+
+/// Record that an order was sent, for the `orders_per_second` gauge.
+///
+/// The gauge reports the orders recorded during the last window (see
+/// [`set_orders_rate_window`]) divided by its length, computed whenever it
+/// is read. It appears in the exposition output from the first call on.
+/// Does not touch `orders_total`.
+pub fn record_order() {
+    if !is_enabled() {
+        return;
+    }
+    init_metrics();
+    DEFAULT.record_order();
+}
+
+/// Current value of the `orders_per_second` gauge.
+pub fn orders_per_second() -> f64 {
+    init_metrics();
+    DEFAULT.orders_per_second()
+}
+
+/// Set the sliding window `orders_per_second` averages over; defaults to
+/// [`DEFAULT_RATE_WINDOW`].
+///
+/// # Returns
+/// * `Ok(())` - The window applies from the next read
+/// * `Err(TelemetryError::InvalidValue)` - If `window` is zero
+pub fn set_orders_rate_window(window: Duration) -> Result<(), TelemetryError> {
+    init_metrics();
+    DEFAULT.set_orders_rate_window(window)
+}
+
+/// Increment a labeled counter, creating it on first use.
+///
+/// The first call for `name` fixes its label keys; later calls must use the
+/// same keys (in any order). Keys and values follow the same rules as
+/// operation names in [`record_latency`].
+///
+/// # Arguments
+/// * `name` - Metric name, following Prometheus naming rules
+/// * `labels` - `(key, value)` pairs, e.g. `[("venue", "nyse"), ("side", "buy")]`
+/// * `value` - Amount to increment by
+///
+/// # Returns
+/// * `Ok(())` - The value was applied
+/// * `Err(TelemetryError::InvalidValue)` - If `value` is negative, NaN,
+///   infinite, or not a whole number (see [`emit_metric_checked`])
+/// * `Err(TelemetryError::InvalidMetricName)` - If `name` is not a valid metric name
+/// * `Err(TelemetryError::InvalidLabel)` - If a label key or value is invalid
+/// * `Err(TelemetryError::LabelMismatch)` - If the label keys differ from the first call
+/// * `Err(TelemetryError::Registry)` - If `name` is already used by another metric
+pub fn emit_counter(name: &str, labels: &[(&str, &str)], value: f64) -> Result<(), TelemetryError> {
+    if !is_enabled() {
+        return Ok(());
+    }
+    init_metrics();
+    DEFAULT.emit_counter(name, labels, value)?;
+    statsd::send(name, value, statsd::Kind::Counter, labels);
+    Ok(())
+}
+
+fn validate_labels(labels: &[(&str, &str)]) -> Result<(), TelemetryError> {
+    match labels
+        .iter()
+        .find(|(key, value)| !is_valid_operation(key) || !is_valid_operation(value))
+    {
+        Some((key, value)) => Err(TelemetryError::InvalidLabel(format!("{key}={value}"))),
+        None => Ok(()),
+    }
+}
+
+/// Order the values of `labels` by `keys`, which must be the same key set.
+fn label_values<'a>(
+    name: &str,
+    keys: &[String],
+    labels: &[(&str, &'a str)],
+) -> Result<Vec<&'a str>, TelemetryError> {
+    let mut values = Vec::with_capacity(keys.len());
+    for key in keys {
+        match labels.iter().find(|(k, _)| k == key) {
+            Some((_, value)) => values.push(*value),
+            None => return Err(label_mismatch(name, keys, labels)),
+        }
+    }
+    if values.len() != labels.len() {
+        return Err(label_mismatch(name, keys, labels));
+    }
+    Ok(values)
+}
+
+fn label_mismatch(name: &str, expected: &[String], labels: &[(&str, &str)]) -> TelemetryError {
+    TelemetryError::LabelMismatch {
+        name: name.to_string(),
+        expected: expected.to_vec(),
+        actual: labels.iter().map(|(key, _)| key.to_string()).collect(),
+    }
+}
+
+/// Set a gauge, creating it on first use.
+///
+/// Label handling matches [`emit_counter`]: the first call fixes the label
+/// keys, and `labels` may be empty for an unlabeled gauge.
+///
+/// # Arguments
+/// * `name` - Metric name, following Prometheus naming rules
+/// * `labels` - `(key, value)` pairs, e.g. `[("venue", "nyse")]`
+/// * `value` - New gauge value
+///
+/// # Returns
+/// * `Ok(())` - The gauge was set
+/// * `Err(TelemetryError::InvalidValue)` - If `value` is NaN or infinite
+/// * `Err(TelemetryError::InvalidMetricName)` - If `name` is not a valid metric name
+/// * `Err(TelemetryError::InvalidLabel)` - If a label key or value is invalid
+/// * `Err(TelemetryError::LabelMismatch)` - If the label keys differ from the first call
+/// * `Err(TelemetryError::Registry)` - If `name` is already used by another metric
+pub fn set_gauge(name: &str, labels: &[(&str, &str)], value: f64) -> Result<(), TelemetryError> {
+    if !is_enabled() {
+        return Ok(());
+    }
+    init_metrics();
+    DEFAULT.set_gauge(name, labels, value)?;
+    statsd::send(name, value, statsd::Kind::Gauge, labels);
+    Ok(())
+}
+
+/// Register a histogram with its own bucket layout.
+///
+/// Registering a name again with identical buckets is a no-op, so callers
+/// need not coordinate who registers first.
+///
+/// # Arguments
+/// * `name` - Metric name, following Prometheus naming rules
+/// * `buckets` - Bucket upper bounds, finite and strictly increasing
+///
+/// # Returns
+/// * `Ok(())` - The histogram is registered
+/// * `Err(TelemetryError::InvalidMetricName)` - If `name` is not a valid metric name
+/// * `Err(TelemetryError::InvalidBuckets)` - If `buckets` is empty, non-finite or unsorted
+/// * `Err(TelemetryError::BucketMismatch)` - If `name` exists with different buckets
+/// * `Err(TelemetryError::Registry)` - If `name` is already used by another metric
+pub fn register_histogram(name: &str, buckets: &[f64]) -> Result<(), TelemetryError> {
+    init_metrics();
+    DEFAULT.register_histogram(name, buckets)
+}
+
+/// Check that histogram bucket bounds are non-empty, finite and strictly increasing.
+fn validate_buckets(buckets: &[f64]) -> Result<(), TelemetryError> {
+    if buckets.is_empty() {
+        return Err(TelemetryError::InvalidBuckets(
+            "no buckets given".to_string(),
+        ));
+    }
+    if buckets.iter().any(|bound| !bound.is_finite()) {
+        return Err(TelemetryError::InvalidBuckets(format!(
+            "bounds must be finite, got {buckets:?}"
+        )));
+    }
+    if buckets.windows(2).any(|pair| pair[0] >= pair[1]) {
+        return Err(TelemetryError::InvalidBuckets(format!(
+            "bounds must be strictly increasing, got {buckets:?}"
+        )));
+    }
+    Ok(())
+}
+
+/// Observe `value` in a histogram created by [`register_histogram`].
+///
+/// # Returns
+/// * `Ok(())` - The value was observed
+/// * `Err(TelemetryError::InvalidValue)` - If `value` is NaN or infinite
+/// * `Err(TelemetryError::UnknownMetric)` - If no histogram is registered under `name`
+pub fn observe_histogram(name: &str, value: f64) -> Result<(), TelemetryError> {
+    if !is_enabled() {
+        return Ok(());
+    }
+    init_metrics();
+    DEFAULT.observe_histogram(name, value)
+}
+
+/// Register a summary reporting client-side quantiles.
+///
+/// Each objective is a `(quantile, allowed error)` pair such as
+/// `(0.99, 0.001)`. Quantiles are computed exactly over the most recent
+/// [`SUMMARY_WINDOW`] observations, so the error bound is always met.
+/// As with [`register_histogram`], registering a name again with identical
+/// objectives is a no-op.
+///
+/// # Arguments
+/// * `name` - Metric name, following Prometheus naming rules
+/// * `objectives` - `(quantile, error)` pairs, both in `0.0..=1.0`
+///
+/// # Returns
+/// * `Ok(())` - The summary is registered
+/// * `Err(TelemetryError::InvalidMetricName)` - If `name` is not a valid metric name
+/// * `Err(TelemetryError::InvalidObjectives)` - If `objectives` is empty or out of range
+/// * `Err(TelemetryError::ObjectiveMismatch)` - If `name` exists with different objectives
+/// * `Err(TelemetryError::Registry)` - If `name` is already used by another metric
+pub fn register_summary(name: &str, objectives: &[(f64, f64)]) -> Result<(), TelemetryError> {
+    init_metrics();
+    DEFAULT.register_summary(name, objectives)
+}
+
+/// Observe `value` in a summary created by [`register_summary`].
+///
+/// # Returns
+/// * `Ok(())` - The value was observed
+/// * `Err(TelemetryError::InvalidValue)` - If `value` is NaN or infinite
+/// * `Err(TelemetryError::UnknownMetric)` - If no summary is registered under `name`
+pub fn observe_summary(name: &str, value: f64) -> Result<(), TelemetryError> {
+    if !is_enabled() {
+        return Ok(());
+    }
+    init_metrics();
+    DEFAULT.observe_summary(name, value)
+}
+
+/// Replace the bucket layout of `latency_seconds`.
+///
+/// Prometheus histograms cannot be rebucketed once created, so this must
+/// run before the first [`record_latency`] (typically at startup).
+///
+/// # Arguments
+/// * `buckets` - Bucket upper bounds in seconds, finite and strictly increasing
+///
+/// # Returns
+/// * `Ok(())` - Latency samples will use `buckets`
+/// * `Err(TelemetryError::InvalidBuckets)` - If `buckets` is empty, non-finite or unsorted
+/// * `Err(TelemetryError::LatencyInUse)` - If `latency_seconds` already exists
+pub fn configure_latency_buckets(buckets: &[f64]) -> Result<(), TelemetryError> {
+    init_metrics();
+    DEFAULT.configure_latency_buckets(buckets)
+}
+
+/// The bucket upper bounds (in seconds) of `latency_seconds`.
+pub fn latency_buckets() -> Vec<f64> {
+    init_metrics();
+    DEFAULT.latency_buckets()
+}
+
+/// Record the latency of an operation.
+///
+/// Operation names become label values, so they are restricted to ASCII
+/// alphanumerics, `_`, `-`, `.` and `:` (at most 64 characters). Invalid
+/// names are dropped with a warning through the `log` facade; use
+/// [`try_record_latency`] to get the error instead.
+///
+/// # Arguments
+/// * `operation` - Operation name, e.g. `"order_send"`
+/// * `duration_us` - Elapsed time in microseconds
+pub fn record_latency(operation: &str, duration_us: f64) {
+    if let Err(err) = try_record_latency(operation, duration_us) {
+        log::warn!("dropping latency sample: {err}");
+    }
+}
+
+/// Set how many distinct operations `latency_seconds` may track.
+///
+/// Each operation name is its own series, so a caller passing IDs as
+/// operations could otherwise create millions. Once `limit` names are
+/// tracked, samples for new names go to
+/// `latency_seconds{operation="overflow"}` ([`OVERFLOW_OPERATION`]) and
+/// increment `telemetry_label_overflow_total`. Names already tracked keep
+/// their series; [`remove_operation`] and [`prune_idle_operations`] free
+/// slots. Defaults to [`DEFAULT_OPERATION_LIMIT`].
+pub fn set_operation_limit(limit: usize) {
+    init_metrics();
+    DEFAULT.set_operation_limit(limit);
+}
+
+/// Delete `operation`'s `latency_seconds` series and free its slot under
+/// [`set_operation_limit`].
+///
+/// Its samples still buffered by a [`ThreadLocalRecorder`] are discarded.
+/// Recording the operation again starts a new series from zero.
+///
+/// # Returns
+/// * `true` - If `operation` was tracked
+/// * `false` - If it was never recorded or already removed
+pub fn remove_operation(operation: &str) -> bool {
+    init_metrics();
+    DEFAULT.remove_operation(operation)
+}
+
+/// Delete `operation`'s `latency_seconds` series, e.g. when a strategy is
+/// decommissioned, so it stops being scraped at its last values.
+///
+/// Same as [`remove_operation`] (the slot is freed and a later sample
+/// starts a new series from zero), but reports whether a series existed.
+///
+/// # Returns
+/// * `true` - If the series was removed
+/// * `false` - If there was no such series
+pub fn remove_latency_series(operation: &str) -> bool {
+    init_metrics();
+    DEFAULT.remove_latency_series(operation)
+}
+
+/// [`remove_operation`] every operation with no latency sample recorded in
+/// the last `max_idle_secs` seconds, the overflow series included.
+///
+/// # Returns
+/// The removed operation names.
+pub fn prune_idle_operations(max_idle_secs: u64) -> Vec<String> {
+    init_metrics();
+    DEFAULT.prune_idle_operations(Duration::from_secs(max_idle_secs))
+}
+
+/// Record only a deterministic subset of `operation`'s latency samples.
+///
+/// For operations recorded so often that the histogram's atomics show up
+/// in profiles. With `rate` 1/N, the 1st, (N+1)th, (2N+1)th... call to
+/// [`record_latency`] (or [`record_duration`] and friends) for `operation`
+/// is observed; the rest only increment
+/// `latency_samples_skipped_total{operation}`. Nothing is scaled, so the
+/// histogram's `_count` and `_sum` for a sampled operation count sampled
+/// calls only, while its quantiles remain representative.
+///
+/// # Arguments
+/// * `operation` - Operation name, as for [`record_latency`]
+/// * `rate` - Fraction to keep, rounded to the nearest 1/N: `1.0` records
+///   every call (the default), `0.0` none
+///
+/// # Returns
+/// * `Ok(())` - Sampling applies from the next call
+/// * `Err(TelemetryError::InvalidLabel)` - If `operation` is invalid
+/// * `Err(TelemetryError::InvalidValue)` - If `rate` is outside `0.0..=1.0`
+pub fn set_sampling(operation: &str, rate: f64) -> Result<(), TelemetryError> {
+    init_metrics();
+    DEFAULT.set_sampling(operation, rate)
+}
+
+/// Number of `operation`'s latency samples skipped by [`set_sampling`].
+pub fn sampled_count(operation: &str) -> u64 {
+    init_metrics();
+    DEFAULT.sampled_count(operation)
+}
+
+/// The [`ThreadLocalRecorder`] feeding `latency_seconds` in [`REGISTRY`].
+///
+/// For operations recorded concurrently from many threads: samples are
+/// buffered per thread and reach `latency_seconds` on
+/// [`flush_thread_local`], on a tick of a [`start_flusher`] thread, or when
+/// the recording thread exits. Scrapes lag by at most the flush interval.
+/// Every call returns a handle to the same recorder.
+pub fn thread_local_recorder() -> ThreadLocalRecorder {
+    init_metrics();
+    DEFAULT.thread_local_recorder()
+}
+
+/// Merge every thread's samples buffered by [`thread_local_recorder`] into
+/// `latency_seconds`.
+pub fn flush_thread_local() {
+    init_metrics();
+    DEFAULT.flush_thread_local();
+}
+
+/// Call [`flush_thread_local`] every `interval_ms` milliseconds on a
+/// background thread, until the returned handle is stopped or dropped.
+pub fn start_flusher(interval_ms: u64) -> FlusherHandle {
+    thread_local_recorder().start_flusher(Duration::from_millis(interval_ms))
+}
+
+/// Record the latency of an operation and link it to a distributed trace.
+///
+/// Observes `latency_seconds{operation}` like [`record_latency`] and keeps
+/// `trace_id` as the exemplar of the bucket the sample falls in (the latest
+/// one per bucket wins). Exemplars only appear in
+/// [`get_metrics_openmetrics`]; the classic text format has no room for
+/// them.
+///
+/// # Arguments
+/// * `operation` - Operation name, as for [`record_latency`]
+/// * `duration_us` - Elapsed time in microseconds
+/// * `trace_id` - Trace ID, 1 to [`MAX_TRACE_ID_LEN`] ASCII alphanumerics,
+///   `-` or `_`; anything else drops the exemplar (with a warning) but
+///   keeps the observation
+pub fn record_latency_with_exemplar(operation: &str, duration_us: f64, trace_id: &str) {
+    if !is_enabled() {
+        return;
+    }
+    match try_record_latency(operation, duration_us) {
+        Ok(()) => DEFAULT.attach_exemplar(operation, duration_us / 1e6, trace_id),
+        Err(err) => log::warn!("dropping latency sample: {err}"),
+    }
+}
+
+/// Record the latency of an operation, rejecting invalid operation names.
+///
+/// # Returns
+/// * `Ok(())` - The sample was recorded
+/// * `Err(TelemetryError::InvalidLabel)` - If `operation` breaks the rules
+///   in [`record_latency`]
+pub fn try_record_latency(operation: &str, duration_us: f64) -> Result<(), TelemetryError> {
+    if !is_enabled() {
+        return Ok(());
+    }
+    init_metrics();
+    DEFAULT.try_record_latency(operation, duration_us)?;
+    statsd::send(
+        "latency",
+        duration_us / 1e3,
+        statsd::Kind::Timing,
+        &[("operation", operation)],
+    );
+    Ok(())
+}
+
+/// Record the latency of an operation broken down by venue and/or symbol.
+///
+/// Feeds `labeled_latency_seconds{operation, venue, symbol}`, a separate
+/// histogram from `latency_seconds` (a metric's label set is fixed) with
+/// the same buckets. Labels not given are recorded as empty strings.
+///
+/// Each label keeps at most [`set_label_value_limit`] distinct values;
+/// later new values are recorded as [`OTHER_LABEL_VALUE`] so an unbounded
+/// symbol universe cannot explode the series count.
+///
+/// # Arguments
+/// * `operation` - Operation name, as for [`record_latency`]
+/// * `extra_labels` - `("venue", ..)` and/or `("symbol", ..)` pairs
+/// * `duration_us` - Elapsed time in microseconds
+///
+/// # Returns
+/// * `Ok(())` - The sample was recorded
+/// * `Err(TelemetryError::InvalidLabel)` - If a key is not `venue` or
+///   `symbol`, or a value breaks the rules for operation names
+pub fn record_latency_labeled(
+    operation: &str,
+    extra_labels: &[(&str, &str)],
+    duration_us: f64,
+) -> Result<(), TelemetryError> {
+    if !is_enabled() {
+        return Ok(());
+    }
+    init_metrics();
+    DEFAULT.record_latency_labeled(operation, extra_labels, duration_us)
+}
+
+/// Set how many distinct values each `labeled_latency_seconds` label may take.
+///
+/// Values already seen keep their own series. Defaults to
+/// [`DEFAULT_LABEL_VALUE_LIMIT`].
+pub fn set_label_value_limit(limit: usize) {
+    init_metrics();
+    DEFAULT.set_label_value_limit(limit);
+}
+
+/// Record the latency of an operation measured in nanoseconds.
+///
+/// Same as [`record_duration`] with `Duration::from_nanos(duration_ns)`;
+/// use it (or [`record_duration`]) on hot paths timed with `Instant` to
+/// avoid unit mistakes and the precision lost converting to microseconds.
+///
+/// # Arguments
+/// * `operation` - Operation name, as for [`record_latency`]
+/// * `duration_ns` - Elapsed time in nanoseconds
+pub fn record_latency_ns(operation: &str, duration_ns: u64) {
+    record_duration(operation, Duration::from_nanos(duration_ns));
+}
+
+/// Record the latency of an operation as a [`Duration`].
+///
+/// Feeds the same `latency_seconds{operation}` histogram as
+/// [`record_latency`].
+///
+/// # Arguments
+/// * `operation` - Operation name, as for [`record_latency`]
+/// * `duration` - Elapsed time, e.g. from `Instant::elapsed`
+pub fn record_duration(operation: &str, duration: Duration) {
+    if let Err(err) = try_record_duration(operation, duration) {
+        log::warn!("dropping latency sample: {err}");
+    }
+}
+
+/// [`record_duration`], returning `Err(TelemetryError::InvalidLabel)` for
+/// an invalid operation name instead of logging it.
+pub fn try_record_duration(operation: &str, duration: Duration) -> Result<(), TelemetryError> {
+    if !is_enabled() {
+        return Ok(());
+    }
+    init_metrics();
+    DEFAULT.try_record_duration(operation, duration)?;
+    statsd::send(
+        "latency",
+        duration.as_secs_f64() * 1e3,
+        statsd::Kind::Timing,
+        &[("operation", operation)],
+    );
+    Ok(())
+}
+
+/// Count a failed operation in `errors_total{operation, kind}`.
+///
+/// Both labels follow the rules for operation names in [`record_latency`];
+/// invalid ones are dropped with a warning through the `log` facade.
+///
+/// # Arguments
+/// * `operation` - Operation name, e.g. `"order_send"`
+/// * `kind` - Error kind, e.g. `"timeout"`
+pub fn record_error(operation: &str, kind: &str) {
+    if let Err(err) = try_record_error(operation, kind) {
+        log::warn!("dropping error sample: {err}");
+    }
+}
+
+/// [`record_error`], returning `Err(TelemetryError::InvalidLabel)` for an
+/// invalid operation or kind instead of logging it.
+pub fn try_record_error(operation: &str, kind: &str) -> Result<(), TelemetryError> {
+    if !is_enabled() {
+        return Ok(());
+    }
+    init_metrics();
+    DEFAULT.try_record_error(operation, kind)?;
+    statsd::send(
+        "errors_total",
+        1.0,
+        statsd::Kind::Counter,
+        &[("operation", operation), ("kind", kind)],
+    );
+    Ok(())
+}
+
+/// Record an operation's latency and, if it failed, count the error.
+///
+/// Equivalent to [`record_latency`] followed by
+/// `record_error(operation, RESULT_ERROR_KIND)` when `ok` is false, so
+/// callers cannot record one without the other.
+///
+/// # Arguments
+/// * `operation` - Operation name, e.g. `"order_send"`
+/// * `duration_us` - Elapsed time in microseconds
+/// * `ok` - Whether the operation succeeded
+pub fn record_result(operation: &str, duration_us: f64, ok: bool) {
+    record_latency(operation, duration_us);
+    if !ok {
+        record_error(operation, RESULT_ERROR_KIND);
+    }
+}
+
+/// Approximate a latency percentile for `operation` from its histogram.
+///
+/// Finds the bucket holding the requested rank and interpolates linearly
+/// between its bounds, like PromQL's `histogram_quantile`. Ranks that land
+/// above the last finite bucket report that bucket's upper bound.
+///
+/// # Arguments
+/// * `operation` - Operation label passed to [`record_latency`]
+/// * `quantile` - Requested quantile, clamped to `[0, 1]`
+///
+/// # Returns
+/// * `Some(us)` - The estimated latency in microseconds
+/// * `None` - If `operation` has no samples or `quantile` is NaN
+pub fn latency_percentile(operation: &str, quantile: f64) -> Option<f64> {
+    init_metrics();
+    DEFAULT.latency_percentile(operation, quantile)
+}
+
+/// Reset every counter created through this crate to zero.
+fn reset_counters() {
+    DEFAULT.reset_counters();
+}
+
+/// Render all registered metrics in the Prometheus text format.
+///
+/// Encoding failures are logged and give an empty string; see
+/// [`try_get_metrics`].
+pub fn get_metrics() -> String {
+    init_metrics();
+    DEFAULT.get_metrics()
+}
+
+/// [`get_metrics`], reusing the previous rendering if it is at most
+/// `max_age_ms` milliseconds old.
+///
+/// For scrape handlers polled by several scrapers: rendering thousands of
+/// series takes milliseconds, and with a cache only the first caller in
+/// each window pays for it. Concurrent callers that find the cache stale
+/// wait for a single rendering and share it. The output can be up to
+/// `max_age_ms` behind the registry.
+pub fn get_metrics_cached(max_age_ms: u64) -> String {
+    init_metrics();
+    DEFAULT.get_metrics_cached(Duration::from_millis(max_age_ms))
+}
+
+/// [`get_metrics`], returning `Err(TelemetryError::EncodeFailed)` if the
+/// registry cannot be encoded (e.g. two collectors produced conflicting
+/// families).
+pub fn try_get_metrics() -> Result<String, TelemetryError> {
+    init_metrics();
+    DEFAULT.try_get_metrics()
+}
+
+/// Render all registered metrics in the Prometheus protobuf format.
+///
+/// The output is a sequence of length-delimited `MetricFamily` messages,
+/// served with [`prometheus::PROTOBUF_FORMAT`] as its content type.
+/// Encoding failures are logged and give an empty buffer.
+pub fn get_metrics_protobuf() -> Vec<u8> {
+    init_metrics();
+    DEFAULT.get_metrics_protobuf()
+}
+
+/// [`get_metrics_protobuf`], returning `Err(TelemetryError::EncodeFailed)`
+/// if the registry cannot be encoded.
+pub fn try_get_metrics_protobuf() -> Result<Vec<u8>, TelemetryError> {
+    init_metrics();
+    DEFAULT.try_get_metrics_protobuf()
+}
+
+/// Whether `name` follows the Prometheus metric naming rules
+/// (`[a-zA-Z_:][a-zA-Z0-9_:]*`).
+fn is_valid_metric_name(name: &str) -> bool {
+    let mut bytes = name.bytes();
+    bytes
+        .next()
+        .is_some_and(|b| b.is_ascii_alphabetic() || matches!(b, b'_' | b':'))
+        && bytes.all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b':'))
+}
+
+/// Whether `operation` is safe to use as a label value.
+fn is_valid_operation(operation: &str) -> bool {
+    !operation.is_empty()
+        && operation.len() <= MAX_OPERATION_LEN
+        && operation
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.' | b':'))
+}
+
+// PyO3 bindings for Python interop
+// These expose the telemetry functions to Python as the `tinywindow_rust_telemetry` module
+
+/// Render metrics in the Prometheus protobuf format as `bytes` (Python binding).
+#[pyfunction]
+#[pyo3(name = "get_metrics_protobuf")]
+fn py_get_metrics_protobuf(py: Python<'_>) -> Py<PyBytes> {
+    let encoded = py.allow_threads(get_metrics_protobuf);
+    PyBytes::new_bound(py, &encoded).unbind()
+}
+
+/// Emit a counter metric (Python binding).
+///
+/// In strict mode an unregistered name raises `TelemetryError`; other
+/// rejected samples are dropped.
+#[pyfunction]
+#[pyo3(name = "emit_metric")]
+fn py_emit_metric(name: &str, value: f64) -> PyResult<()> {
+    match emit_metric_checked(name, value) {
+        Err(err @ TelemetryError::UnknownMetric(_)) => Err(err.into()),
+        // Rejected samples are documented as dropped.
+        _ => Ok(()),
+    }
+}
+
+/// Turn the recording functions on or off (Python binding).
+#[pyfunction]
+#[pyo3(name = "set_enabled")]
+fn py_set_enabled(enabled: bool) {
+    set_enabled(enabled);
+}
+
+/// Whether telemetry is enabled (Python binding).
+#[pyfunction]
+#[pyo3(name = "is_enabled")]
+fn py_is_enabled() -> bool {
+    is_enabled()
+}
+
+/// Record a sent order for `orders_per_second` (Python binding).
+#[pyfunction]
+#[pyo3(name = "record_order")]
+fn py_record_order() {
+    record_order();
+}
+
+/// Current orders-per-second rate (Python binding).
+#[pyfunction]
+#[pyo3(name = "orders_per_second")]
+fn py_orders_per_second() -> f64 {
+    orders_per_second()
+}
+
+/// Set the `orders_per_second` window in milliseconds (Python binding).
+#[pyfunction]
+#[pyo3(name = "set_orders_rate_window")]
+fn py_set_orders_rate_window(window_ms: u64) -> PyResult<()> {
+    Ok(set_orders_rate_window(Duration::from_millis(window_ms))?)
+}
+
+/// Make unregistered counter names an error (Python binding).
+#[pyfunction]
+#[pyo3(name = "set_strict_mode")]
+fn py_set_strict_mode(strict: bool) {
+    set_strict_mode(strict);
+}
+
+/// Emit a counter metric, raising `ValueError` on invalid values (Python binding).
+#[pyfunction]
+#[pyo3(name = "emit_metric_checked")]
+fn py_emit_metric_checked(name: &str, value: f64) -> PyResult<()> {
+    Ok(emit_metric_checked(name, value)?)
+}
+
+/// Increment a labeled counter; `labels` is a dict of strings (Python binding).
+///
+/// Raises `ValueError` for invalid names, labels or values, or a label set
+/// that differs from the first call.
+#[pyfunction]
+#[pyo3(name = "emit_counter")]
+fn py_emit_counter(name: &str, labels: HashMap<String, String>, value: f64) -> PyResult<()> {
+    let labels: Vec<(&str, &str)> = labels
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+    Ok(emit_counter(name, &labels, value)?)
+}
+
+/// Set a gauge; `labels` is a dict of strings (Python binding).
+///
+/// Raises `ValueError` for invalid names, labels or values, or a label set
+/// that differs from the first call.
+#[pyfunction]
+#[pyo3(name = "set_gauge")]
+fn py_set_gauge(name: &str, labels: HashMap<String, String>, value: f64) -> PyResult<()> {
+    let labels: Vec<(&str, &str)> = labels
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+    Ok(set_gauge(name, &labels, value)?)
+}
+
+/// Remove a counter, gauge, histogram or summary by name; `False` if there
+/// was none or it is built in (Python binding).
+#[pyfunction]
+#[pyo3(name = "unregister_metric")]
+fn py_unregister_metric(name: &str) -> bool {
+    unregister_metric(name)
+}
+
+/// Delete an operation's latency series; `False` if it had none (Python
+/// binding).
+#[pyfunction]
+#[pyo3(name = "remove_latency_series")]
+fn py_remove_latency_series(operation: &str) -> bool {
+    remove_latency_series(operation)
+}
+
+/// Remove a counter by name, raising `ValueError` if it does not exist or
+/// is built in (Python binding).
+#[pyfunction]
+#[pyo3(name = "unregister_counter")]
+fn py_unregister_counter(name: &str) -> PyResult<()> {
+    Ok(unregister_counter(name)?)
+}
+
+/// Zero counters and clear observations in the registry (Python binding).
+#[pyfunction]
+#[pyo3(name = "reset_metrics")]
+fn py_reset_metrics() {
+    reset_metrics();
+}
+
+/// Set the maximum number of distinct counter names (Python binding).
+#[pyfunction]
+#[pyo3(name = "set_counter_limit")]
+fn py_set_counter_limit(limit: usize) {
+    set_counter_limit(limit);
+}
+
+/// Register a histogram with custom buckets (Python binding).
+///
+/// Raises `ValueError` for invalid names or buckets, or if `name` exists
+/// with different buckets.
+#[pyfunction]
+#[pyo3(name = "register_histogram")]
+fn py_register_histogram(name: &str, buckets: Vec<f64>) -> PyResult<()> {
+    Ok(register_histogram(name, &buckets)?)
+}
+
+/// Observe a value in a registered histogram (Python binding).
+#[pyfunction]
+#[pyo3(name = "observe_histogram")]
+fn py_observe_histogram(name: &str, value: f64) -> PyResult<()> {
+    Ok(observe_histogram(name, value)?)
+}
+
+/// Register a summary; `objectives` is a list of `(quantile, error)` tuples
+/// (Python binding).
+#[pyfunction]
+#[pyo3(name = "register_summary")]
+fn py_register_summary(name: &str, objectives: Vec<(f64, f64)>) -> PyResult<()> {
+    Ok(register_summary(name, &objectives)?)
+}
+
+/// Observe a value in a registered summary (Python binding).
+#[pyfunction]
+#[pyo3(name = "observe_summary")]
+fn py_observe_summary(name: &str, value: f64) -> PyResult<()> {
+    Ok(observe_summary(name, value)?)
+}
+
+/// Set the latency histogram buckets; raises `ValueError` once latency has
+/// been recorded (Python binding).
+#[pyfunction]
+#[pyo3(name = "configure_latency_buckets")]
+fn py_configure_latency_buckets(buckets: Vec<f64>) -> PyResult<()> {
+    Ok(configure_latency_buckets(&buckets)?)
+}
+
+/// The latency histogram bucket bounds in seconds (Python binding).
+#[pyfunction]
+#[pyo3(name = "latency_buckets")]
+fn py_latency_buckets() -> Vec<f64> {
+    latency_buckets()
+}
+
+/// Record operation latency in microseconds (Python binding).
+#[pyfunction]
+#[pyo3(name = "record_latency")]
+fn py_record_latency(operation: &str, duration_us: f64) {
+    record_latency(operation, duration_us);
+}
+
+/// Record only 1 in `1 / rate` latency samples of an operation (Python binding).
+#[pyfunction]
+#[pyo3(name = "set_sampling")]
+fn py_set_sampling(operation: &str, rate: f64) -> PyResult<()> {
+    Ok(set_sampling(operation, rate)?)
+}
+
+/// Latency samples of an operation skipped by sampling (Python binding).
+#[pyfunction]
+#[pyo3(name = "sampled_count")]
+fn py_sampled_count(operation: &str) -> u64 {
+    sampled_count(operation)
+}
+
+/// Register a counter that `take_and_reset_counter` can drain (Python binding).
+#[pyfunction]
+#[pyo3(name = "register_resettable_counter")]
+fn py_register_resettable_counter(name: &str, help: &str) -> PyResult<()> {
+    Ok(register_resettable_counter(name, help)?)
+}
+
+/// Read and zero a resettable counter; `None` if unknown (Python binding).
+#[pyfunction]
+#[pyo3(name = "take_and_reset_counter")]
+fn py_take_and_reset_counter(name: &str) -> Option<f64> {
+    take_and_reset_counter(name)
+}
+
+/// Record operation latency with a trace ID exemplar (Python binding).
+#[pyfunction]
+#[pyo3(name = "record_latency_with_exemplar")]
+fn py_record_latency_with_exemplar(operation: &str, duration_us: f64, trace_id: &str) {
+    record_latency_with_exemplar(operation, duration_us, trace_id);
+}
+
+/// Prefix every metric name; call before anything else (Python binding).
+#[pyfunction]
+#[pyo3(name = "set_namespace")]
+fn py_set_namespace(ns: &str) -> PyResult<()> {
+    Ok(set_namespace(ns)?)
+}
+
+/// Tag every series with fixed labels from a dict; call before anything
+/// else (Python binding).
+#[pyfunction]
+#[pyo3(name = "set_global_labels")]
+fn py_set_global_labels(labels: HashMap<String, String>) -> PyResult<()> {
+    let labels: Vec<(&str, &str)> = labels
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+    Ok(set_global_labels(&labels)?)
+}
+
+/// Record latency with `venue`/`symbol` labels from a dict (Python binding).
+///
+/// Raises `ValueError` for unknown label keys or invalid values.
+#[pyfunction]
+#[pyo3(name = "record_latency_labeled")]
+fn py_record_latency_labeled(
+    operation: &str,
+    labels: HashMap<String, String>,
+    duration_us: f64,
+) -> PyResult<()> {
+    let labels: Vec<(&str, &str)> = labels
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+    Ok(record_latency_labeled(operation, &labels, duration_us)?)
+}
+
+/// Cap distinct operations of the latency histogram (Python binding).
+#[pyfunction]
+#[pyo3(name = "set_operation_limit")]
+fn py_set_operation_limit(limit: usize) {
+    set_operation_limit(limit);
+}
+
+/// Delete an operation's latency series and free its slot (Python binding).
+#[pyfunction]
+#[pyo3(name = "remove_operation")]
+fn py_remove_operation(operation: &str) -> bool {
+    remove_operation(operation)
+}
+
+/// Delete latency series idle for longer than `max_idle_secs`, returning
+/// their operations (Python binding).
+#[pyfunction]
+#[pyo3(name = "prune_idle_operations")]
+fn py_prune_idle_operations(max_idle_secs: u64) -> Vec<String> {
+    prune_idle_operations(max_idle_secs)
+}
+
+/// Cap distinct values per labeled-latency label (Python binding).
+#[pyfunction]
+#[pyo3(name = "set_label_value_limit")]
+fn py_set_label_value_limit(limit: usize) {
+    set_label_value_limit(limit);
+}
+
+/// Record operation latency in nanoseconds (Python binding).
+#[pyfunction]
+#[pyo3(name = "record_latency_ns")]
+fn py_record_latency_ns(operation: &str, duration_ns: u64) {
+    record_latency_ns(operation, duration_ns);
+}
+
+/// Count a failed operation (Python binding).
+#[pyfunction]
+#[pyo3(name = "record_error")]
+fn py_record_error(operation: &str, kind: &str) {
+    record_error(operation, kind);
+}
+
+/// Record latency and count a failure when `ok` is false (Python binding).
+#[pyfunction]
+#[pyo3(name = "record_result")]
+fn py_record_result(operation: &str, duration_us: f64, ok: bool) {
+    record_result(operation, duration_us, ok);
+}
+
+/// Approximate latency percentile in microseconds, or `None` (Python binding).
+#[pyfunction]
+#[pyo3(name = "latency_percentile")]
+fn py_latency_percentile(operation: &str, quantile: f64) -> Option<f64> {
+    latency_percentile(operation, quantile)
+}
+
+/// Render metrics in the Prometheus text format (Python binding).
+///
+/// The GIL is released while rendering, so other Python threads (e.g. an
+/// asyncio loop) keep running during large scrapes.
+#[pyfunction]
+#[pyo3(name = "get_metrics")]
+fn py_get_metrics(py: Python<'_>) -> String {
+    py.allow_threads(get_metrics)
+}
+
+/// Render metrics, reusing a rendering at most `max_age_ms` old (Python
+/// binding). Releases the GIL like `get_metrics`.
+#[pyfunction]
+#[pyo3(name = "get_metrics_cached")]
+fn py_get_metrics_cached(py: Python<'_>, max_age_ms: u64) -> String {
+    py.allow_threads(|| get_metrics_cached(max_age_ms))
+}
+
+/// Python module for TinyWindow Rust telemetry.
+#[pymodule]
+fn tinywindow_rust_telemetry(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add(
+        "TelemetryError",
+        m.py().get_type_bound::<error::py::TelemetryError>(),
+    )?;
+    m.add_function(wrap_pyfunction!(py_emit_metric, m)?)?;
+    m.add_function(wrap_pyfunction!(py_set_strict_mode, m)?)?;
+    m.add_function(wrap_pyfunction!(py_set_enabled, m)?)?;
+    m.add_function(wrap_pyfunction!(py_is_enabled, m)?)?;
+    m.add_function(wrap_pyfunction!(py_emit_metric_checked, m)?)?;
+    m.add_function(wrap_pyfunction!(py_record_order, m)?)?;
+    m.add_function(wrap_pyfunction!(py_orders_per_second, m)?)?;
+    m.add_function(wrap_pyfunction!(py_set_orders_rate_window, m)?)?;
+    m.add_function(wrap_pyfunction!(py_emit_counter, m)?)?;
+    m.add_function(wrap_pyfunction!(py_unregister_counter, m)?)?;
+    m.add_function(wrap_pyfunction!(py_unregister_metric, m)?)?;
+    m.add_function(wrap_pyfunction!(py_remove_latency_series, m)?)?;
+    m.add_function(wrap_pyfunction!(py_set_gauge, m)?)?;
+    m.add_function(wrap_pyfunction!(py_set_counter_limit, m)?)?;
+    m.add_function(wrap_pyfunction!(py_reset_metrics, m)?)?;
+    m.add_function(wrap_pyfunction!(py_register_histogram, m)?)?;
+    m.add_function(wrap_pyfunction!(py_observe_histogram, m)?)?;
+    m.add_function(wrap_pyfunction!(py_register_summary, m)?)?;
+    m.add_function(wrap_pyfunction!(py_observe_summary, m)?)?;
+    m.add_function(wrap_pyfunction!(py_configure_latency_buckets, m)?)?;
+    m.add_function(wrap_pyfunction!(py_latency_buckets, m)?)?;
+    m.add_function(wrap_pyfunction!(py_record_latency, m)?)?;
+    m.add_function(wrap_pyfunction!(py_record_latency_ns, m)?)?;
+    m.add_function(wrap_pyfunction!(py_set_namespace, m)?)?;
+    m.add_function(wrap_pyfunction!(py_set_sampling, m)?)?;
+    m.add_function(wrap_pyfunction!(py_sampled_count, m)?)?;
+    m.add_function(wrap_pyfunction!(py_register_resettable_counter, m)?)?;
+    m.add_function(wrap_pyfunction!(py_take_and_reset_counter, m)?)?;
+    m.add_function(wrap_pyfunction!(py_record_latency_with_exemplar, m)?)?;
+    m.add_function(wrap_pyfunction!(
+        openmetrics::py_get_metrics_openmetrics,
+        m
+    )?)?;
+    m.add_function(wrap_pyfunction!(py_set_global_labels, m)?)?;
+    m.add_function(wrap_pyfunction!(py_record_latency_labeled, m)?)?;
+    m.add_function(wrap_pyfunction!(py_set_operation_limit, m)?)?;
+    m.add_function(wrap_pyfunction!(py_remove_operation, m)?)?;
+    m.add_function(wrap_pyfunction!(py_prune_idle_operations, m)?)?;
+    m.add_function(wrap_pyfunction!(py_set_label_value_limit, m)?)?;
+    m.add_function(wrap_pyfunction!(py_record_error, m)?)?;
+    m.add_function(wrap_pyfunction!(py_record_result, m)?)?;
+    m.add_function(wrap_pyfunction!(py_latency_percentile, m)?)?;
+    m.add_function(wrap_pyfunction!(py_get_metrics, m)?)?;
+    m.add_function(wrap_pyfunction!(py_get_metrics_cached, m)?)?;
+    m.add_function(wrap_pyfunction!(filter::py_get_metrics_filtered, m)?)?;
+    m.add_function(wrap_pyfunction!(py_get_metrics_protobuf, m)?)?;
+    m.add_function(wrap_pyfunction!(process::py_init_process_metrics, m)?)?;
+    m.add_function(wrap_pyfunction!(process::py_register_build_info, m)?)?;
+    m.add_function(wrap_pyfunction!(process::py_register_process_metrics, m)?)?;
+    m.add_function(wrap_pyfunction!(json::py_get_metrics_json, m)?)?;
+    m.add_function(wrap_pyfunction!(csv::py_get_metrics_csv, m)?)?;
+    m.add_function(wrap_pyfunction!(csv::py_dump_metrics_csv, m)?)?;
+    m.add_function(wrap_pyfunction!(introspect::py_list_metrics, m)?)?;
+    m.add_function(wrap_pyfunction!(introspect::py_get_counter_value, m)?)?;
+    m.add_function(wrap_pyfunction!(introspect::py_get_histogram_count, m)?)?;
+    m.add_function(wrap_pyfunction!(introspect::py_get_histogram_sum, m)?)?;
+    m.add_function(wrap_pyfunction!(introspect::py_histogram_quantile, m)?)?;
+    m.add_function(wrap_pyfunction!(introspect::py_latency_quantile, m)?)?;
+    m.add_function(wrap_pyfunction!(server::py_serve_metrics, m)?)?;
+    m.add_function(wrap_pyfunction!(push::py_push_metrics, m)?)?;
+    m.add_function(wrap_pyfunction!(influx::py_get_metrics_influx, m)?)?;
+    m.add_function(wrap_pyfunction!(influx::py_push_influx, m)?)?;
+    m.add_function(wrap_pyfunction!(statsd::py_enable_statsd, m)?)?;
+    m.add_function(wrap_pyfunction!(statsd::py_disable_statsd, m)?)?;
+    m.add_class::<server::PyMetricsServer>()?;
+    m.add_function(wrap_pyfunction!(snapshot::py_snapshot_to_file, m)?)?;
+    m.add_function(wrap_pyfunction!(snapshot::py_restore_from_file, m)?)?;
+    m.add_function(wrap_pyfunction!(snapshot::py_start_snapshotter, m)?)?;
+    m.add_class::<snapshot::PySnapshotter>()?;
+    m.add_function(wrap_pyfunction!(threshold::py_register_threshold, m)?)?;
+    m.add_function(wrap_pyfunction!(threshold::py_clear_thresholds, m)?)?;
+    m.add_function(wrap_pyfunction!(health::py_health_report, m)?)?;
+    m.add_function(wrap_pyfunction!(health::py_configure_health, m)?)?;
+    m.add_function(wrap_pyfunction!(subregistry::py_subregistry, m)?)?;
+    m.add_class::<subregistry::PySubRegistry>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decode length-delimited protobuf output into its metric families.
+    pub(crate) fn decode_protobuf(bytes: &[u8]) -> Vec<prometheus::proto::MetricFamily> {
+        let mut input = protobuf::CodedInputStream::from_bytes(bytes);
+        let mut families = Vec::new();
+        while !input.eof().unwrap() {
+            families.push(input.read_message().unwrap());
+        }
+        families
+    }
+
+    #[test]
+    fn test_emit_metric_increments_orders_total() {
+        let before = ORDERS_TOTAL.get();
+        emit_metric("orders_total", 1.0);
+        // Other tests may increment concurrently; tests/emit_metric_value.rs
+        // checks the exact count in its own process.
+        assert!(ORDERS_TOTAL.get() > before);
+        assert!(get_metrics().contains("orders_total"));
+    }
+
+    #[test]
+    fn test_emit_metric_ignores_invalid_values() {
+        emit_metric("orders_total", 1.0);
+        let before = ORDERS_TOTAL.get();
+        emit_metric("orders_total", -3.0);
+        emit_metric("orders_total", f64::NAN);
+        // Other tests may increment concurrently, but never decrement.
+        assert!(ORDERS_TOTAL.get() >= before);
+    }
+
+    #[test]
+    fn test_emit_metric_checked_rejects_invalid_values() {
+        for value in [-1.0, 0.5, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(matches!(
+                emit_metric_checked("orders_total", value),
+                Err(TelemetryError::InvalidValue { .. })
+            ));
+        }
+        assert_eq!(emit_metric_checked("orders_total", 0.0), Ok(()));
+    }
+
+    #[test]
+    fn test_emit_metric_creates_counter_on_first_use() {
+        emit_metric("test_fills_total", 2.0);
+        emit_metric("test_fills_total", 3.0);
+        assert!(get_metrics().contains("test_fills_total 5"));
+    }
+
+    #[test]
+    fn test_emit_metric_rejects_invalid_names() {
+        for name in ["", "9lives_total", "bad-name", "bad name", "bad\"} 1\nfake"] {
+            assert_eq!(
+                emit_metric_checked(name, 1.0),
+                Err(TelemetryError::InvalidMetricName(name.to_string()))
+            );
+        }
+        assert!(!get_metrics().contains("fake"));
+    }
+
+    #[test]
+    fn test_emit_metric_rejects_non_counter_name() {
+        assert!(matches!(
+            emit_metric_checked("latency_seconds", 1.0),
+            Err(TelemetryError::Registry(_))
+        ));
+    }
+
+    #[test]
+    fn test_register_counter_vec() {
+        let counter =
+            register_counter_vec("test_labeled_total", "Test counter", &["kind"]).unwrap();
+        counter.with_label_values(&["a"]).inc();
+        assert!(get_metrics().contains("test_labeled_total{kind=\"a\"} 1"));
+    }
+
+    #[test]
+    fn test_emit_metric_reaches_registered_counter() {
+        let counter = register_counter("test_preregistered_total", "Test counter").unwrap();
+        emit_metric("test_preregistered_total", 4.0);
+        assert_eq!(counter.get(), 4);
+    }
+
+    #[test]
+    fn test_concurrent_first_emit_registers_one_counter() {
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                std::thread::spawn(|| {
+                    for _ in 0..100 {
+                        emit_metric_checked("test_hammered_total", 1.0).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let output = get_metrics();
+        assert_eq!(
+            output.matches("# TYPE test_hammered_total counter").count(),
+            1
+        );
+        assert!(output.contains("test_hammered_total 800"));
+    }
+
+    #[test]
+    fn test_emit_counter_separates_label_values() {
+        emit_counter(
+            "test_venue_orders_total",
+            &[("venue", "nyse"), ("side", "buy")],
+            1.0,
+        )
+        .unwrap();
+        emit_counter(
+            "test_venue_orders_total",
+            &[("side", "buy"), ("venue", "lse")],
+            2.0,
+        )
+        .unwrap();
+        emit_counter(
+            "test_venue_orders_total",
+            &[("venue", "nyse"), ("side", "buy")],
+            1.0,
+        )
+        .unwrap();
+
+        let output = get_metrics();
+        assert!(output.contains("test_venue_orders_total{side=\"buy\",venue=\"nyse\"} 2"));
+        assert!(output.contains("test_venue_orders_total{side=\"buy\",venue=\"lse\"} 2"));
+    }
+
+    #[test]
+    fn test_emit_counter_rejects_mismatched_label_keys() {
+        emit_counter("test_fixed_keys_total", &[("venue", "nyse")], 1.0).unwrap();
+        for labels in [
+            &[("side", "buy")][..],
+            &[("venue", "nyse"), ("side", "buy")],
+            &[],
+        ] {
+            assert!(matches!(
+                emit_counter("test_fixed_keys_total", labels, 1.0),
+                Err(TelemetryError::LabelMismatch { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_emit_counter_rejects_label_injection() {
+        for labels in [
+            &[("venue", "nyse\"} 1\nfake_metric{")][..],
+            &[("bad\"key", "nyse")],
+            &[("venue", "")],
+        ] {
+            assert!(matches!(
+                emit_counter("test_injected_total", labels, 1.0),
+                Err(TelemetryError::InvalidLabel(_))
+            ));
+        }
+        assert!(!get_metrics().contains("fake_metric"));
+    }
+
+    #[test]
+    fn test_register_histogram_uses_custom_buckets() {
+        register_histogram("test_batch_seconds", &[1.0, 60.0, 3600.0]).unwrap();
+        observe_histogram("test_batch_seconds", 90.0).unwrap();
+
+        let output = get_metrics();
+        assert!(output.contains("test_batch_seconds_bucket{le=\"1\"} 0"));
+        assert!(output.contains("test_batch_seconds_bucket{le=\"60\"} 0"));
+        assert!(output.contains("test_batch_seconds_bucket{le=\"3600\"} 1"));
+        assert!(output.contains("test_batch_seconds_bucket{le=\"+Inf\"} 1"));
+    }
+
+    #[test]
+    fn test_register_histogram_same_buckets_is_noop() {
+        register_histogram("test_rereg_seconds", &[0.5, 5.0]).unwrap();
+        observe_histogram("test_rereg_seconds", 1.0).unwrap();
+        register_histogram("test_rereg_seconds", &[0.5, 5.0]).unwrap();
+        assert!(get_metrics().contains("test_rereg_seconds_count 1"));
+
+        assert!(matches!(
+            register_histogram("test_rereg_seconds", &[0.5, 50.0]),
+            Err(TelemetryError::BucketMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_register_histogram_rejects_invalid_input() {
+        for buckets in [&[][..], &[1.0, 1.0], &[2.0, 1.0], &[1.0, f64::NAN]] {
+            assert!(matches!(
+                register_histogram("test_bad_buckets_seconds", buckets),
+                Err(TelemetryError::InvalidBuckets(_))
+            ));
+        }
+        assert_eq!(
+            register_histogram("bad-name", &[1.0]),
+            Err(TelemetryError::InvalidMetricName("bad-name".to_string()))
+        );
+        assert!(matches!(
+            register_histogram("orders_total", &[1.0]),
+            Err(TelemetryError::Registry(_))
+        ));
+    }
+
+    #[test]
+    fn test_observe_histogram_requires_registration() {
+        assert_eq!(
+            observe_histogram("test_unregistered_seconds", 1.0),
+            Err(TelemetryError::UnknownMetric(
+                "test_unregistered_seconds".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_record_latency_appears_in_output() {
+        record_latency("test_op", 250.0);
+        let output = get_metrics();
+        assert!(output.contains("latency_seconds_bucket{operation=\"test_op\""));
+        assert!(output.contains("latency_seconds_count{operation=\"test_op\"} "));
+    }
+
+    #[test]
+    fn test_summary_reports_quantiles() {
+        register_summary("test_fill_seconds", &[(0.5, 0.05), (0.99, 0.001)]).unwrap();
+        // A skewed distribution: 1..=99 plus one large outlier.
+        for value in 1..=99 {
+            observe_summary("test_fill_seconds", f64::from(value)).unwrap();
+        }
+        observe_summary("test_fill_seconds", 1000.0).unwrap();
+
+        let output = get_metrics();
+        assert!(output.contains("# TYPE test_fill_seconds summary"));
+        assert!(output.contains("test_fill_seconds_count 100"));
+        let p50: f64 = output
+            .lines()
+            .find_map(|line| line.strip_prefix("test_fill_seconds{quantile=\"0.5\"} "))
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((1.0..=1000.0).contains(&p50));
+        assert_eq!(p50, 50.0);
+        assert!(output.contains("test_fill_seconds{quantile=\"0.99\"} 99"));
+    }
+
+    #[test]
+    fn test_register_summary_mirrors_histogram_rules() {
+        register_summary("test_resummary_seconds", &[(0.9, 0.01)]).unwrap();
+        register_summary("test_resummary_seconds", &[(0.9, 0.01)]).unwrap();
+        assert!(matches!(
+            register_summary("test_resummary_seconds", &[(0.5, 0.01)]),
+            Err(TelemetryError::ObjectiveMismatch { .. })
+        ));
+        for objectives in [&[][..], &[(1.5, 0.01)], &[(0.5, -0.1)]] {
+            assert!(matches!(
+                register_summary("test_bad_summary_seconds", objectives),
+                Err(TelemetryError::InvalidObjectives(_))
+            ));
+        }
+        assert_eq!(
+            observe_summary("test_missing_summary", 1.0),
+            Err(TelemetryError::UnknownMetric(
+                "test_missing_summary".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_default_latency_buckets_reach_ten_seconds() {
+        assert_eq!(latency_buckets().last(), Some(&10.0));
+        record_latency("test_slow_op", 7_000_000.0);
+        let output = get_metrics();
+        assert!(output.contains("latency_seconds_bucket{operation=\"test_slow_op\",le=\"5\"} 0"));
+        assert!(output.contains("latency_seconds_bucket{operation=\"test_slow_op\",le=\"10\"} 1"));
+    }
+
+    #[test]
+    fn test_configure_latency_buckets_validates_before_use() {
+        assert!(matches!(
+            configure_latency_buckets(&[1.0, 0.5]),
+            Err(TelemetryError::InvalidBuckets(_))
+        ));
+        record_latency("test_op", 1.0);
+        assert_eq!(
+            configure_latency_buckets(&[1.0, 60.0]),
+            Err(TelemetryError::LatencyInUse)
+        );
+        assert_eq!(latency_buckets(), LATENCY_BUCKETS);
+    }
+
+    #[test]
+    fn test_sampling_records_one_in_n() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.set_sampling("order_send", 0.1).unwrap();
+        for _ in 0..100 {
+            handle.record_latency("order_send", 250.0);
+        }
+        handle.record_latency("order_cancel", 250.0);
+
+        let count =
+            |operation| handle.get_histogram_count("latency_seconds", &[("operation", operation)]);
+        assert_eq!(count("order_send"), Some(10));
+        assert_eq!(handle.sampled_count("order_send"), 90);
+        assert_eq!(count("order_cancel"), Some(1));
+        assert_eq!(handle.sampled_count("order_cancel"), 0);
+    }
+
+    #[test]
+    fn test_sampling_rate_bounds() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.set_sampling("order_send", 0.0).unwrap();
+        handle.record_duration("order_send", Duration::from_micros(5));
+        assert_eq!(
+            handle.get_histogram_count("latency_seconds", &[("operation", "order_send")]),
+            None
+        );
+        assert_eq!(handle.sampled_count("order_send"), 1);
+
+        handle.set_sampling("order_send", 1.0).unwrap();
+        for _ in 0..3 {
+            handle.record_latency("order_send", 5.0);
+        }
+        assert_eq!(
+            handle.get_histogram_count("latency_seconds", &[("operation", "order_send")]),
+            Some(3)
+        );
+
+        for rate in [-0.1, 1.5, f64::NAN] {
+            assert!(matches!(
+                handle.set_sampling("order_send", rate),
+                Err(TelemetryError::InvalidValue { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_take_and_reset_counter() {
+        register_resettable_counter("test_pulled_total", "Drained every scrape").unwrap();
+        for _ in 0..5 {
+            emit_metric("test_pulled_total", 1.0);
+        }
+
+        assert_eq!(take_and_reset_counter("test_pulled_total"), Some(5.0));
+        assert_eq!(take_and_reset_counter("test_pulled_total"), Some(0.0));
+        assert!(get_metrics().contains("\ntest_pulled_total 0\n"));
+        assert_eq!(take_and_reset_counter("orders_total"), None);
+        assert_eq!(take_and_reset_counter("test_never_registered_total"), None);
+        assert!(matches!(
+            register_resettable_counter("test_pulled_total", "again"),
+            Err(TelemetryError::Registry(_))
+        ));
+    }
+
+    #[test]
+    fn test_try_record_latency_rejects_invalid_operation() {
+        for operation in ["", "order send", "order\"}", &"x".repeat(65)] {
+            assert!(matches!(
+                try_record_latency(operation, 1.0),
+                Err(TelemetryError::InvalidLabel(_))
+            ));
+        }
+        assert_eq!(try_record_latency("test_try_op", 1.0), Ok(()));
+
+        let handle = TelemetryHandle::new_isolated();
+        assert!(matches!(
+            handle.try_record_duration("bad op", Duration::from_micros(1)),
+            Err(TelemetryError::InvalidLabel(_))
+        ));
+        assert!(matches!(
+            handle.try_record_error("order_send", "time out"),
+            Err(TelemetryError::InvalidLabel(_))
+        ));
+        handle.record_latency("bad op", 1.0);
+        assert_eq!(
+            handle.get_histogram_count("latency_seconds", &[("operation", "bad op")]),
+            None
+        );
+        assert!(handle.try_get_metrics().is_ok());
+    }
+
+    #[test]
+    fn test_namespace_prefixes_every_metric() {
+        let handle = TelemetryHandle::with_namespace("tw_exec").unwrap();
+        handle.emit_metric("orders_total", 2.0);
+        handle.record_latency("order_send", 250.0);
+
+        let metrics = handle.get_metrics();
+        assert!(metrics.contains("# HELP tw_exec_orders_total Total number of orders processed"));
+        assert!(metrics.contains("# TYPE tw_exec_orders_total counter"));
+        assert!(metrics.contains("\ntw_exec_orders_total 2"));
+        assert!(metrics.contains("tw_exec_latency_seconds_count{operation=\"order_send\"} 1"));
+        assert!(!metrics.contains("\norders_total"));
+
+        assert!(handle
+            .get_metrics_json()
+            .contains("\"tw_exec_orders_total\""));
+        assert!(handle
+            .list_metrics()
+            .iter()
+            .any(|info| info.name == "tw_exec_latency_seconds"));
+        assert_eq!(handle.get_counter_value("tw_exec_orders_total"), Some(2.0));
+        assert_eq!(handle.get_counter_value("orders_total"), None);
+        assert_eq!(handle.latency_quantile("order_send", 1.0), Some(0.0005));
+
+        assert!(matches!(
+            TelemetryHandle::with_namespace("tw-exec"),
+            Err(TelemetryError::InvalidMetricName(_))
+        ));
+    }
+
+    #[test]
+    fn test_global_labels_on_every_series() {
+        let handle =
+            TelemetryHandle::with_global_labels(&[("env", "prod"), ("instance", "node-1")])
+                .unwrap();
+        handle.emit_metric("orders_total", 1.0);
+        handle.emit_metric("handle_fills_total", 1.0);
+        handle.record_latency("order_send", 250.0);
+
+        let metrics = handle.get_metrics();
+        for prefix in [
+            "orders_total{",
+            "handle_fills_total{",
+            "latency_seconds_count{",
+        ] {
+            let line = metrics
+                .lines()
+                .find(|line| line.starts_with(prefix))
+                .unwrap_or_else(|| panic!("no {prefix} series in {metrics}"));
+            assert!(line.contains("env=\"prod\""), "{line}");
+            assert!(line.contains("instance=\"node-1\""), "{line}");
+        }
+
+        assert_eq!(handle.get_counter_value("orders_total"), Some(1.0));
+        assert_eq!(
+            handle.get_histogram_count("latency_seconds", &[("operation", "order_send")]),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_global_labels_rejects_bad_labels() {
+        for labels in [
+            &[("env", "pr od")][..],
+            &[("env", "")],
+            &[("1env", "prod")],
+            &[("__env", "prod")],
+            &[("operation", "prod")],
+            &[("env", "prod"), ("env", "dev")],
+        ] {
+            assert!(matches!(
+                TelemetryHandle::with_global_labels(labels),
+                Err(TelemetryError::InvalidLabel(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_labeled_latency_series() {
+        let handle = TelemetryHandle::new_isolated();
+        handle
+            .record_latency_labeled("order_send", &[("venue", "XNAS")], 250.0)
+            .unwrap();
+        handle
+            .record_latency_labeled(
+                "order_send",
+                &[("symbol", "AAPL"), ("venue", "XNAS")],
+                250.0,
+            )
+            .unwrap();
+
+        assert_eq!(
+            handle.get_histogram_count(
+                "labeled_latency_seconds",
+                &[
+                    ("operation", "order_send"),
+                    ("venue", "XNAS"),
+                    ("symbol", "")
+                ]
+            ),
+            Some(1)
+        );
+        assert!(handle.get_metrics().contains(
+            "labeled_latency_seconds_count{operation=\"order_send\",symbol=\"AAPL\",venue=\"XNAS\"} 1"
+        ));
+        assert!(!handle.get_metrics().contains("\nlatency_seconds_count"));
+    }
+
+    #[test]
+    fn test_labeled_latency_rejects_bad_labels() {
+        let handle = TelemetryHandle::new_isolated();
+        for labels in [
+            &[("venue", "XNAS\"} 1\n")][..],
+            &[("account", "A1")],
+            &[("operation", "other_op")],
+        ] {
+            assert!(matches!(
+                handle.record_latency_labeled("order_send", labels, 1.0),
+                Err(TelemetryError::InvalidLabel(_))
+            ));
+        }
+        assert!(handle
+            .record_latency_labeled("order send", &[], 1.0)
+            .is_err());
+        assert!(!handle
+            .get_metrics()
+            .contains("labeled_latency_seconds_count"));
+    }
+
+    #[test]
+    fn test_labeled_latency_caps_label_values() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.set_label_value_limit(2);
+        for symbol in ["AAPL", "MSFT", "GOOG", "AMZN", "AAPL"] {
+            handle
+                .record_latency_labeled("order_send", &[("symbol", symbol)], 1.0)
+                .unwrap();
+        }
+
+        let count = |symbol| {
+            handle.get_histogram_count(
+                "labeled_latency_seconds",
+                &[
+                    ("operation", "order_send"),
+                    ("venue", ""),
+                    ("symbol", symbol),
+                ],
+            )
+        };
+        assert_eq!(count("AAPL"), Some(2));
+        assert_eq!(count("MSFT"), Some(1));
+        assert_eq!(count(OTHER_LABEL_VALUE), Some(2));
+        assert_eq!(count("GOOG"), None);
+    }
+
+    #[test]
+    fn test_nanosecond_latency_lands_in_microsecond_bucket() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.record_latency_ns("order_gen", 1500);
+
+        let output = handle.get_metrics();
+        assert!(
+            output.contains("latency_seconds_bucket{operation=\"order_gen\",le=\"0.000001\"} 0")
+        );
+        assert!(
+            output.contains("latency_seconds_bucket{operation=\"order_gen\",le=\"0.0000025\"} 1")
+        );
+        assert_eq!(
+            handle.get_histogram_sum("latency_seconds", &[("operation", "order_gen")]),
+            Some(0.0000015)
+        );
+    }
+
+    #[test]
+    fn test_duration_and_nanosecond_paths_agree() {
+        let by_ns = TelemetryHandle::new_isolated();
+        let by_duration = TelemetryHandle::new_isolated();
+        for ns in [1, 1500, 999_999, 1_234_567_890, 12_000_000_001] {
+            by_ns.record_latency_ns("order_gen", ns);
+            by_duration.record_duration("order_gen", Duration::from_nanos(ns));
+        }
+        assert_eq!(by_ns.get_metrics(), by_duration.get_metrics());
+    }
+
+    #[test]
+    fn test_latency_percentile_interpolates_within_bucket() {
+        // Half the samples in (100us, 500us], half in (1ms, 5ms].
+        for _ in 0..50 {
+            record_latency("test_percentile", 200.0);
+            record_latency("test_percentile", 3000.0);
+        }
+        let p50 = latency_percentile("test_percentile", 0.5).unwrap();
+        assert!((100.0..=500.0).contains(&p50), "p50 was {p50}");
+        let p99 = latency_percentile("test_percentile", 0.99).unwrap();
+        assert!((1000.0..=5000.0).contains(&p99), "p99 was {p99}");
+        assert_eq!(latency_percentile("test_percentile", 7.0), Some(5000.0));
+        assert_eq!(latency_percentile("test_percentile", -1.0), Some(100.0));
+        assert_eq!(latency_percentile("test_percentile_unknown", 0.5), None);
+        assert_eq!(latency_percentile("test_percentile", f64::NAN), None);
+    }
+
+    #[test]
+    fn test_record_error_counts_by_operation_and_kind() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.record_error("order_send", "timeout");
+        handle.record_error("order_send", "timeout");
+        handle.record_error("order_send", "rejected");
+        handle.record_error("order send", "timeout");
+
+        let output = handle.get_metrics();
+        assert!(output.contains("errors_total{kind=\"timeout\",operation=\"order_send\"} 2"));
+        assert!(output.contains("errors_total{kind=\"rejected\",operation=\"order_send\"} 1"));
+        assert!(!output.contains("order send"));
+    }
+
+    #[test]
+    fn test_record_result_pairs_latency_and_errors() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.record_result("order_send", 250.0, true);
+        handle.record_result("order_send", 900.0, false);
+
+        assert_eq!(
+            handle.get_histogram_count("latency_seconds", &[("operation", "order_send")]),
+            Some(2)
+        );
+        assert_eq!(
+            handle
+                .errors_total()
+                .with_label_values(&["order_send", RESULT_ERROR_KIND])
+                .get(),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_record_error_reaches_global_registry() {
+        record_error("test_record_error_op", "timeout");
+        assert!(get_metrics()
+            .contains("errors_total{kind=\"timeout\",operation=\"test_record_error_op\"} 1"));
+    }
+
+    #[test]
+    fn test_record_latency_rejects_invalid_operation() {
+        record_latency("bad\"} 1\nfake_metric{", 1.0);
+        record_latency("", 1.0);
+        assert!(!get_metrics().contains("fake_metric"));
+    }
+
+    #[test]
+    fn test_unregister_counter_removes_it_from_output() {
+        register_counter("test_session_total", "Per-session counter")
+            .unwrap()
+            .inc();
+        assert!(get_metrics().contains("test_session_total 1"));
+
+        unregister_counter("test_session_total").unwrap();
+        assert!(!get_metrics().contains("test_session_total"));
+        assert_eq!(
+            unregister_counter("test_session_total"),
+            Err(TelemetryError::UnknownMetric(
+                "test_session_total".to_string()
+            ))
+        );
+
+        // The name is free again, starting from zero.
+        register_counter("test_session_total", "Per-session counter")
+            .unwrap()
+            .inc_by(2);
+        assert!(get_metrics().contains("test_session_total 2"));
+    }
+
+    #[test]
+    fn test_unregister_counter_refuses_builtin_metrics() {
+        emit_metric("orders_total", 1.0);
+        for name in ["orders_total", "telemetry_dropped_metrics_total"] {
+            assert_eq!(
+                unregister_counter(name),
+                Err(TelemetryError::BuiltinMetric(name.to_string()))
+            );
+        }
+        assert!(get_metrics().contains("\norders_total "));
+    }
+
+    #[test]
+    fn test_unregister_labeled_counter() {
+        emit_counter("test_session_fills_total", &[("venue", "nyse")], 1.0).unwrap();
+        unregister_counter("test_session_fills_total").unwrap();
+        assert!(!get_metrics().contains("test_session_fills_total"));
+    }
+
+    #[test]
+    fn test_unregister_counter_vec() {
+        register_counter_vec("test_session_rejects_total", "Rejects", &["venue"])
+            .unwrap()
+            .with_label_values(&["nyse"])
+            .inc();
+        assert!(get_metrics().contains("test_session_rejects_total{venue=\"nyse\"} 1"));
+
+        unregister_counter("test_session_rejects_total").unwrap();
+        assert!(!get_metrics().contains("test_session_rejects_total"));
+        assert_eq!(
+            unregister_counter("test_session_rejects_total"),
+            Err(TelemetryError::UnknownMetric(
+                "test_session_rejects_total".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_protobuf_output_round_trips() {
+        emit_metric("test_protobuf_total", 4.0);
+        let families = decode_protobuf(&get_metrics_protobuf());
+        let family = families
+            .iter()
+            .find(|family| family.get_name() == "test_protobuf_total")
+            .unwrap();
+        assert_eq!(
+            family.get_field_type(),
+            prometheus::proto::MetricType::COUNTER
+        );
+        assert_eq!(family.get_metric()[0].get_counter().get_value(), 4.0);
+    }
+
+    #[test]
+    fn test_set_gauge_overwrites_value() {
+        set_gauge("test_queue_depth", &[("venue", "nyse")], 4.0).unwrap();
+        set_gauge("test_queue_depth", &[("venue", "nyse")], -1.5).unwrap();
+        assert!(get_metrics().contains("test_queue_depth{venue=\"nyse\"} -1.5"));
+        assert!(matches!(
+            set_gauge("test_queue_depth", &[("side", "buy")], 1.0),
+            Err(TelemetryError::LabelMismatch { .. })
+        ));
+        assert!(matches!(
+            set_gauge("test_queue_depth", &[("venue", "nyse")], f64::NAN),
+            Err(TelemetryError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_register_counter() {
+        let counter = register_counter("test_registered_total", "Test counter").unwrap();
+        counter.inc();
+        assert!(get_metrics().contains("test_registered_total 1"));
+        assert!(register_counter("test_registered_total", "Duplicate").is_err());
+    }
+}
@@ -0,0 +1,346 @@
+//! Thread-local latency aggregation.
+//!
+//! When many threads record the same operation, every observation contends
+//! on the shared `latency_seconds` atomics. A [`ThreadLocalRecorder`] gives
+//! each thread its own `prometheus::LocalHistogram` per operation instead,
+//! and merges them into the shared histogram when flushed, either by
+//! [`flush`](ThreadLocalRecorder::flush) or by a background flusher started
+//! with [`start_flusher`](ThreadLocalRecorder::start_flusher). Scrapes
+//! therefore lag behind by at most the flush interval.
+//!
+//! Merging is exact: a thread's buffers are flushed when the thread exits,
+//! and flushes and exits serialize on a per-thread lock, so no observation
+//! is lost or counted twice. The exit flush runs in the thread's
+//! thread-local destructors, which finish before `JoinHandle::join`
+//! returns but may still be running when a `thread::scope` ends. The main
+//! thread never runs them, so flush before the process exits.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use prometheus::local::LocalHistogram;
+use prometheus::HistogramVec;
+
+use crate::operations::Operations;
+use crate::{validate_labels, TelemetryError};
+
+/// One thread's buffered observations, keyed by operation.
+type Shard = Mutex<HashMap<String, LocalHistogram>>;
+
+/// Source of [`ThreadLocalRecorder`] IDs, which key [`SHARDS`].
+static NEXT_RECORDER_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// The calling thread's shard for each recorder it has recorded into.
+    /// Dropping a shard (at thread exit) flushes its histograms.
+    static SHARDS: RefCell<HashMap<usize, Arc<Shard>>> = RefCell::default();
+}
+
+struct Inner {
+    id: usize,
+    latency: HistogramVec,
+    operations: Arc<Operations>,
+    /// Every thread's shard; dead ones are pruned on flush.
+    shards: Mutex<Vec<Weak<Shard>>>,
+}
+
+/// Records latencies into per-thread buffers merged on flush.
+///
+/// Feeds the same `latency_seconds{operation}` histogram as
+/// [`record_latency`](crate::record_latency), under the same
+/// [operation limit](crate::set_operation_limit), but without sampling,
+/// exemplars, StatsD mirroring or `latency_overflow_total`. Cheap to clone; clones share buffers.
+///
+/// Buffered operations count as recorded (for
+/// [`prune_idle_operations`](crate::prune_idle_operations)) when flushed.
+/// While telemetry is [disabled](crate::set_enabled) samples are dropped
+/// unbuffered; those buffered before are still merged by the next flush.
+#[derive(Clone)]
+pub struct ThreadLocalRecorder {
+    inner: Arc<Inner>,
+}
+
+impl ThreadLocalRecorder {
+    pub(crate) fn new(latency: HistogramVec, operations: Arc<Operations>) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                id: NEXT_RECORDER_ID.fetch_add(1, Ordering::Relaxed),
+                latency,
+                operations,
+                shards: Mutex::default(),
+            }),
+        }
+    }
+
+    /// Buffer a latency sample in the calling thread.
+    ///
+    /// Invalid operation names are dropped with a warning, as in
+    /// [`record_latency`](crate::record_latency).
+    pub fn record_latency(&self, operation: &str, duration_us: f64) {
+        if let Err(err) = self.try_record_latency(operation, duration_us) {
+            log::warn!("dropping latency sample: {err}");
+        }
+    }
+
+    /// [`record_latency`](Self::record_latency), returning
+    /// `Err(TelemetryError::InvalidLabel)` for an invalid operation name.
+    pub fn try_record_latency(
+        &self,
+        operation: &str,
+        duration_us: f64,
+    ) -> Result<(), TelemetryError> {
+        if !crate::is_enabled() {
+            return Ok(());
+        }
+        validate_labels(&[("operation", operation)])?;
+        let seconds = duration_us / 1e6;
+        let buffered = SHARDS.try_with(|shards| {
+            let mut shards = shards.borrow_mut();
+            let shard = shards
+                .entry(self.inner.id)
+                .or_insert_with(|| self.new_shard());
+            let mut histograms = shard.lock().unwrap();
+            if let Some(histogram) = histograms.get(operation) {
+                histogram.observe(seconds);
+                return;
+            }
+            // Keyed by label, so overflowing names share one buffer.
+            let label = self.inner.operations.admit(operation);
+            histograms
+                .entry(label.to_string())
+                .or_insert_with(|| self.inner.latency.with_label_values(&[label]).local())
+                .observe(seconds);
+        });
+        if buffered.is_err() {
+            // The thread is exiting and its buffers are gone.
+            let label = self.inner.operations.admit(operation);
+            self.inner
+                .latency
+                .with_label_values(&[label])
+                .observe(seconds);
+        }
+        Ok(())
+    }
+
+    fn new_shard(&self) -> Arc<Shard> {
+        let shard = Arc::default();
+        self.inner
+            .shards
+            .lock()
+            .unwrap()
+            .push(Arc::downgrade(&shard));
+        shard
+    }
+
+    /// Live shards, pruning those of exited threads.
+    fn shards(&self) -> Vec<Arc<Shard>> {
+        let mut shards = self.inner.shards.lock().unwrap();
+        shards.retain(|shard| shard.strong_count() > 0);
+        shards.iter().filter_map(Weak::upgrade).collect()
+    }
+
+    /// Merge every thread's buffered samples into `latency_seconds`.
+    pub fn flush(&self) {
+        for shard in self.shards() {
+            for (operation, histogram) in shard.lock().unwrap().iter() {
+                if histogram.get_sample_count() > 0 {
+                    self.inner.operations.touch(operation);
+                }
+                histogram.flush();
+            }
+        }
+    }
+
+    /// Drop every thread's buffered samples for `operation`, for when its
+    /// series is removed.
+    pub(crate) fn forget(&self, operation: &str) {
+        for shard in self.shards() {
+            if let Some(histogram) = shard.lock().unwrap().remove(operation) {
+                histogram.clear();
+            }
+        }
+    }
+
+    /// Drop every thread's buffered samples and its cached series, for
+    /// when `latency_seconds` is reset.
+    pub(crate) fn discard(&self) {
+        for shard in self.shards() {
+            let mut histograms = shard.lock().unwrap();
+            for histogram in histograms.values() {
+                histogram.clear();
+            }
+            histograms.clear();
+        }
+    }
+
+    /// Flush every `interval` on a background thread.
+    ///
+    /// The flusher flushes one last time when stopped.
+    pub fn start_flusher(&self, interval: Duration) -> FlusherHandle {
+        let recorder = self.clone();
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = std::thread::Builder::new()
+            .name("telemetry-flusher".to_string())
+            .spawn(move || loop {
+                let result = stopped.recv_timeout(interval);
+                recorder.flush();
+                if !matches!(result, Err(RecvTimeoutError::Timeout)) {
+                    return;
+                }
+            })
+            .expect("failed to spawn the telemetry flusher thread");
+        FlusherHandle {
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+/// A running background flusher; stops (after a final flush) when dropped.
+pub struct FlusherHandle {
+    stop: Sender<()>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FlusherHandle {
+    /// Flush one last time and stop the flusher thread.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            // A send error means the thread already exited.
+            let _ = self.stop.send(());
+            // The thread only flushes; a panic there has nothing to clean up.
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for FlusherHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crate::TelemetryHandle;
+
+    const THREADS: u64 = 16;
+    const SAMPLES: u64 = 10_000;
+
+    fn count(handle: &TelemetryHandle) -> Option<u64> {
+        handle.get_histogram_count("latency_seconds", &[("operation", "order_send")])
+    }
+
+    #[test]
+    fn test_merge_is_exact_across_threads() {
+        let handle = TelemetryHandle::new_isolated();
+        let recorder = handle.thread_local_recorder();
+
+        // Joined (not scoped) threads: `join` returns only after the
+        // thread-local destructors that flush the remainders have run.
+        let writers: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let recorder = recorder.clone();
+                thread::spawn(move || {
+                    for i in 0..SAMPLES {
+                        recorder.record_latency("order_send", 250.0);
+                        if i == SAMPLES / 2 {
+                            recorder.flush();
+                        }
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        // Exited threads flushed their remainders on the way out.
+        assert_eq!(count(&handle), Some(THREADS * SAMPLES));
+
+        recorder.record_latency("order_send", 250.0);
+        assert_eq!(count(&handle), Some(THREADS * SAMPLES));
+        handle.flush_thread_local();
+        assert_eq!(count(&handle), Some(THREADS * SAMPLES + 1));
+        let sum = handle
+            .get_histogram_sum("latency_seconds", &[("operation", "order_send")])
+            .unwrap();
+        assert!((sum - (THREADS * SAMPLES + 1) as f64 * 250e-6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_flusher_merges_live_threads() {
+        let handle = TelemetryHandle::new_isolated();
+        let recorder = handle.thread_local_recorder();
+        let flusher = recorder.start_flusher(std::time::Duration::from_millis(5));
+
+        let (done, wait) = std::sync::mpsc::channel::<()>();
+        let writer = {
+            let recorder = recorder.clone();
+            thread::spawn(move || {
+                for _ in 0..100 {
+                    recorder.record_latency("order_send", 1.0);
+                }
+                // Stay alive so only the flusher can merge the samples.
+                let _ = wait.recv();
+            })
+        };
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while count(&handle) != Some(100) {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "flusher did not merge the samples"
+            );
+            thread::yield_now();
+        }
+        drop(done);
+        writer.join().unwrap();
+        flusher.stop();
+    }
+
+    #[test]
+    fn test_reset_discards_buffers() {
+        let handle = TelemetryHandle::new_isolated();
+        let recorder = handle.thread_local_recorder();
+        recorder.record_latency("order_send", 1.0);
+        handle.reset();
+        handle.flush_thread_local();
+        assert_eq!(count(&handle), None);
+
+        recorder.record_latency("order_send", 1.0);
+        handle.flush_thread_local();
+        assert_eq!(count(&handle), Some(1));
+        assert!(recorder.try_record_latency("bad op", 1.0).is_err());
+    }
+
+    #[test]
+    fn test_operation_limit_applies_to_buffers() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.set_operation_limit(1);
+        let recorder = handle.thread_local_recorder();
+        for operation in ["order_send", "uuid-1", "uuid-2"] {
+            recorder.record_latency(operation, 1.0);
+        }
+        handle.flush_thread_local();
+        assert_eq!(count(&handle), Some(1));
+        assert_eq!(
+            handle.get_histogram_count("latency_seconds", &[("operation", "overflow")]),
+            Some(2)
+        );
+
+        recorder.record_latency("order_send", 1.0);
+        assert!(handle.remove_operation("order_send"));
+        handle.flush_thread_local();
+        assert_eq!(count(&handle), None);
+    }
+}
@@ -0,0 +1,329 @@
+//! OpenMetrics text rendering with latency exemplars.
+//!
+//! The `prometheus` crate only encodes the classic text format, which has
+//! no exemplars, so this module renders the registry's gather output in
+//! the OpenMetrics 1.0 text format itself. Each `latency_seconds` bucket
+//! line can carry the trace ID of the latest observation that landed in it
+//! (see [`record_latency_with_exemplar`](crate::record_latency_with_exemplar)):
+//!
+//! ```text
+//! latency_seconds_bucket{operation="order_send",le="0.0005"} 3 # {trace_id="4bf92f35"} 0.00025 1700000000.123
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use prometheus::proto::{LabelPair, Metric, MetricFamily, MetricType};
+use pyo3::prelude::*;
+
+use crate::{init_metrics, DEFAULT};
+
+/// Content type of [`get_metrics_openmetrics`] output.
+pub const OPENMETRICS_CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Longest trace ID kept as an exemplar label.
+pub const MAX_TRACE_ID_LEN: usize = 64;
+
+/// The latest observation recorded into one latency bucket with a trace ID.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Exemplar {
+    pub(crate) trace_id: String,
+    /// Observed value in seconds
+    pub(crate) value: f64,
+    /// Unix time of the observation in seconds
+    pub(crate) timestamp: f64,
+}
+
+impl Exemplar {
+    pub(crate) fn new(trace_id: &str, value: f64) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs_f64())
+            .unwrap_or_default();
+        Self {
+            trace_id: trace_id.to_string(),
+            value,
+            timestamp,
+        }
+    }
+}
+
+/// Latest exemplar per `(operation, bucket index)`; the `+Inf` bucket's
+/// index is the number of finite buckets.
+pub(crate) type Exemplars = HashMap<(String, usize), Exemplar>;
+
+/// Whether `trace_id` can be used as an exemplar label value: 1 to
+/// [`MAX_TRACE_ID_LEN`] ASCII alphanumerics, `-` or `_`.
+pub(crate) fn is_valid_trace_id(trace_id: &str) -> bool {
+    !trace_id.is_empty()
+        && trace_id.len() <= MAX_TRACE_ID_LEN
+        && trace_id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_'))
+}
+
+/// Render all registered metrics in the OpenMetrics text format.
+///
+/// Same metrics as [`get_metrics`](crate::get_metrics), plus exemplars on
+/// the `latency_seconds` buckets and the closing `# EOF` line. Counter
+/// families are named without their `_total` suffix, as OpenMetrics
+/// requires; their samples keep it. Serve it with
+/// [`OPENMETRICS_CONTENT_TYPE`].
+pub fn get_metrics_openmetrics() -> String {
+    init_metrics();
+    DEFAULT.get_metrics_openmetrics()
+}
+
+/// Render `families` as OpenMetrics text, attaching `exemplars` to the
+/// buckets of the histogram family called `latency_family`.
+pub(crate) fn render(
+    families: &[MetricFamily],
+    latency_family: &str,
+    exemplars: &Exemplars,
+) -> String {
+    let mut families: Vec<&MetricFamily> = families.iter().collect();
+    families.sort_by(|a, b| a.get_name().cmp(b.get_name()));
+
+    let mut out = String::new();
+    for family in families {
+        let kind = family.get_field_type();
+        let name = match kind {
+            MetricType::COUNTER => family
+                .get_name()
+                .strip_suffix("_total")
+                .unwrap_or(family.get_name()),
+            _ => family.get_name(),
+        };
+        let type_name = match kind {
+            MetricType::COUNTER => "counter",
+            MetricType::GAUGE => "gauge",
+            MetricType::HISTOGRAM => "histogram",
+            MetricType::SUMMARY => "summary",
+            MetricType::UNTYPED => "unknown",
+        };
+        let _ = writeln!(out, "# TYPE {name} {type_name}");
+        if !family.get_help().is_empty() {
+            let _ = writeln!(out, "# HELP {name} {}", escape(family.get_help()));
+        }
+
+        for metric in family.get_metric() {
+            let labels = metric.get_label();
+            match kind {
+                MetricType::COUNTER => {
+                    let value = float(metric.get_counter().get_value());
+                    sample(&mut out, &format!("{name}_total"), labels, None, &value);
+                }
+                MetricType::GAUGE => {
+                    let value = float(metric.get_gauge().get_value());
+                    sample(&mut out, name, labels, None, &value);
+                }
+                MetricType::UNTYPED => {
+                    let value = float(metric.get_untyped().get_value());
+                    sample(&mut out, name, labels, None, &value);
+                }
+                MetricType::HISTOGRAM => {
+                    let operation = (name == latency_family)
+                        .then(|| label(metric, "operation"))
+                        .flatten();
+                    histogram(&mut out, name, metric, operation, exemplars);
+                }
+                MetricType::SUMMARY => {
+                    let summary = metric.get_summary();
+                    for quantile in summary.get_quantile() {
+                        let q = float(quantile.get_quantile());
+                        let value = float(quantile.get_value());
+                        sample(&mut out, name, labels, Some(("quantile", &q)), &value);
+                    }
+                    let count = summary.get_sample_count().to_string();
+                    sample(&mut out, &format!("{name}_count"), labels, None, &count);
+                    let sum = float(summary.get_sample_sum());
+                    sample(&mut out, &format!("{name}_sum"), labels, None, &sum);
+                }
+            }
+        }
+    }
+    out.push_str("# EOF\n");
+    out
+}
+
+fn histogram(
+    out: &mut String,
+    name: &str,
+    metric: &Metric,
+    operation: Option<&str>,
+    exemplars: &Exemplars,
+) {
+    let labels = metric.get_label();
+    let histogram = metric.get_histogram();
+    let buckets = histogram.get_bucket();
+    let bucket_name = format!("{name}_bucket");
+    let exemplar = |index: usize| {
+        operation.and_then(|operation| exemplars.get(&(operation.to_string(), index)))
+    };
+
+    for (index, bucket) in buckets.iter().enumerate() {
+        let le = float(bucket.get_upper_bound());
+        let count = bucket.get_cumulative_count().to_string();
+        sample_value(out, &bucket_name, labels, Some(("le", &le)), &count);
+        annotate(out, exemplar(index));
+    }
+    let count = histogram.get_sample_count().to_string();
+    sample_value(out, &bucket_name, labels, Some(("le", "+Inf")), &count);
+    annotate(out, exemplar(buckets.len()));
+    sample(out, &format!("{name}_count"), labels, None, &count);
+    let sum = float(histogram.get_sample_sum());
+    sample(out, &format!("{name}_sum"), labels, None, &sum);
+}
+
+/// Write one sample line.
+fn sample(
+    out: &mut String,
+    name: &str,
+    labels: &[LabelPair],
+    extra: Option<(&str, &str)>,
+    value: &str,
+) {
+    sample_value(out, name, labels, extra, value);
+    out.push('\n');
+}
+
+/// Write a sample without the trailing newline, so [`annotate`] can append
+/// an exemplar.
+fn sample_value(
+    out: &mut String,
+    name: &str,
+    labels: &[LabelPair],
+    extra: Option<(&str, &str)>,
+    value: &str,
+) {
+    out.push_str(name);
+    let pairs = labels
+        .iter()
+        .map(|pair| (pair.get_name(), pair.get_value()))
+        .chain(extra);
+    for (i, (key, value)) in pairs.enumerate() {
+        out.push(if i == 0 { '{' } else { ',' });
+        let _ = write!(out, "{key}=\"{}\"", escape(value));
+    }
+    if !labels.is_empty() || extra.is_some() {
+        out.push('}');
+    }
+    let _ = write!(out, " {value}");
+}
+
+/// Finish a bucket line, appending the exemplar if there is one.
+fn annotate(out: &mut String, exemplar: Option<&Exemplar>) {
+    if let Some(exemplar) = exemplar {
+        let _ = write!(
+            out,
+            " # {{trace_id=\"{}\"}} {} {:.3}",
+            exemplar.trace_id,
+            float(exemplar.value),
+            exemplar.timestamp
+        );
+    }
+    out.push('\n');
+}
+
+fn label<'a>(metric: &'a Metric, name: &str) -> Option<&'a str> {
+    metric
+        .get_label()
+        .iter()
+        .find(|pair| pair.get_name() == name)
+        .map(LabelPair::get_value)
+}
+
+/// Format a number the way OpenMetrics expects (`1.0`, `+Inf`, `NaN`).
+fn float(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{value:.1}")
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Render all metrics as OpenMetrics text with exemplars (Python binding).
+#[pyfunction]
+#[pyo3(name = "get_metrics_openmetrics")]
+pub(crate) fn py_get_metrics_openmetrics() -> String {
+    get_metrics_openmetrics()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TelemetryHandle;
+
+    fn line<'a>(text: &'a str, prefix: &str) -> &'a str {
+        text.lines()
+            .find(|line| line.starts_with(prefix))
+            .unwrap_or_else(|| panic!("no line starting with {prefix} in {text}"))
+    }
+
+    #[test]
+    fn test_exemplar_on_matching_bucket() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.record_latency_with_exemplar("order_send", 250.0, "4bf92f3577b34da6");
+        handle.record_latency("order_send", 3.0);
+
+        let text = handle.get_metrics_openmetrics();
+        let bucket = line(
+            &text,
+            "latency_seconds_bucket{operation=\"order_send\",le=\"0.0005\"}",
+        );
+        assert!(
+            bucket.starts_with(
+                "latency_seconds_bucket{operation=\"order_send\",le=\"0.0005\"} 2 \
+                 # {trace_id=\"4bf92f3577b34da6\"} 0.00025 "
+            ),
+            "{bucket}"
+        );
+        assert!(!line(
+            &text,
+            "latency_seconds_bucket{operation=\"order_send\",le=\"0.0001\"}"
+        )
+        .contains('#'));
+        assert_eq!(text.matches("trace_id=").count(), 1);
+        assert!(text.ends_with("# EOF\n"));
+    }
+
+    #[test]
+    fn test_invalid_trace_id_keeps_observation() {
+        let handle = TelemetryHandle::new_isolated();
+        for trace_id in ["", "abc\"} 1", &"a".repeat(MAX_TRACE_ID_LEN + 1)] {
+            handle.record_latency_with_exemplar("order_send", 250.0, trace_id);
+        }
+
+        assert!(!handle.get_metrics_openmetrics().contains("trace_id"));
+        assert_eq!(
+            handle.get_histogram_count("latency_seconds", &[("operation", "order_send")]),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn test_counter_families_drop_total_suffix() {
+        let handle = TelemetryHandle::new_isolated();
+        handle.emit_metric("orders_total", 2.0);
+        handle.set_gauge("queue_depth", &[], 3.5).unwrap();
+
+        let text = handle.get_metrics_openmetrics();
+        assert!(text.contains("# TYPE orders counter\n"));
+        assert!(text.contains("# HELP orders Total number of orders processed\n"));
+        assert!(text.contains("\norders_total 2.0\n"));
+        assert!(text.contains("# TYPE queue_depth gauge\n"));
+        assert!(text.contains("\nqueue_depth 3.5\n"));
+    }
+}
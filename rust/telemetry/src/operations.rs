@@ -0,0 +1,223 @@
+//! Cardinality control for the `operation` label of `latency_seconds`.
+//!
+//! Every distinct operation name is a separate series, so a caller that
+//! passes an ID as the operation (one bug did, with a UUID per order) can
+//! create millions of them. [`Operations`] admits at most a configurable
+//! number of names; later new names are recorded under
+//! [`OVERFLOW_OPERATION`] and counted in `telemetry_label_overflow_total`.
+//! It also remembers when each name was last recorded, so idle ones can be
+//! pruned to make room.
+//!
+//! [`OVERFLOW_OPERATION`]: crate::OVERFLOW_OPERATION
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use prometheus::IntCounter;
+
+use crate::{DEFAULT_OPERATION_LIMIT, OVERFLOW_OPERATION};
+
+/// `last_seen` value of an operation that was never recorded.
+const NEVER: u64 = u64::MAX;
+
+/// The operation names admitted to `latency_seconds`.
+pub(crate) struct Operations {
+    epoch: Instant,
+    limit: AtomicUsize,
+    /// Milliseconds since `epoch` at which each admitted name was last
+    /// recorded. [`OVERFLOW_OPERATION`] is tracked in `overflow_seen`
+    /// instead, so it never takes up one of the `limit` slots.
+    last_seen: RwLock<HashMap<String, AtomicU64>>,
+    overflow_seen: AtomicU64,
+    overflow: IntCounter,
+}
+
+impl Operations {
+    /// Track operations, counting refused names in `overflow`.
+    pub(crate) fn new(overflow: IntCounter) -> Self {
+        Self {
+            epoch: Instant::now(),
+            limit: AtomicUsize::new(DEFAULT_OPERATION_LIMIT),
+            last_seen: RwLock::default(),
+            overflow_seen: AtomicU64::new(NEVER),
+            overflow,
+        }
+    }
+
+    fn millis(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.epoch).as_millis() as u64
+    }
+
+    /// Admit at most `limit` names from now on; names already admitted keep
+    /// their series.
+    pub(crate) fn set_limit(&self, limit: usize) {
+        self.limit.store(limit, Ordering::Relaxed);
+    }
+
+    /// The label value to record `operation` under: `operation` itself if it
+    /// is admitted or there is room for it, [`OVERFLOW_OPERATION`] otherwise.
+    pub(crate) fn admit<'a>(&self, operation: &'a str) -> &'a str {
+        self.admit_at(operation, Instant::now())
+    }
+
+    fn admit_at<'a>(&self, operation: &'a str, now: Instant) -> &'a str {
+        let now = self.millis(now);
+        if operation != OVERFLOW_OPERATION {
+            let last_seen = self.last_seen.read().unwrap();
+            if let Some(seen) = last_seen.get(operation) {
+                seen.store(now, Ordering::Relaxed);
+                return operation;
+            }
+            if last_seen.len() < self.limit.load(Ordering::Relaxed) {
+                drop(last_seen);
+                let mut last_seen = self.last_seen.write().unwrap();
+                // Re-check: other threads may have filled the last slots.
+                if last_seen.contains_key(operation)
+                    || last_seen.len() < self.limit.load(Ordering::Relaxed)
+                {
+                    last_seen.insert(operation.to_string(), AtomicU64::new(now));
+                    return operation;
+                }
+            }
+            self.overflow.inc();
+        }
+        self.overflow_seen.store(now, Ordering::Relaxed);
+        OVERFLOW_OPERATION
+    }
+
+    /// The label value samples for `operation` were recorded under, without
+    /// admitting it.
+    pub(crate) fn label_of<'a>(&self, operation: &'a str) -> &'a str {
+        if self.last_seen.read().unwrap().contains_key(operation) {
+            operation
+        } else {
+            OVERFLOW_OPERATION
+        }
+    }
+
+    /// Mark `operation` as recorded now, e.g. when buffered samples for it
+    /// are flushed.
+    pub(crate) fn touch(&self, operation: &str) {
+        let now = self.millis(Instant::now());
+        if operation == OVERFLOW_OPERATION {
+            self.overflow_seen.store(now, Ordering::Relaxed);
+        } else if let Some(seen) = self.last_seen.read().unwrap().get(operation) {
+            seen.store(now, Ordering::Relaxed);
+        }
+    }
+
+    /// Stop tracking `operation`, freeing its slot.
+    ///
+    /// Returns whether it was tracked.
+    pub(crate) fn remove(&self, operation: &str) -> bool {
+        if operation == OVERFLOW_OPERATION {
+            return self.overflow_seen.swap(NEVER, Ordering::Relaxed) != NEVER;
+        }
+        self.last_seen.write().unwrap().remove(operation).is_some()
+    }
+
+    /// Stop tracking every operation not recorded within `max_idle`, and
+    /// return their names.
+    pub(crate) fn prune_idle(&self, max_idle: Duration) -> Vec<String> {
+        self.prune_idle_at(max_idle, Instant::now())
+    }
+
+    fn prune_idle_at(&self, max_idle: Duration, now: Instant) -> Vec<String> {
+        let now = self.millis(now);
+        let max_idle = max_idle.as_millis() as u64;
+        let idle = |seen: u64| now.saturating_sub(seen) > max_idle;
+
+        let mut pruned = Vec::new();
+        self.last_seen.write().unwrap().retain(|operation, seen| {
+            let keep = !idle(seen.load(Ordering::Relaxed));
+            if !keep {
+                pruned.push(operation.clone());
+            }
+            keep
+        });
+        let overflow_seen = self.overflow_seen.load(Ordering::Relaxed);
+        if overflow_seen != NEVER
+            && idle(overflow_seen)
+            && self
+                .overflow_seen
+                .compare_exchange(overflow_seen, NEVER, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            pruned.push(OVERFLOW_OPERATION.to_string());
+        }
+        pruned
+    }
+
+    /// How long before `now` each tracked operation was last recorded.
+    pub(crate) fn idle_at(&self, now: Instant) -> BTreeMap<String, Duration> {
+        let now = self.millis(now);
+        let idle = |seen: u64| Duration::from_millis(now.saturating_sub(seen));
+        let mut idle_times: BTreeMap<String, Duration> = self
+            .last_seen
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(operation, seen)| (operation.clone(), idle(seen.load(Ordering::Relaxed))))
+            .collect();
+        let overflow_seen = self.overflow_seen.load(Ordering::Relaxed);
+        if overflow_seen != NEVER {
+            idle_times.insert(OVERFLOW_OPERATION.to_string(), idle(overflow_seen));
+        }
+        idle_times
+    }
+
+    /// Forget every operation, for when `latency_seconds` is reset.
+    pub(crate) fn clear(&self) {
+        self.last_seen.write().unwrap().clear();
+        self.overflow_seen.store(NEVER, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn operations(limit: usize) -> Operations {
+        let operations = Operations::new(IntCounter::new("overflow", "help").unwrap());
+        operations.set_limit(limit);
+        operations
+    }
+
+    #[test]
+    fn test_names_beyond_the_limit_overflow() {
+        let ops = operations(2);
+        assert_eq!(ops.admit("a"), "a");
+        assert_eq!(ops.admit("b"), "b");
+        assert_eq!(ops.admit("c"), OVERFLOW_OPERATION);
+        assert_eq!(ops.admit("a"), "a");
+        assert_eq!(ops.admit(OVERFLOW_OPERATION), OVERFLOW_OPERATION);
+        assert_eq!(ops.overflow.get(), 1);
+        assert_eq!(ops.label_of("c"), OVERFLOW_OPERATION);
+
+        assert!(ops.remove("b"));
+        assert!(!ops.remove("b"));
+        assert_eq!(ops.admit("c"), "c");
+        assert_eq!(ops.label_of("c"), "c");
+    }
+
+    #[test]
+    fn test_prune_idle_frees_slots() {
+        let ops = operations(2);
+        let start = ops.epoch;
+        ops.admit_at("a", start);
+        ops.admit_at("b", start);
+        // Overflows at 1s, so the overflow series is idle from then on.
+        ops.admit_at("c", start + Duration::from_secs(1));
+        ops.admit_at("b", start + Duration::from_secs(5));
+
+        let now = start + Duration::from_secs(8);
+        assert!(ops.prune_idle_at(Duration::from_secs(10), now).is_empty());
+        let mut pruned = ops.prune_idle_at(Duration::from_secs(4), now);
+        pruned.sort();
+        assert_eq!(pruned, ["a", OVERFLOW_OPERATION]);
+        assert_eq!(ops.admit_at("d", now), "d");
+        assert_eq!(ops.admit_at("e", now), OVERFLOW_OPERATION);
+    }
+}
@@ -0,0 +1,473 @@
+//! Periodic OTLP export to an OpenTelemetry collector.
+//!
+//! [`start_otlp_exporter`] snapshots [`REGISTRY`] on a background thread
+//! and POSTs it as an OTLP/HTTP protobuf request to `{endpoint}/v1/metrics`.
+//! Prometheus counters become cumulative monotonic sums, histograms become
+//! cumulative histogram data points and every label (including
+//! `operation`) becomes a data point attribute. All cumulative points from
+//! one exporter share its start time, so collectors compute rates exactly
+//! as Prometheus would.
+
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use opentelemetry_proto::tonic::collector::metrics::v1::ExportMetricsServiceRequest;
+use opentelemetry_proto::tonic::common::v1::{any_value, AnyValue, InstrumentationScope, KeyValue};
+use opentelemetry_proto::tonic::metrics::v1::{
+    metric, number_data_point, summary_data_point::ValueAtQuantile, AggregationTemporality, Gauge,
+    Histogram, HistogramDataPoint, Metric, NumberDataPoint, ResourceMetrics, ScopeMetrics, Sum,
+    Summary, SummaryDataPoint,
+};
+use opentelemetry_proto::tonic::resource::v1::Resource;
+use prometheus::proto::{self as prom, MetricFamily, MetricType};
+use prost::Message;
+
+use crate::http::{self, HttpUrl};
+use crate::{TelemetryError, REGISTRY};
+
+/// Timeout for each phase of an export request.
+pub const OTLP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// `service.name` resource attribute sent with every export.
+const SERVICE_NAME: &str = "tinywindow";
+
+/// Start exporting [`REGISTRY`] to an OTLP/HTTP collector every `interval_secs`.
+///
+/// Failed periodic exports are logged to stderr and retried on the next
+/// tick; use [`OtlpHandle::flush`] to observe errors directly.
+///
+/// # Arguments
+/// * `endpoint` - Collector base URL, e.g. `"http://otel-collector:4318"`
+/// * `interval_secs` - Seconds between exports; must be at least 1
+///
+/// # Returns
+/// * `Ok(OtlpHandle)` - The running exporter
+/// * `Err(TelemetryError::Otlp)` - If the endpoint is unusable or the interval is zero
+pub fn start_otlp_exporter(
+    endpoint: &str,
+    interval_secs: u64,
+) -> Result<OtlpHandle, TelemetryError> {
+    if interval_secs == 0 {
+        return Err(TelemetryError::Otlp(
+            "export interval must be at least 1 second".to_string(),
+        ));
+    }
+    let url = HttpUrl::parse(endpoint).map_err(TelemetryError::Otlp)?;
+    let exporter = Exporter {
+        host: url.host,
+        path: if url.path.ends_with("/v1/metrics") {
+            url.path
+        } else {
+            format!("{}/v1/metrics", url.path)
+        },
+        start_ns: unix_nanos(),
+    };
+
+    let (commands, inbox) = mpsc::channel();
+    let interval = Duration::from_secs(interval_secs);
+    let thread = std::thread::Builder::new()
+        .name("otlp-exporter".to_string())
+        .spawn(move || exporter.run(&inbox, interval))
+        .map_err(|err| TelemetryError::Otlp(format!("cannot spawn exporter thread: {err}")))?;
+
+    Ok(OtlpHandle {
+        commands,
+        thread: Some(thread),
+    })
+}
+
+enum Command {
+    Flush(Sender<Result<(), TelemetryError>>),
+    Stop,
+}
+
+struct Exporter {
+    host: String,
+    path: String,
+    start_ns: u64,
+}
+
+impl Exporter {
+    fn run(&self, inbox: &Receiver<Command>, interval: Duration) {
+        loop {
+            match inbox.recv_timeout(interval) {
+                Err(RecvTimeoutError::Timeout) => {
+                    if let Err(err) = self.export() {
+                        log::warn!("{err}");
+                    }
+                }
+                Ok(Command::Flush(reply)) => {
+                    // The caller may have given up waiting; nothing to do then.
+                    let _ = reply.send(self.export());
+                }
+                Ok(Command::Stop) | Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+
+    fn export(&self) -> Result<(), TelemetryError> {
+        let request = to_otlp(&REGISTRY.gather(), self.start_ns, unix_nanos());
+        let response = http::send(
+            "POST",
+            &self.host,
+            &self.path,
+            "application/x-protobuf",
+            &request.encode_to_vec(),
+            OTLP_TIMEOUT,
+        )
+        .map_err(TelemetryError::Otlp)?;
+        if !(200..300).contains(&response.status) {
+            return Err(TelemetryError::Otlp(format!(
+                "collector rejected export with status {}: {}",
+                response.status, response.body
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// A running OTLP exporter; stops (without a final export) when dropped.
+pub struct OtlpHandle {
+    commands: Sender<Command>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl OtlpHandle {
+    /// Export immediately and wait for the collector's answer.
+    ///
+    /// # Returns
+    /// * `Ok(())` - The collector accepted the export
+    /// * `Err(TelemetryError::Otlp)` - If the collector is unreachable or answered non-2xx
+    pub fn flush(&self) -> Result<(), TelemetryError> {
+        let (reply, result) = mpsc::channel();
+        self.commands
+            .send(Command::Flush(reply))
+            .map_err(|_| TelemetryError::Otlp("exporter thread has stopped".to_string()))?;
+        result
+            .recv()
+            .map_err(|_| TelemetryError::Otlp("exporter thread has stopped".to_string()))?
+    }
+
+    /// Export one last time, then stop the exporter thread.
+    ///
+    /// The thread is stopped even if the final export fails.
+    pub fn shutdown(mut self) -> Result<(), TelemetryError> {
+        let flushed = self.flush();
+        self.stop();
+        flushed
+    }
+
+    fn stop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            // A send error means the thread already exited.
+            let _ = self.commands.send(Command::Stop);
+            // The thread only exports; a panic there has nothing to clean up.
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for OtlpHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn unix_nanos() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+}
+
+/// Convert a registry snapshot into an OTLP export request.
+///
+/// `start_ns` is the start time of every cumulative point and must stay
+/// fixed between calls; `now_ns` is the observation time.
+fn to_otlp(families: &[MetricFamily], start_ns: u64, now_ns: u64) -> ExportMetricsServiceRequest {
+    let metrics = families
+        .iter()
+        .map(|family| {
+            let data = match family.get_field_type() {
+                MetricType::COUNTER => metric::Data::Sum(Sum {
+                    data_points: number_points(family, start_ns, now_ns, |m| {
+                        m.get_counter().get_value()
+                    }),
+                    aggregation_temporality: AggregationTemporality::Cumulative as i32,
+                    is_monotonic: true,
+                }),
+                MetricType::GAUGE => metric::Data::Gauge(Gauge {
+                    data_points: number_points(family, 0, now_ns, |m| m.get_gauge().get_value()),
+                }),
+                MetricType::UNTYPED => metric::Data::Gauge(Gauge {
+                    data_points: number_points(family, 0, now_ns, |m| m.get_untyped().get_value()),
+                }),
+                MetricType::HISTOGRAM => metric::Data::Histogram(Histogram {
+                    data_points: family
+                        .get_metric()
+                        .iter()
+                        .map(|m| histogram_point(m, start_ns, now_ns))
+                        .collect(),
+                    aggregation_temporality: AggregationTemporality::Cumulative as i32,
+                }),
+                MetricType::SUMMARY => metric::Data::Summary(Summary {
+                    data_points: family
+                        .get_metric()
+                        .iter()
+                        .map(|m| summary_point(m, start_ns, now_ns))
+                        .collect(),
+                }),
+            };
+            Metric {
+                name: family.get_name().to_string(),
+                description: family.get_help().to_string(),
+                data: Some(data),
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    ExportMetricsServiceRequest {
+        resource_metrics: vec![ResourceMetrics {
+            resource: Some(Resource {
+                attributes: vec![key_value("service.name", SERVICE_NAME)],
+                ..Default::default()
+            }),
+            scope_metrics: vec![ScopeMetrics {
+                scope: Some(InstrumentationScope {
+                    name: env!("CARGO_PKG_NAME").to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                    ..Default::default()
+                }),
+                metrics,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }],
+    }
+}
+
+fn number_points(
+    family: &MetricFamily,
+    start_ns: u64,
+    now_ns: u64,
+    value: impl Fn(&prom::Metric) -> f64,
+) -> Vec<NumberDataPoint> {
+    family
+        .get_metric()
+        .iter()
+        .map(|m| NumberDataPoint {
+            attributes: attributes(m),
+            start_time_unix_nano: start_ns,
+            time_unix_nano: now_ns,
+            value: Some(number_data_point::Value::AsDouble(value(m))),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Prometheus buckets are cumulative and omit `+Inf`; OTLP wants a count
+/// per bucket plus a final overflow bucket.
+fn histogram_point(m: &prom::Metric, start_ns: u64, now_ns: u64) -> HistogramDataPoint {
+    let histogram = m.get_histogram();
+    let mut explicit_bounds = Vec::new();
+    let mut bucket_counts = Vec::new();
+    let mut below = 0;
+    for bucket in histogram.get_bucket() {
+        if bucket.get_upper_bound().is_infinite() {
+            continue;
+        }
+        explicit_bounds.push(bucket.get_upper_bound());
+        bucket_counts.push(bucket.get_cumulative_count().saturating_sub(below));
+        below = bucket.get_cumulative_count();
+    }
+    bucket_counts.push(histogram.get_sample_count().saturating_sub(below));
+
+    HistogramDataPoint {
+        attributes: attributes(m),
+        start_time_unix_nano: start_ns,
+        time_unix_nano: now_ns,
+        count: histogram.get_sample_count(),
+        sum: Some(histogram.get_sample_sum()),
+        bucket_counts,
+        explicit_bounds,
+        ..Default::default()
+    }
+}
+
+fn summary_point(m: &prom::Metric, start_ns: u64, now_ns: u64) -> SummaryDataPoint {
+    let summary = m.get_summary();
+    SummaryDataPoint {
+        attributes: attributes(m),
+        start_time_unix_nano: start_ns,
+        time_unix_nano: now_ns,
+        count: summary.get_sample_count(),
+        sum: summary.get_sample_sum(),
+        quantile_values: summary
+            .get_quantile()
+            .iter()
+            .map(|q| ValueAtQuantile {
+                quantile: q.get_quantile(),
+                value: q.get_value(),
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+fn attributes(m: &prom::Metric) -> Vec<KeyValue> {
+    m.get_label()
+        .iter()
+        .map(|label| key_value(label.get_name(), label.get_value()))
+        .collect()
+}
+
+fn key_value(key: &str, value: &str) -> KeyValue {
+    KeyValue {
+        key: key.to_string(),
+        value: Some(AnyValue {
+            value: Some(any_value::Value::StringValue(value.to_string())),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prometheus::{CounterVec, HistogramOpts, HistogramVec, Opts, Registry};
+
+    use crate::mock_http::serve_once;
+
+    fn only_metric(request: &ExportMetricsServiceRequest, name: &str) -> Metric {
+        request.resource_metrics[0].scope_metrics[0]
+            .metrics
+            .iter()
+            .find(|m| m.name == name)
+            .cloned()
+            .unwrap()
+    }
+
+    fn operation(attributes: &[KeyValue]) -> String {
+        let attr = attributes.iter().find(|kv| kv.key == "operation").unwrap();
+        match &attr.value.as_ref().unwrap().value {
+            Some(any_value::Value::StringValue(value)) => value.clone(),
+            other => panic!("unexpected attribute value {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_counters_become_cumulative_monotonic_sums() {
+        let registry = Registry::new();
+        let counter = CounterVec::new(Opts::new("orders_total", "Orders"), &["operation"]).unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        counter.with_label_values(&["send"]).inc_by(2.0);
+
+        let first = to_otlp(&registry.gather(), 100, 200);
+        counter.with_label_values(&["send"]).inc_by(3.0);
+        let second = to_otlp(&registry.gather(), 100, 300);
+
+        for (request, expected, now) in [(first, 2.0, 200), (second, 5.0, 300)] {
+            let Some(metric::Data::Sum(sum)) = only_metric(&request, "orders_total").data else {
+                panic!("counter should convert to a sum");
+            };
+            assert!(sum.is_monotonic);
+            assert_eq!(
+                sum.aggregation_temporality,
+                AggregationTemporality::Cumulative as i32
+            );
+            let point = &sum.data_points[0];
+            assert_eq!(point.start_time_unix_nano, 100);
+            assert_eq!(point.time_unix_nano, now);
+            assert_eq!(
+                point.value,
+                Some(number_data_point::Value::AsDouble(expected))
+            );
+            assert_eq!(operation(&point.attributes), "send");
+        }
+    }
+
+    #[test]
+    fn test_histogram_buckets_become_per_bucket_counts() {
+        let registry = Registry::new();
+        let histogram = HistogramVec::new(
+            HistogramOpts::new("latency_seconds", "Latency").buckets(vec![0.1, 1.0]),
+            &["operation"],
+        )
+        .unwrap();
+        registry.register(Box::new(histogram.clone())).unwrap();
+        for value in [0.05, 0.5, 0.7, 5.0] {
+            histogram.with_label_values(&["order_send"]).observe(value);
+        }
+
+        let request = to_otlp(&registry.gather(), 100, 200);
+        let Some(metric::Data::Histogram(data)) = only_metric(&request, "latency_seconds").data
+        else {
+            panic!("histogram should convert to a histogram");
+        };
+        assert_eq!(
+            data.aggregation_temporality,
+            AggregationTemporality::Cumulative as i32
+        );
+        let point = &data.data_points[0];
+        assert_eq!(point.explicit_bounds, vec![0.1, 1.0]);
+        assert_eq!(point.bucket_counts, vec![1, 2, 1]);
+        assert_eq!(point.count, 4);
+        assert_eq!(point.sum, Some(6.25));
+        assert_eq!(point.start_time_unix_nano, 100);
+        assert_eq!(operation(&point.attributes), "order_send");
+    }
+
+    #[test]
+    fn test_request_carries_service_resource_and_scope() {
+        let request = to_otlp(&[], 0, 0);
+        let resource = request.resource_metrics[0].resource.as_ref().unwrap();
+        assert_eq!(
+            resource.attributes,
+            vec![key_value("service.name", "tinywindow")]
+        );
+        let scope = request.resource_metrics[0].scope_metrics[0]
+            .scope
+            .as_ref()
+            .unwrap();
+        assert_eq!(scope.name, "telemetry");
+    }
+
+    #[test]
+    fn test_rejects_bad_endpoint_and_interval() {
+        assert!(matches!(
+            start_otlp_exporter("http://127.0.0.1:4318", 0),
+            Err(TelemetryError::Otlp(_))
+        ));
+        assert!(matches!(
+            start_otlp_exporter("grpc://127.0.0.1:4317", 10),
+            Err(TelemetryError::Otlp(_))
+        ));
+    }
+
+    #[test]
+    fn test_flush_posts_protobuf_to_metrics_path() {
+        crate::emit_metric("test_otlp_flushed_total", 1.0);
+        let (url, collector) = serve_once(200, "");
+        let exporter = start_otlp_exporter(&url, 3600).unwrap();
+
+        exporter.flush().unwrap();
+
+        let (request_line, body) = collector.join().unwrap();
+        assert_eq!(request_line, "POST /v1/metrics HTTP/1.1");
+        let request = ExportMetricsServiceRequest::decode(body.as_slice()).unwrap();
+        assert!(matches!(
+            only_metric(&request, "test_otlp_flushed_total").data,
+            Some(metric::Data::Sum(_))
+        ));
+    }
+
+    #[test]
+    fn test_flush_to_unreachable_collector_fails() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let exporter = start_otlp_exporter(&url, 3600).unwrap();
+        assert!(matches!(exporter.flush(), Err(TelemetryError::Otlp(_))));
+        assert!(exporter.shutdown().is_err());
+    }
+}
@@ -0,0 +1,263 @@
+//! Process-level metrics: uptime and build info everywhere, plus the
+//! standard `process_*` metrics when built with the `process` feature on
+//! Linux.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, Once};
+use std::time::Instant;
+
+use lazy_static::lazy_static;
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::MetricFamily;
+use prometheus::Gauge;
+use pyo3::prelude::*;
+
+use crate::handle::namespaced_opts;
+use crate::{global_label_map, init_metrics, namespace, TelemetryError, REGISTRY};
+
+lazy_static! {
+    /// When this process first touched the telemetry crate.
+    pub(crate) static ref START: Instant = Instant::now();
+}
+
+static PROCESS_INIT: Once = Once::new();
+
+static COLLECTOR_INIT: Once = Once::new();
+
+/// The registered `build_info` gauge, replaced by each
+/// [`register_build_info`].
+static BUILD_INFO: Mutex<Option<Gauge>> = Mutex::new(None);
+
+/// `tinywindow_uptime_seconds`, refreshed every time the registry is gathered.
+struct Uptime {
+    gauge: Gauge,
+}
+
+impl Collector for Uptime {
+    fn desc(&self) -> Vec<&Desc> {
+        self.gauge.desc()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        self.gauge.set(START.elapsed().as_secs_f64());
+        self.gauge.collect()
+    }
+}
+
+/// Register process metrics with [`REGISTRY`].
+///
+/// Adds `tinywindow_uptime_seconds`, measured from the first use of this
+/// crate and updated on every scrape, and the metrics of
+/// [`register_process_metrics`].
+///
+/// Safe to call any number of times; registration happens once. A metric
+/// the registry refuses, e.g. because the application already registered
+/// that name, is logged and left out.
+pub fn init_process_metrics() {
+    init_metrics();
+    PROCESS_INIT.call_once(|| {
+        let gauge = Gauge::with_opts(namespaced_opts(
+            namespace(),
+            "tinywindow_uptime_seconds",
+            "Seconds since the process started using telemetry",
+        ))
+        .expect("tinywindow_uptime_seconds metric definition is valid");
+        if let Err(err) = REGISTRY.register(Box::new(Uptime { gauge })) {
+            log::warn!("not exporting tinywindow_uptime_seconds: {err}");
+        }
+    });
+    register_process_metrics();
+}
+
+/// Register process resource metrics with [`REGISTRY`].
+///
+/// With the `process` feature on Linux, every scrape reads `/proc/self`
+/// for `process_cpu_seconds_total`, `process_resident_memory_bytes`,
+/// `process_virtual_memory_bytes`, `process_open_fds`, `process_max_fds`,
+/// `process_threads` and `process_start_time_seconds`. A failed read
+/// leaves its metrics out of that scrape and increments
+/// `telemetry_process_read_errors_total`. Elsewhere this does nothing.
+///
+/// Safe to call any number of times; registration happens once. If one of
+/// the names is already taken the registry refuses them all, which is
+/// logged.
+pub fn register_process_metrics() {
+    init_metrics();
+    COLLECTOR_INIT.call_once(|| {
+        #[cfg(all(feature = "process", target_os = "linux"))]
+        if let Err(err) = REGISTRY.register(Box::new(
+            crate::process_collector::ProcessCollector::for_self(namespace()),
+        )) {
+            log::warn!("not exporting process metrics: {err}");
+        }
+    });
+}
+
+/// Export `build_info{version, commit, ...} 1`, the conventional constant
+/// gauge identifying the running build, and register the uptime gauge (see
+/// [`init_process_metrics`]).
+///
+/// Calling it again replaces the previous values; the registry keeps the
+/// label names, so `extra` must have the same keys every time.
+///
+/// # Arguments
+/// * `version` - Release version, e.g. `"1.4.0"`
+/// * `commit` - Source revision, e.g. a git hash
+/// * `extra` - Further `(key, value)` labels, e.g. `[("profile", "release")]`
+///
+/// # Returns
+/// * `Ok(())` - `build_info` carries the given labels
+/// * `Err(TelemetryError::InvalidLabel)` - If a value is empty or contains
+///   characters other than ASCII alphanumerics, `_`, `-`, `.` and `:`, a
+///   key is not a valid label name, or a key repeats (including `version`
+///   or `commit` in `extra`)
+/// * `Err(TelemetryError::Registry)` - If a label collides with a global
+///   label, or the `extra` keys differ from an earlier call; the earlier
+///   `build_info` stays exported
+pub fn register_build_info(
+    version: &str,
+    commit: &str,
+    extra: &[(&str, &str)],
+) -> Result<(), TelemetryError> {
+    let mut labels = vec![("version", version), ("commit", commit)];
+    labels.extend_from_slice(extra);
+    let labels = global_label_map(&labels)?;
+
+    // Fixes the namespace before the gauge is named.
+    init_process_metrics();
+    let gauge = Gauge::with_opts(
+        namespaced_opts(
+            namespace(),
+            "build_info",
+            "Build of the running process; always 1",
+        )
+        .const_labels(labels),
+    )?;
+    gauge.set(1.0);
+
+    let mut current = BUILD_INFO.lock().unwrap();
+    // Two gauges with different values cannot be registered side by side.
+    if let Some(previous) = current.take() {
+        REGISTRY.unregister(Box::new(previous.clone()))?;
+        if let Err(err) = REGISTRY.register(Box::new(gauge.clone())) {
+            match REGISTRY.register(Box::new(previous.clone())) {
+                Ok(()) => *current = Some(previous),
+                Err(err) => log::warn!("cannot restore the previous build_info: {err}"),
+            }
+            return Err(err.into());
+        }
+    } else {
+        REGISTRY.register(Box::new(gauge.clone()))?;
+    }
+    *current = Some(gauge);
+    Ok(())
+}
+
+/// Export `build_info` with `version`, `commit` and the `extra` dict as
+/// labels; raises `ValueError` on unsafe labels (Python binding).
+#[pyfunction]
+#[pyo3(name = "register_build_info", signature = (version, commit, extra = None))]
+pub(crate) fn py_register_build_info(
+    version: &str,
+    commit: &str,
+    extra: Option<HashMap<String, String>>,
+) -> PyResult<()> {
+    let extra: Vec<(&str, &str)> = extra
+        .iter()
+        .flatten()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+    Ok(register_build_info(version, commit, &extra)?)
+}
+
+/// Register process resource metrics (Python binding).
+#[pyfunction]
+#[pyo3(name = "register_process_metrics")]
+pub(crate) fn py_register_process_metrics() {
+    register_process_metrics();
+}
+
+/// Register process metrics (Python binding).
+#[pyfunction]
+#[pyo3(name = "init_process_metrics")]
+pub(crate) fn py_init_process_metrics() {
+    init_process_metrics();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_metrics;
+
+    fn uptime() -> f64 {
+        get_metrics()
+            .lines()
+            .find_map(|line| line.strip_prefix("tinywindow_uptime_seconds "))
+            .and_then(|value| value.parse().ok())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_uptime_increases_between_scrapes() {
+        init_process_metrics();
+        init_process_metrics();
+        let first = uptime();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let second = uptime();
+        assert!(second > first, "{second} <= {first}");
+    }
+
+    #[test]
+    fn test_build_info_is_exported_and_replaced() {
+        register_build_info("1.4.0", "abc123", &[("profile", "release")]).unwrap();
+        let first = uptime();
+        let output = get_metrics();
+        assert!(output
+            .contains("build_info{commit=\"abc123\",profile=\"release\",version=\"1.4.0\"} 1\n"));
+
+        register_build_info("1.4.1", "def456", &[("profile", "debug")]).unwrap();
+        let replaced = "build_info{commit=\"def456\",profile=\"debug\",version=\"1.4.1\"} 1\n";
+        let output = get_metrics();
+        assert!(output.contains(replaced));
+        assert!(!output.contains("abc123"));
+        assert!(uptime() >= first);
+
+        assert!(matches!(
+            register_build_info("1.4.2", "def456", &[]),
+            Err(TelemetryError::Registry(_))
+        ));
+
+        for (version, commit, extra) in [
+            ("1.4.2 beta", "def456", &[][..]),
+            ("1.4.2", "", &[][..]),
+            ("1.4.2", "def456", &[("version", "1.4.3")][..]),
+            ("1.4.2", "def456", &[("bad-key", "x")][..]),
+        ] {
+            assert!(matches!(
+                register_build_info(version, commit, extra),
+                Err(TelemetryError::InvalidLabel(_))
+            ));
+        }
+        assert!(get_metrics().contains(replaced));
+    }
+
+    #[cfg(all(feature = "process", target_os = "linux"))]
+    #[test]
+    fn test_process_collector_is_registered() {
+        init_process_metrics();
+        let rss: f64 = get_metrics()
+            .lines()
+            .find_map(|line| line.strip_prefix("process_resident_memory_bytes "))
+            .and_then(|value| value.parse().ok())
+            .unwrap();
+        assert!(rss > 0.0);
+    }
+
+    #[cfg(not(all(feature = "process", target_os = "linux")))]
+    #[test]
+    fn test_process_metrics_are_a_no_op() {
+        register_process_metrics();
+        let output = crate::try_get_metrics().unwrap();
+        assert!(!output.contains("process_resident_memory_bytes"));
+    }
+}
@@ -0,0 +1,264 @@
+//! Process resource metrics read from `/proc` (requires the `process`
+//! feature, Linux only).
+//!
+//! [`ProcessCollector`] reads the process's `stat`, `fd` and `limits` on
+//! every gather and exports the standard `process_*` metrics. Each value is
+//! read on its own: when one read fails, only the metrics it feeds are left
+//! out of that scrape and `telemetry_process_read_errors_total` goes up, so
+//! a transient `/proc` error never fails the whole scrape.
+
+use std::path::PathBuf;
+
+use procfs::process::{LimitValue, Process};
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::MetricFamily;
+use prometheus::{Counter, Gauge, IntCounter, IntGauge};
+
+use crate::handle::namespaced_opts;
+
+/// Collects CPU, memory, file descriptor and thread metrics of a process.
+pub(crate) struct ProcessCollector {
+    /// The process's `/proc` directory.
+    root: PathBuf,
+    /// Namespace of the exported metrics.
+    namespace: Option<String>,
+    descs: Vec<Desc>,
+    open_fds: IntGauge,
+    max_fds: IntGauge,
+    virtual_memory: IntGauge,
+    resident_memory: IntGauge,
+    start_time: Gauge,
+    threads: IntGauge,
+    read_errors: IntCounter,
+}
+
+impl ProcessCollector {
+    /// Collect metrics of the current process, in `namespace` if given.
+    pub(crate) fn for_self(namespace: Option<&str>) -> Self {
+        Self::with_root(PathBuf::from("/proc/self"), namespace)
+    }
+
+    /// Collect metrics of the process whose `/proc` directory is `root`.
+    pub(crate) fn with_root(root: PathBuf, namespace: Option<&str>) -> Self {
+        let opts = |name: &str, help: &str| namespaced_opts(namespace, name, help);
+        let int_gauge = |name: &str, help: &str| {
+            IntGauge::with_opts(opts(name, help)).expect("process metric definitions are valid")
+        };
+        let cpu_seconds = cpu_seconds_counter(namespace);
+        let open_fds = int_gauge("process_open_fds", "Number of open file descriptors.");
+        let max_fds = int_gauge(
+            "process_max_fds",
+            "Maximum number of open file descriptors.",
+        );
+        let virtual_memory = int_gauge(
+            "process_virtual_memory_bytes",
+            "Virtual memory size in bytes.",
+        );
+        let resident_memory = int_gauge(
+            "process_resident_memory_bytes",
+            "Resident memory size in bytes.",
+        );
+        let start_time = Gauge::with_opts(opts(
+            "process_start_time_seconds",
+            "Start time of the process since unix epoch in seconds.",
+        ))
+        .expect("process metric definitions are valid");
+        let threads = int_gauge("process_threads", "Number of OS threads in the process.");
+        let read_errors = IntCounter::with_opts(opts(
+            "telemetry_process_read_errors_total",
+            "Process metric reads from /proc that failed, leaving the metric out of the scrape",
+        ))
+        .expect("process metric definitions are valid");
+
+        let descs = [
+            cpu_seconds.desc(),
+            open_fds.desc(),
+            max_fds.desc(),
+            virtual_memory.desc(),
+            resident_memory.desc(),
+            start_time.desc(),
+            threads.desc(),
+            read_errors.desc(),
+        ]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+        Self {
+            root,
+            namespace: namespace.map(str::to_string),
+            descs,
+            open_fds,
+            max_fds,
+            virtual_memory,
+            resident_memory,
+            start_time,
+            threads,
+            read_errors,
+        }
+    }
+
+    /// Read every metric into `families`, counting failed reads.
+    fn read(&self, families: &mut Vec<MetricFamily>) {
+        let process = match Process::new_with_root(self.root.clone()) {
+            Ok(process) => process,
+            Err(err) => return self.read_failed("process", err),
+        };
+
+        match process.stat() {
+            Ok(stat) => {
+                let ticks = procfs::ticks_per_second() as f64;
+                // The kernel keeps the total, so each scrape exports it in a
+                // fresh counter rather than adding the difference to a shared
+                // one, which concurrent scrapes would both add.
+                let cpu_seconds = cpu_seconds_counter(self.namespace.as_deref());
+                cpu_seconds.inc_by((stat.utime + stat.stime) as f64 / ticks);
+                self.virtual_memory.set(stat.vsize as i64);
+                self.resident_memory
+                    .set((stat.rss * procfs::page_size()) as i64);
+                self.threads.set(stat.num_threads);
+                families.extend(cpu_seconds.collect());
+                families.extend(self.virtual_memory.collect());
+                families.extend(self.resident_memory.collect());
+                families.extend(self.threads.collect());
+
+                match procfs::boot_time_secs() {
+                    Ok(boot_time) => {
+                        self.start_time
+                            .set(boot_time as f64 + stat.starttime as f64 / ticks);
+                        families.extend(self.start_time.collect());
+                    }
+                    Err(err) => self.read_failed("boot time", err),
+                }
+            }
+            Err(err) => self.read_failed("stat", err),
+        }
+
+        match process.fd_count() {
+            Ok(count) => {
+                self.open_fds.set(count as i64);
+                families.extend(self.open_fds.collect());
+            }
+            Err(err) => self.read_failed("fd", err),
+        }
+
+        match process.limits() {
+            Ok(limits) => {
+                if let LimitValue::Value(max) = limits.max_open_files.soft_limit {
+                    self.max_fds.set(max as i64);
+                    families.extend(self.max_fds.collect());
+                }
+            }
+            Err(err) => self.read_failed("limits", err),
+        }
+    }
+
+    fn read_failed(&self, what: &str, err: procfs::ProcError) {
+        log::debug!(
+            "cannot read process {what} from {}: {err}",
+            self.root.display()
+        );
+        self.read_errors.inc();
+    }
+}
+
+/// An unregistered `process_cpu_seconds_total` in `namespace`, at zero.
+fn cpu_seconds_counter(namespace: Option<&str>) -> Counter {
+    Counter::with_opts(namespaced_opts(
+        namespace,
+        "process_cpu_seconds_total",
+        "Total user and system CPU time spent in seconds.",
+    ))
+    .expect("process metric definitions are valid")
+}
+
+impl Collector for ProcessCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        self.descs.iter().collect()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let mut families = Vec::with_capacity(self.descs.len());
+        self.read(&mut families);
+        families.extend(self.read_errors.collect());
+        families
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(families: &[MetricFamily], name: &str) -> Option<f64> {
+        let family = families.iter().find(|family| family.get_name() == name)?;
+        let metric = &family.get_metric()[0];
+        Some(match family.get_field_type() {
+            prometheus::proto::MetricType::COUNTER => metric.get_counter().get_value(),
+            _ => metric.get_gauge().get_value(),
+        })
+    }
+
+    #[test]
+    fn test_reads_own_process() {
+        let collector = ProcessCollector::for_self(None);
+        let families = collector.collect();
+        assert!(value(&families, "process_resident_memory_bytes").unwrap() > 0.0);
+        assert!(value(&families, "process_virtual_memory_bytes").unwrap() > 0.0);
+        assert!(value(&families, "process_threads").unwrap() >= 1.0);
+        assert!(value(&families, "process_open_fds").unwrap() >= 1.0);
+        assert!(value(&families, "process_cpu_seconds_total").unwrap() >= 0.0);
+        assert!(value(&families, "process_start_time_seconds").unwrap() > 0.0);
+        assert_eq!(
+            value(&families, "telemetry_process_read_errors_total"),
+            Some(0.0)
+        );
+
+        let families = ProcessCollector::for_self(Some("tw_exec")).collect();
+        assert!(value(&families, "tw_exec_process_threads").unwrap() >= 1.0);
+        assert!(value(&families, "tw_exec_process_cpu_seconds_total").is_some());
+    }
+
+    #[test]
+    fn test_concurrent_scrapes_report_kernel_cpu_time() {
+        let collector = ProcessCollector::for_self(None);
+        let reported: Vec<f64> = std::thread::scope(|scope| {
+            let scrapes: Vec<_> = (0..8)
+                .map(|_| {
+                    scope
+                        .spawn(|| value(&collector.collect(), "process_cpu_seconds_total").unwrap())
+                })
+                .collect();
+            scrapes.into_iter().map(|s| s.join().unwrap()).collect()
+        });
+        let stat = Process::myself().unwrap().stat().unwrap();
+        let total = (stat.utime + stat.stime) as f64 / procfs::ticks_per_second() as f64;
+        for cpu_seconds in reported {
+            assert!(cpu_seconds <= total, "{cpu_seconds} > {total}");
+        }
+    }
+
+    #[test]
+    fn test_failed_reads_are_skipped_and_counted() {
+        let collector = ProcessCollector::with_root(PathBuf::from("/nonexistent/1"), None);
+        let families = collector.collect();
+        assert_eq!(families.len(), 1);
+        assert_eq!(
+            value(&families, "telemetry_process_read_errors_total"),
+            Some(1.0)
+        );
+
+        // A directory that is not a real /proc entry: every file read fails.
+        let root = std::env::temp_dir()
+            .join(format!("telemetry-proc-{}", std::process::id()))
+            .join("1");
+        std::fs::create_dir_all(&root).unwrap();
+        let collector = ProcessCollector::with_root(root.clone(), None);
+        let families = collector.collect();
+        std::fs::remove_dir_all(root.parent().unwrap()).unwrap();
+        assert_eq!(families.len(), 1);
+        assert_eq!(
+            value(&families, "telemetry_process_read_errors_total"),
+            Some(3.0)
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! Prometheus Pushgateway client for short-lived jobs.
+//!
+//! Batch jobs may exit before Prometheus scrapes them, so
+//! [`push_metrics`] PUTs the whole registry to a Pushgateway instead. Only
+//! plain `http://` gateways are supported, with [`PUSH_TIMEOUT`] applied to
+//! connecting, writing and reading.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use pyo3::prelude::*;
+
+use crate::http::{self, HttpUrl};
+use crate::{
+    get_metrics, is_valid_metric_name, is_valid_operation, TelemetryError, METRICS_CONTENT_TYPE,
+};
+
+/// Timeout for each phase of a push.
+pub const PUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Push every registered metric to a Pushgateway.
+///
+/// Replaces the group identified by `job` and `grouping_labels`
+/// (`PUT /metrics/job/<job>/<label>/<value>...`).
+///
+/// # Arguments
+/// * `gateway_url` - Gateway base URL, e.g. `"http://pushgateway:9091"`
+/// * `job` - Job name
+/// * `grouping_labels` - Extra `(name, value)` pairs identifying the group
+///
+/// # Returns
+/// * `Ok(())` - The gateway accepted the push
+/// * `Err(TelemetryError::InvalidLabel)` - If `job` or a grouping label is invalid
+/// * `Err(TelemetryError::PushConnection)` - If the gateway is unreachable or the URL is unusable
+/// * `Err(TelemetryError::PushRejected)` - If the gateway answered with a non-2xx status
+pub fn push_metrics(
+    gateway_url: &str,
+    job: &str,
+    grouping_labels: &[(&str, &str)],
+) -> Result<(), TelemetryError> {
+    if !is_valid_operation(job) {
+        return Err(TelemetryError::InvalidLabel(format!("job={job}")));
+    }
+    let mut path = format!("/metrics/job/{job}");
+    for (name, value) in grouping_labels {
+        if !is_valid_metric_name(name) || !is_valid_operation(value) {
+            return Err(TelemetryError::InvalidLabel(format!("{name}={value}")));
+        }
+        path.push_str(&format!("/{name}/{value}"));
+    }
+
+    let url = HttpUrl::parse(gateway_url).map_err(TelemetryError::PushConnection)?;
+    let response = http::send(
+        "PUT",
+        &url.host,
+        &format!("{}{path}", url.path),
+        METRICS_CONTENT_TYPE,
+        get_metrics().as_bytes(),
+        PUSH_TIMEOUT,
+    )
+    .map_err(TelemetryError::PushConnection)?;
+    if !(200..300).contains(&response.status) {
+        return Err(TelemetryError::PushRejected {
+            status: response.status,
+            body: response.body,
+        });
+    }
+    Ok(())
+}
+
+/// [`push_metrics`], then reset every counter to zero.
+///
+/// Only counters created through this crate ([`emit_metric`],
+/// [`emit_counter`], [`register_counter`]) are reset, and only after a
+/// successful push; histograms are left untouched.
+///
+/// [`emit_metric`]: crate::emit_metric
+/// [`emit_counter`]: crate::emit_counter
+/// [`register_counter`]: crate::register_counter
+pub fn push_metrics_and_clear(
+    gateway_url: &str,
+    job: &str,
+    grouping_labels: &[(&str, &str)],
+) -> Result<(), TelemetryError> {
+    push_metrics(gateway_url, job, grouping_labels)?;
+    crate::reset_counters();
+    Ok(())
+}
+
+/// Push metrics to a Pushgateway; `grouping_labels` is a dict (Python binding).
+///
+/// Raises `ValueError` on invalid labels, connection failures or non-2xx responses.
+#[pyfunction]
+#[pyo3(name = "push_metrics", signature = (gateway_url, job, grouping_labels = None, clear = false))]
+pub(crate) fn py_push_metrics(
+    gateway_url: &str,
+    job: &str,
+    grouping_labels: Option<HashMap<String, String>>,
+    clear: bool,
+) -> PyResult<()> {
+    let grouping_labels = grouping_labels.unwrap_or_default();
+    let labels: Vec<(&str, &str)> = grouping_labels
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+    if clear {
+        Ok(push_metrics_and_clear(gateway_url, job, &labels)?)
+    } else {
+        Ok(push_metrics(gateway_url, job, &labels)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emit_metric;
+    use crate::mock_http::serve_once;
+
+    #[test]
+    fn test_push_puts_registry_to_job_path() {
+        emit_metric("test_pushed_total", 2.0);
+        let (url, request) = serve_once(200, "");
+
+        push_metrics(&url, "eod_recon", &[("instance", "host-1")]).unwrap();
+
+        let (request_line, body) = request.join().unwrap();
+        assert_eq!(
+            request_line,
+            "PUT /metrics/job/eod_recon/instance/host-1 HTTP/1.1"
+        );
+        assert!(String::from_utf8(body)
+            .unwrap()
+            .contains("test_pushed_total 2"));
+    }
+
+    #[test]
+    fn test_push_keeps_gateway_path_prefix() {
+        let (url, request) = serve_once(202, "");
+        push_metrics(&format!("{url}/gateway/"), "eod_recon", &[]).unwrap();
+        let (request_line, _) = request.join().unwrap();
+        assert_eq!(request_line, "PUT /gateway/metrics/job/eod_recon HTTP/1.1");
+    }
+
+    #[test]
+    fn test_non_2xx_response_is_rejected() {
+        let (url, _request) = serve_once(500, "nope");
+        assert_eq!(
+            push_metrics(&url, "eod_recon", &[]),
+            Err(TelemetryError::PushRejected {
+                status: 500,
+                body: "nope".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_unreachable_gateway_is_a_connection_error() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        assert!(matches!(
+            push_metrics(&url, "eod_recon", &[]),
+            Err(TelemetryError::PushConnection(_))
+        ));
+        assert!(matches!(
+            push_metrics("https://gateway:9091", "eod_recon", &[]),
+            Err(TelemetryError::PushConnection(_))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_unsafe_path_segments() {
+        assert!(matches!(
+            push_metrics("http://127.0.0.1:1", "eod/recon", &[]),
+            Err(TelemetryError::InvalidLabel(_))
+        ));
+        assert!(matches!(
+            push_metrics("http://127.0.0.1:1", "eod", &[("instance", "a/b")]),
+            Err(TelemetryError::InvalidLabel(_))
+        ));
+    }
+}
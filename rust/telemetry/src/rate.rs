@@ -0,0 +1,159 @@
+//! Sliding-window event rates exported as gauges.
+//!
+//! A [`RateGauge`] keeps the timestamps of recent events in a ring buffer
+//! and reports how many fell within the last window, per second. The rate
+//! is computed when read, so a scrape after traffic stops sees it decay to
+//! zero instead of the last value set.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use prometheus::core::{Collector, Desc, Describer};
+use prometheus::{proto, Opts};
+
+/// Window [`orders_per_second`](crate::orders_per_second) averages over
+/// unless changed with
+/// [`set_orders_rate_window`](crate::set_orders_rate_window).
+pub const DEFAULT_RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Most timestamps a [`RateGauge`] keeps. Above `MAX_RATE_SAMPLES` events
+/// per window the oldest are dropped and the rate is measured over the
+/// span the buffer still covers.
+const MAX_RATE_SAMPLES: usize = 65_536;
+
+struct Window {
+    length: Duration,
+    stamps: VecDeque<Instant>,
+}
+
+impl Window {
+    /// Forget events older than the window.
+    fn prune(&mut self, now: Instant) {
+        while let Some(&oldest) = self.stamps.front() {
+            if now.saturating_duration_since(oldest) <= self.length {
+                break;
+            }
+            self.stamps.pop_front();
+        }
+    }
+}
+
+/// Events per second over a sliding window.
+#[derive(Clone)]
+pub(crate) struct RateGauge {
+    desc: Desc,
+    window: Arc<Mutex<Window>>,
+}
+
+impl RateGauge {
+    /// Create an unregistered gauge averaging over [`DEFAULT_RATE_WINDOW`].
+    pub(crate) fn new(opts: Opts) -> prometheus::Result<Self> {
+        Ok(Self {
+            desc: opts.describe()?,
+            window: Arc::new(Mutex::new(Window {
+                length: DEFAULT_RATE_WINDOW,
+                stamps: VecDeque::new(),
+            })),
+        })
+    }
+
+    /// Record one event now.
+    pub(crate) fn record(&self) {
+        self.record_at(Instant::now());
+    }
+
+    fn record_at(&self, now: Instant) {
+        let mut window = self.window.lock().unwrap();
+        window.prune(now);
+        if window.stamps.len() == MAX_RATE_SAMPLES {
+            window.stamps.pop_front();
+        }
+        window.stamps.push_back(now);
+    }
+
+    /// Events per second over the window ending now.
+    pub(crate) fn rate(&self) -> f64 {
+        self.rate_at(Instant::now())
+    }
+
+    fn rate_at(&self, now: Instant) -> f64 {
+        let mut window = self.window.lock().unwrap();
+        window.prune(now);
+        let events = window.stamps.len() as f64;
+        let span = match window.stamps.front() {
+            Some(&oldest) if window.stamps.len() == MAX_RATE_SAMPLES => {
+                now.saturating_duration_since(oldest)
+            }
+            _ => window.length,
+        };
+        if span.is_zero() {
+            return 0.0;
+        }
+        events / span.as_secs_f64()
+    }
+
+    /// Average over `length` from now on; `length` must be non-zero.
+    pub(crate) fn set_window(&self, length: Duration) {
+        self.window.lock().unwrap().length = length;
+    }
+
+    /// Forget every recorded event.
+    pub(crate) fn clear(&self) {
+        self.window.lock().unwrap().stamps.clear();
+    }
+}
+
+impl Collector for RateGauge {
+    fn desc(&self) -> Vec<&Desc> {
+        vec![&self.desc]
+    }
+
+    fn collect(&self) -> Vec<proto::MetricFamily> {
+        let mut gauge = proto::Gauge::default();
+        gauge.set_value(self.rate());
+        let mut metric = proto::Metric::default();
+        metric.set_gauge(gauge);
+
+        let mut family = proto::MetricFamily::default();
+        family.set_name(self.desc.fq_name.clone());
+        family.set_help(self.desc.help.clone());
+        family.set_field_type(proto::MetricType::GAUGE);
+        family.set_metric(vec![metric].into());
+        vec![family]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_over_sliding_window() {
+        let gauge = RateGauge::new(Opts::new("test_per_second", "help")).unwrap();
+        let start = Instant::now();
+        for ms in [0, 100, 200, 300] {
+            gauge.record_at(start + Duration::from_millis(ms));
+        }
+        assert_eq!(gauge.rate_at(start + Duration::from_millis(300)), 4.0);
+        // The first two events have left the window.
+        assert_eq!(gauge.rate_at(start + Duration::from_millis(1150)), 2.0);
+        assert_eq!(gauge.rate_at(start + Duration::from_secs(2)), 0.0);
+
+        gauge.set_window(Duration::from_millis(500));
+        gauge.record_at(start + Duration::from_secs(3));
+        assert_eq!(gauge.rate_at(start + Duration::from_secs(3)), 2.0);
+    }
+
+    #[test]
+    fn test_full_buffer_measures_its_own_span() {
+        let gauge = RateGauge::new(Opts::new("test_per_second", "help")).unwrap();
+        let start = Instant::now();
+        for i in 0..MAX_RATE_SAMPLES as u64 + 10 {
+            gauge.record_at(start + Duration::from_micros(i));
+        }
+        let now = start + Duration::from_micros(MAX_RATE_SAMPLES as u64 + 10);
+        let rate = gauge.rate_at(now);
+        assert!((rate - 1e6).abs() < 1.0, "{rate}");
+    }
+}
@@ -0,0 +1,100 @@
+//! Counters that can be read and zeroed in one step.
+//!
+//! A Prometheus `Counter` only offers a separate `get` and `reset`, so an
+//! increment landing between the two would be lost. [`ResettableCounter`]
+//! keeps its value as `f64` bits in an `AtomicU64`, which lets
+//! [`take`](ResettableCounter::take) swap it for zero atomically. It is
+//! exported as an ordinary counter; scrapers see it drop back to zero after
+//! every take, which Prometheus treats as a counter reset.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use prometheus::core::{Collector, Desc, Describer};
+use prometheus::{proto, Opts};
+
+/// A counter supporting pull-and-reset reads.
+#[derive(Clone)]
+pub(crate) struct ResettableCounter {
+    desc: Desc,
+    bits: Arc<AtomicU64>,
+}
+
+impl ResettableCounter {
+    /// Create an unregistered counter at zero.
+    pub(crate) fn new(opts: Opts) -> prometheus::Result<Self> {
+        Ok(Self {
+            desc: opts.describe()?,
+            bits: Arc::new(AtomicU64::new(0.0f64.to_bits())),
+        })
+    }
+
+    /// Add `value`, which the caller has checked is finite and non-negative.
+    pub(crate) fn inc_by(&self, value: f64) {
+        let _ = self
+            .bits
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |bits| {
+                Some((f64::from_bits(bits) + value).to_bits())
+            });
+    }
+
+    /// Current value.
+    pub(crate) fn get(&self) -> f64 {
+        f64::from_bits(self.bits.load(Ordering::Acquire))
+    }
+
+    /// Return the current value and set the counter to zero, atomically.
+    pub(crate) fn take(&self) -> f64 {
+        f64::from_bits(self.bits.swap(0.0f64.to_bits(), Ordering::AcqRel))
+    }
+}
+
+impl Collector for ResettableCounter {
+    fn desc(&self) -> Vec<&Desc> {
+        vec![&self.desc]
+    }
+
+    fn collect(&self) -> Vec<proto::MetricFamily> {
+        let mut counter = proto::Counter::default();
+        counter.set_value(self.get());
+        let mut metric = proto::Metric::default();
+        metric.set_counter(counter);
+
+        let mut family = proto::MetricFamily::default();
+        family.set_name(self.desc.fq_name.clone());
+        family.set_help(self.desc.help.clone());
+        family.set_field_type(proto::MetricType::COUNTER);
+        family.set_metric(vec![metric].into());
+        vec![family]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_take_loses_no_increments() {
+        let counter = ResettableCounter::new(Opts::new("test_take_total", "help")).unwrap();
+        let taken: f64 = thread::scope(|scope| {
+            let writers: Vec<_> = (0..4)
+                .map(|_| {
+                    scope.spawn(|| {
+                        for _ in 0..1000 {
+                            counter.inc_by(1.0);
+                        }
+                    })
+                })
+                .collect();
+            let mut taken = 0.0;
+            while writers.iter().any(|writer| !writer.is_finished()) {
+                taken += counter.take();
+            }
+            taken
+        });
+        assert_eq!(taken + counter.take(), 4000.0);
+        assert_eq!(counter.get(), 0.0);
+    }
+}
@@ -0,0 +1,283 @@
+//! Built-in HTTP endpoint for Prometheus scrapes.
+//!
+//! [`serve_metrics`] runs a small HTTP server on a background thread that
+//! answers `GET /metrics` with [`get_metrics`], `GET /healthz` with the
+//! [`health_report`](crate::health_report) as JSON (200 when healthy, 503
+//! otherwise) and 404 for anything else.
+//! Scrapers that accept the protobuf format (per the `Accept` header) get
+//! [`get_metrics_protobuf`] instead. `prefix[]`, `name[]` and
+//! `label[]=key=value` query parameters narrow the output, as a
+//! [`MetricFilter`] would.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use pyo3::prelude::*;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::filter::MetricFilter;
+use crate::health::HealthStatus;
+use crate::{get_metrics, get_metrics_protobuf, init_metrics, TelemetryError, DEFAULT};
+
+/// Content type of the Prometheus text exposition format.
+pub const METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Start serving the registry at `http://{addr}/metrics`.
+///
+/// # Arguments
+/// * `addr` - Address to bind, e.g. `"0.0.0.0:9100"`; port 0 picks a free port
+///
+/// # Returns
+/// * `Ok(MetricsServerHandle)` - The running server
+/// * `Err(TelemetryError::Server)` - If `addr` cannot be bound
+pub fn serve_metrics(addr: &str) -> Result<MetricsServerHandle, TelemetryError> {
+    let server = Server::http(addr)
+        .map_err(|err| TelemetryError::Server(format!("cannot bind {addr}: {err}")))?;
+    let local_addr = server
+        .server_addr()
+        .to_ip()
+        .ok_or_else(|| TelemetryError::Server(format!("{addr} is not an IP address")))?;
+    let server = Arc::new(server);
+    let worker = Arc::clone(&server);
+    let thread = std::thread::Builder::new()
+        .name("metrics-server".to_string())
+        .spawn(move || {
+            for request in worker.incoming_requests() {
+                respond(request);
+            }
+        })
+        .map_err(|err| TelemetryError::Server(format!("cannot spawn server thread: {err}")))?;
+
+    Ok(MetricsServerHandle {
+        server,
+        thread: Some(thread),
+        local_addr,
+    })
+}
+
+fn respond(request: Request) {
+    let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+    let result = if *request.method() == Method::Get && path == "/metrics" {
+        let filter = MetricFilter::from_query(query);
+        init_metrics();
+        let (body, content_type) = match (accepts_protobuf(&request), filter.is_empty()) {
+            (true, true) => (get_metrics_protobuf(), prometheus::PROTOBUF_FORMAT),
+            (true, false) => (
+                DEFAULT.get_metrics_protobuf_filtered(&filter),
+                prometheus::PROTOBUF_FORMAT,
+            ),
+            (false, true) => (get_metrics().into_bytes(), METRICS_CONTENT_TYPE),
+            (false, false) => (
+                DEFAULT.get_metrics_filtered(&filter).into_bytes(),
+                METRICS_CONTENT_TYPE,
+            ),
+        };
+        let content_type =
+            Header::from_bytes("Content-Type", content_type).expect("content type header is valid");
+        request.respond(Response::from_data(body).with_header(content_type))
+    } else if *request.method() == Method::Get && path == "/healthz" {
+        init_metrics();
+        let report = DEFAULT.health_report();
+        let status = if report.status == HealthStatus::Healthy {
+            200
+        } else {
+            503
+        };
+        let content_type = Header::from_bytes("Content-Type", "application/json")
+            .expect("content type header is valid");
+        request.respond(
+            Response::from_string(report.to_json())
+                .with_status_code(status)
+                .with_header(content_type),
+        )
+    } else {
+        request.respond(Response::from_string("not found").with_status_code(404))
+    };
+    if let Err(err) = result {
+        log::warn!("failed to answer metrics request: {err}");
+    }
+}
+
+/// Whether the scraper asked for the delimited `MetricFamily` protobuf format.
+fn accepts_protobuf(request: &Request) -> bool {
+    request.headers().iter().any(|header| {
+        header.field.equiv("Accept")
+            && header
+                .value
+                .as_str()
+                .contains("proto=io.prometheus.client.MetricFamily")
+    })
+}
+
+/// A running metrics server; shuts down when dropped.
+pub struct MetricsServerHandle {
+    server: Arc<Server>,
+    thread: Option<JoinHandle<()>>,
+    local_addr: SocketAddr,
+}
+
+impl MetricsServerHandle {
+    /// The address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Stop accepting requests and wait for the server thread to exit.
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            self.server.unblock();
+            // The thread only answers requests; a panic there has nothing to clean up.
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for MetricsServerHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Python wrapper around [`MetricsServerHandle`].
+#[pyclass(name = "MetricsServer")]
+pub(crate) struct PyMetricsServer {
+    handle: Option<MetricsServerHandle>,
+    local_addr: SocketAddr,
+}
+
+#[pymethods]
+impl PyMetricsServer {
+    /// The `host:port` the server is listening on.
+    fn local_addr(&self) -> String {
+        self.local_addr.to_string()
+    }
+
+    /// Stop the server; calling it again does nothing.
+    fn shutdown(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            handle.shutdown();
+        }
+    }
+}
+
+/// Start serving `/metrics` on `addr` (Python binding).
+#[pyfunction]
+#[pyo3(name = "serve_metrics")]
+pub(crate) fn py_serve_metrics(addr: &str) -> PyResult<PyMetricsServer> {
+    let handle = serve_metrics(addr)?;
+    Ok(PyMetricsServer {
+        local_addr: handle.local_addr(),
+        handle: Some(handle),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emit_metric;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    fn http_get(addr: SocketAddr, path: &str) -> String {
+        String::from_utf8(request(addr, path, "")).unwrap()
+    }
+
+    fn request(addr: SocketAddr, path: &str, extra_headers: &str) -> Vec<u8> {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "GET {path} HTTP/1.1\r\nHost: {addr}\r\n{extra_headers}Connection: close\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_serves_metrics_with_prometheus_content_type() {
+        emit_metric("test_served_total", 3.0);
+        let server = serve_metrics("127.0.0.1:0").unwrap();
+        assert_ne!(server.local_addr().port(), 0);
+
+        let response = http_get(server.local_addr(), "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.contains("Content-Type: text/plain; version=0.0.4"));
+        assert!(response.contains("test_served_total 3"));
+
+        server.shutdown();
+    }
+
+    #[test]
+    fn test_serves_protobuf_when_accepted() {
+        emit_metric("test_served_protobuf_total", 2.0);
+        let server = serve_metrics("127.0.0.1:0").unwrap();
+        let response = request(
+            server.local_addr(),
+            "/metrics",
+            "Accept: application/vnd.google.protobuf;proto=io.prometheus.client.MetricFamily;\
+             encoding=delimited;q=0.7,text/plain;version=0.0.4;q=0.3\r\n",
+        );
+        let split = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+        let head = String::from_utf8_lossy(&response[..split]);
+        assert!(head.starts_with("HTTP/1.1 200"));
+        assert!(head.contains(&format!("Content-Type: {}", prometheus::PROTOBUF_FORMAT)));
+
+        let families = crate::tests::decode_protobuf(&response[split + 4..]);
+        assert!(families
+            .iter()
+            .any(|family| family.get_name() == "test_served_protobuf_total"));
+    }
+
+    #[test]
+    fn test_query_filters_output() {
+        emit_metric("test_served_filtered_total", 1.0);
+        emit_metric("test_served_unfiltered_total", 1.0);
+        let server = serve_metrics("127.0.0.1:0").unwrap();
+
+        let response = http_get(
+            server.local_addr(),
+            "/metrics?name%5B%5D=test_served_filtered_total",
+        );
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.contains("test_served_filtered_total 1"));
+        assert!(!response.contains("test_served_unfiltered_total"));
+
+        let response = http_get(
+            server.local_addr(),
+            "/metrics?prefix[]=test_served_nothing_",
+        );
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(!response.contains("# TYPE"));
+    }
+
+    #[test]
+    fn test_serves_health_report() {
+        let server = serve_metrics("127.0.0.1:0").unwrap();
+        let response = http_get(server.local_addr(), "/healthz");
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.contains("Content-Type: application/json"));
+        assert!(response.contains("\"status\":\"Healthy\""));
+        assert!(http_get(server.local_addr(), "/healthzx").starts_with("HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn test_other_paths_are_not_found() {
+        let server = serve_metrics("127.0.0.1:0").unwrap();
+        assert!(http_get(server.local_addr(), "/").starts_with("HTTP/1.1 404"));
+        assert!(http_get(server.local_addr(), "/metricsx").starts_with("HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn test_bind_failure_is_reported() {
+        assert!(matches!(
+            serve_metrics("not an address"),
+            Err(TelemetryError::Server(_))
+        ));
+    }
+}
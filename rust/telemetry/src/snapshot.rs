@@ -0,0 +1,314 @@
+//! Counter and gauge persistence across restarts.
+//!
+//! A restart resets every counter to zero, which Prometheus reads as a
+//! counter reset. [`snapshot_to_file`] saves the current values of the
+//! counters and gauges created by name, and [`restore_from_file`] puts them
+//! back in a new process, so the series continue where they left off:
+//!
+//! ```json
+//! {
+//!   "counters": [{"name": "fills_total", "labels": {"venue": "nyse"}, "value": 12}],
+//!   "gauges": [{"name": "queue_depth", "labels": {}, "value": 3}]
+//! }
+//! ```
+//!
+//! Only counters from [`emit_metric`], [`register_counter`] and
+//! [`emit_counter`] (including `orders_total`) and gauges from
+//! [`set_gauge`] are saved; histograms, summaries, resettable counters and
+//! the built-in `errors_total` and `telemetry_*` counters are not. Names
+//! are saved without the [`set_namespace`](crate::set_namespace) prefix.
+//!
+//! [`emit_metric`]: crate::emit_metric
+//! [`register_counter`]: crate::register_counter
+//! [`emit_counter`]: crate::emit_counter
+//! [`set_gauge`]: crate::set_gauge
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{init_metrics, TelemetryError, DEFAULT};
+
+/// Saved counter and gauge values.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Snapshot {
+    pub(crate) counters: Vec<Sample>,
+    pub(crate) gauges: Vec<Sample>,
+}
+
+/// The value of one series.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Sample {
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) labels: BTreeMap<String, String>,
+    pub(crate) value: f64,
+}
+
+impl Sample {
+    pub(crate) fn labels(&self) -> Vec<(&str, &str)> {
+        self.labels
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect()
+    }
+}
+
+/// Save the current counter and gauge values to `path` as JSON.
+///
+/// The file is written next to `path` and renamed over it, so a crash
+/// mid-write leaves the previous snapshot intact.
+///
+/// # Returns
+/// * `Ok(())` - The snapshot was saved
+/// * `Err(TelemetryError::Io)` - If the file could not be written
+pub fn snapshot_to_file(path: &Path) -> Result<(), TelemetryError> {
+    init_metrics();
+    DEFAULT.snapshot_to_file(path)
+}
+
+/// Add the values saved by [`snapshot_to_file`] to the counters and set
+/// the gauges.
+///
+/// Call it at startup, before anything is recorded, so the counters
+/// resume from their saved values. Counters that do not exist yet are
+/// created as by [`emit_metric`](crate::emit_metric) and
+/// [`emit_counter`](crate::emit_counter); in strict mode, register them
+/// first. Series that cannot be restored are skipped with a warning.
+///
+/// A missing or unreadable file is not an error: a warning is logged and
+/// every metric starts from zero.
+///
+/// # Returns
+/// Whether a snapshot was loaded
+pub fn restore_from_file(path: &Path) -> bool {
+    init_metrics();
+    DEFAULT.restore_from_file(path)
+}
+
+/// Call [`snapshot_to_file`] every `interval_secs` seconds on a background
+/// thread, until the returned handle is stopped or dropped.
+///
+/// Failed snapshots are logged and retried on the next tick.
+pub fn start_snapshotter(path: &Path, interval_secs: u64) -> SnapshotterHandle {
+    init_metrics();
+    let path = path.to_path_buf();
+    let interval = Duration::from_secs(interval_secs);
+    let (stop, stopped) = mpsc::channel::<()>();
+    let thread = std::thread::Builder::new()
+        .name("telemetry-snapshotter".to_string())
+        .spawn(move || loop {
+            let result = stopped.recv_timeout(interval);
+            if let Err(err) = snapshot_to_file(&path) {
+                log::warn!("metrics snapshot failed: {err}");
+            }
+            if !matches!(result, Err(RecvTimeoutError::Timeout)) {
+                return;
+            }
+        })
+        .expect("failed to spawn the telemetry snapshotter thread");
+    SnapshotterHandle {
+        stop,
+        thread: Some(thread),
+    }
+}
+
+/// A running background snapshotter; stops (after a final snapshot) when
+/// dropped.
+pub struct SnapshotterHandle {
+    stop: Sender<()>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SnapshotterHandle {
+    /// Take one last snapshot and stop the snapshotter thread.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            // A send error means the thread already exited.
+            let _ = self.stop.send(());
+            // The thread only writes snapshots; a panic there has nothing
+            // to clean up.
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for SnapshotterHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Write `snapshot` to `path` through a temporary file.
+pub(crate) fn write(snapshot: &Snapshot, path: &Path) -> Result<(), TelemetryError> {
+    let describe = |err: std::io::Error| TelemetryError::Io(format!("{}: {err}", path.display()));
+    let json = serde_json::to_string(snapshot).expect("snapshots always serialize");
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    fs::write(&temp, json).map_err(describe)?;
+    fs::rename(&temp, path).map_err(describe)
+}
+
+/// Read a snapshot from `path`, logging why if there is none.
+pub(crate) fn read(path: &Path) -> Option<Snapshot> {
+    let json = match fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(err) => {
+            log::warn!(
+                "no metrics snapshot restored from {}: {err}",
+                path.display()
+            );
+            return None;
+        }
+    };
+    match serde_json::from_str(&json) {
+        Ok(snapshot) => Some(snapshot),
+        Err(err) => {
+            log::warn!(
+                "ignoring corrupt metrics snapshot {}: {err}",
+                path.display()
+            );
+            None
+        }
+    }
+}
+
+/// Python wrapper around [`SnapshotterHandle`].
+#[pyclass(name = "Snapshotter")]
+pub(crate) struct PySnapshotter(Option<SnapshotterHandle>);
+
+#[pymethods]
+impl PySnapshotter {
+    /// Take one last snapshot and stop; calling it again does nothing.
+    fn stop(&mut self, py: Python<'_>) {
+        if let Some(handle) = self.0.take() {
+            py.allow_threads(|| handle.stop());
+        }
+    }
+}
+
+/// Save counter and gauge values to `path` (Python binding).
+///
+/// Raises `ValueError` if the file cannot be written.
+#[pyfunction]
+#[pyo3(name = "snapshot_to_file")]
+pub(crate) fn py_snapshot_to_file(path: PathBuf) -> PyResult<()> {
+    Ok(snapshot_to_file(&path)?)
+}
+
+/// Restore values saved by `snapshot_to_file`; `False` if there was no
+/// usable snapshot (Python binding).
+#[pyfunction]
+#[pyo3(name = "restore_from_file")]
+pub(crate) fn py_restore_from_file(path: PathBuf) -> bool {
+    restore_from_file(&path)
+}
+
+/// Snapshot to `path` every `interval_secs` seconds until stopped (Python
+/// binding).
+#[pyfunction]
+#[pyo3(name = "start_snapshotter")]
+pub(crate) fn py_start_snapshotter(path: PathBuf, interval_secs: u64) -> PySnapshotter {
+    PySnapshotter(Some(start_snapshotter(&path, interval_secs)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{emit_metric, TelemetryHandle};
+
+    /// A path in the temp directory unique to this process and test.
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("tinywindow-{}-{name}.json", std::process::id()))
+    }
+
+    #[test]
+    fn test_round_trip_across_restart() {
+        let path = temp_path("restart");
+        let before = TelemetryHandle::new_isolated();
+        before.emit_metric("fills_total", 12.0);
+        before
+            .emit_counter("rejects_total", &[("venue", "nyse")], 3.0)
+            .unwrap();
+        before
+            .set_gauge("queue_depth", &[("venue", "nyse")], -2.5)
+            .unwrap();
+        before.orders_total().inc_by(7);
+        before.record_latency("order_send", 100.0);
+        before.snapshot_to_file(&path).unwrap();
+
+        let after = TelemetryHandle::new_isolated();
+        assert!(after.restore_from_file(&path));
+        after.emit_metric("fills_total", 1.0);
+        assert_eq!(after.get_counter_value("fills_total"), Some(13.0));
+        assert_eq!(after.orders_total().get(), 7);
+        let output = after.get_metrics();
+        assert!(output.contains("rejects_total{venue=\"nyse\"} 3\n"));
+        assert!(output.contains("queue_depth{venue=\"nyse\"} -2.5\n"));
+        assert!(!output.contains("latency_seconds"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_round_trip_with_namespace() {
+        let path = temp_path("namespace");
+        let before = TelemetryHandle::with_namespace("tw_exec").unwrap();
+        before.emit_metric("fills_total", 2.0);
+        before
+            .emit_counter("rejects_total", &[("venue", "nyse")], 4.0)
+            .unwrap();
+        before.set_gauge("queue_depth", &[], 5.0).unwrap();
+        before.orders_total().inc_by(3);
+        before.snapshot_to_file(&path).unwrap();
+
+        let after = TelemetryHandle::with_namespace("tw_exec").unwrap();
+        assert!(after.restore_from_file(&path));
+        assert_eq!(after.orders_total().get(), 3);
+        assert_eq!(after.get_counter_value("tw_exec_fills_total"), Some(2.0));
+        assert_eq!(after.unknown_metrics_total().get(), 1.0);
+        let output = after.get_metrics();
+        assert!(output.contains("tw_exec_rejects_total{venue=\"nyse\"} 4\n"));
+        assert!(output.contains("tw_exec_queue_depth 5\n"));
+        assert!(!output.contains("tw_exec_tw_exec_"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_missing_or_corrupt_file_starts_from_zero() {
+        let handle = TelemetryHandle::new_isolated();
+        assert!(!handle.restore_from_file(&temp_path("missing")));
+
+        let path = temp_path("corrupt");
+        fs::write(&path, "{\"counters\": [{\"name\": \"fills_total\"").unwrap();
+        assert!(!handle.restore_from_file(&path));
+        assert_eq!(handle.get_counter_value("fills_total"), None);
+        assert_eq!(handle.orders_total().get(), 0);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_snapshotter_writes_on_stop() {
+        let path = temp_path("snapshotter");
+        let snapshotter = start_snapshotter(&path, 3600);
+        emit_metric("test_snapshotter_total", 4.0);
+        snapshotter.stop();
+
+        let snapshot = read(&path).unwrap();
+        assert!(snapshot
+            .counters
+            .iter()
+            .any(|sample| sample.name == "test_snapshotter_total"));
+        fs::remove_file(&path).unwrap();
+    }
+}
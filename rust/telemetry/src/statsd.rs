@@ -0,0 +1,190 @@
+//! StatsD/DogStatsD mirroring for hosts running a Datadog agent.
+//!
+//! Once [`enable_statsd`] is called, [`emit_metric`], [`emit_counter`],
+//! [`record_latency`] and [`set_gauge`] also send a UDP packet per call:
+//!
+//! ```text
+//! <prefix>.orders_total:1|c
+//! <prefix>.latency:0.25|ms|#operation:order_send
+//! <prefix>.queue_depth:3|g|#venue:nyse
+//! ```
+//!
+//! Sending is fire-and-forget on a non-blocking socket: an unreachable
+//! agent or a full socket buffer drops the packet silently.
+//!
+//! [`emit_metric`]: crate::emit_metric
+//! [`emit_counter`]: crate::emit_counter
+//! [`record_latency`]: crate::record_latency
+//! [`set_gauge`]: crate::set_gauge
+
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::sync::RwLock;
+
+use pyo3::prelude::*;
+
+use crate::TelemetryError;
+
+/// Current StatsD destination, if enabled.
+static SINK: RwLock<Option<Sink>> = RwLock::new(None);
+
+struct Sink {
+    socket: UdpSocket,
+    prefix: String,
+}
+
+/// StatsD metric type of a packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    Counter,
+    /// Milliseconds
+    Timing,
+    Gauge,
+}
+
+impl Kind {
+    fn suffix(self) -> &'static str {
+        match self {
+            Kind::Counter => "c",
+            Kind::Timing => "ms",
+            Kind::Gauge => "g",
+        }
+    }
+}
+
+/// Start mirroring metrics to a StatsD agent.
+///
+/// Replaces any previous destination.
+///
+/// # Arguments
+/// * `addr` - Agent address, e.g. `"127.0.0.1:8125"`
+/// * `prefix` - Prepended to every metric name with a `.`; may be empty
+///
+/// # Returns
+/// * `Ok(())` - Metrics are now mirrored to `addr`
+/// * `Err(TelemetryError::Statsd)` - If `addr` does not resolve, `prefix`
+///   contains StatsD delimiters, or no local socket can be opened
+pub fn enable_statsd(addr: &str, prefix: &str) -> Result<(), TelemetryError> {
+    if prefix
+        .chars()
+        .any(|c| c.is_whitespace() || matches!(c, ':' | '|' | '@' | '#' | ','))
+    {
+        return Err(TelemetryError::Statsd(format!(
+            "prefix {prefix:?} contains a StatsD delimiter"
+        )));
+    }
+    let describe = |err: std::io::Error| TelemetryError::Statsd(format!("{addr}: {err}"));
+    let target = addr
+        .to_socket_addrs()
+        .map_err(describe)?
+        .next()
+        .ok_or_else(|| TelemetryError::Statsd(format!("{addr}: no address")))?;
+    let local = if target.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(local).map_err(describe)?;
+    socket.connect(target).map_err(describe)?;
+    socket.set_nonblocking(true).map_err(describe)?;
+
+    *SINK.write().unwrap() = Some(Sink {
+        socket,
+        prefix: prefix.to_string(),
+    });
+    Ok(())
+}
+
+/// Stop mirroring metrics to StatsD. Does nothing if it is not enabled.
+pub fn disable_statsd() {
+    *SINK.write().unwrap() = None;
+}
+
+/// Whether metrics are currently mirrored to StatsD.
+pub fn statsd_enabled() -> bool {
+    SINK.read().unwrap().is_some()
+}
+
+/// Send one packet if StatsD is enabled.
+pub(crate) fn send(name: &str, value: f64, kind: Kind, tags: &[(&str, &str)]) {
+    if let Some(sink) = SINK.read().unwrap().as_ref() {
+        let packet = format_packet(&sink.prefix, name, value, kind, tags);
+        // Fire-and-forget: StatsD is best effort by design.
+        let _ = sink.socket.send(packet.as_bytes());
+    }
+}
+
+fn format_packet(
+    prefix: &str,
+    name: &str,
+    value: f64,
+    kind: Kind,
+    tags: &[(&str, &str)],
+) -> String {
+    let mut packet = if prefix.is_empty() {
+        format!("{name}:{value}|{}", kind.suffix())
+    } else {
+        format!("{prefix}.{name}:{value}|{}", kind.suffix())
+    };
+    if !tags.is_empty() {
+        let tags: Vec<String> = tags
+            .iter()
+            .map(|(key, value)| format!("{key}:{value}"))
+            .collect();
+        packet.push_str("|#");
+        packet.push_str(&tags.join(","));
+    }
+    packet
+}
+
+/// Mirror metrics to a StatsD agent at `addr` (Python binding).
+#[pyfunction]
+#[pyo3(name = "enable_statsd", signature = (addr, prefix = ""))]
+pub(crate) fn py_enable_statsd(addr: &str, prefix: &str) -> PyResult<()> {
+    Ok(enable_statsd(addr, prefix)?)
+}
+
+/// Stop mirroring metrics to StatsD (Python binding).
+#[pyfunction]
+#[pyo3(name = "disable_statsd")]
+pub(crate) fn py_disable_statsd() {
+    disable_statsd();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packet_format() {
+        assert_eq!(
+            format_packet("tw", "orders_total", 1.0, Kind::Counter, &[]),
+            "tw.orders_total:1|c"
+        );
+        assert_eq!(
+            format_packet("", "latency", 0.25, Kind::Timing, &[("operation", "send")]),
+            "latency:0.25|ms|#operation:send"
+        );
+        assert_eq!(
+            format_packet(
+                "tw",
+                "depth",
+                -2.5,
+                Kind::Gauge,
+                &[("venue", "nyse"), ("side", "buy")]
+            ),
+            "tw.depth:-2.5|g|#venue:nyse,side:buy"
+        );
+    }
+
+    #[test]
+    fn test_rejects_bad_prefix_and_address() {
+        assert!(matches!(
+            enable_statsd("127.0.0.1:8125", "tw|x"),
+            Err(TelemetryError::Statsd(_))
+        ));
+        assert!(matches!(
+            enable_statsd("not an address", "tw"),
+            Err(TelemetryError::Statsd(_))
+        ));
+    }
+}
@@ -0,0 +1,257 @@
+//! Per-subsystem metric groups served from the default registry.
+//!
+//! A [`SubRegistry`] is a [`TelemetryHandle`] with its own registry, so
+//! the encryption, exec and strategy code can each inspect and reset their
+//! own metrics. Every series it exports carries a `subsystem` label, and
+//! its registry is plugged into [`REGISTRY`] as one collector, so
+//! [`get_metrics`](crate::get_metrics) (and every other exposition,
+//! exporter and introspection function) sees all groups in one scrape.
+//! Families of the same name merge into one, so a subsystem's
+//! `orders_total` shows up as `orders_total{subsystem="exec"}` next to the
+//! default `orders_total`.
+//!
+//! [`REGISTRY`]: crate::REGISTRY
+
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+
+use lazy_static::lazy_static;
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::MetricFamily;
+use prometheus::Registry;
+use pyo3::prelude::*;
+
+use crate::{
+    init_metrics, namespace, validate_labels, TelemetryError, TelemetryHandle, GLOBAL_LABELS,
+    REGISTRY,
+};
+
+/// Label identifying a [`SubRegistry`]'s series in the combined scrape.
+pub const SUBSYSTEM_LABEL: &str = "subsystem";
+
+lazy_static! {
+    static ref SUBREGISTRIES: Mutex<HashMap<String, SubRegistry>> = Mutex::default();
+}
+
+/// A named group of metrics with its own registry.
+///
+/// Derefs to [`TelemetryHandle`], so it has the same emit, record, gauge
+/// and introspection methods; introspection sees only this group, without
+/// the `subsystem` label. Cheap to clone; clones share the metrics.
+#[derive(Clone)]
+pub struct SubRegistry {
+    subsystem: Arc<str>,
+    handle: Arc<TelemetryHandle>,
+}
+
+impl SubRegistry {
+    /// The subsystem name, the value of its `subsystem` label.
+    pub fn subsystem(&self) -> &str {
+        &self.subsystem
+    }
+}
+
+impl Deref for SubRegistry {
+    type Target = TelemetryHandle;
+
+    fn deref(&self) -> &TelemetryHandle {
+        &self.handle
+    }
+}
+
+/// Exposes a sub-registry's metrics through [`REGISTRY`].
+///
+/// Its only descriptor is a placeholder that keeps the collector unique;
+/// the registry does not check gathered families against descriptors.
+struct SubsystemCollector {
+    desc: Desc,
+    registry: Registry,
+}
+
+impl Collector for SubsystemCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        vec![&self.desc]
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        self.registry.gather()
+    }
+}
+
+/// The [`SubRegistry`] for `subsystem`, created on first use.
+///
+/// Later calls with the same name return the same group.
+///
+/// # Arguments
+/// * `subsystem` - Group name, e.g. `"exec"`; follows the rules for
+///   operation names in [`record_latency`](crate::record_latency)
+///
+/// # Returns
+/// * `Ok(SubRegistry)` - The group
+/// * `Err(TelemetryError::InvalidLabel)` - If `subsystem` is not a valid
+///   label value, or `subsystem` is already a global label
+pub fn subregistry(subsystem: &str) -> Result<SubRegistry, TelemetryError> {
+    validate_labels(&[(SUBSYSTEM_LABEL, subsystem)])?;
+    init_metrics();
+    if let Some(value) = GLOBAL_LABELS
+        .get()
+        .and_then(|labels| labels.get(SUBSYSTEM_LABEL))
+    {
+        return Err(TelemetryError::InvalidLabel(format!(
+            "{SUBSYSTEM_LABEL}={value} is a global label"
+        )));
+    }
+
+    let mut subregistries = SUBREGISTRIES.lock().unwrap();
+    if let Some(existing) = subregistries.get(subsystem) {
+        return Ok(existing.clone());
+    }
+    // In the default registry's namespace, so families merge with its own.
+    let handle = TelemetryHandle::labeled(namespace(), &[(SUBSYSTEM_LABEL, subsystem)])?;
+    REGISTRY.register(Box::new(SubsystemCollector {
+        desc: Desc::new(
+            "telemetry_subregistry".to_string(),
+            "Placeholder for a subsystem's metrics".to_string(),
+            Vec::new(),
+            HashMap::from([(SUBSYSTEM_LABEL.to_string(), subsystem.to_string())]),
+        )?,
+        registry: handle.registry().clone(),
+    }))?;
+    let group = SubRegistry {
+        subsystem: subsystem.into(),
+        handle: Arc::new(handle),
+    };
+    subregistries.insert(subsystem.to_string(), group.clone());
+    Ok(group)
+}
+
+/// [`TelemetryHandle::reset`] every sub-registry.
+pub(crate) fn reset_all() {
+    for group in SUBREGISTRIES.lock().unwrap().values() {
+        group.reset();
+    }
+}
+
+/// Python wrapper around [`SubRegistry`].
+#[pyclass(name = "SubRegistry", frozen)]
+pub(crate) struct PySubRegistry(SubRegistry);
+
+#[pymethods]
+impl PySubRegistry {
+    /// The subsystem name.
+    #[getter]
+    fn subsystem(&self) -> &str {
+        self.0.subsystem()
+    }
+
+    /// Increment a counter of this subsystem.
+    fn emit_metric(&self, name: &str, value: f64) -> PyResult<()> {
+        Ok(self.0.emit_metric_checked(name, value)?)
+    }
+
+    /// Increment a labeled counter; `labels` is a dict of strings.
+    fn emit_counter(
+        &self,
+        name: &str,
+        labels: HashMap<String, String>,
+        value: f64,
+    ) -> PyResult<()> {
+        let labels: Vec<(&str, &str)> = labels
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        Ok(self.0.emit_counter(name, &labels, value)?)
+    }
+
+    /// Set a gauge; `labels` is a dict of strings.
+    fn set_gauge(&self, name: &str, labels: HashMap<String, String>, value: f64) -> PyResult<()> {
+        let labels: Vec<(&str, &str)> = labels
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        Ok(self.0.set_gauge(name, &labels, value)?)
+    }
+
+    /// Record operation latency in microseconds.
+    fn record_latency(&self, operation: &str, duration_us: f64) -> PyResult<()> {
+        Ok(self.0.try_record_latency(operation, duration_us)?)
+    }
+
+    /// Count a failed operation.
+    fn record_error(&self, operation: &str, kind: &str) -> PyResult<()> {
+        Ok(self.0.try_record_error(operation, kind)?)
+    }
+
+    /// This subsystem's metrics alone, in the Prometheus text format.
+    fn get_metrics(&self, py: Python<'_>) -> String {
+        py.allow_threads(|| self.0.get_metrics())
+    }
+}
+
+/// The sub-registry for `subsystem` (Python binding).
+///
+/// Raises `ValueError` for an invalid name.
+#[pyfunction]
+#[pyo3(name = "subregistry")]
+pub(crate) fn py_subregistry(subsystem: &str) -> PyResult<PySubRegistry> {
+    Ok(PySubRegistry(subregistry(subsystem)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{emit_metric, get_metrics};
+
+    #[test]
+    fn test_subsystems_and_default_share_one_scrape() {
+        let exec = subregistry("test_exec").unwrap();
+        let strategy = subregistry("test_strategy").unwrap();
+        exec.emit_metric("test_sub_fills_total", 2.0);
+        exec.record_latency("order_send", 100.0);
+        strategy.emit_metric("test_sub_fills_total", 5.0);
+        strategy
+            .set_gauge("test_sub_position", &[("symbol", "AAPL")], 3.0)
+            .unwrap();
+        emit_metric("test_sub_fills_total", 1.0);
+
+        let output = get_metrics();
+        assert!(output.contains("test_sub_fills_total 1\n"));
+        assert!(output.contains("test_sub_fills_total{subsystem=\"test_exec\"} 2"));
+        assert!(output.contains("test_sub_fills_total{subsystem=\"test_strategy\"} 5"));
+        assert!(output.contains("test_sub_position{symbol=\"AAPL\",subsystem=\"test_strategy\"} 3"));
+        assert!(output
+            .contains("latency_seconds_count{operation=\"order_send\",subsystem=\"test_exec\"} 1"));
+        // Merged into one family rather than repeated.
+        assert_eq!(output.matches("# TYPE test_sub_fills_total ").count(), 1);
+
+        // Each group inspects its own metrics, without the subsystem label.
+        assert_eq!(exec.get_counter_value("test_sub_fills_total"), Some(2.0));
+        assert_eq!(
+            exec.get_histogram_count("latency_seconds", &[("operation", "order_send")]),
+            Some(1)
+        );
+        assert!(!exec.get_metrics().contains("test_strategy"));
+    }
+
+    #[test]
+    fn test_same_subsystem_returns_same_group() {
+        let first = subregistry("test_same").unwrap();
+        let second = subregistry("test_same").unwrap();
+        assert!(Arc::ptr_eq(&first.handle, &second.handle));
+        first.emit_metric("test_same_total", 1.0);
+        assert_eq!(second.get_counter_value("test_same_total"), Some(1.0));
+        assert_eq!(second.subsystem(), "test_same");
+
+        assert!(matches!(
+            subregistry("bad name"),
+            Err(TelemetryError::InvalidLabel(_))
+        ));
+    }
+
+    #[test]
+    fn test_groups_are_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync + Clone>() {}
+        assert_send_sync::<SubRegistry>();
+    }
+}
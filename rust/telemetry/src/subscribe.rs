@@ -0,0 +1,194 @@
+//! Metric change subscriptions (requires the `async` feature).
+//!
+//! [`subscribe`] hands out a `tokio::sync::watch` receiver that follows one
+//! metric's value, so an in-process monitor can await changes instead of
+//! polling [`get_metrics`](crate::get_metrics) and parsing the text. One
+//! background thread, started by the first subscription, samples every
+//! subscribed metric each [`set_subscription_interval`] and publishes the
+//! values that changed; changes between two samples arrive as one update,
+//! with the latest value. The thread stops once every receiver is dropped.
+//!
+//! Receivers do not depend on a particular runtime, and the sampling
+//! thread needs none.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+use crate::{init_metrics, introspect, TelemetryError, DEFAULT};
+
+/// Default time between two samples of the subscribed metrics.
+pub const DEFAULT_SUBSCRIPTION_INTERVAL: Duration = Duration::from_millis(100);
+
+struct State {
+    /// One sender per subscribed metric, shared by all its receivers.
+    senders: BTreeMap<String, watch::Sender<f64>>,
+    interval: Duration,
+    /// Whether the sampling thread is running.
+    polling: bool,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    senders: BTreeMap::new(),
+    interval: DEFAULT_SUBSCRIPTION_INTERVAL,
+    polling: false,
+});
+
+/// Follow a metric's value.
+///
+/// The value is that of [`get_counter_value`](crate::get_counter_value)
+/// for counters, and likewise the sum over all series for gauges. A metric
+/// that does not exist yet reads 0.0 until it appears.
+///
+/// # Arguments
+/// * `metric` - Counter or gauge name, as rendered (including any
+///   [`set_namespace`](crate::set_namespace) prefix)
+///
+/// # Returns
+/// A receiver holding the current value, notified whenever it changes
+pub fn subscribe(metric: &str) -> watch::Receiver<f64> {
+    init_metrics();
+    let mut state = STATE.lock().unwrap();
+    let receiver = match state.senders.get(metric) {
+        Some(sender) => sender.subscribe(),
+        None => {
+            let (sender, receiver) = watch::channel(current_value(metric));
+            state.senders.insert(metric.to_string(), sender);
+            receiver
+        }
+    };
+    if !state.polling {
+        std::thread::Builder::new()
+            .name("telemetry-subscriptions".to_string())
+            .spawn(poll)
+            .expect("failed to spawn the telemetry subscription thread");
+        state.polling = true;
+    }
+    receiver
+}
+
+/// Set how often subscribed metrics are sampled; defaults to
+/// [`DEFAULT_SUBSCRIPTION_INTERVAL`].
+///
+/// # Returns
+/// * `Ok(())` - The interval applies from the next sample
+/// * `Err(TelemetryError::InvalidValue)` - If `interval` is zero
+pub fn set_subscription_interval(interval: Duration) -> Result<(), TelemetryError> {
+    if interval.is_zero() {
+        return Err(TelemetryError::InvalidValue {
+            name: "subscription_interval".to_string(),
+            value: 0.0,
+        });
+    }
+    STATE.lock().unwrap().interval = interval;
+    Ok(())
+}
+
+fn current_value(metric: &str) -> f64 {
+    introspect::selector_value(&DEFAULT.gather_own(), metric, &[]).unwrap_or(0.0)
+}
+
+/// Body of the sampling thread: publish changed values until no metric has
+/// a receiver left.
+fn poll() {
+    loop {
+        let interval = STATE.lock().unwrap().interval;
+        std::thread::sleep(interval);
+
+        let families = DEFAULT.gather_own();
+        let mut state = STATE.lock().unwrap();
+        state
+            .senders
+            .retain(|_, sender| sender.receiver_count() > 0);
+        if state.senders.is_empty() {
+            state.polling = false;
+            return;
+        }
+        for (metric, sender) in &state.senders {
+            let value = introspect::selector_value(&families, metric, &[]).unwrap_or(0.0);
+            sender.send_if_modified(|current| {
+                let changed = current.to_bits() != value.to_bits();
+                *current = value;
+                changed
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{emit_metric, set_gauge};
+
+    const WAIT: Duration = Duration::from_secs(5);
+
+    fn fast_sampling() {
+        set_subscription_interval(Duration::from_millis(10)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_receiver_follows_counter() {
+        fast_sampling();
+        let mut receiver = subscribe("test_subscribe_total");
+        assert_eq!(*receiver.borrow_and_update(), 0.0);
+
+        emit_metric("test_subscribe_total", 3.0);
+        tokio::time::timeout(WAIT, receiver.changed())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(*receiver.borrow_and_update(), 3.0);
+
+        emit_metric("test_subscribe_total", 2.0);
+        tokio::time::timeout(WAIT, receiver.wait_for(|value| *value == 5.0))
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_existing_gauge_starts_at_its_value() {
+        fast_sampling();
+        set_gauge("test_subscribe_depth", &[("venue", "nyse")], 4.0).unwrap();
+        set_gauge("test_subscribe_depth", &[("venue", "arca")], 1.0).unwrap();
+        let mut receiver = subscribe("test_subscribe_depth");
+        assert_eq!(*receiver.borrow(), 5.0);
+
+        set_gauge("test_subscribe_depth", &[("venue", "arca")], -1.0).unwrap();
+        tokio::time::timeout(WAIT, receiver.wait_for(|value| *value == 3.0))
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dropping_receivers_removes_sender() {
+        fast_sampling();
+        let receiver = subscribe("test_subscribe_dropped_total");
+        let clone = subscribe("test_subscribe_dropped_total");
+        drop(receiver);
+        drop(clone);
+
+        let removed = async {
+            while STATE
+                .lock()
+                .unwrap()
+                .senders
+                .contains_key("test_subscribe_dropped_total")
+            {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(WAIT, removed).await.unwrap();
+    }
+
+    #[test]
+    fn test_zero_interval_is_rejected() {
+        assert!(matches!(
+            set_subscription_interval(Duration::ZERO),
+            Err(TelemetryError::InvalidValue { .. })
+        ));
+    }
+}
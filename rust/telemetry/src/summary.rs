@@ -0,0 +1,140 @@
+//! Summary metrics with client-side quantiles.
+//!
+//! The `prometheus` crate has no summary collector, so [`Summary`] provides
+//! one. Quantiles are computed exactly over the most recent
+//! [`SUMMARY_WINDOW`] observations each time the registry is scraped;
+//! `_count` and `_sum` cover every observation.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use prometheus::core::{Collector, Desc, Describer};
+use prometheus::{proto, Opts};
+
+/// Number of recent observations quantiles are computed over.
+pub const SUMMARY_WINDOW: usize = 4096;
+
+/// A summary metric reporting fixed quantile objectives.
+#[derive(Clone)]
+pub(crate) struct Summary {
+    desc: Desc,
+    objectives: Vec<(f64, f64)>,
+    state: Arc<Mutex<SummaryState>>,
+}
+
+#[derive(Default)]
+struct SummaryState {
+    window: VecDeque<f64>,
+    count: u64,
+    sum: f64,
+}
+
+impl Summary {
+    /// Create an unregistered summary reporting `objectives`.
+    pub(crate) fn new(opts: Opts, objectives: &[(f64, f64)]) -> prometheus::Result<Self> {
+        Ok(Self {
+            desc: opts.describe()?,
+            objectives: objectives.to_vec(),
+            state: Arc::default(),
+        })
+    }
+
+    /// The `(quantile, allowed error)` pairs this summary was created with.
+    pub(crate) fn objectives(&self) -> &[(f64, f64)] {
+        &self.objectives
+    }
+
+    /// Record one observation.
+    pub(crate) fn observe(&self, value: f64) {
+        let mut state = self.state.lock().unwrap();
+        if state.window.len() == SUMMARY_WINDOW {
+            state.window.pop_front();
+        }
+        state.window.push_back(value);
+        state.count += 1;
+        state.sum += value;
+    }
+
+    /// Forget every observation, including `_count` and `_sum`.
+    pub(crate) fn clear(&self) {
+        *self.state.lock().unwrap() = SummaryState::default();
+    }
+
+    fn metric(&self) -> proto::Metric {
+        let (mut sorted, count, sum) = {
+            let state = self.state.lock().unwrap();
+            (Vec::from(state.window.clone()), state.count, state.sum)
+        };
+        sorted.sort_unstable_by(f64::total_cmp);
+
+        let quantiles: Vec<proto::Quantile> = self
+            .objectives
+            .iter()
+            .map(|&(quantile, _)| {
+                let mut q = proto::Quantile::default();
+                q.set_quantile(quantile);
+                q.set_value(nearest_rank(&sorted, quantile));
+                q
+            })
+            .collect();
+
+        let mut summary = proto::Summary::default();
+        summary.set_sample_count(count);
+        summary.set_sample_sum(sum);
+        summary.set_quantile(quantiles.into());
+        let mut metric = proto::Metric::default();
+        metric.set_summary(summary);
+        metric
+    }
+}
+
+impl Collector for Summary {
+    fn desc(&self) -> Vec<&Desc> {
+        vec![&self.desc]
+    }
+
+    fn collect(&self) -> Vec<proto::MetricFamily> {
+        let mut family = proto::MetricFamily::default();
+        family.set_name(self.desc.fq_name.clone());
+        family.set_help(self.desc.help.clone());
+        family.set_field_type(proto::MetricType::SUMMARY);
+        family.set_metric(vec![self.metric()].into());
+        vec![family]
+    }
+}
+
+/// The `quantile` of ascending `sorted` by the nearest-rank method; NaN if empty.
+fn nearest_rank(sorted: &[f64], quantile: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+    let rank = (quantile * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearest_rank() {
+        let sorted: Vec<f64> = (1..=100).map(f64::from).collect();
+        assert_eq!(nearest_rank(&sorted, 0.5), 50.0);
+        assert_eq!(nearest_rank(&sorted, 0.99), 99.0);
+        assert_eq!(nearest_rank(&sorted, 0.0), 1.0);
+        assert_eq!(nearest_rank(&sorted, 1.0), 100.0);
+        assert!(nearest_rank(&[], 0.5).is_nan());
+    }
+
+    #[test]
+    fn test_window_keeps_recent_observations() {
+        let summary = Summary::new(Opts::new("test_window", "help"), &[(0.5, 0.05)]).unwrap();
+        for value in 0..SUMMARY_WINDOW + 10 {
+            summary.observe(value as f64);
+        }
+        let state = summary.state.lock().unwrap();
+        assert_eq!(state.window.len(), SUMMARY_WINDOW);
+        assert_eq!(state.window.front(), Some(&10.0));
+        assert_eq!(state.count, (SUMMARY_WINDOW + 10) as u64);
+    }
+}
@@ -0,0 +1,354 @@
+//! In-process threshold alerts.
+//!
+//! [`register_threshold`] watches a counter or gauge and calls back when it
+//! rises by more than a set amount within a sliding window, e.g. to flip a
+//! kill switch when `errors_total{operation="order_send"}` jumps. One
+//! background thread, started by the first registration and stopped by
+//! [`clear_thresholds`], samples every watched metric a few times per
+//! window (at most every 10ms, at least every second).
+//!
+//! After firing, a threshold measures from the value it fired at, so one
+//! burst fires once; the callback fires again only after a further rise of
+//! more than the maximum. Callbacks run on the checker thread, one at a
+//! time, so a slow callback delays the others. A callback that panics is
+//! logged and skipped; the checker keeps running.
+
+use std::collections::{HashMap, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::{
+    init_metrics, introspect, is_valid_metric_name, validate_labels, TelemetryError, DEFAULT,
+};
+
+/// Bounds on how often the checker samples: a quarter of the shortest
+/// window, within this range.
+const MIN_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+const MAX_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What a threshold callback is told when it fires.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThresholdEvent {
+    /// Metric name, as passed to [`register_threshold`]
+    pub metric: String,
+    /// The label selector, as passed to [`register_threshold`]
+    pub labels: Vec<(String, String)>,
+    /// The metric's value when the threshold fired
+    pub value: f64,
+    /// How far the value rose within the window
+    pub delta: f64,
+    /// The configured maximum rise
+    pub max_delta: f64,
+    /// The configured window
+    pub window: Duration,
+}
+
+/// Callback of a threshold registered with [`register_threshold`].
+pub type ThresholdCallback = Box<dyn Fn(ThresholdEvent) + Send + Sync>;
+
+struct Threshold {
+    metric: String,
+    labels: Vec<(String, String)>,
+    window: Duration,
+    max_delta: f64,
+    callback: ThresholdCallback,
+    /// `(time, value)` samples within the window, oldest first.
+    samples: Mutex<VecDeque<(Instant, f64)>>,
+}
+
+impl Threshold {
+    fn labels(&self) -> Vec<(&str, &str)> {
+        self.labels
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect()
+    }
+
+    /// Record `value`, sampled at `now`, and return the event to fire if
+    /// it rose too far within the window.
+    fn observe(&self, now: Instant, value: f64) -> Option<ThresholdEvent> {
+        let mut samples = self.samples.lock().unwrap();
+        while samples
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > self.window)
+        {
+            samples.pop_front();
+        }
+        let delta = value - samples.front().map_or(value, |(_, oldest)| *oldest);
+        if delta > self.max_delta {
+            samples.clear();
+        }
+        samples.push_back((now, value));
+        (delta > self.max_delta).then(|| ThresholdEvent {
+            metric: self.metric.clone(),
+            labels: self.labels.clone(),
+            value,
+            delta,
+            max_delta: self.max_delta,
+            window: self.window,
+        })
+    }
+}
+
+struct Checker {
+    stop: Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+struct State {
+    thresholds: Vec<Arc<Threshold>>,
+    checker: Option<Checker>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    thresholds: Vec::new(),
+    checker: None,
+});
+
+/// Call `callback` when a metric rises by more than `max_delta` within
+/// `window_secs` seconds.
+///
+/// The watched value is the sum of the series of counter or gauge `metric`
+/// whose labels include every pair in `labels`, like a PromQL selector:
+/// `[("operation", "order_send")]` watches `errors_total` for every error
+/// kind of `order_send`. Names and labels are read as by
+/// [`get_counter_value`](crate::get_counter_value); a metric that does not
+/// exist yet counts as 0.
+///
+/// The window starts now, so earlier increments never fire. A newly
+/// registered window shorter than every existing one may take up to a
+/// second to be sampled at its own rate.
+///
+/// # Arguments
+/// * `metric` - Counter or gauge name, e.g. `"errors_total"`
+/// * `labels` - `(key, value)` pairs the series must carry; empty for all
+/// * `window_secs` - Length of the sliding window, in seconds
+/// * `max_delta` - Largest rise within the window that does not fire
+/// * `callback` - Called on the checker thread with the details
+///
+/// # Returns
+/// * `Ok(())` - The threshold is being watched
+/// * `Err(TelemetryError::InvalidMetricName)` - If `metric` is not a valid metric name
+/// * `Err(TelemetryError::InvalidLabel)` - If a label key or value is invalid
+/// * `Err(TelemetryError::InvalidValue)` - If `window_secs` is not positive
+///   and finite, or `max_delta` is negative or not finite
+pub fn register_threshold(
+    metric: &str,
+    labels: &[(&str, &str)],
+    window_secs: f64,
+    max_delta: f64,
+    callback: ThresholdCallback,
+) -> Result<(), TelemetryError> {
+    if !is_valid_metric_name(metric) {
+        return Err(TelemetryError::InvalidMetricName(metric.to_string()));
+    }
+    validate_labels(labels)?;
+    let invalid = |value| TelemetryError::InvalidValue {
+        name: metric.to_string(),
+        value,
+    };
+    let window = Duration::try_from_secs_f64(window_secs)
+        .ok()
+        .filter(|window| !window.is_zero())
+        .ok_or_else(|| invalid(window_secs))?;
+    if !(max_delta.is_finite() && max_delta >= 0.0) {
+        return Err(invalid(max_delta));
+    }
+
+    init_metrics();
+    let value = introspect::selector_value(&DEFAULT.gather_own(), metric, labels).unwrap_or(0.0);
+    let threshold = Arc::new(Threshold {
+        metric: metric.to_string(),
+        labels: labels
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+        window,
+        max_delta,
+        callback,
+        samples: Mutex::new(VecDeque::from([(Instant::now(), value)])),
+    });
+
+    let mut state = STATE.lock().unwrap();
+    state.thresholds.push(threshold);
+    if state.checker.is_none() {
+        state.checker = Some(start_checker());
+    }
+    Ok(())
+}
+
+/// Remove every threshold and stop the checker thread.
+///
+/// May be called from a threshold callback, e.g. so a kill switch fires
+/// once; thresholds the checker was already evaluating may still fire.
+pub fn clear_thresholds() {
+    let checker = {
+        let mut state = STATE.lock().unwrap();
+        state.thresholds.clear();
+        state.checker.take()
+    };
+    if let Some(checker) = checker {
+        // A send error means the thread already exited.
+        let _ = checker.stop.send(());
+        // Called from a callback, the checker exits once it returns.
+        if checker.thread.thread().id() != std::thread::current().id() {
+            // Callback panics are caught, so the join cannot fail.
+            let _ = checker.thread.join();
+        }
+    }
+}
+
+fn start_checker() -> Checker {
+    let (stop, stopped) = mpsc::channel::<()>();
+    let thread = std::thread::Builder::new()
+        .name("telemetry-thresholds".to_string())
+        .spawn(move || loop {
+            let Some(interval) = check_interval() else {
+                return;
+            };
+            match stopped.recv_timeout(interval) {
+                Err(RecvTimeoutError::Timeout) => check(),
+                _ => return,
+            }
+        })
+        .expect("failed to spawn the telemetry threshold thread");
+    Checker { stop, thread }
+}
+
+/// How long to wait before the next check; `None` once every threshold is
+/// cleared.
+fn check_interval() -> Option<Duration> {
+    let state = STATE.lock().unwrap();
+    let shortest = state.thresholds.iter().map(|t| t.window).min()?;
+    Some((shortest / 4).clamp(MIN_CHECK_INTERVAL, MAX_CHECK_INTERVAL))
+}
+
+/// Sample every threshold and run the callbacks of those that fire.
+fn check() {
+    let thresholds = STATE.lock().unwrap().thresholds.clone();
+    if thresholds.is_empty() {
+        return;
+    }
+    let families = DEFAULT.gather_own();
+    let now = Instant::now();
+    for threshold in thresholds {
+        let value = introspect::selector_value(&families, &threshold.metric, &threshold.labels())
+            .unwrap_or(0.0);
+        let Some(event) = threshold.observe(now, value) else {
+            continue;
+        };
+        if panic::catch_unwind(AssertUnwindSafe(|| (threshold.callback)(event))).is_err() {
+            log::error!("threshold callback for {} panicked", threshold.metric);
+        }
+    }
+}
+
+/// Call `callback` with a dict when `metric{labels}` rises by more than
+/// `max_delta` within `window_secs` seconds (Python binding).
+///
+/// The dict has `metric`, `labels`, `value`, `delta`, `max_delta` and
+/// `window_secs` keys. Exceptions raised by the callback are logged and
+/// ignored. Raises `ValueError` on an invalid name, label, window or
+/// maximum.
+#[pyfunction]
+#[pyo3(
+    name = "register_threshold",
+    signature = (metric, labels, window_secs, max_delta, callback)
+)]
+pub(crate) fn py_register_threshold(
+    metric: &str,
+    labels: Option<HashMap<String, String>>,
+    window_secs: f64,
+    max_delta: f64,
+    callback: PyObject,
+) -> PyResult<()> {
+    let labels: Vec<(&str, &str)> = labels
+        .iter()
+        .flatten()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+    let callback: ThresholdCallback = Box::new(move |event| {
+        Python::with_gil(|py| {
+            let result = event_dict(py, &event).and_then(|dict| callback.call1(py, (dict,)));
+            if let Err(err) = result {
+                log::error!("threshold callback for {} raised: {err}", event.metric);
+            }
+        })
+    });
+    Ok(register_threshold(
+        metric,
+        &labels,
+        window_secs,
+        max_delta,
+        callback,
+    )?)
+}
+
+/// Remove every threshold and stop the checker thread (Python binding).
+///
+/// Releases the GIL while the checker finishes a running callback.
+#[pyfunction]
+#[pyo3(name = "clear_thresholds")]
+pub(crate) fn py_clear_thresholds(py: Python<'_>) {
+    py.allow_threads(clear_thresholds)
+}
+
+fn event_dict<'py>(py: Python<'py>, event: &ThresholdEvent) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    dict.set_item("metric", &event.metric)?;
+    dict.set_item(
+        "labels",
+        event.labels.iter().cloned().collect::<HashMap<_, _>>(),
+    )?;
+    dict.set_item("value", event.value)?;
+    dict.set_item("delta", event.delta)?;
+    dict.set_item("max_delta", event.max_delta)?;
+    dict.set_item("window_secs", event.window.as_secs_f64())?;
+    Ok(dict)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn threshold(window: Duration, max_delta: f64, start: Instant) -> Threshold {
+        Threshold {
+            metric: "errors_total".to_string(),
+            labels: Vec::new(),
+            window,
+            max_delta,
+            callback: Box::new(|_| {}),
+            samples: Mutex::new(VecDeque::from([(start, 0.0)])),
+        }
+    }
+
+    #[test]
+    fn test_rise_within_window_fires_once() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let threshold = threshold(ms(100), 5.0, start);
+
+        assert_eq!(threshold.observe(start + ms(10), 5.0), None);
+        let event = threshold.observe(start + ms(20), 6.0).unwrap();
+        assert_eq!((event.value, event.delta), (6.0, 6.0));
+        // Measured from the firing value from now on.
+        assert_eq!(threshold.observe(start + ms(30), 11.0), None);
+        assert!(threshold.observe(start + ms(40), 12.0).is_some());
+    }
+
+    #[test]
+    fn test_slow_rise_does_not_fire() {
+        let start = Instant::now();
+        let threshold = threshold(Duration::from_millis(100), 5.0, start);
+        for step in 1..=20 {
+            let now = start + Duration::from_millis(50 * step);
+            assert_eq!(threshold.observe(now, step as f64), None);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! RAII latency timing.
+//!
+//! ```
+//! use tinywindow_rust_telemetry::start_timer;
+//!
+//! {
+//!     let _timer = start_timer("order_send");
+//!     // ... timed work ...
+//! } // recorded into `latency_seconds{operation="order_send"}` here
+//! ```
+
+use std::time::Instant;
+
+use crate::record_duration;
+
+/// Start timing `operation`.
+///
+/// The returned guard records the elapsed time with [`record_duration`] when
+/// it is dropped, including during a panic. Each guard records at most
+/// once, so nested timers for different operations are independent.
+pub fn start_timer(operation: &str) -> LatencyTimer {
+    LatencyTimer {
+        operation: operation.to_string(),
+        start: Instant::now(),
+        armed: true,
+    }
+}
+
+/// Time a block with a [`start_timer`] guard and evaluate to its value.
+///
+/// The latency is recorded however the block is left, so early `return`s,
+/// `?` and panics are timed too.
+///
+/// ```
+/// use tinywindow_rust_telemetry::time_operation;
+///
+/// fn parse(input: &str) -> Result<u64, std::num::ParseIntError> {
+///     let value = time_operation!("parse", { input.trim().parse::<u64>()? });
+///     Ok(value * 2)
+/// }
+/// # assert_eq!(parse(" 21 "), Ok(42));
+/// ```
+#[macro_export]
+macro_rules! time_operation {
+    ($operation:expr, $body:block) => {{
+        let _timer = $crate::start_timer($operation);
+        $body
+    }};
+}
+
+/// Guard returned by [`start_timer`].
+#[must_use = "the timer records when dropped; binding it to `_` drops it immediately"]
+#[derive(Debug)]
+pub struct LatencyTimer {
+    operation: String,
+    start: Instant,
+    armed: bool,
+}
+
+impl LatencyTimer {
+    /// Record now and return the elapsed time in seconds.
+    pub fn stop(mut self) -> f64 {
+        self.record()
+    }
+
+    /// Cancel the timer without recording anything.
+    pub fn discard(mut self) {
+        self.armed = false;
+    }
+
+    /// Record the elapsed time if not done already; returns it in seconds.
+    fn record(&mut self) -> f64 {
+        let elapsed = self.start.elapsed();
+        if self.armed {
+            self.armed = false;
+            record_duration(&self.operation, elapsed);
+        }
+        elapsed.as_secs_f64()
+    }
+}
+
+impl Drop for LatencyTimer {
+    fn drop(&mut self) {
+        self.record();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LATENCY;
+
+    fn sample_count(operation: &str) -> u64 {
+        LATENCY.with_label_values(&[operation]).get_sample_count()
+    }
+
+    #[test]
+    fn test_timer_records_once_on_drop() {
+        {
+            let _timer = start_timer("test_timer_drop");
+        }
+        assert_eq!(sample_count("test_timer_drop"), 1);
+    }
+
+    #[test]
+    fn test_stop_records_once_and_returns_seconds() {
+        let timer = start_timer("test_timer_stop");
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let elapsed = timer.stop();
+        assert!((0.002..1.0).contains(&elapsed));
+        assert_eq!(sample_count("test_timer_stop"), 1);
+    }
+
+    #[test]
+    fn test_discard_records_nothing() {
+        start_timer("test_timer_discard").discard();
+        assert_eq!(sample_count("test_timer_discard"), 0);
+    }
+
+    #[test]
+    fn test_nested_timers_are_independent() {
+        let outer = start_timer("test_timer_outer");
+        for _ in 0..3 {
+            let _inner = start_timer("test_timer_inner");
+        }
+        assert_eq!(sample_count("test_timer_outer"), 0);
+        drop(outer);
+        assert_eq!(sample_count("test_timer_outer"), 1);
+        assert_eq!(sample_count("test_timer_inner"), 3);
+    }
+
+    #[test]
+    fn test_time_operation_returns_block_value() {
+        let sum = time_operation!("test_time_operation", { (1..=10).sum::<u32>() });
+        assert_eq!(sum, 55);
+        assert_eq!(sample_count("test_time_operation"), 1);
+    }
+
+    #[test]
+    fn test_time_operation_records_on_early_return() {
+        fn parse(input: &str) -> Result<u32, std::num::ParseIntError> {
+            let value = time_operation!("test_time_operation_err", { input.parse::<u32>()? });
+            Ok(value)
+        }
+        assert!(parse("nope").is_err());
+        assert_eq!(parse("7"), Ok(7));
+        assert_eq!(sample_count("test_time_operation_err"), 2);
+    }
+
+    #[test]
+    fn test_timer_records_during_panic() {
+        let result = std::panic::catch_unwind(|| {
+            let _timer = start_timer("test_timer_panic");
+            panic!("timed work failed");
+        });
+        assert!(result.is_err());
+        assert_eq!(sample_count("test_timer_panic"), 1);
+    }
+}
@@ -0,0 +1,56 @@
+//! Many threads initializing, recording and scraping at once.
+//!
+//! Runs in its own process so the first call into the crate happens under
+//! contention and no other test touches `orders_total`.
+
+use std::sync::Barrier;
+use std::thread;
+
+use tinywindow_rust_telemetry::{
+    emit_metric_checked, get_counter_value, get_histogram_count, get_metrics, record_latency,
+    register_counter,
+};
+
+const THREADS: usize = 64;
+const ITERATIONS: usize = 2_000;
+/// Names created on first emit, each by whichever thread gets there first.
+const DYNAMIC_NAMES: usize = 8;
+
+#[test]
+fn test_parallel_emit_record_and_scrape() {
+    let barrier = Barrier::new(THREADS);
+    thread::scope(|scope| {
+        for t in 0..THREADS {
+            let barrier = &barrier;
+            scope.spawn(move || {
+                barrier.wait();
+                if t == 0 {
+                    // Races the emits below; losing to them is fine.
+                    let _ = register_counter("stress_registered_total", "Registered under load");
+                }
+                for i in 0..ITERATIONS {
+                    emit_metric_checked("orders_total", 1.0).unwrap();
+                    emit_metric_checked("stress_registered_total", 1.0).unwrap();
+                    let name = format!("stress_dynamic_{}_total", (t + i) % DYNAMIC_NAMES);
+                    emit_metric_checked(&name, 1.0).unwrap();
+                    record_latency("stress_op", 100.0);
+                    if i % 250 == 0 {
+                        assert!(get_metrics().contains("orders_total"));
+                    }
+                }
+            });
+        }
+    });
+
+    let total = (THREADS * ITERATIONS) as f64;
+    assert_eq!(get_counter_value("orders_total"), Some(total));
+    assert_eq!(get_counter_value("stress_registered_total"), Some(total));
+    let dynamic: f64 = (0..DYNAMIC_NAMES)
+        .map(|n| get_counter_value(&format!("stress_dynamic_{n}_total")).unwrap())
+        .sum();
+    assert_eq!(dynamic, total);
+    assert_eq!(
+        get_histogram_count("latency_seconds", &[("operation", "stress_op")]),
+        Some((THREADS * ITERATIONS) as u64)
+    );
+}
@@ -0,0 +1,36 @@
+//! New counter names beyond the limit are dropped and counted.
+//!
+//! Runs in its own process because the limit is global.
+
+use tinywindow_rust_telemetry::{
+    emit_metric, emit_metric_checked, get_metrics, set_counter_limit, TelemetryError,
+};
+
+fn sample(name: &str) -> Option<f64> {
+    get_metrics().lines().find_map(|line| {
+        let (metric, value) = line.split_once(' ')?;
+        (metric == name).then(|| value.parse().ok())?
+    })
+}
+
+#[test]
+fn test_new_names_are_dropped_past_the_limit() {
+    // `orders_total` already occupies one slot.
+    set_counter_limit(3);
+    emit_metric("first_total", 1.0);
+    emit_metric("second_total", 1.0);
+
+    assert_eq!(
+        emit_metric_checked("third_total", 1.0),
+        Err(TelemetryError::TooManyMetrics { limit: 3 })
+    );
+    emit_metric("fourth_total", 1.0);
+
+    assert_eq!(sample("telemetry_dropped_metrics_total"), Some(2.0));
+    assert_eq!(sample("third_total"), None);
+    assert_eq!(sample("fourth_total"), None);
+
+    // Existing counters keep working at the limit.
+    emit_metric("first_total", 1.0);
+    assert_eq!(sample("first_total"), Some(2.0));
+}
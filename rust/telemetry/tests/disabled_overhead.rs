@@ -0,0 +1,46 @@
+//! Cost of the recording functions while telemetry is disabled, next to a
+//! bare atomic load.
+//!
+//! Ignored by default; run with
+//! `cargo test --release -p telemetry --test disabled_overhead -- --ignored --nocapture`.
+
+use std::hint::black_box;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use tinywindow_rust_telemetry::{emit_metric, record_latency, set_enabled};
+
+const CALLS: u32 = 10_000_000;
+
+#[test]
+#[ignore = "benchmark; run in release mode"]
+fn bench_disabled_recording() {
+    set_enabled(false);
+    let flag = AtomicBool::new(false);
+
+    let start = Instant::now();
+    for _ in 0..CALLS {
+        black_box(black_box(&flag).load(Ordering::Relaxed));
+    }
+    let load = start.elapsed();
+
+    let start = Instant::now();
+    for i in 0..CALLS {
+        emit_metric(black_box("orders_total"), black_box(f64::from(i)));
+    }
+    let emit = start.elapsed();
+
+    let start = Instant::now();
+    for i in 0..CALLS {
+        record_latency(black_box("order_send"), black_box(f64::from(i)));
+    }
+    let latency = start.elapsed();
+
+    let per_call = |elapsed: std::time::Duration| elapsed.as_nanos() as f64 / f64::from(CALLS);
+    println!(
+        "{CALLS} calls: atomic load {:.2} ns, emit_metric {:.2} ns, record_latency {:.2} ns",
+        per_call(load),
+        per_call(emit),
+        per_call(latency),
+    );
+}
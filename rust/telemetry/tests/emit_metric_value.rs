@@ -0,0 +1,27 @@
+//! `emit_metric` increments by the value it is given.
+//!
+//! Runs in its own process so no other test touches `orders_total`.
+
+use tinywindow_rust_telemetry::{emit_metric, emit_metric_checked, get_metrics, ORDERS_TOTAL};
+
+fn orders_total() -> Option<f64> {
+    get_metrics().lines().find_map(|line| {
+        let value = line.strip_prefix("orders_total ")?;
+        value.parse().ok()
+    })
+}
+
+#[test]
+fn test_emit_metric_increments_by_value() {
+    let before = ORDERS_TOTAL.get();
+    emit_metric("orders_total", 1.0);
+    assert_eq!(ORDERS_TOTAL.get(), before + 1);
+
+    emit_metric("orders_total", 4.0);
+    emit_metric("orders_total", 5.0);
+    assert_eq!(orders_total(), Some(10.0));
+
+    emit_metric("orders_total", -3.0);
+    assert!(emit_metric_checked("orders_total", -3.0).is_err());
+    assert_eq!(orders_total(), Some(10.0));
+}
@@ -0,0 +1,49 @@
+//! Samples sent while telemetry is disabled are dropped for good.
+//!
+//! Runs in its own process because the switch is global.
+
+use tinywindow_rust_telemetry::{
+    emit_counter, emit_metric, emit_metric_checked, flush_thread_local, get_counter_value,
+    get_histogram_count, get_metrics, is_enabled, record_error, record_latency, set_enabled,
+    set_gauge, thread_local_recorder, try_record_latency,
+};
+
+#[test]
+fn test_disabled_samples_do_not_appear_after_reenabling() {
+    assert!(is_enabled());
+    set_enabled(false);
+    assert!(!is_enabled());
+
+    emit_metric("fills_total", 1.0);
+    emit_counter("venue_fills_total", &[("venue", "nyse")], 1.0).unwrap();
+    set_gauge("queue_depth", &[], 3.0).unwrap();
+    record_latency("order_send", 250.0);
+    record_error("order_send", "timeout");
+    // Validation is skipped along with everything else.
+    emit_metric_checked("bad name", -1.0).unwrap();
+    try_record_latency("bad operation", 1.0).unwrap();
+    thread_local_recorder().record_latency("order_send", 250.0);
+    thread_local_recorder()
+        .try_record_latency("bad operation", 1.0)
+        .unwrap();
+
+    set_enabled(true);
+    flush_thread_local();
+    assert_eq!(get_counter_value("fills_total"), None);
+    assert_eq!(
+        get_histogram_count("latency_seconds", &[("operation", "order_send")]),
+        None
+    );
+    let text = get_metrics();
+    for name in ["venue_fills_total", "queue_depth", "errors_total{"] {
+        assert!(!text.contains(name), "{name} in {text}");
+    }
+
+    emit_metric("fills_total", 1.0);
+    record_latency("order_send", 250.0);
+    assert_eq!(get_counter_value("fills_total"), Some(1.0));
+    assert_eq!(
+        get_histogram_count("latency_seconds", &[("operation", "order_send")]),
+        Some(1)
+    );
+}
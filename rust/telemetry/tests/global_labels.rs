@@ -0,0 +1,32 @@
+//! `set_global_labels` against the process-wide registry, in its own test
+//! binary so nothing else has created the registry first.
+
+use tinywindow_rust_telemetry::{
+    emit_metric, get_histogram_count, get_metrics, record_latency, set_global_labels,
+    TelemetryError,
+};
+
+#[test]
+fn test_global_labels_apply_to_builtin_metrics() {
+    set_global_labels(&[("env", "prod"), ("instance", "node-1")]).unwrap();
+    assert_eq!(
+        set_global_labels(&[("env", "dev")]),
+        Err(TelemetryError::GlobalLabelsFrozen)
+    );
+
+    emit_metric("orders_total", 1.0);
+    record_latency("order_send", 250.0);
+
+    let metrics = get_metrics();
+    for prefix in ["orders_total{", "latency_seconds_count{"] {
+        let line = metrics
+            .lines()
+            .find(|line| line.starts_with(prefix))
+            .unwrap_or_else(|| panic!("no {prefix} series in {metrics}"));
+        assert!(line.contains("env=\"prod\""), "{line}");
+    }
+    assert_eq!(
+        get_histogram_count("latency_seconds", &[("operation", "order_send")]),
+        Some(1)
+    );
+}
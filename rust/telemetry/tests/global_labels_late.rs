@@ -0,0 +1,13 @@
+//! `set_global_labels` after the registry exists, in its own test binary.
+
+use tinywindow_rust_telemetry::{emit_metric, get_metrics, set_global_labels, TelemetryError};
+
+#[test]
+fn test_global_labels_rejected_after_first_metric() {
+    emit_metric("orders_total", 1.0);
+    assert_eq!(
+        set_global_labels(&[("env", "prod")]),
+        Err(TelemetryError::GlobalLabelsFrozen)
+    );
+    assert!(!get_metrics().contains("env="));
+}
@@ -0,0 +1,26 @@
+//! Custom latency buckets apply to `latency_seconds` and freeze on first use.
+//!
+//! Runs in its own process because the latency histogram is global.
+
+use tinywindow_rust_telemetry::{
+    configure_latency_buckets, get_metrics, latency_buckets, record_latency, TelemetryError,
+};
+
+#[test]
+fn test_configured_buckets_apply_until_first_record() {
+    configure_latency_buckets(&[0.1, 1.0, 30.0, 120.0]).unwrap();
+    assert_eq!(latency_buckets(), vec![0.1, 1.0, 30.0, 120.0]);
+
+    // 15 seconds, e.g. an order-gen stall during a venue outage.
+    record_latency("order_gen", 15_000_000.0);
+    let output = get_metrics();
+    assert!(output.contains("latency_seconds_bucket{operation=\"order_gen\",le=\"1\"} 0"));
+    assert!(output.contains("latency_seconds_bucket{operation=\"order_gen\",le=\"30\"} 1"));
+    assert!(output.contains("latency_seconds_bucket{operation=\"order_gen\",le=\"120\"} 1"));
+
+    assert_eq!(
+        configure_latency_buckets(&[1.0, 10.0]),
+        Err(TelemetryError::LatencyInUse)
+    );
+    assert_eq!(latency_buckets(), vec![0.1, 1.0, 30.0, 120.0]);
+}
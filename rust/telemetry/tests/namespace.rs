@@ -0,0 +1,31 @@
+//! `set_namespace` against the process-wide registry, in its own test
+//! binary so nothing else has created the registry first.
+
+use tinywindow_rust_telemetry::{
+    emit_metric, get_counter_value, get_metrics, get_metrics_json, list_metrics, set_namespace,
+    subregistry, TelemetryError,
+};
+
+#[test]
+fn test_namespace_prefixes_builtin_metrics() {
+    assert!(matches!(
+        set_namespace("tw exec"),
+        Err(TelemetryError::InvalidMetricName(_))
+    ));
+    set_namespace("tw_exec").unwrap();
+    assert_eq!(set_namespace("tw_md"), Err(TelemetryError::NamespaceFrozen));
+
+    emit_metric("orders_total", 1.0);
+
+    let metrics = get_metrics();
+    assert!(metrics.contains("# TYPE tw_exec_orders_total counter"));
+    assert!(metrics.contains("\ntw_exec_orders_total 1"));
+    assert!(get_metrics_json().contains("\"tw_exec_orders_total\""));
+    assert!(list_metrics()
+        .iter()
+        .all(|info| info.name.starts_with("tw_exec_")));
+    assert_eq!(get_counter_value("tw_exec_orders_total"), Some(1.0));
+
+    subregistry("md").unwrap().emit_metric("orders_total", 2.0);
+    assert!(get_metrics().contains("tw_exec_orders_total{subsystem=\"md\"} 2"));
+}
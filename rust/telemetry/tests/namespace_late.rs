@@ -0,0 +1,13 @@
+//! `set_namespace` after the registry exists, in its own test binary.
+
+use tinywindow_rust_telemetry::{emit_metric, get_metrics, set_namespace, TelemetryError};
+
+#[test]
+fn test_namespace_rejected_after_first_metric() {
+    emit_metric("orders_total", 1.0);
+    assert_eq!(
+        set_namespace("tw_exec"),
+        Err(TelemetryError::NamespaceFrozen)
+    );
+    assert!(get_metrics().contains("\norders_total 1"));
+}
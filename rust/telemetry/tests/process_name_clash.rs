@@ -0,0 +1,22 @@
+//! Process metrics whose names are taken are skipped, in their own test
+//! binary so nothing registered them before.
+
+use tinywindow_rust_telemetry::{
+    get_metrics, init_process_metrics, register_build_info, register_counter,
+};
+
+#[test]
+fn test_taken_names_do_not_panic() {
+    register_counter("tinywindow_uptime_seconds", "Taken by the application").unwrap();
+    // Clashes with the process collector with the `process` feature.
+    register_counter("process_threads", "Taken by the application").unwrap();
+
+    init_process_metrics();
+    init_process_metrics();
+    register_build_info("1.4.0", "abc123", &[]).unwrap();
+
+    let output = get_metrics();
+    assert!(output.contains("# HELP tinywindow_uptime_seconds Taken by the application"));
+    assert!(output.contains("# HELP process_threads Taken by the application"));
+    assert!(output.contains("build_info{commit=\"abc123\",version=\"1.4.0\"} 1\n"));
+}
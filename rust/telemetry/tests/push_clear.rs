@@ -0,0 +1,29 @@
+//! `push_metrics_and_clear` resets counters only after a successful push.
+//!
+//! Runs in its own process because resetting counters is global.
+
+mod common;
+
+use common::serve_once;
+use tinywindow_rust_telemetry::{emit_metric, get_metrics, push_metrics_and_clear, TelemetryError};
+
+fn sample(name: &str) -> Option<f64> {
+    get_metrics().lines().find_map(|line| {
+        let (metric, value) = line.split_once(' ')?;
+        (metric == name).then(|| value.parse().ok())?
+    })
+}
+
+#[test]
+fn test_counters_reset_after_successful_push_only() {
+    emit_metric("recon_rows_total", 7.0);
+
+    assert!(matches!(
+        push_metrics_and_clear(&serve_once(503, "").0, "eod_recon", &[]),
+        Err(TelemetryError::PushRejected { status: 503, .. })
+    ));
+    assert_eq!(sample("recon_rows_total"), Some(7.0));
+
+    push_metrics_and_clear(&serve_once(200, "").0, "eod_recon", &[]).unwrap();
+    assert_eq!(sample("recon_rows_total"), Some(0.0));
+}
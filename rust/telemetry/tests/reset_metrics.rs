@@ -0,0 +1,30 @@
+//! `reset_metrics` zeroes the global registry.
+//!
+//! Runs in its own process so resetting doesn't disturb other tests.
+
+use tinywindow_rust_telemetry::{
+    emit_counter, emit_metric, get_metrics, observe_histogram, record_latency, register_histogram,
+    reset_metrics,
+};
+
+#[test]
+fn test_reset_returns_counters_to_zero() {
+    emit_metric("orders_total", 3.0);
+    emit_metric("reset_fills_total", 2.0);
+    emit_counter("reset_rejects_total", &[("reason", "risk")], 1.0).unwrap();
+    register_histogram("reset_batch_seconds", &[1.0, 10.0]).unwrap();
+    observe_histogram("reset_batch_seconds", 5.0).unwrap();
+    record_latency("order_send", 100.0);
+
+    reset_metrics();
+
+    let output = get_metrics();
+    assert!(output.contains("orders_total 0"));
+    assert!(output.contains("reset_fills_total 0"));
+    assert!(!output.contains("reset_rejects_total{"));
+    assert!(output.contains("reset_batch_seconds_count 0"));
+    assert!(!output.contains("latency_seconds_count"));
+
+    emit_metric("reset_fills_total", 1.0);
+    assert!(get_metrics().contains("reset_fills_total 1"));
+}
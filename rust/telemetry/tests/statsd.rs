@@ -0,0 +1,54 @@
+//! StatsD packets on the wire.
+//!
+//! Runs in its own process because enabling StatsD is process-wide.
+
+use std::net::UdpSocket;
+use std::time::Duration;
+
+use tinywindow_rust_telemetry::{
+    disable_statsd, emit_metric, enable_statsd, record_latency, set_gauge, statsd_enabled,
+};
+
+fn recv(agent: &UdpSocket) -> String {
+    let mut buf = [0; 512];
+    let len = agent.recv(&mut buf).unwrap();
+    String::from_utf8(buf[..len].to_vec()).unwrap()
+}
+
+#[test]
+fn test_statsd_wire_format() {
+    let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    agent
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    enable_statsd(&agent.local_addr().unwrap().to_string(), "tw").unwrap();
+    assert!(statsd_enabled());
+
+    emit_metric("statsd_orders_total", 2.0);
+    assert_eq!(recv(&agent), "tw.statsd_orders_total:2|c");
+
+    record_latency("order_send", 1500.0);
+    assert_eq!(recv(&agent), "tw.latency:1.5|ms|#operation:order_send");
+
+    set_gauge("statsd_queue_depth", &[("venue", "nyse")], 3.0).unwrap();
+    assert_eq!(recv(&agent), "tw.statsd_queue_depth:3|g|#venue:nyse");
+
+    // Rejected samples are not mirrored.
+    emit_metric("statsd_orders_total", -1.0);
+
+    disable_statsd();
+    assert!(!statsd_enabled());
+    emit_metric("statsd_orders_total", 1.0);
+    agent.set_nonblocking(true).unwrap();
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(agent.recv(&mut [0; 512]).is_err());
+
+    // An agent that went away must not block or fail emission.
+    let addr = agent.local_addr().unwrap().to_string();
+    drop(agent);
+    enable_statsd(&addr, "tw").unwrap();
+    for _ in 0..1000 {
+        emit_metric("statsd_orders_total", 1.0);
+    }
+    disable_statsd();
+}
@@ -0,0 +1,27 @@
+//! Strict mode is read from the environment at init and toggles globally.
+//!
+//! Runs in its own process because the mode is global.
+
+use tinywindow_rust_telemetry::{
+    emit_metric, emit_metric_checked, register_counter, set_strict_mode, strict_mode,
+    TelemetryError, STRICT_MODE_ENV, UNKNOWN_METRICS_TOTAL,
+};
+
+#[test]
+fn test_strict_mode_from_env_then_lenient() {
+    std::env::set_var(STRICT_MODE_ENV, "1");
+    assert!(strict_mode());
+
+    register_counter("fills_total", "Fills").unwrap();
+    emit_metric_checked("fills_total", 1.0).unwrap();
+    assert_eq!(
+        emit_metric_checked("fill_total", 1.0),
+        Err(TelemetryError::UnknownMetric("fill_total".to_string()))
+    );
+    emit_metric("fill_total", 1.0);
+    assert_eq!(UNKNOWN_METRICS_TOTAL.get(), 0.0);
+
+    set_strict_mode(false);
+    emit_metric_checked("fill_total", 1.0).unwrap();
+    assert_eq!(UNKNOWN_METRICS_TOTAL.get(), 1.0);
+}
@@ -0,0 +1,82 @@
+//! Threshold callbacks fired by the background checker.
+//!
+//! Runs in its own process because thresholds and their checker thread are
+//! process-wide.
+
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+use std::time::Duration;
+
+use tinywindow_rust_telemetry::{
+    clear_thresholds, record_error, register_threshold, ThresholdEvent,
+};
+
+/// Long enough for the checker to sample a 50ms window several times.
+const SETTLE: Duration = Duration::from_millis(300);
+
+#[test]
+fn test_burst_fires_exactly_once() {
+    let events: Arc<Mutex<Vec<ThresholdEvent>>> = Arc::default();
+    let recorded = Arc::clone(&events);
+    register_threshold(
+        "errors_total",
+        &[("operation", "threshold_send")],
+        0.05,
+        5.0,
+        Box::new(move |event| recorded.lock().unwrap().push(event)),
+    )
+    .unwrap();
+    // A callback that panics must not stop the checker.
+    register_threshold(
+        "errors_total",
+        &[("operation", "threshold_send")],
+        0.05,
+        5.0,
+        Box::new(|_| panic!("bad callback")),
+    )
+    .unwrap();
+
+    // Within the limit, across error kinds.
+    for kind in ["timeout", "timeout", "rejected", "rejected", "rejected"] {
+        record_error("threshold_send", kind);
+    }
+    sleep(SETTLE);
+    assert!(events.lock().unwrap().is_empty());
+
+    for _ in 0..10 {
+        record_error("threshold_send", "timeout");
+    }
+    record_error("threshold_other", "timeout");
+    sleep(SETTLE);
+    {
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 1, "{events:?}");
+        let event = &events[0];
+        assert_eq!(event.metric, "errors_total");
+        assert_eq!(
+            event.labels,
+            [("operation".to_string(), "threshold_send".to_string())]
+        );
+        assert!(event.delta > 5.0 && event.delta <= 10.0, "{event:?}");
+        assert_eq!(event.max_delta, 5.0);
+        assert_eq!(event.window, Duration::from_millis(50));
+    }
+
+    // A second burst fires again: the checker survived the panics.
+    for _ in 0..10 {
+        record_error("threshold_send", "timeout");
+    }
+    sleep(SETTLE);
+    assert_eq!(events.lock().unwrap().len(), 2);
+
+    clear_thresholds();
+    for _ in 0..10 {
+        record_error("threshold_send", "timeout");
+    }
+    sleep(SETTLE);
+    assert_eq!(events.lock().unwrap().len(), 2);
+
+    assert!(register_threshold("errors_total", &[], 0.0, 1.0, Box::new(|_| {})).is_err());
+    assert!(register_threshold("errors_total", &[], 1.0, f64::NAN, Box::new(|_| {})).is_err());
+    assert!(register_threshold("bad name", &[], 1.0, 1.0, Box::new(|_| {})).is_err());
+}
@@ -88,3 +88,83 @@ def test_rust_encryption_security():
     key = tinywindow.keygen(42)
     unique_bytes = len(set(key))
     assert unique_bytes > 10, f"Key should have >10 unique bytes, got {unique_bytes}"
+
+
+def test_rust_encryption_constant_time_eq():
+    """Test constant-time comparison exposed to Python."""
+    tinywindow = pytest.importorskip("tinywindow_rust_encryption")
+
+    sig = tinywindow.sign(tinywindow.keygen(42), b"payload")
+    assert tinywindow.constant_time_eq(sig, sig) is True
+    assert tinywindow.constant_time_eq(sig, sig[:-1]) is False
+    assert tinywindow.constant_time_eq(sig, sig[:-1] + bytes([sig[-1] ^ 1])) is False
+
+
+def test_rust_encryption_signature_envelope_migration():
+    """Test legacy and envelope signatures through the compat path."""
+    tinywindow = pytest.importorskip("tinywindow_rust_encryption")
+
+    key = tinywindow.keygen(42)
+    payload = b"envelope payload"
+    legacy = tinywindow.sign(key, payload)
+    envelope = tinywindow.sign_envelope(key, payload)
+
+    assert tinywindow.verify_compat(key, payload, legacy) is True
+    assert tinywindow.verify_compat(key, payload, envelope) is True
+    assert tinywindow.migrate_signature(key, payload, legacy) == envelope
+
+    tampered = bytes([legacy[0] ^ 0xFF]) + legacy[1:]
+    with pytest.raises(ValueError):
+        tinywindow.migrate_signature(key, payload, tampered)
+
+
+def test_rust_encryption_dual_signatures():
+    """Test dual-signature envelopes under each verification policy."""
+    tinywindow = pytest.importorskip("tinywindow_rust_encryption")
+
+    hmac_key = tinywindow.keygen(1)
+    alt_key = tinywindow.keygen(2)
+    payload = b"dual payload"
+    envelope = tinywindow.sign_dual(hmac_key, alt_key, "hmac-sha512", payload)
+
+    for policy in ("require_both", "require_either", "prefer_new_fallback_old"):
+        assert tinywindow.verify_dual(
+            policy, hmac_key, alt_key, "hmac-sha512", payload, envelope
+        )
+
+    # With the wrong new key, require_both fails; require_either still passes
+    # on the legacy signature alone.
+    wrong_alt = tinywindow.keygen(3)
+    assert not tinywindow.verify_dual(
+        "require_both", hmac_key, wrong_alt, "hmac-sha512", payload, envelope
+    )
+    assert tinywindow.verify_dual(
+        "require_either", hmac_key, wrong_alt, "hmac-sha512", payload, envelope
+    )
+
+    with pytest.raises(ValueError):
+        tinywindow.verify_dual(
+            "require_both", hmac_key, alt_key, "hmac-sha256", payload, envelope
+        )
+
+
+def test_rust_encryption_key_registry():
+    """Test labelled key lookup, duplicate handling and metadata export."""
+    tinywindow = pytest.importorskip("tinywindow_rust_encryption")
+
+    key = tinywindow.keygen(42)
+    registry = tinywindow.KeyRegistry()
+    registry.insert("orders-prod-2024", key, tags=["prod"])
+
+    assert "orders-prod-2024" in registry
+    assert registry.list_labels() == ["orders-prod-2024"]
+    sig = registry.sign_as("orders-prod-2024", b"payload")
+    assert tinywindow.verify(key, b"payload", sig) is True
+
+    with pytest.raises(ValueError):
+        registry.insert("orders-prod-2024", tinywindow.keygen(43))
+    registry.insert("orders-prod-2024", tinywindow.keygen(43), replace=True)
+
+    metadata = registry.metadata_json()
+    assert '"algorithm":"hmac-sha256"' in metadata
+    assert key.hex() not in metadata
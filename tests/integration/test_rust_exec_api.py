@@ -0,0 +1,43 @@
+"""Integration tests for the Rust execution adapter bindings."""
+
+import asyncio
+
+import pytest
+
+
+def test_rust_exec_send_order_returns_ack():
+    """Test awaiting send_order from asyncio."""
+    exec_adapter = pytest.importorskip("tinywindow_rust_exec")
+
+    ack = asyncio.run(exec_adapter.send_order(b"BUY AAPL 10"))
+
+    assert ack["order_id"] > 0
+    assert ack["accepted"] is True
+    assert ack["reason"] is None
+    assert ack["remaining_quantity"] == 0
+
+
+def test_rust_exec_errors_map_to_exception_types():
+    """Test that rejected orders raise OrderRejected."""
+    exec_adapter = pytest.importorskip("tinywindow_rust_exec")
+
+    exec_adapter.pre_trade_check(b"BUY AAPL 10")
+    with pytest.raises(exec_adapter.OrderRejected) as excinfo:
+        exec_adapter.pre_trade_check(b"")
+    assert excinfo.value.args[1] == "empty_order"
+
+    with pytest.raises(exec_adapter.ExecError):
+        asyncio.run(exec_adapter.send_order(b""))
+    assert issubclass(exec_adapter.ExecConnectionError, exec_adapter.ExecError)
+    assert issubclass(exec_adapter.ExecTimeoutError, exec_adapter.ExecError)
+
+
+def test_rust_exec_validation_error_carries_reason():
+    """Test that an empty order raises ExecValidationError with its reason."""
+    exec_adapter = pytest.importorskip("tinywindow_rust_exec")
+
+    with pytest.raises(exec_adapter.ExecValidationError) as excinfo:
+        asyncio.run(exec_adapter.send_order(b""))
+    assert excinfo.value.reason == "empty_order"
+    assert isinstance(excinfo.value, exec_adapter.ExecError)
+    assert exec_adapter.OrderRejected is exec_adapter.ExecValidationError